@@ -0,0 +1,103 @@
+/*
+ * The Electron 2 emulator core, for C and C++: the declarations of ffi/src/lib.rs. After
+ * changing the API, regenerate this file with cbindgen (settings in cbindgen.toml):
+ *
+ *     cbindgen --config cbindgen.toml --output electron.h
+ *
+ * Link against libelectron (build it with `cargo build --release` in ffi/). Every function
+ * accepts a null emulator and then does nothing; reads give 0. Strings handed out stay valid
+ * until the next call that loads a program, reads the fault again, or destroys the emulator.
+ */
+
+#ifndef ELECTRON_H
+#define ELECTRON_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum ElectronStatus {
+  /* The next clock runs the program. */
+  ELECTRON_STATUS_RUNNING = 0,
+  /* INP is waiting for electron_input. */
+  ELECTRON_STATUS_WAITING_FOR_INPUT = 1,
+  /* HLT executed; only a reset or load starts it again. */
+  ELECTRON_STATUS_HALTED = 2,
+  /* See electron_fault; only a reset or load starts it again. */
+  ELECTRON_STATUS_FAULTED = 3,
+} ElectronStatus;
+
+typedef struct ElectronEmulator ElectronEmulator;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A new emulator running source, a NUL-terminated V2 program. Always returns an emulator;
+ * if the program has errors (see electron_error_count) it runs nothing but NOOPs. */
+ElectronEmulator *electron_create(const char *source);
+
+void electron_destroy(ElectronEmulator *emulator);
+
+/* Swaps in source and starts it, unless it has errors, in which case the running program
+ * is kept. Returns the number of errors. */
+size_t electron_load(ElectronEmulator *emulator, const char *source);
+
+size_t electron_error_count(const ElectronEmulator *emulator);
+
+/* The index-th error of the last program given, or null past the last one. */
+const char *electron_error(const ElectronEmulator *emulator, size_t index);
+
+/* Starts the program over. */
+void electron_reset(ElectronEmulator *emulator);
+
+/* One clock, and then what the emulator is doing. */
+ElectronStatus electron_clock(ElectronEmulator *emulator);
+
+/* Clocks until the next instruction executes, passing over pipeline bubbles. Returns how many
+ * clocks that took. */
+uint64_t electron_step(ElectronEmulator *emulator);
+
+/* Clocks up to clocks times, stopping early on INP, HLT or a fault. Returns how many
+ * clocks ran. */
+uint64_t electron_run(ElectronEmulator *emulator, uint64_t clocks);
+
+ElectronStatus electron_status(const ElectronEmulator *emulator);
+
+/* Why the emulator stopped, or null if it hasn't faulted. */
+const char *electron_fault(ElectronEmulator *emulator);
+
+/* Answers the INP the program is waiting on. Returns false if it isn't waiting. */
+bool electron_input(ElectronEmulator *emulator, uint8_t value);
+
+/* RAM address #0-#15. Other addresses read 0. */
+uint8_t electron_peek_ram(const ElectronEmulator *emulator, uint8_t address);
+
+/* Writes RAM address #0-#15. Writes elsewhere are ignored. */
+void electron_poke_ram(ElectronEmulator *emulator, uint8_t address, uint8_t value);
+
+/* Register R0-R7. R0 always reads 0. */
+uint8_t electron_peek_register(const ElectronEmulator *emulator, uint8_t number);
+
+/* Sets register R1-R7 at once, not at the end of the next clock as the program's writes are.
+ * R0 and other numbers are ignored. */
+void electron_poke_register(ElectronEmulator *emulator, uint8_t number, uint8_t value);
+
+/* Reads a port as INP Rx %port would: the display ports %0-%7 read back what was written,
+ * and every other port reads 0, since no devices are attached. */
+uint8_t electron_peek_port(ElectronEmulator *emulator, uint8_t port);
+
+/* Writes a port as OUT %port Rx would; %0-%7 change the display. */
+void electron_poke_port(ElectronEmulator *emulator, uint8_t port, uint8_t value);
+
+/* The address of the next instruction to fetch. */
+int32_t electron_pc(const ElectronEmulator *emulator);
+
+/* Clocks since the last reset or load, not counting those spent waiting on INP. */
+uint64_t electron_cycles(const ElectronEmulator *emulator);
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif /* ELECTRON_H */
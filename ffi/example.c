@@ -0,0 +1,35 @@
+/* Runs a program through the C API and prints its display ports.
+ *
+ *     cargo build --release
+ *     cc example.c -I. -Ltarget/release -lelectron -o example
+ *     LD_LIBRARY_PATH=target/release ./example
+ */
+
+#include <stdio.h>
+#include "electron.h"
+
+static const char *PROGRAM =
+    "IMM R1 1\n"
+    "IMM R2 10\n"
+    "loop:\n"
+    "OUT %0 R1\n"
+    "ADD R1 R1\n"
+    "XSUB R2 R1\n"
+    "BIL loop\n"
+    "HLT\n";
+
+int main(void) {
+    ElectronEmulator *emulator = electron_create(PROGRAM);
+    for (size_t i = 0; i < electron_error_count(emulator); i++) {
+        fprintf(stderr, "Error: %s\n", electron_error(emulator, i));
+    }
+    if (electron_error_count(emulator) > 0) {
+        electron_destroy(emulator);
+        return 1;
+    }
+    uint64_t clocks = electron_run(emulator, 10000);
+    printf("Ran %llu clocks, status %d.\n", (unsigned long long)clocks, electron_status(emulator));
+    printf("%%0 = %u, R1 = %u\n", electron_peek_port(emulator, 0), electron_peek_register(emulator, 1));
+    electron_destroy(emulator);
+    return 0;
+}
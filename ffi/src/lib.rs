@@ -0,0 +1,231 @@
+//! A C API for the V2 core, so it can be embedded in C and C++ front ends and game engines.
+//! `electron.h` declares everything here; regenerate it with cbindgen after changing the API.
+//!
+//! An emulator is an opaque `ElectronEmulator *` from `electron_create`, freed with
+//! `electron_destroy`. Every function accepts a null emulator and then does nothing (reads
+//! give 0). Strings handed out stay valid until the next call that loads a program, reads the
+//! fault again, or destroys the emulator.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+#[path = "../../src/electron-2/lib.rs"]
+#[allow(dead_code)]
+mod electron_2;
+
+use electron_2::Emulator;
+
+pub struct ElectronEmulator {
+    emulator: Emulator,
+    /// The errors of the last program given to `electron_create` or `electron_load`.
+    errors: Vec<CString>,
+    /// The fault as last returned by `electron_fault`.
+    fault: Option<CString>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ElectronStatus {
+    /// The next clock runs the program.
+    Running = 0,
+    /// `INP` is waiting for `electron_input`.
+    WaitingForInput = 1,
+    /// `HLT` executed; only a reset or load starts it again.
+    Halted = 2,
+    /// See `electron_fault`; only a reset or load starts it again.
+    Faulted = 3,
+}
+
+fn c_string(text: &str) -> CString {
+    CString::new(text.replace('\0', " ")).unwrap_or_default()
+}
+
+/// The program text, or why it can't be read.
+unsafe fn read_source(source: *const c_char) -> Result<String, String> {
+    if source.is_null() {
+        return Err("No source was given.".to_string());
+    }
+    CStr::from_ptr(source).to_str().map(str::to_string).map_err(|_| "The source isn't valid UTF-8.".to_string())
+}
+
+fn status(emulator: &Emulator) -> ElectronStatus {
+    if emulator.fault.is_some() {
+        ElectronStatus::Faulted
+    } else if emulator.halted {
+        ElectronStatus::Halted
+    } else if emulator.waiting_for_input {
+        ElectronStatus::WaitingForInput
+    } else {
+        ElectronStatus::Running
+    }
+}
+
+/// A new emulator running `source`, a NUL-terminated V2 program. Always returns an emulator;
+/// if the program has errors (see `electron_error_count`) it runs nothing but `NOOP`s.
+#[no_mangle]
+pub unsafe extern "C" fn electron_create(source: *const c_char) -> *mut ElectronEmulator {
+    let (emulator, errors) = match read_source(source) {
+        Ok(code) => {
+            let emulator = Emulator::new(code);
+            let errors = emulator.errors.iter().map(|error| c_string(error)).collect();
+            (emulator, errors)
+        }
+        Err(error) => (Emulator::new(String::new()), vec![c_string(&error)]),
+    };
+    Box::into_raw(Box::new(ElectronEmulator { emulator, errors, fault: None }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn electron_destroy(emulator: *mut ElectronEmulator) {
+    if !emulator.is_null() {
+        drop(Box::from_raw(emulator));
+    }
+}
+
+/// Swaps in `source` and starts it, unless it has errors, in which case the running program
+/// is kept. Returns the number of errors.
+#[no_mangle]
+pub unsafe extern "C" fn electron_load(emulator: *mut ElectronEmulator, source: *const c_char) -> usize {
+    let Some(handle) = emulator.as_mut() else { return 0 };
+    let errors = match read_source(source) {
+        // Parsed on the side first, so a broken program doesn't stop the running one.
+        Ok(code) => match Emulator::new(code.clone()).errors {
+            errors if errors.is_empty() => {
+                let _ = handle.emulator.load_program(code);
+                Vec::new()
+            }
+            errors => errors,
+        },
+        Err(error) => vec![error],
+    };
+    handle.errors = errors.iter().map(|error| c_string(error)).collect();
+    handle.errors.len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn electron_error_count(emulator: *const ElectronEmulator) -> usize {
+    emulator.as_ref().map_or(0, |handle| handle.errors.len())
+}
+
+/// The `index`th error of the last program given, or null past the last one.
+#[no_mangle]
+pub unsafe extern "C" fn electron_error(emulator: *const ElectronEmulator, index: usize) -> *const c_char {
+    emulator.as_ref().and_then(|handle| handle.errors.get(index)).map_or(ptr::null(), |error| error.as_ptr())
+}
+
+/// Starts the program over.
+#[no_mangle]
+pub unsafe extern "C" fn electron_reset(emulator: *mut ElectronEmulator) {
+    if let Some(handle) = emulator.as_mut() {
+        handle.emulator.reset();
+    }
+}
+
+/// One clock, and then what the emulator is doing.
+#[no_mangle]
+pub unsafe extern "C" fn electron_clock(emulator: *mut ElectronEmulator) -> ElectronStatus {
+    let Some(handle) = emulator.as_mut() else { return ElectronStatus::Halted };
+    handle.emulator.clock();
+    status(&handle.emulator)
+}
+
+/// Clocks until the next instruction executes, passing over pipeline bubbles. Returns how many
+/// clocks that took.
+#[no_mangle]
+pub unsafe extern "C" fn electron_step(emulator: *mut ElectronEmulator) -> u64 {
+    emulator.as_mut().map_or(0, |handle| handle.emulator.step())
+}
+
+/// Clocks up to `clocks` times, stopping early on `INP`, `HLT` or a fault. Returns how many
+/// clocks ran.
+#[no_mangle]
+pub unsafe extern "C" fn electron_run(emulator: *mut ElectronEmulator, clocks: u64) -> u64 {
+    let Some(handle) = emulator.as_mut() else { return 0 };
+    let mut ran = 0;
+    while ran < clocks && status(&handle.emulator) == ElectronStatus::Running {
+        handle.emulator.clock();
+        ran += 1;
+    }
+    ran
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn electron_status(emulator: *const ElectronEmulator) -> ElectronStatus {
+    emulator.as_ref().map_or(ElectronStatus::Halted, |handle| status(&handle.emulator))
+}
+
+/// Why the emulator stopped, or null if it hasn't faulted.
+#[no_mangle]
+pub unsafe extern "C" fn electron_fault(emulator: *mut ElectronEmulator) -> *const c_char {
+    let Some(handle) = emulator.as_mut() else { return ptr::null() };
+    handle.fault = handle.emulator.fault.as_deref().map(c_string);
+    handle.fault.as_ref().map_or(ptr::null(), |fault| fault.as_ptr())
+}
+
+/// Answers the `INP` the program is waiting on. Returns false if it isn't waiting.
+#[no_mangle]
+pub unsafe extern "C" fn electron_input(emulator: *mut ElectronEmulator, value: u8) -> bool {
+    let Some(handle) = emulator.as_mut().filter(|handle| handle.emulator.waiting_for_input) else { return false };
+    handle.emulator.resolve_input(value as i32);
+    true
+}
+
+/// RAM address #0-#15. Other addresses read 0.
+#[no_mangle]
+pub unsafe extern "C" fn electron_peek_ram(emulator: *const ElectronEmulator, address: u8) -> u8 {
+    emulator.as_ref().and_then(|handle| handle.emulator.ram.get(address as usize).copied()).unwrap_or(0)
+}
+
+/// Writes RAM address #0-#15. Writes elsewhere are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn electron_poke_ram(emulator: *mut ElectronEmulator, address: u8, value: u8) {
+    if let Some(cell) = emulator.as_mut().and_then(|handle| handle.emulator.ram.get_mut(address as usize)) {
+        *cell = value;
+    }
+}
+
+/// Register R0-R7. R0 always reads 0.
+#[no_mangle]
+pub unsafe extern "C" fn electron_peek_register(emulator: *const ElectronEmulator, number: u8) -> u8 {
+    emulator.as_ref().map_or(0, |handle| handle.emulator.registers.read(number as i32))
+}
+
+/// Sets register R1-R7 at once, not at the end of the next clock as the program's writes are.
+/// R0 and other numbers are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn electron_poke_register(emulator: *mut ElectronEmulator, number: u8, value: u8) {
+    if let Some(handle) = emulator.as_mut().filter(|_| (1..8).contains(&number)) {
+        let registers = &mut handle.emulator.registers;
+        registers.regs[number as usize] = value;
+        registers.next_regs[number as usize] = value;
+    }
+}
+
+/// Reads a port as `INP Rx %port` would: the display ports %0-%7 read back what was written,
+/// and every other port reads 0, since no devices are attached.
+#[no_mangle]
+pub unsafe extern "C" fn electron_peek_port(emulator: *mut ElectronEmulator, port: u8) -> u8 {
+    emulator.as_mut().map_or(0, |handle| handle.emulator.read_port(port as i32))
+}
+
+/// Writes a port as `OUT %port Rx` would; %0-%7 change the display.
+#[no_mangle]
+pub unsafe extern "C" fn electron_poke_port(emulator: *mut ElectronEmulator, port: u8, value: u8) {
+    if let Some(handle) = emulator.as_mut() {
+        handle.emulator.write_port(port as i32, value);
+    }
+}
+
+/// The address of the next instruction to fetch.
+#[no_mangle]
+pub unsafe extern "C" fn electron_pc(emulator: *const ElectronEmulator) -> i32 {
+    emulator.as_ref().map_or(0, |handle| handle.emulator.pc)
+}
+
+/// Clocks since the last reset or load, not counting those spent waiting on `INP`.
+#[no_mangle]
+pub unsafe extern "C" fn electron_cycles(emulator: *const ElectronEmulator) -> u64 {
+    emulator.as_ref().map_or(0, |handle| handle.emulator.stats.cycles)
+}
@@ -0,0 +1,23 @@
+//! Feeds arbitrary text to both assemblers. Any panic is a bug: programs come from students, so
+//! bad input has to come back as an error message.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The emulator is a binary crate, so the parsers are compiled in here the same way main.rs
+// includes the V2 core.
+#[path = "../../src/electron-2/lib.rs"]
+#[allow(dead_code)]
+mod electron_2;
+#[path = "../../src/parser.rs"]
+#[allow(dead_code)]
+mod parser;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(code) = std::str::from_utf8(data) {
+        let _ = electron_2::parser::Parser::parse(code.to_string());
+        let _ = electron_2::parser::Parser::scan_assertions(code);
+        let _ = parser::ProgramLoader::compile(code);
+    }
+});
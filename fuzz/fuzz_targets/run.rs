@@ -0,0 +1,24 @@
+//! Loads arbitrary text as a V2 program and clocks it for a while, answering every `INP`, so
+//! whatever the parser accepts is also safe to execute.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../src/electron-2/lib.rs"]
+#[allow(dead_code)]
+mod electron_2;
+
+const CLOCKS: usize = 256;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(code) = std::str::from_utf8(data) {
+        let mut emulator = electron_2::Emulator::new(code.to_string());
+        for i in 0..CLOCKS {
+            if emulator.waiting_for_input {
+                emulator.resolve_input(i as i32);
+            }
+            emulator.clock();
+        }
+    }
+});
@@ -0,0 +1,142 @@
+//! The CPU architectures the toolchain knows. Each one is listed once in `ARCHITECTURES`, which
+//! drives `--arch` and `--help`; `Arch::assemble` is the one place the assemblers are told
+//! apart, so the tools built on it work for every architecture.
+
+use crate::electron_2::codes::{split_code, Code};
+use crate::electron_2::disassembler::Disassembler;
+use crate::electron_2::hazards::Hazard;
+use crate::electron_2::parser::split_location;
+use crate::electron_2::Emulator as EmulatorV2;
+use crate::parser::{self as v1, ProgramLoader};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Arch {
+    Electron1,
+    Electron2,
+}
+
+pub struct ArchInfo {
+    pub arch: Arch,
+    /// The first name is canonical; the rest are accepted by `--arch` too.
+    pub names: &'static [&'static str],
+    pub title: &'static str,
+    pub description: &'static str,
+}
+
+pub const ARCHITECTURES: &[ArchInfo] = &[
+    ArchInfo {
+        arch: Arch::Electron1,
+        names: &["electron1", "v1"],
+        title: "Electron Emulator",
+        description: "The original CPU built in Minecraft (the default)",
+    },
+    ArchInfo {
+        arch: Arch::Electron2,
+        names: &["electron2", "v2"],
+        title: "Electron 2 Emulator",
+        description: "Hardware stack, RAM, peripherals and the debugging tools",
+    },
+];
+
+/// What an assembler made of a source file, in a form every architecture can fill in.
+pub struct Assembled {
+    /// One canonical line per instruction, in address order.
+    pub listing: Vec<String>,
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+    /// The instructions that conflict with the one before them, by address (V2).
+    pub hazards: Vec<Hazard>,
+}
+
+impl Assembled {
+    /// Makes every warning an error, for `--strict`. A V2 program's `.strict` line already has.
+    pub fn promote_warnings(&mut self) {
+        self.errors.append(&mut self.warnings);
+    }
+}
+
+/// An error or warning, and where it is in the source when that's known.
+pub struct Diagnostic {
+    /// Counted from 1.
+    pub line: Option<usize>,
+    /// Counted in characters from 1.
+    pub column: Option<usize>,
+    /// V2 messages have one; see `electron explain`.
+    pub code: Option<Code>,
+    pub message: String,
+}
+
+impl Arch {
+    pub fn from_name(name: &str) -> Option<Arch> {
+        let name = name.to_lowercase();
+        ARCHITECTURES.iter().find(|info| info.names.contains(&name.as_str())).map(|info| info.arch)
+    }
+
+    pub fn info(self) -> &'static ArchInfo {
+        ARCHITECTURES.iter().find(|info| info.arch == self).expect("every Arch is in ARCHITECTURES")
+    }
+
+    pub fn name(self) -> &'static str {
+        self.info().names[0]
+    }
+
+    pub fn assemble(self, source: &str) -> Assembled {
+        match self {
+            Arch::Electron1 => {
+                let (instructions, errors, warnings) = ProgramLoader::compile(source);
+                let convert = |messages: Vec<v1::CompileMessage>| {
+                    messages.into_iter().map(|m| Diagnostic { line: Some(m.line + 1), column: None, code: None, message: m.message }).collect()
+                };
+                Assembled {
+                    listing: instructions.iter().map(format_v1).collect(),
+                    errors: convert(errors),
+                    warnings: convert(warnings),
+                    hazards: Vec::new(),
+                }
+            }
+            Arch::Electron2 => {
+                let emulator = EmulatorV2::new(source.to_string());
+                let split = |messages: &[String]| {
+                    messages
+                        .iter()
+                        .map(|m| {
+                            let (line, column, message) = split_location(m);
+                            let (code, message) = split_code(message);
+                            Diagnostic { line, column, code, message: message.to_string() }
+                        })
+                        .collect()
+                };
+                Assembled {
+                    listing: emulator.instructions.iter().map(Disassembler::instruction).collect(),
+                    errors: split(&emulator.errors),
+                    warnings: split(&emulator.warnings),
+                    hazards: emulator.hazards(),
+                }
+            }
+        }
+    }
+}
+
+fn format_v1(instr: &v1::Instruction) -> String {
+    let prefix = match instr.operation_args {
+        v1::OperationArgs::None => "",
+        v1::OperationArgs::S => "S",
+        v1::OperationArgs::U => "U",
+        v1::OperationArgs::X => "X",
+    };
+    let mut text = format!("{prefix}{:?}", instr.operation);
+    let (needs_a, needs_b) = instr.operation.needed_oprands(&instr.operation_args);
+    for (needed, operand) in [(needs_a.is_some(), &instr.a), (needs_b.is_some(), &instr.b)] {
+        if needed {
+            let operand = match operand {
+                v1::Oprand::Register(n) => format!("R{n}"),
+                v1::Oprand::MemoryAddress(n) => format!("#{n}"),
+                v1::Oprand::Immediate(n) => n.to_string(),
+                v1::Oprand::Port(n) => format!("%{n}"),
+            };
+            text.push(' ');
+            text.push_str(&operand);
+        }
+    }
+    text
+}
@@ -0,0 +1,88 @@
+//! `--run-until-halt`: run a V2 program headless, without a window or dashboard, until it executes
+//! `HLT`. Blocking `INP`s read one line each from stdin, so programs can sit in shell pipelines.
+
+use crate::electron_2::Emulator as EmulatorV2;
+use crate::multicore::LinkedCore;
+use crate::profiler::Profiler;
+use crate::state_dump::StateDump;
+use crate::signals;
+use std::io::BufRead;
+
+pub enum BatchError {
+    CycleLimit(u64),
+    Fault(String),
+    Input(String),
+    Interrupted,
+}
+
+impl BatchError {
+    /// 124 for the cycle limit, like `timeout`; 1 for everything else.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BatchError::CycleLimit(_) => 124,
+            _ => 1,
+        }
+    }
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BatchError::CycleLimit(limit) => write!(f, "The program did not halt within {limit} cycles (raise it with --max-cycles)."),
+            BatchError::Fault(fault) => write!(f, "{fault}"),
+            BatchError::Input(message) => write!(f, "INP: {message}"),
+            BatchError::Interrupted => write!(f, "Interrupted before the program halted."),
+        }
+    }
+}
+
+/// Runs until `emulator` halts. A linked core is clocked alongside it and must not fault.
+pub fn run_until_halt(
+    emulator: &mut EmulatorV2,
+    max_cycles: u64,
+    uart: bool,
+    mut profiler: Option<&mut Profiler>,
+    mut linked: Option<&mut LinkedCore>,
+    dumps: &mut [StateDump],
+) -> Result<(), BatchError> {
+    while !emulator.halted {
+        if let Some(fault) = &emulator.fault {
+            return Err(BatchError::Fault(fault.clone()));
+        }
+        if signals::shutdown_requested() {
+            return Err(BatchError::Interrupted);
+        }
+        if emulator.waiting_for_input {
+            if uart {
+                return Err(BatchError::Input("stdin is attached to the UART (--uart), so there is nothing to read a value from.".to_string()));
+            }
+            let mut line = String::new();
+            // Locked per read, not up front: the UART's reader thread holds stdin under --uart.
+            match std::io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => return Err(BatchError::Input("stdin closed before a value was read.".to_string())),
+                Ok(_) => match line.trim().parse::<u8>() {
+                    Ok(value) => emulator.resolve_input(value as i32),
+                    Err(_) => return Err(BatchError::Input(format!("\"{}\" is not a value between 0 and 255.", line.trim()))),
+                },
+            }
+            continue;
+        }
+        if emulator.stats.cycles >= max_cycles {
+            return Err(BatchError::CycleLimit(max_cycles));
+        }
+        emulator.clock();
+        if let Some(profiler) = profiler.as_deref_mut() {
+            profiler.record(emulator);
+        }
+        for dump in dumps.iter_mut() {
+            dump.record(emulator);
+        }
+        if let Some(core) = linked.as_deref_mut() {
+            core.clock();
+            if let Some(problem) = core.problem() {
+                return Err(BatchError::Fault(problem));
+            }
+        }
+    }
+    Ok(())
+}
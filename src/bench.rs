@@ -0,0 +1,79 @@
+//! `--bench <cycles>`: clock a program headless, with no window, dashboard or devices, as fast as
+//! the host allows, and report how fast the emulator went. A program that halts starts over, and
+//! `INP` reads 0, so any program can be benched for any number of clocks.
+
+use crate::emulator::Emulator;
+use crate::electron_2::Emulator as EmulatorV2;
+use crate::signals;
+use std::time::{Duration, Instant};
+
+/// How many clocks run between checks for Ctrl-C.
+const CHUNK: u64 = 4096;
+
+pub struct BenchResult {
+    pub clocks: u64,
+    /// Instructions that completed writeback; V1 has no counter for them.
+    pub retired: Option<u64>,
+    pub elapsed: Duration,
+    /// How many times the program halted and was started over.
+    pub restarts: u64,
+}
+
+impl BenchResult {
+    pub fn report(&self) -> String {
+        let seconds = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut out = format!("Bench: {} clocks in {:.3} s\n", self.clocks, seconds);
+        out.push_str(&format!("  {:.2} M clocks/s", self.clocks as f64 / seconds / 1e6));
+        if let Some(retired) = self.retired {
+            out.push_str(&format!(", {:.2} MIPS ({retired} instructions retired)", retired as f64 / seconds / 1e6));
+        }
+        out.push('\n');
+        if self.restarts > 0 {
+            out.push_str(&format!("  The program halted and was restarted {} times.\n", self.restarts));
+        }
+        out
+    }
+}
+
+pub fn bench_v2(emulator: &mut EmulatorV2, clocks: u64) -> Result<BenchResult, String> {
+    let (mut done, mut retired, mut restarts) = (0, 0, 0);
+    let start = Instant::now();
+    while done < clocks {
+        if signals::shutdown_requested() {
+            return Err("Interrupted before the bench finished.".to_string());
+        }
+        let chunk_end = (done + CHUNK).min(clocks);
+        while done < chunk_end {
+            if emulator.halted {
+                retired += emulator.stats.retired;
+                restarts += 1;
+                emulator.reset();
+            }
+            emulator.resolve_input(0);
+            emulator.clock();
+            if let Some(fault) = &emulator.fault {
+                return Err(format!("After {} clocks: {fault}", done + 1));
+            }
+            done += 1;
+        }
+    }
+    let elapsed = start.elapsed();
+    Ok(BenchResult { clocks, retired: Some(retired + emulator.stats.retired), elapsed, restarts })
+}
+
+/// V1 has no `HLT` and no faults; the program counter just wraps around.
+pub fn bench_v1(emulator: &mut Emulator, clocks: u64) -> Result<BenchResult, String> {
+    let mut done = 0;
+    let start = Instant::now();
+    while done < clocks {
+        if signals::shutdown_requested() {
+            return Err("Interrupted before the bench finished.".to_string());
+        }
+        let chunk_end = (done + CHUNK).min(clocks);
+        while done < chunk_end {
+            emulator.clock();
+            done += 1;
+        }
+    }
+    Ok(BenchResult { clocks, retired: None, elapsed: start.elapsed(), restarts: 0 })
+}
@@ -0,0 +1,133 @@
+use raylib::prelude::*;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SAMPLE_RATE: u32 = 44100;
+const BUFFER_SAMPLES: usize = 4096;
+const AMPLITUDE: u8 = 48;
+
+/// Every step of the port value raises the tone by this many Hz (1 = 10 Hz, 255 = 2550 Hz).
+pub const HZ_PER_STEP: f32 = 10.0;
+
+/// `--speaker` measures the emulated clock rate over this long. The clock thread runs clocks in
+/// bursts, which this smooths out.
+const RATE_WINDOW: Duration = Duration::from_millis(500);
+/// How much sound `--speaker` waits to have before it starts playing, in seconds, so the
+/// clock's bursts don't leave gaps; and the most it lets build up before skipping ahead.
+const MIN_LATENCY: f64 = 0.15;
+const MAX_LATENCY: f64 = 0.3;
+
+/// What the buzzer plays.
+pub enum Sound {
+    /// `--buzzer`: a square wave at the port's value × 10 Hz.
+    Tone(usize),
+    /// `--speaker`: a port bit clock by clock, from the `Speaker` device, resampled from the
+    /// clock rate to the sound card's.
+    Bits(Arc<Mutex<Vec<bool>>>),
+}
+
+/// Plays a `Sound` through raylib's audio stream.
+pub struct Buzzer {
+    sound: Sound,
+    // Declared before `audio` so the stream is closed before the device.
+    stream: AudioStream,
+    audio: RaylibAudio,
+    buffer: Vec<u8>,
+    phase: f32,
+    /// `Sound::Bits` samples not played yet, and how many arrived when, for the clock rate.
+    pending: VecDeque<bool>,
+    arrivals: VecDeque<(Instant, usize)>,
+    /// How far into `pending` the next sound card sample starts, in clocks.
+    position: f64,
+    /// Whether `pending` has been full enough to play since it last ran out.
+    playing: bool,
+}
+
+impl Buzzer {
+    pub fn new(thread: &RaylibThread, sound: Sound) -> Self {
+        let mut audio = RaylibAudio::init_audio_device();
+        // 8-bit unsigned samples, so sample count and byte count line up for update_audio_stream.
+        let mut stream = AudioStream::init_audio_stream(thread, SAMPLE_RATE, 8, 1);
+        audio.play_audio_stream(&mut stream);
+        Self {
+            sound,
+            stream,
+            audio,
+            buffer: vec![128; BUFFER_SAMPLES],
+            phase: 0.0,
+            pending: VecDeque::new(),
+            arrivals: VecDeque::new(),
+            position: 0.0,
+            playing: false,
+        }
+    }
+
+    /// Refills the audio stream from the output ports, or from the speaker's samples. Call once
+    /// per frame.
+    pub fn update(&mut self, ports: &[u8; 8]) {
+        if let Sound::Bits(samples) = &self.sound {
+            let arrived: Vec<bool> = std::mem::take(&mut *samples.lock().unwrap());
+            self.arrivals.push_back((Instant::now(), arrived.len()));
+            self.pending.extend(arrived);
+        }
+        if !self.audio.is_audio_stream_processed(&self.stream) {
+            return;
+        }
+        match self.sound {
+            Sound::Tone(port) => self.fill_tone(ports[port]),
+            Sound::Bits(_) => self.fill_bits(),
+        }
+        self.stream.update_audio_stream(&self.buffer);
+    }
+
+    fn fill_tone(&mut self, value: u8) {
+        let step = value as f32 * HZ_PER_STEP / SAMPLE_RATE as f32;
+        for sample in self.buffer.iter_mut() {
+            *sample = if value == 0 {
+                128
+            } else if self.phase < 0.5 {
+                128 + AMPLITUDE
+            } else {
+                128 - AMPLITUDE
+            };
+            self.phase = (self.phase + step).fract();
+        }
+    }
+
+    /// Each sound card sample is the average level over the clocks it spans, which also
+    /// filters out what's too high to play. Silent once the clocks run out (paused, say).
+    fn fill_bits(&mut self) {
+        let now = Instant::now();
+        while self.arrivals.front().is_some_and(|(time, _)| now.duration_since(*time) > RATE_WINDOW) {
+            self.arrivals.pop_front();
+        }
+        let span = self.arrivals.front().map_or(RATE_WINDOW, |(time, _)| now.duration_since(*time).max(Duration::from_millis(20)));
+        // The first batch's clocks ran before the span began.
+        let rate = self.arrivals.iter().skip(1).map(|(_, count)| *count).sum::<usize>() as f64 / span.as_secs_f64();
+        let behind = self.pending.len() as f64 - rate * MAX_LATENCY;
+        if behind > 0.0 {
+            self.pending.drain(..behind as usize);
+        }
+        self.playing |= rate > 0.0 && self.pending.len() as f64 >= rate * MIN_LATENCY;
+        let step = rate / SAMPLE_RATE as f64;
+        for sample in self.buffer.iter_mut() {
+            let end = self.position + step;
+            let clocks = (end as usize).max(self.position as usize + 1);
+            if clocks > self.pending.len() {
+                self.playing = false;
+            }
+            if !self.playing {
+                *sample = 128;
+                continue;
+            }
+            let high = self.pending.range(self.position as usize..clocks).filter(|level| **level).count();
+            let level = high as f32 / (clocks - self.position as usize) as f32;
+            *sample = (128.0 - f32::from(AMPLITUDE) + 2.0 * f32::from(AMPLITUDE) * level) as u8;
+            self.position = end;
+        }
+        let played = (self.position as usize).min(self.pending.len());
+        self.pending.drain(..played);
+        self.position -= played as f64;
+    }
+}
@@ -0,0 +1,470 @@
+//! Command line parsing. Every flag is listed once in `FLAGS` with the subcommands that accept it,
+//! which drives both validation and `--help`. Without a subcommand the emulator runs, so
+//! `electron -f prog.elt` keeps working.
+
+use crate::arch::{Arch, ARCHITECTURES};
+use crate::devices::adc::MAX_CHANNELS;
+use crate::electron_2::{StackLayout, Watchdog};
+use crate::syntax::Format;
+use crate::window::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    Run,
+    Check,
+    Assemble,
+    Disasm,
+    Repl,
+    Test,
+    Isa,
+    Explain,
+    Lsp,
+    GenSyntax,
+}
+
+const COMMANDS: &[(Command, &str, &str)] = &[
+    (Command::Run, "run", "Assemble and run a program in the emulator (the default)"),
+    (Command::Check, "check", "Report errors and warnings without running"),
+    (Command::Assemble, "assemble", "Print the assembled instruction listing"),
+    (Command::Disasm, "disasm", "Print a V2 program back as canonical assembly"),
+    (Command::Repl, "repl", "Type V2 instructions and run them one at a time"),
+    (Command::Test, "test", "Run every V2 program in a directory and compare it with its .expected file"),
+    (Command::Isa, "isa", "Print the V2 instruction set reference as Markdown"),
+    (Command::Explain, "explain", "Explain an assembler error or warning code, e.g. W003"),
+    (Command::Lsp, "lsp", "Serve diagnostics, go-to-definition, hover and completion to an editor over stdio"),
+    (Command::GenSyntax, "gen-syntax", "Print a TextMate or tree-sitter grammar for editors' highlighting"),
+];
+
+struct Flag {
+    name: &'static str,
+    value: Option<&'static str>,
+    help: &'static str,
+    commands: &'static [Command],
+}
+
+use Command::*;
+
+const FILE_COMMANDS: &[Command] = &[Run, Check, Assemble, Disasm];
+const DEVICE_COMMANDS: &[Command] = &[Run, Repl];
+
+const FLAGS: &[Flag] = &[
+    Flag { name: "-f", value: Some("<file>"), help: "Program to load (the file can also be given without -f)", commands: FILE_COMMANDS },
+    Flag { name: "--arch", value: Some("<name>"), help: "CPU architecture to use (see below; default electron1)", commands: &[Run, Check, Assemble, Lsp] },
+    Flag { name: "-v2", value: None, help: "Short for --arch electron2", commands: &[Run, Check, Assemble, Disasm, Lsp] },
+    Flag { name: "-o", value: Some("<file>"), help: "Write the output to a file instead of stdout", commands: &[Assemble, Disasm, Isa, GenSyntax] },
+    Flag { name: "--format", value: Some("<name>"), help: "textmate (the default), tree-sitter or tree-sitter-highlights", commands: &[GenSyntax] },
+    Flag { name: "-c", value: Some("<hz>"), help: "Clock speed in Hz (default 1)", commands: &[Run] },
+    Flag { name: "--turbo", value: None, help: "Run as many clocks as possible per frame, ignoring -c", commands: &[Run] },
+    Flag { name: "--display", value: Some("<name>"), help: "Draw the LEDs in the window, the lite window, the terminal or none (default window, else lite, else terminal, as built)", commands: &[Run] },
+    Flag { name: "-nt", value: None, help: "Don't draw the terminal dashboard", commands: &[Run] },
+    Flag { name: "-fps", value: None, help: "Show the frame rate in the window", commands: &[Run] },
+    Flag { name: "--debug", value: None, help: "Step through the program from a debugger prompt (V2)", commands: &[Run] },
+    Flag { name: "--run-until-halt", value: None, help: "Run headless until HLT, then exit (V2)", commands: &[Run] },
+    Flag { name: "--exit-port", value: Some("<port>"), help: "With --run-until-halt, exit with this port's final value", commands: &[Run] },
+    Flag { name: "--max-cycles", value: Some("<n>"), help: "With --run-until-halt, give up after this many clocks (default 1000000)", commands: &[Run] },
+    Flag { name: "--bench", value: Some("<cycles>"), help: "Clock headless as fast as possible and report clocks per second", commands: &[Run] },
+    Flag { name: "--deterministic", value: None, help: "Refuse host input and print a final state hash (V2)", commands: &[Run] },
+    Flag { name: "--strict", value: None, help: "Treat every warning as an error, like a .strict line", commands: &[Run, Check, Assemble] },
+    Flag { name: "--interlock", value: None, help: "Stall on RAW hazards instead of reading stale registers (V2)", commands: &[Run] },
+    Flag { name: "--separate-stack", value: None, help: "Give the stack 16 bytes of its own instead of RAM from #15 down (V2)", commands: &[Run] },
+    Flag { name: "--stack", value: Some("<#base[,up]>"), help: "Start the stack at this address, growing down or up, over .stack (V2)", commands: &[Run] },
+    Flag { name: "--watchdog", value: Some("<clocks[,clear]>"), help: "Restart the program unless it writes %36 this often, over .watchdog (V2)", commands: &[Run] },
+    Flag { name: "--rom", value: Some("<file>"), help: "Assemble this library in front of the program, from address 0 (V2)", commands: &[Run] },
+    Flag { name: "--von-neumann", value: None, help: "Put the program in memory that STORE can rewrite (V2)", commands: &[Run] },
+    Flag { name: "--profile", value: None, help: "Print per-instruction execution counts on exit (V2)", commands: &[Run] },
+    Flag { name: "--timing", value: None, help: "Print where the clocks went (work, NOOPs, flushes, stalls) on exit (V2)", commands: &[Run] },
+    Flag { name: "--stats", value: None, help: "Print cycle and instruction counters on exit (V2)", commands: &[Run] },
+    Flag { name: "--dashboard", value: Some("<file>"), help: "Load dashboard layouts from a script (repeatable, V2)", commands: &[Run] },
+    Flag { name: "--layout", value: Some("<name>"), help: "Start on this dashboard layout (V2)", commands: &[Run] },
+    Flag { name: "--buzzer", value: Some("<port>"), help: "Play output port 0-7 as a tone", commands: &[Run] },
+    Flag { name: "--speaker", value: Some("<port:bit>"), help: "Play one bit of a port, clock by clock, as 1-bit audio (V2)", commands: &[Run] },
+    Flag { name: "--theme", value: Some("<name|file>"), help: "LED look: classic, flat, nixie or a theme file", commands: &[Run] },
+    Flag { name: "--scale", value: Some("<factor>"), help: "Draw the window this many times larger (default: from the monitor's DPI)", commands: &[Run] },
+    Flag { name: "--fullscreen", value: None, help: "Fill the screen with the LEDs alone, as F11 does", commands: &[Run] },
+    Flag { name: "--matrix", value: None, help: "Draw the ports as one 8x8 LED matrix", commands: &[Run] },
+    Flag { name: "--strobe", value: Some("<port>"), help: "With --matrix, show a new frame only when this port is written (V2)", commands: &[Run] },
+    Flag { name: "--adc", value: Some("<channels>"), help: "Add 1-4 analog inputs on ports %37 and up, set by sliders in the window (V2)", commands: &[Run] },
+    Flag { name: "--gamepad", value: None, help: "Read the gamepad's buttons on port %41, as ~/.electronrc maps them (V2)", commands: &[Run] },
+    Flag { name: "--mouse", value: None, help: "Read the pointer over the LEDs on ports %42-%43 and the buttons on %44 (V2)", commands: &[Run] },
+    Flag { name: "--uart", value: None, help: "Bridge ports %8/%9 to stdin and stdout (V2)", commands: &[Run] },
+    Flag { name: "--disk", value: Some("<image>"), help: "Attach a disk image on ports %10-%12 (V2)", commands: DEVICE_COMMANDS },
+    Flag { name: "--gpio-dir", value: Some("<dir>"), help: "Mirror the ports to files in a directory (V2)", commands: DEVICE_COMMANDS },
+    Flag { name: "--link", value: Some("<file>"), help: "Run a second program wired to this one on ports %20-%23 (V2)", commands: &[Run] },
+    Flag { name: "--compare", value: Some("<file>"), help: "Clock a second program alongside and show both LED grids", commands: &[Run] },
+    Flag { name: "--compare-arch", value: Some("<name>"), help: "Architecture of the --compare program (default: --arch)", commands: &[Run] },
+    Flag { name: "--serve", value: Some("<address>"), help: "Serve an HTTP and WebSocket API, e.g. 127.0.0.1:8080 (V2)", commands: &[Run] },
+    Flag { name: "--record", value: Some("<file>"), help: "Save every INP answer and host port read to a replay file (V2)", commands: &[Run] },
+    Flag { name: "--replay", value: Some("<file>"), help: "Feed a recorded run's inputs back in, then read live ones (V2)", commands: &[Run] },
+    Flag { name: "--dump-state", value: Some("<file>"), help: "Write the machine state after every clock as NDJSON (V2)", commands: &[Run] },
+    Flag { name: "--log-ports", value: Some("<file>"), help: "Write the output ports after every clock as CSV (V2)", commands: &[Run] },
+    Flag { name: "--seed", value: Some("<n>"), help: "Seed the random number port %13 (V2; test uses 0 by default)", commands: &[Run, Repl, Test] },
+    Flag { name: "--cycles", value: Some("<n>"), help: "Clocks to run each program for unless it halts first (default 10000)", commands: &[Test] },
+    Flag { name: "--log-level", value: Some("<filter>"), help: "Log to stderr: off, error, warn, info, debug, trace or a filter like electron=debug", commands: &[Run, Check, Assemble, Disasm, Repl, Test, Isa, Explain, Lsp, GenSyntax] },
+    Flag { name: "--help", value: None, help: "Show this help (also -h)", commands: &[Run, Check, Assemble, Disasm, Repl, Test, Isa, Explain, Lsp, GenSyntax] },
+];
+
+pub struct Options {
+    pub command: Command,
+    pub help: bool,
+    pub file_name: String,
+    pub arch: Arch,
+    pub output: Option<String>,
+    pub clock_speed: f32,
+    pub terminal_output: bool,
+    /// None for the build's default.
+    pub display: Option<Display>,
+    pub show_fps: bool,
+    pub turbo: bool,
+    pub debug: bool,
+    pub run_until_halt: bool,
+    pub exit_port: Option<u8>,
+    pub max_cycles: u64,
+    pub bench: Option<u64>,
+    pub deterministic: bool,
+    pub von_neumann: bool,
+    pub interlock: bool,
+    pub strict: bool,
+    pub separate_stack: bool,
+    pub stack: Option<StackLayout>,
+    pub watchdog: Option<Watchdog>,
+    pub rom_file: Option<String>,
+    pub profile: bool,
+    pub timing: bool,
+    pub show_stats: bool,
+    pub dashboards: Vec<String>,
+    pub layout_name: Option<String>,
+    pub buzzer_port: Option<usize>,
+    pub speaker: Option<(u8, u8)>,
+    pub uart: bool,
+    pub disk_path: Option<String>,
+    pub gpio_dir: Option<String>,
+    pub seed: Option<u64>,
+    pub link_file: Option<String>,
+    pub compare_file: Option<String>,
+    pub compare_arch: Option<Arch>,
+    pub serve: Option<String>,
+    pub theme: Option<String>,
+    /// `--scale`: None to go by the monitor's DPI.
+    pub scale: Option<f32>,
+    pub fullscreen: bool,
+    pub matrix: bool,
+    pub strobe_port: Option<u8>,
+    pub adc_channels: Option<usize>,
+    pub gamepad: bool,
+    pub mouse: bool,
+    pub record_file: Option<String>,
+    pub replay_file: Option<String>,
+    pub dump_state: Option<String>,
+    pub log_ports: Option<String>,
+    pub log_level: Option<String>,
+    /// The directory given to `test`.
+    pub test_dir: String,
+    /// The code given to `explain`; empty to list them all.
+    pub explain_code: String,
+    pub test_cycles: u64,
+    /// What `gen-syntax` prints.
+    pub syntax_format: Format,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            command: Command::Run,
+            help: false,
+            file_name: String::new(),
+            arch: Arch::Electron1,
+            output: None,
+            clock_speed: 1.0,
+            terminal_output: true,
+            display: None,
+            show_fps: false,
+            turbo: false,
+            debug: false,
+            run_until_halt: false,
+            exit_port: None,
+            max_cycles: 1_000_000,
+            bench: None,
+            deterministic: false,
+            von_neumann: false,
+            interlock: false,
+            strict: false,
+            separate_stack: false,
+            stack: None,
+            watchdog: None,
+            rom_file: None,
+            profile: false,
+            timing: false,
+            show_stats: false,
+            dashboards: Vec::new(),
+            layout_name: None,
+            buzzer_port: None,
+            speaker: None,
+            uart: false,
+            disk_path: None,
+            gpio_dir: None,
+            seed: None,
+            link_file: None,
+            compare_file: None,
+            compare_arch: None,
+            serve: None,
+            theme: None,
+            scale: None,
+            fullscreen: false,
+            matrix: false,
+            strobe_port: None,
+            adc_channels: None,
+            gamepad: false,
+            mouse: false,
+            record_file: None,
+            replay_file: None,
+            dump_state: None,
+            log_ports: None,
+            log_level: None,
+            test_dir: String::new(),
+            explain_code: String::new(),
+            test_cycles: 10_000,
+            syntax_format: Format::TextMate,
+        }
+    }
+}
+
+/// Parses the arguments after the program name.
+pub fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut rest = args;
+    if let Some(first) = args.first() {
+        if let Some((command, _, _)) = COMMANDS.iter().find(|(_, name, _)| name == first) {
+            options.command = *command;
+            rest = &args[1..];
+        }
+    }
+    let command = options.command;
+
+    let mut i = 0;
+    while i < rest.len() {
+        let arg = rest[i].as_str();
+        i += 1;
+        if arg == "-h" || arg == "--help" {
+            options.help = true;
+            continue;
+        }
+        if !arg.starts_with('-') {
+            if command == Test {
+                if !options.test_dir.is_empty() {
+                    return Err(format!("Only one test directory can be given (got \"{}\" and \"{arg}\")", options.test_dir));
+                }
+                options.test_dir = arg.to_string();
+                continue;
+            }
+            if command == Explain {
+                if !options.explain_code.is_empty() {
+                    return Err(format!("Only one code can be given (got \"{}\" and \"{arg}\")", options.explain_code));
+                }
+                options.explain_code = arg.to_string();
+                continue;
+            }
+            if !FILE_COMMANDS.contains(&command) {
+                return Err(format!("`{}` doesn't take a file (got \"{arg}\")", command_name(command)));
+            }
+            if !options.file_name.is_empty() {
+                return Err(format!("Only one program file can be given (got \"{}\" and \"{arg}\")", options.file_name));
+            }
+            options.file_name = arg.to_string();
+            continue;
+        }
+
+        let flag = FLAGS.iter().find(|flag| flag.name == arg).ok_or(format!("Unknown option {arg}"))?;
+        if !flag.commands.contains(&command) {
+            return Err(format!("{arg} can't be used with `{}`", command_name(command)));
+        }
+        let value = match flag.value {
+            Some(placeholder) => {
+                let value = rest.get(i).ok_or(format!("{arg} needs a value: {arg} {placeholder}"))?;
+                i += 1;
+                value.as_str()
+            }
+            None => "",
+        };
+
+        match arg {
+            "-f" => options.file_name = value.to_string(),
+            "--arch" => options.arch = parse_arch(value)?,
+            "-v2" => options.arch = Arch::Electron2,
+            "-o" => options.output = Some(value.to_string()),
+            "--format" => {
+                options.syntax_format = Format::from_name(value).ok_or(format!(
+                    "Unknown syntax format \"{value}\" (choose from {})",
+                    Format::NAMES.join(", ")
+                ))?
+            }
+            "-c" => {
+                options.clock_speed = match value.parse::<f32>() {
+                    Ok(hz) if hz > 0.0 && hz.is_finite() => hz,
+                    _ => return Err(format!("-c takes a clock speed in Hz greater than 0 (got \"{value}\")")),
+                }
+            }
+            "--display" => {
+                options.display = Some(Display::from_name(value).ok_or(format!(
+                    "Unknown display \"{value}\" (choose from {})",
+                    Display::NAMES.join(", ")
+                ))?)
+            }
+            "-nt" => options.terminal_output = false,
+            "-fps" => options.show_fps = true,
+            "--turbo" => options.turbo = true,
+            "--debug" => options.debug = true,
+            "--run-until-halt" => options.run_until_halt = true,
+            "--exit-port" => {
+                options.exit_port = Some(
+                    value.parse::<u8>().map_err(|_| format!("--exit-port takes a port number (0-255) (got \"{value}\")"))?,
+                )
+            }
+            "--max-cycles" => {
+                options.max_cycles = match value.parse::<u64>() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(format!("--max-cycles takes a positive whole number (got \"{value}\")")),
+                }
+            }
+            "--bench" => {
+                options.bench = match value.parse::<u64>() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => return Err(format!("--bench takes a positive whole number (got \"{value}\")")),
+                }
+            }
+            "--deterministic" => options.deterministic = true,
+            "--von-neumann" => options.von_neumann = true,
+            "--interlock" => options.interlock = true,
+            "--strict" => options.strict = true,
+            "--separate-stack" => options.separate_stack = true,
+            "--stack" => options.stack = Some(StackLayout::parse(value).map_err(|error| format!("--stack: {error}"))?),
+            "--watchdog" => options.watchdog = Some(Watchdog::parse(value).map_err(|error| format!("--watchdog: {error}"))?),
+            "--rom" => options.rom_file = Some(value.to_string()),
+            "--profile" => options.profile = true,
+            "--timing" => options.timing = true,
+            "--stats" => options.show_stats = true,
+            "--dashboard" => options.dashboards.push(value.to_string()),
+            "--layout" => options.layout_name = Some(value.to_string()),
+            "--buzzer" => {
+                options.buzzer_port = match value.parse::<usize>() {
+                    Ok(port) if port < 8 => Some(port),
+                    _ => return Err(format!("--buzzer takes an output port number (0-7) (got \"{value}\")")),
+                }
+            }
+            "--speaker" => {
+                let parsed = value.split_once(':').and_then(|(port, bit)| Some((port.trim_start_matches('%').parse::<u8>().ok()?, bit.parse::<u8>().ok()?)));
+                options.speaker = match parsed {
+                    Some((port, bit)) if bit < 8 => Some((port, bit)),
+                    _ => return Err(format!("--speaker takes a port and a bit (0-7), like 7:0 (got \"{value}\")")),
+                }
+            }
+            "--uart" => options.uart = true,
+            "--gamepad" => options.gamepad = true,
+            "--mouse" => options.mouse = true,
+            "--disk" => options.disk_path = Some(value.to_string()),
+            "--link" => options.link_file = Some(value.to_string()),
+            "--compare" => options.compare_file = Some(value.to_string()),
+            "--compare-arch" => options.compare_arch = Some(parse_arch(value)?),
+            "--serve" => options.serve = Some(value.to_string()),
+            "--theme" => options.theme = Some(value.to_string()),
+            "--scale" => {
+                options.scale = match value.parse::<f32>() {
+                    Ok(scale) if (0.5..=4.0).contains(&scale) => Some(scale),
+                    _ => return Err(format!("--scale takes a factor from 0.5 to 4, like 1.5 (got \"{value}\")")),
+                }
+            }
+            "--fullscreen" => options.fullscreen = true,
+            "--matrix" => options.matrix = true,
+            "--strobe" => {
+                options.strobe_port = match value.parse::<u8>() {
+                    Ok(port) if port >= 8 => Some(port),
+                    _ => return Err(format!("--strobe takes a port number from 8 to 255, outside the display ports (got \"{value}\")")),
+                }
+            }
+            "--adc" => {
+                options.adc_channels = match value.parse::<usize>() {
+                    Ok(channels) if (1..=MAX_CHANNELS).contains(&channels) => Some(channels),
+                    _ => return Err(format!("--adc takes a number of channels from 1 to {MAX_CHANNELS} (got \"{value}\")")),
+                }
+            }
+            "--record" => options.record_file = Some(value.to_string()),
+            "--replay" => options.replay_file = Some(value.to_string()),
+            "--dump-state" => options.dump_state = Some(value.to_string()),
+            "--log-ports" => options.log_ports = Some(value.to_string()),
+            "--log-level" => options.log_level = Some(value.to_string()),
+            "--gpio-dir" => options.gpio_dir = Some(value.to_string()),
+            "--seed" => {
+                options.seed = Some(
+                    value.parse::<u64>().map_err(|_| format!("--seed takes a non-negative whole number (got \"{value}\")"))?,
+                )
+            }
+            "--cycles" => {
+                options.test_cycles = match value.parse::<u64>() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(format!("--cycles takes a positive whole number (got \"{value}\")")),
+                }
+            }
+            _ => unreachable!("{arg} is in FLAGS but not handled"),
+        }
+    }
+
+    if !options.run_until_halt && (options.exit_port.is_some() || options.max_cycles != Options::default().max_cycles) {
+        return Err("--exit-port and --max-cycles only apply with --run-until-halt".to_string());
+    }
+    if options.compare_arch.is_some() && options.compare_file.is_none() {
+        return Err("--compare-arch only applies with --compare".to_string());
+    }
+    // `run` without one asks for it in a window.
+    if !options.help && FILE_COMMANDS.contains(&command) && command != Run && options.file_name.is_empty() {
+        return Err("No file name given. Use -f <filename>".to_string());
+    }
+    if !options.help && command == Test && options.test_dir.is_empty() {
+        return Err("No test directory given. Use electron test <dir>".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_arch(name: &str) -> Result<Arch, String> {
+    Arch::from_name(name).ok_or(format!(
+        "Unknown architecture \"{name}\" (choose from {})",
+        ARCHITECTURES.iter().map(|info| info.arch.name()).collect::<Vec<_>>().join(", ")
+    ))
+}
+
+fn command_name(command: Command) -> &'static str {
+    COMMANDS.iter().find(|(c, _, _)| *c == command).map(|(_, name, _)| *name).unwrap_or("run")
+}
+
+/// Help for one subcommand, or the overview when `command` is `Run` (the default).
+pub fn usage(command: Command) -> String {
+    let mut out = String::new();
+    if command == Command::Run {
+        out.push_str("Usage: electron [command] [options] [file]\n\nCommands:\n");
+        for (_, name, help) in COMMANDS {
+            out.push_str(&format!("  {name:<10} {help}\n"));
+        }
+        out.push_str("\nRun `electron <command> --help` for that command's options.\n\nOptions for run:\n");
+    } else {
+        let name = command_name(command);
+        let file = match command {
+            Test => " <dir>",
+            Explain => " [code]",
+            _ if FILE_COMMANDS.contains(&command) => " [file]",
+            _ => "",
+        };
+        out.push_str(&format!("Usage: electron {name} [options]{file}\n\nOptions:\n"));
+    }
+    for flag in FLAGS.iter().filter(|flag| flag.commands.contains(&command)) {
+        let spelled = match flag.value {
+            Some(value) => format!("{} {}", flag.name, value),
+            None => flag.name.to_string(),
+        };
+        out.push_str(&format!("  {spelled:<20} {}\n", flag.help));
+    }
+    if FLAGS.iter().any(|flag| flag.name == "--arch" && flag.commands.contains(&command)) {
+        out.push_str("\nArchitectures:\n");
+        for info in ARCHITECTURES {
+            out.push_str(&format!("  {:<20} {}\n", info.names.join(", "), info.description));
+        }
+    }
+    out
+}
@@ -0,0 +1,82 @@
+//! `--compare <file>`: a second program clocked together with the main one, so the two LED grids
+//! can be watched side by side — before and after an optimization, say, or the same program on
+//! V1 and V2 (`--compare-arch`). Nothing reaches it but the clock and Reset: it has no devices,
+//! and the window's keys and the dashboard work the main program.
+
+use crate::arch::Arch;
+use crate::electron_2::{Emulator as EmulatorV2, StackLayout, Watchdog};
+use crate::emulator::Emulator;
+use crate::parser::ProgramLoader;
+
+enum Core {
+    V1(Emulator),
+    V2(Box<EmulatorV2>),
+}
+
+pub struct Comparison {
+    /// The file name, for the caption over its grid.
+    pub name: String,
+    core: Core,
+}
+
+impl Comparison {
+    /// Loads `path` for `arch`. A V2 program gets the main program's pipeline settings, so the
+    /// two differ only in their code.
+    pub fn load(path: &str, arch: Arch, von_neumann: bool, interlock: bool, separate_stack: bool, stack: Option<StackLayout>, watchdog: Option<Watchdog>) -> Result<Self, String> {
+        let core = match arch {
+            // Parse errors come back as "Error on line N: ..." and file errors already name the file.
+            Arch::Electron1 => Core::V1(Emulator::new(ProgramLoader::reload_program(path, false).map_err(|error| match error.strip_prefix("Error on line") {
+                Some(rest) => format!("{path}: line{rest}"),
+                None => error,
+            })?)),
+            Arch::Electron2 => {
+                let code = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+                let mut emulator = EmulatorV2::new(code);
+                if let Some(error) = emulator.errors.first() {
+                    return Err(format!("{path}: {error}"));
+                }
+                emulator.stack_override = stack;
+                emulator.watchdog_override = watchdog;
+                emulator.set_von_neumann(von_neumann);
+                emulator.interlock = interlock;
+                emulator.separate_stack = separate_stack;
+                Core::V2(Box::new(emulator))
+            }
+        };
+        Ok(Self { name: path.to_string(), core })
+    }
+
+    /// One clock, in step with the main program's. A halted V2 program stays halted.
+    pub fn clock(&mut self) {
+        match &mut self.core {
+            Core::V1(emulator) => emulator.clock(),
+            Core::V2(emulator) => emulator.clock(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        match &mut self.core {
+            Core::V1(emulator) => emulator.reset(),
+            Core::V2(emulator) => emulator.reset(),
+        }
+    }
+
+    pub fn ports(&self) -> [u8; 8] {
+        match &self.core {
+            Core::V1(emulator) => emulator.ports.out,
+            Core::V2(emulator) => emulator.ports_out,
+        }
+    }
+
+    /// Why the program can't keep up with the main one, if it can't.
+    pub fn problem(&self) -> Option<String> {
+        let Core::V2(emulator) = &self.core else { return None };
+        if let Some(fault) = &emulator.fault {
+            return Some(format!("{}: {fault}", self.name));
+        }
+        if emulator.waiting_for_input {
+            return Some(format!("{}: INP waits for a typed value, but only the main program can ask for one.", self.name));
+        }
+        None
+    }
+}
@@ -0,0 +1,175 @@
+//! Scripted terminal dashboards. A layout script describes alternative screens built from the
+//! emulator's snapshot query API, one widget per line:
+//!
+//! ```text
+//! layout thermometer          ; starts a new layout, selectable by name
+//! title "Boiler"
+//! bar %3 255 "Temperature"    ; horizontal bar from 0 to 255
+//! value R1 dec "Counter"      ; dec, hex or bin
+//! leds %0 "Status"            ; the 8 bits of a value as LEDs
+//! text "Press L to switch layouts"
+//! blank
+//! ```
+//!
+//! Values are named the way operands are written (`R1`, `#3`, `%0`) or as `ACC`, `PC`, `SP` and
+//! the flag names; see `StateField`.
+
+use crate::electron_2::{Snapshot, StateField};
+
+const BAR_WIDTH: i32 = 32;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Dec,
+    Hex,
+    Bin,
+}
+
+enum Widget {
+    Title(String),
+    Text(String),
+    Blank,
+    Value { field: StateField, format: Format, label: String },
+    Bar { field: StateField, max: i32, label: String },
+    Leds { field: StateField, label: String },
+}
+
+pub struct Layout {
+    pub name: String,
+    widgets: Vec<Widget>,
+}
+
+impl Layout {
+    pub fn load(path: &str) -> Result<Vec<Layout>, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        Self::parse(&source).map_err(|e| format!("{path}: {e}"))
+    }
+
+    pub fn parse(source: &str) -> Result<Vec<Layout>, String> {
+        let mut layouts: Vec<Layout> = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let tokens = tokenize(line.split(';').next().unwrap_or(""))
+                .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+            if tokens.is_empty() {
+                continue;
+            }
+            if tokens[0] == "layout" {
+                let name = tokens.get(1).ok_or(format!("Line {}: layout needs a name", i + 1))?;
+                layouts.push(Layout { name: name.clone(), widgets: Vec::new() });
+                continue;
+            }
+            let widget = parse_widget(&tokens).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+            match layouts.last_mut() {
+                Some(layout) => layout.widgets.push(widget),
+                None => return Err(format!("Line {}: widgets must come after a `layout <name>` line", i + 1)),
+            }
+        }
+        if layouts.is_empty() {
+            return Err("no layouts defined".to_string());
+        }
+        Ok(layouts)
+    }
+
+    pub fn render(&self, snapshot: &Snapshot) -> String {
+        let mut out = String::new();
+        for widget in &self.widgets {
+            let line = match widget {
+                Widget::Title(text) => format!("▓▓▓▒▒▒░░░ {} ░░░▒▒▒▓▓▓", text),
+                Widget::Text(text) => text.clone(),
+                Widget::Blank => String::new(),
+                Widget::Value { field, format, label } => {
+                    let value = snapshot.get(*field);
+                    let text = match format {
+                        Format::Dec => value.to_string(),
+                        Format::Hex => format!("0x{:02X}", value),
+                        Format::Bin => format!("{:08b}", value),
+                    };
+                    format!("{label}: {text}")
+                }
+                Widget::Bar { field, max, label } => {
+                    let value = snapshot.get(*field);
+                    let filled = (value.clamp(0, *max) * BAR_WIDTH / max) as usize;
+                    format!(
+                        "{label}: {}{} {value}",
+                        "▓".repeat(filled),
+                        "░".repeat(BAR_WIDTH as usize - filled)
+                    )
+                }
+                Widget::Leds { field, label } => {
+                    let bits = format!("{:08b}", snapshot.get(*field) & 0xFF);
+                    let leds: String = bits.chars().map(|b| if b == '1' { "▓▓" } else { "░░" }).collect();
+                    format!("{label}: {leds}")
+                }
+            };
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn parse_widget(tokens: &[String]) -> Result<Widget, String> {
+    let field = |i: usize| -> Result<StateField, String> {
+        let name = tokens.get(i).ok_or(format!("{} needs a value to show", tokens[0]))?;
+        StateField::parse(name).ok_or(format!("\"{name}\" is not a register, RAM address, port or flag"))
+    };
+    // Labels default to the field as written.
+    let label = |i: usize| tokens.get(i).cloned().unwrap_or_else(|| tokens[1].clone());
+    match tokens[0].as_str() {
+        "title" => Ok(Widget::Title(tokens[1..].join(" "))),
+        "text" => Ok(Widget::Text(tokens[1..].join(" "))),
+        "blank" => Ok(Widget::Blank),
+        "value" => {
+            let (format, label_at) = match tokens.get(2).map(|t| t.as_str()) {
+                Some("dec") => (Format::Dec, 3),
+                Some("hex") => (Format::Hex, 3),
+                Some("bin") => (Format::Bin, 3),
+                _ => (Format::Dec, 2),
+            };
+            Ok(Widget::Value { field: field(1)?, format, label: label(label_at) })
+        }
+        "bar" => {
+            let (max, label_at) = match tokens.get(2).and_then(|t| t.parse::<i32>().ok()) {
+                Some(max) if max > 0 => (max, 3),
+                Some(_) => return Err("bar maximum must be positive".to_string()),
+                None => (255, 2),
+            };
+            Ok(Widget::Bar { field: field(1)?, max, label: label(label_at) })
+        }
+        "leds" => Ok(Widget::Leds { field: field(1)?, label: label(2) }),
+        other => Err(format!("unknown widget \"{other}\" (expected title, text, blank, value, bar or leds)")),
+    }
+}
+
+/// Splits on whitespace, keeping "double quoted" strings together.
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => text.push(c),
+                    None => return Err("unterminated string".to_string()),
+                }
+            }
+            tokens.push(text);
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(word);
+        }
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,401 @@
+//! Terminal debugger for the V2 emulator (`--debug`). Commands are read with line editing and
+//! persistent history (`~/.electron_history`); `~/.electronrc` runs before the first prompt, so
+//! breakpoints, aliases and devices can be set up once instead of every session.
+
+use crate::devices;
+use crate::electron_2::disassembler::Disassembler;
+use crate::electron_2::{Checkpoint, Emulator as EmulatorV2, SourceLocation};
+use crate::gamepad;
+use crate::line_editor::{home_file, LineEditor};
+use std::collections::{BTreeMap, VecDeque};
+
+/// `continue` gives up after this many clocks if nothing stops it.
+const CONTINUE_LIMIT: u64 = 100_000;
+/// How many clocks `back` can undo.
+const REWIND_LIMIT: usize = 10_000;
+
+const HELP: &str = "\
+Commands:
+  step [n]            (s)  clock n times (default 1)
+  continue [n]        (c)  run until a breakpoint, INP or fault (at most n clocks)
+  back [n]           (rs)  undo the last n clocks (default 1; devices are not rewound)
+  break <addr|label>  (b)  stop when that instruction reaches EXECUTE
+  delete <addr|label|all>  remove breakpoints
+  breaks                   list breakpoints
+  input <value>            answer a waiting INP
+  regs | ram | ports       show part of the machine state
+  stats                    cycle, retired, branch and flush counters
+  interlock                turn stalling on RAW hazards on or off
+  list [n]            (l)  disassemble n instructions either side of EXECUTE
+  source                   the source line in each pipeline stage
+  show                     show the full dashboard
+  attach disk <image>      attach a disk device
+  reset                    reload the program (breakpoints and devices are kept)
+  alias [name [command]]   list, show or define aliases
+  unalias <name>           remove an alias
+  help                     this text
+  quit                (q)  leave the debugger";
+
+pub struct Debugger {
+    emulator: EmulatorV2,
+    code: String,
+    aliases: BTreeMap<String, String>,
+    /// The core as it was before each recent clock, oldest first.
+    history: VecDeque<Checkpoint>,
+}
+
+enum Flow {
+    Continue,
+    Quit,
+}
+
+impl Debugger {
+    pub fn new(emulator: EmulatorV2, code: String) -> Self {
+        Self { emulator, code, aliases: BTreeMap::new(), history: VecDeque::new() }
+    }
+
+    pub fn into_emulator(self) -> EmulatorV2 {
+        self.emulator
+    }
+
+    pub fn run(&mut self) {
+        println!("Electron 2 debugger. Type `help` for commands.");
+        if let Some(rc) = home_file(".electronrc") {
+            if let Ok(script) = std::fs::read_to_string(&rc) {
+                for line in script.lines() {
+                    let command = line.trim();
+                    // `gamepad` lines set up `--gamepad` rather than the debugger.
+                    if command.is_empty() || command.starts_with('#') || command.starts_with(';') || gamepad::is_config(command) {
+                        continue;
+                    }
+                    if let Flow::Quit = self.execute(command) {
+                        return;
+                    }
+                }
+            }
+        }
+
+        let mut editor = LineEditor::new(home_file(".electron_history"));
+        while let Some(line) = editor.read_line("(electron) ") {
+            editor.add_history(&line);
+            if let Flow::Quit = self.execute(&line) {
+                break;
+            }
+        }
+    }
+
+    fn execute(&mut self, line: &str) -> Flow {
+        let line = self.expand_alias(line.trim());
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some(&command) = words.first() else { return Flow::Continue };
+        let arg = words.get(1).copied();
+
+        match command {
+            "help" | "h" | "?" => println!("{HELP}"),
+            "quit" | "q" | "exit" => return Flow::Quit,
+            "step" | "s" => match parse_count(arg, 1) {
+                Ok(n) => {
+                    for _ in 0..n {
+                        if !self.clock() {
+                            break;
+                        }
+                    }
+                    self.print_status();
+                }
+                Err(e) => println!("Error: {e}"),
+            },
+            "continue" | "c" => match parse_count(arg, CONTINUE_LIMIT) {
+                Ok(n) => {
+                    let mut ran = 0;
+                    while ran < n {
+                        ran += 1;
+                        if !self.clock() {
+                            break;
+                        }
+                        if self.emulator.at_breakpoint() {
+                            println!("Breakpoint at address {}.", self.emulator.execute_reg.address);
+                            break;
+                        }
+                    }
+                    self.print_status();
+                }
+                Err(e) => println!("Error: {e}"),
+            },
+            "back" | "rs" => match parse_count(arg, 1) {
+                Ok(n) => {
+                    let steps = (n as usize).min(self.history.len());
+                    if steps == 0 {
+                        println!("Nothing to rewind.");
+                    } else {
+                        let checkpoint = self.history.drain(self.history.len() - steps..).next().unwrap();
+                        self.emulator.restore(&checkpoint);
+                        println!("Rewound {steps} clocks ({} more available).", self.history.len());
+                        self.print_status();
+                    }
+                }
+                Err(e) => println!("Error: {e}"),
+            },
+            "break" | "b" => match arg.map(|a| self.resolve_address(a)) {
+                Some(Ok(address)) => {
+                    self.emulator.breakpoints.insert(address);
+                    println!("Breakpoint set at address {address}.");
+                }
+                Some(Err(e)) => println!("Error: {e}"),
+                None => println!("Error: break needs an address or label."),
+            },
+            "delete" | "d" => match arg {
+                Some("all") => {
+                    self.emulator.breakpoints.clear();
+                    println!("All breakpoints removed.");
+                }
+                Some(a) => match self.resolve_address(a) {
+                    Ok(address) if self.emulator.breakpoints.remove(&address) => {
+                        println!("Breakpoint at address {address} removed.")
+                    }
+                    Ok(address) => println!("No breakpoint at address {address}."),
+                    Err(e) => println!("Error: {e}"),
+                },
+                None => println!("Error: delete needs an address, label or `all`."),
+            },
+            "breaks" => {
+                if self.emulator.breakpoints.is_empty() {
+                    println!("No breakpoints.");
+                }
+                for address in &self.emulator.breakpoints {
+                    println!("  {address}");
+                }
+            }
+            "input" => match arg.map(|a| a.parse::<u8>()) {
+                Some(Ok(value)) if self.emulator.waiting_for_input => {
+                    self.emulator.resolve_input(value as i32);
+                    println!("INP resolved with {value}.");
+                }
+                Some(Ok(_)) => println!("The program is not waiting for input."),
+                _ => println!("Error: input needs a value between 0 and 255."),
+            },
+            "regs" => print_registers(&self.emulator),
+            "ram" => print_ram(&self.emulator),
+            "ports" => print_ports(&self.emulator),
+            "list" | "l" => match parse_count(arg, 5) {
+                Ok(radius) => self.list(radius as i32),
+                Err(e) => println!("Error: {e}"),
+            },
+            "source" => self.print_sources(),
+            "interlock" => println!("{}", crate::toggle_interlock(&mut self.emulator)),
+            "stats" => println!("{}", crate::format_stats_v2(&self.emulator)),
+            "show" => println!("{}", crate::tui::snapshot_v2(&self.emulator)),
+            "attach" => match (arg, words.get(2)) {
+                (Some("disk"), Some(path)) => match devices::Disk::open(path) {
+                    Ok(disk) => {
+                        self.emulator.devices.push(Box::new(disk));
+                        println!("Disk {path} attached on ports %10-%12.");
+                    }
+                    Err(e) => println!("Error: {e}"),
+                },
+                (Some("uart"), _) => println!("Error: The UART needs stdin, which the debugger console is using."),
+                _ => println!("Error: usage: attach disk <image>"),
+            },
+            "reset" => {
+                // Assembled without errors when the debugger started.
+                let _ = self.emulator.load_program(self.code.clone());
+                self.history.clear();
+                println!("Program reloaded.");
+            }
+            "alias" => match arg {
+                None => {
+                    for (name, expansion) in &self.aliases {
+                        println!("  {name} = {expansion}");
+                    }
+                }
+                Some(name) if words.len() == 2 => match self.aliases.get(name) {
+                    Some(expansion) => println!("  {name} = {expansion}"),
+                    None => println!("No alias named {name}."),
+                },
+                Some(name) => {
+                    self.aliases.insert(name.to_string(), words[2..].join(" "));
+                }
+            },
+            "unalias" => match arg {
+                Some(name) if self.aliases.remove(name).is_some() => {}
+                Some(name) => println!("No alias named {name}."),
+                None => println!("Error: unalias needs a name."),
+            },
+            other => println!("Unknown command \"{other}\". Type `help` for commands."),
+        }
+        Flow::Continue
+    }
+
+    /// Replaces a leading alias with its expansion, keeping any extra arguments.
+    fn expand_alias(&self, line: &str) -> String {
+        let (first, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match self.aliases.get(first) {
+            Some(expansion) if rest.is_empty() => expansion.clone(),
+            Some(expansion) => format!("{expansion} {rest}"),
+            None => line.to_string(),
+        }
+    }
+
+    fn resolve_address(&self, arg: &str) -> Result<i32, String> {
+        if let Ok(address) = arg.parse::<i32>() {
+            return Ok(address);
+        }
+        self.emulator
+            .labels
+            .get(arg)
+            .or_else(|| self.emulator.labels.get(&arg.to_uppercase()))
+            .copied()
+            .ok_or(format!("\"{arg}\" is not an address or a known label"))
+    }
+
+    /// Clocks once. Returns false (and says why) when the emulator can't make progress.
+    fn clock(&mut self) -> bool {
+        if let Some(fault) = &self.emulator.fault {
+            println!("Error: {fault}");
+            return false;
+        }
+        if self.emulator.waiting_for_input {
+            println!("Waiting for INP; answer with `input <value>`.");
+            return false;
+        }
+        if self.emulator.halted {
+            println!("The program has halted; `reset` to run it again.");
+            return false;
+        }
+        if self.history.len() == REWIND_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.emulator.checkpoint());
+        self.emulator.clock();
+        if let Some(fault) = &self.emulator.fault {
+            println!("Error: {fault}");
+            return false;
+        }
+        true
+    }
+
+    /// Disassembly around the instruction in EXECUTE (or the PC before anything has run), with
+    /// `>` on that instruction and `*` on breakpoints.
+    fn list(&self, radius: i32) {
+        let execute = self.emulator.execute_reg.address;
+        let center = if execute >= 0 { execute } else { self.emulator.pc };
+        let names: BTreeMap<i32, &String> = self.emulator.labels.iter().map(|(name, &addr)| (addr, name)).collect();
+        let start = (center - radius).max(0);
+        let end = (center + radius).min(self.emulator.instructions.len() as i32 - 1);
+        for address in start..=end {
+            if let Some(name) = names.get(&address) {
+                println!("       {name}:");
+            }
+            let marker = if address == execute { '>' } else { ' ' };
+            let breakpoint = if self.emulator.breakpoints.contains(&address) { '*' } else { ' ' };
+            // Under --von-neumann this is the code as it is now, not as it was loaded.
+            let text = match self.emulator.instruction_at(address) {
+                Ok(Some(instr)) => Disassembler::instruction(&instr),
+                Ok(None) => continue,
+                Err(error) => error,
+            };
+            println!("{marker}{breakpoint} {address:>3}  {text}");
+        }
+    }
+
+    fn print_status(&self) {
+        let stage = |instr: &crate::electron_2::Instruction| {
+            if instr.address < 0 {
+                "-".to_string()
+            } else {
+                format!("{}:{}", instr.address, instr.operation.get_name())
+            }
+        };
+        println!(
+            "clock {}  PC {}  F {}  D {}  E {}  W {}",
+            self.emulator.stats.cycles,
+            self.emulator.pc,
+            stage(&self.emulator.fetch_reg),
+            stage(&self.emulator.decode_reg),
+            stage(&self.emulator.execute_reg),
+            stage(&self.emulator.writeback_reg),
+        );
+        if let Some(source) = self.emulator.source_at(self.emulator.execute_reg.address) {
+            println!("  {:>3} | {}{}", source.source_line, source.text, generated_note(source));
+        }
+    }
+
+    fn print_sources(&self) {
+        let stages = [
+            ('F', &self.emulator.fetch_reg),
+            ('D', &self.emulator.decode_reg),
+            ('E', &self.emulator.execute_reg),
+            ('W', &self.emulator.writeback_reg),
+        ];
+        for (name, instr) in stages {
+            match self.emulator.source_at(instr.address) {
+                Some(source) => println!(
+                    "  {name} {:>3}  {}:{}  {}{}",
+                    instr.address,
+                    source.file,
+                    source.source_line,
+                    source.text,
+                    generated_note(source)
+                ),
+                None => println!("  {name}   -"),
+            }
+        }
+    }
+}
+
+fn generated_note(source: &SourceLocation) -> &'static str {
+    if source.generated {
+        "  (NOOP from .autonop)"
+    } else {
+        ""
+    }
+}
+
+pub fn print_registers(emulator: &EmulatorV2) {
+    let regs: Vec<String> = (0..8).map(|i| format!("R{}={}", i, emulator.registers.read(i))).collect();
+    println!("{}  ACC={}  PC={}  SP={}", regs.join(" "), emulator.alu.accumulator, emulator.pc, emulator.sp);
+    let flags = &emulator.alu.flags;
+    println!("Equals={} Greater={} Less={} Overflow={}", flags.equals, flags.greater, flags.less, flags.overflow);
+}
+
+/// RAM as two rows of eight, with `<` after the cell the stack pointer names. Under
+/// `--separate-stack` the stack follows in the same form, and with another bank selected, so
+/// does that bank.
+pub fn print_ram(emulator: &EmulatorV2) {
+    if emulator.separate_stack {
+        print_memory(&emulator.ram, None);
+        println!("Stack:");
+        print_memory(&emulator.stack, Some(emulator.sp));
+    } else {
+        print_memory(&emulator.ram, Some(emulator.sp));
+    }
+    if emulator.bank != 0 {
+        println!("Bank {}:", emulator.bank);
+        print_memory(&emulator.banks[emulator.bank as usize - 1], None);
+    }
+}
+
+fn print_memory(memory: &[u8; 16], sp: Option<i32>) {
+    for row in 0..2 {
+        let cells: Vec<String> = (0..8)
+            .map(|col| {
+                let addr = row * 8 + col;
+                let marker = if sp == Some(addr as i32) { "<" } else { " " };
+                format!("#{:02}={:3}{}", addr, memory[addr], marker)
+            })
+            .collect();
+        println!("{}", cells.join(" "));
+    }
+}
+
+pub fn print_ports(emulator: &EmulatorV2) {
+    for (port, value) in emulator.ports_out.iter().enumerate() {
+        println!("%{port}: {value:3}  {value:08b}");
+    }
+}
+
+fn parse_count(arg: Option<&str>, default: u64) -> Result<u64, String> {
+    match arg {
+        None => Ok(default),
+        Some(a) => a.parse::<u64>().map_err(|_| format!("\"{a}\" is not a number")),
+    }
+}
@@ -0,0 +1,37 @@
+use crate::electron_2::Device;
+use std::sync::{Arc, Mutex};
+
+/// Channel 0 is read from this port, channel 1 from the next, and so on.
+pub const FIRST_PORT: u8 = 37;
+pub const MAX_CHANNELS: usize = 4;
+
+/// `--adc`: analog inputs, like knobs wired to a converter. Each channel reads as the 0-255
+/// value of its slider in the window, whenever the program reads it, so it never waits the way
+/// `INP` does. The values come from the user, so deterministic mode refuses them.
+pub struct Adc {
+    channels: u8,
+    values: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Adc {
+    /// The device, and its channels' values for the window's sliders to set. They start at 0.
+    pub fn new(channels: usize) -> (Self, Arc<Mutex<Vec<u8>>>) {
+        let channels = channels.min(MAX_CHANNELS);
+        let values = Arc::new(Mutex::new(vec![0; channels]));
+        (Self { channels: channels as u8, values: values.clone() }, values)
+    }
+}
+
+impl Device for Adc {
+    fn claims(&self, port: u8) -> bool {
+        (FIRST_PORT..FIRST_PORT + self.channels).contains(&port)
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        self.values.lock().unwrap()[(port - FIRST_PORT) as usize]
+    }
+
+    fn host_dependent(&self) -> bool {
+        true
+    }
+}
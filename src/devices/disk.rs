@@ -0,0 +1,107 @@
+use crate::electron_2::Device;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+pub const COMMAND_PORT: u8 = 10;
+pub const SECTOR_PORT: u8 = 11;
+pub const DATA_PORT: u8 = 12;
+
+pub const SECTOR_SIZE: usize = 16;
+
+/// Commands written to `COMMAND_PORT`.
+pub const CMD_READ: u8 = 1;
+pub const CMD_WRITE: u8 = 2;
+
+/// Status bits read from `COMMAND_PORT`.
+pub const STATUS_READY: u8 = 0b01;
+pub const STATUS_ERROR: u8 = 0b10;
+
+/// Block storage backed by a host file: 256 sectors of `SECTOR_SIZE` bytes.
+///
+/// Select a sector on `SECTOR_PORT`, then write `CMD_READ` to `COMMAND_PORT` to load it into the
+/// sector buffer, or `CMD_WRITE` to save the buffer to it. `DATA_PORT` streams the buffer one byte
+/// per access in either direction. Selecting a sector or issuing a command rewinds the stream.
+pub struct Disk {
+    file: File,
+    sector: u8,
+    buffer: [u8; SECTOR_SIZE],
+    cursor: usize,
+    error: bool,
+}
+
+impl Disk {
+    /// Opens the image at `path`, creating it if needed. Reads past the end return zeros.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("Could not open disk image {path}: {e}"))?;
+        Ok(Self { file, sector: 0, buffer: [0; SECTOR_SIZE], cursor: 0, error: false })
+    }
+
+    fn offset(&self) -> u64 {
+        self.sector as u64 * SECTOR_SIZE as u64
+    }
+
+    fn read_sector(&mut self) -> std::io::Result<()> {
+        self.buffer = [0; SECTOR_SIZE];
+        self.file.seek(SeekFrom::Start(self.offset()))?;
+        let mut filled = 0;
+        while filled < SECTOR_SIZE {
+            match self.file.read(&mut self.buffer[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_sector(&mut self) -> std::io::Result<()> {
+        self.file.seek(SeekFrom::Start(self.offset()))?;
+        self.file.write_all(&self.buffer)?;
+        self.file.flush()
+    }
+}
+
+impl Device for Disk {
+    fn claims(&self, port: u8) -> bool {
+        (COMMAND_PORT..=DATA_PORT).contains(&port)
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        match port {
+            COMMAND_PORT => STATUS_READY | if self.error { STATUS_ERROR } else { 0 },
+            SECTOR_PORT => self.sector,
+            _ => {
+                let byte = self.buffer[self.cursor];
+                self.cursor = (self.cursor + 1) % SECTOR_SIZE;
+                byte
+            }
+        }
+    }
+
+    fn write(&mut self, port: u8, value: u8) {
+        match port {
+            COMMAND_PORT => {
+                let result = match value {
+                    CMD_READ => self.read_sector(),
+                    CMD_WRITE => self.write_sector(),
+                    _ => Ok(()),
+                };
+                self.error = result.is_err();
+                self.cursor = 0;
+            }
+            SECTOR_PORT => {
+                self.sector = value;
+                self.cursor = 0;
+            }
+            _ => {
+                self.buffer[self.cursor] = value;
+                self.cursor = (self.cursor + 1) % SECTOR_SIZE;
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::electron_2::Device;
+use std::sync::{Arc, Mutex};
+
+/// Double-buffers the display for `--strobe`: it watches writes to the display ports %0-%7,
+/// and a write of any value to the strobe port copies them into the frame the window shows.
+/// A program can then draw a whole frame row by row without the half-drawn rows flickering.
+pub struct FrameLatch {
+    strobe_port: u8,
+    rows: [u8; 8],
+    frame: Arc<Mutex<[u8; 8]>>,
+}
+
+impl FrameLatch {
+    /// The latch, and the frame it shows for the window to read.
+    pub fn new(strobe_port: u8) -> (Self, Arc<Mutex<[u8; 8]>>) {
+        let frame = Arc::new(Mutex::new([0; 8]));
+        (Self { strobe_port, rows: [0; 8], frame: frame.clone() }, frame)
+    }
+}
+
+impl Device for FrameLatch {
+    fn claims(&self, port: u8) -> bool {
+        port < 8 || port == self.strobe_port
+    }
+
+    fn write(&mut self, port: u8, value: u8) {
+        if port == self.strobe_port {
+            *self.frame.lock().unwrap() = self.rows;
+        } else {
+            self.rows[port as usize] = value;
+        }
+    }
+}
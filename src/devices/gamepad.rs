@@ -0,0 +1,32 @@
+use crate::electron_2::Device;
+use std::sync::{Arc, Mutex};
+
+/// `--gamepad`: one input port whose bits are the gamepad buttons held down, as the window last
+/// saw them (see `gamepad::Mapping` for which bit is which). Reads never wait; the buttons come
+/// from the user, so deterministic mode refuses them.
+pub struct Gamepad {
+    port: u8,
+    buttons: Arc<Mutex<u8>>,
+}
+
+impl Gamepad {
+    /// The device, and the port value for the window to keep up to date.
+    pub fn new(port: u8) -> (Self, Arc<Mutex<u8>>) {
+        let buttons = Arc::new(Mutex::new(0));
+        (Self { port, buttons: buttons.clone() }, buttons)
+    }
+}
+
+impl Device for Gamepad {
+    fn claims(&self, port: u8) -> bool {
+        port == self.port
+    }
+
+    fn read(&mut self, _port: u8) -> u8 {
+        *self.buttons.lock().unwrap()
+    }
+
+    fn host_dependent(&self) -> bool {
+        true
+    }
+}
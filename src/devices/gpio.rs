@@ -0,0 +1,80 @@
+use crate::electron_2::Device;
+use std::path::{Path, PathBuf};
+
+/// Input pins are read from these ports, `in0` on `FIRST_INPUT_PORT` up to `in7`.
+pub const FIRST_INPUT_PORT: u8 = 24;
+pub const PIN_COUNT: u8 = 8;
+
+/// Mirrors the pins to a host directory so scripts and hardware bridges can watch and drive them.
+/// Each output port `%N` (the display ports %0-%7) is written to `outN` whenever it changes, and
+/// each clock the input files `in0`-`in7` are read back in and served on ports %24-%31. Files
+/// hold one decimal value; a missing or unreadable input reads as 0.
+pub struct Gpio {
+    dir: PathBuf,
+    outputs: [u8; PIN_COUNT as usize],
+    dirty: [bool; PIN_COUNT as usize],
+    inputs: [u8; PIN_COUNT as usize],
+}
+
+impl Gpio {
+    /// Creates `dir` if needed, zeroes every `outN` file and creates missing `inN` files.
+    pub fn open(dir: &str) -> Result<Self, String> {
+        let dir = PathBuf::from(dir);
+        std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        for pin in 0..PIN_COUNT {
+            write_value(&dir, &format!("out{pin}"), 0)?;
+            let input = dir.join(format!("in{pin}"));
+            if !input.exists() {
+                write_value(&dir, &format!("in{pin}"), 0)?;
+            }
+        }
+        Ok(Self {
+            dir,
+            outputs: [0; PIN_COUNT as usize],
+            dirty: [false; PIN_COUNT as usize],
+            inputs: [0; PIN_COUNT as usize],
+        })
+    }
+}
+
+/// Writes through a temporary file and a rename, so readers never see a half-written value.
+fn write_value(dir: &Path, name: &str, value: u8) -> Result<(), String> {
+    let tmp = dir.join(format!(".{name}.tmp"));
+    std::fs::write(&tmp, format!("{value}\n"))
+        .and_then(|_| std::fs::rename(&tmp, dir.join(name)))
+        .map_err(|e| format!("{}: {e}", dir.join(name).display()))
+}
+
+impl Device for Gpio {
+    fn claims(&self, port: u8) -> bool {
+        port < PIN_COUNT || (FIRST_INPUT_PORT..FIRST_INPUT_PORT + PIN_COUNT).contains(&port)
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        self.inputs[(port - FIRST_INPUT_PORT) as usize]
+    }
+
+    fn write(&mut self, port: u8, value: u8) {
+        if port < PIN_COUNT && self.outputs[port as usize] != value {
+            self.outputs[port as usize] = value;
+            self.dirty[port as usize] = true;
+        }
+    }
+
+    fn tick(&mut self) {
+        for pin in 0..PIN_COUNT as usize {
+            if self.dirty[pin] {
+                // A failed write is retried on the next clock rather than stopping the program.
+                self.dirty[pin] = write_value(&self.dir, &format!("out{pin}"), self.outputs[pin]).is_err();
+            }
+            self.inputs[pin] = std::fs::read_to_string(self.dir.join(format!("in{pin}")))
+                .ok()
+                .and_then(|text| text.trim().parse::<u8>().ok())
+                .unwrap_or(0);
+        }
+    }
+
+    fn host_dependent(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,52 @@
+use crate::electron_2::Device;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const HOURS_PORT: u8 = 16;
+pub const MINUTES_PORT: u8 = 17;
+pub const SECONDS_PORT: u8 = 18;
+
+/// Read-only wall clock: the host's local time of day as hours (0-23), minutes and seconds.
+/// Every read samples the clock, so deterministic mode refuses it.
+pub struct HostClock;
+
+impl Device for HostClock {
+    fn claims(&self, port: u8) -> bool {
+        (HOURS_PORT..=SECONDS_PORT).contains(&port)
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        let (hours, minutes, seconds) = local_time();
+        match port {
+            HOURS_PORT => hours,
+            MINUTES_PORT => minutes,
+            _ => seconds,
+        }
+    }
+
+    fn host_dependent(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(unix)]
+fn local_time() -> (u8, u8, u8) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as libc::time_t;
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return utc_time();
+        }
+        (tm.tm_hour as u8, tm.tm_min as u8, tm.tm_sec as u8)
+    }
+}
+
+// No time zone database to consult without libc, so other platforms get UTC.
+#[cfg(not(unix))]
+fn local_time() -> (u8, u8, u8) {
+    utc_time()
+}
+
+fn utc_time() -> (u8, u8, u8) {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() % 86_400;
+    ((secs / 3600) as u8, (secs / 60 % 60) as u8, (secs % 60) as u8)
+}
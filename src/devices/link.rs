@@ -0,0 +1,59 @@
+use crate::electron_2::Device;
+use std::sync::{Arc, Mutex};
+
+pub const FIRST_PORT: u8 = 20;
+pub const WIRE_COUNT: u8 = 4;
+
+/// Values written on each side, and what the other side currently reads.
+#[derive(Default)]
+struct Wires {
+    pending: [[u8; WIRE_COUNT as usize]; 2],
+    latched: [[u8; WIRE_COUNT as usize]; 2],
+}
+
+/// One end of a link between two cores: four wires on ports %20-%23. Writing a port on one core
+/// drives that wire, and reading the same port on the other core returns the value on it.
+/// Writes made during a clock reach the other side once `LinkBus::commit` runs after both cores
+/// have clocked, so the delay is one clock in both directions.
+pub struct Link {
+    wires: Arc<Mutex<Wires>>,
+    side: usize,
+}
+
+/// The runner's handle on a link, used to latch both cores' writes between clocks.
+pub struct LinkBus {
+    wires: Arc<Mutex<Wires>>,
+}
+
+impl Link {
+    /// Two connected ends, plus the bus that moves values between them.
+    pub fn pair() -> (Link, Link, LinkBus) {
+        let wires = Arc::new(Mutex::new(Wires::default()));
+        (
+            Link { wires: wires.clone(), side: 0 },
+            Link { wires: wires.clone(), side: 1 },
+            LinkBus { wires },
+        )
+    }
+}
+
+impl LinkBus {
+    pub fn commit(&self) {
+        let mut wires = self.wires.lock().unwrap();
+        wires.latched = wires.pending;
+    }
+}
+
+impl Device for Link {
+    fn claims(&self, port: u8) -> bool {
+        (FIRST_PORT..FIRST_PORT + WIRE_COUNT).contains(&port)
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        self.wires.lock().unwrap().latched[1 - self.side][(port - FIRST_PORT) as usize]
+    }
+
+    fn write(&mut self, port: u8, value: u8) {
+        self.wires.lock().unwrap().pending[self.side][(port - FIRST_PORT) as usize] = value;
+    }
+}
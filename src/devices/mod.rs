@@ -0,0 +1,27 @@
+//! Host-backed peripherals for the V2 port bus. Each device documents the ports it claims; the
+//! full map lives in ARCHITECTURE-v2.md.
+
+pub mod adc;
+pub mod disk;
+pub mod frame_latch;
+pub mod gamepad;
+pub mod gpio;
+pub mod host_clock;
+pub mod link;
+pub mod mouse;
+pub mod rng;
+pub mod speaker;
+pub mod timer;
+pub mod uart;
+
+pub use adc::Adc;
+pub use disk::Disk;
+pub use frame_latch::FrameLatch;
+pub use gamepad::Gamepad;
+pub use gpio::Gpio;
+pub use host_clock::HostClock;
+pub use mouse::Mouse;
+pub use rng::Rng;
+pub use speaker::Speaker;
+pub use timer::Timer;
+pub use uart::Uart;
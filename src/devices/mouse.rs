@@ -0,0 +1,41 @@
+use crate::electron_2::Device;
+use std::sync::{Arc, Mutex};
+
+pub const X_PORT: u8 = 42;
+pub const Y_PORT: u8 = 43;
+pub const BUTTONS_PORT: u8 = 44;
+
+/// `--mouse`: the pointer over the LED grid as X and Y from 0 to 255 (so `X / 32` is the LED's
+/// column and `Y / 32` its row), and the buttons held (bit 0 left, bit 1 right, bit 2 middle), as
+/// the window last saw them. Reads never wait; the values come from the user, so deterministic
+/// mode refuses them.
+pub struct Mouse {
+    state: Arc<Mutex<[u8; 3]>>,
+}
+
+impl Mouse {
+    /// The device, and the X, Y and buttons values for the window to keep up to date.
+    pub fn new() -> (Self, Arc<Mutex<[u8; 3]>>) {
+        let state = Arc::new(Mutex::new([0; 3]));
+        (Self { state: state.clone() }, state)
+    }
+}
+
+impl Device for Mouse {
+    fn claims(&self, port: u8) -> bool {
+        (X_PORT..=BUTTONS_PORT).contains(&port)
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        let [x, y, buttons] = *self.state.lock().unwrap();
+        match port {
+            X_PORT => x,
+            Y_PORT => y,
+            _ => buttons,
+        }
+    }
+
+    fn host_dependent(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,54 @@
+use crate::electron_2::Device;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DATA_PORT: u8 = 13;
+
+/// Read-only random number port: every read of `DATA_PORT` returns the next pseudo-random byte
+/// (xorshift64*). The same seed always produces the same sequence; without one the generator is
+/// seeded from the host clock, which deterministic mode refuses.
+pub struct Rng {
+    state: u64,
+    seeded: bool,
+}
+
+impl Rng {
+    pub fn new(seed: Option<u64>) -> Self {
+        let seeded = seed.is_some();
+        let seed = seed.unwrap_or_else(|| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            now.as_nanos() as u64 ^ ((std::process::id() as u64) << 32)
+        });
+        // Scramble the seed so small seeds (0, 1, 2...) don't start out correlated; xorshift
+        // also needs a nonzero state.
+        let state = splitmix64(seed).max(1);
+        Self { state, seeded }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Device for Rng {
+    fn claims(&self, port: u8) -> bool {
+        port == DATA_PORT
+    }
+
+    fn read(&mut self, _port: u8) -> u8 {
+        (self.next() >> 56) as u8
+    }
+
+    fn host_dependent(&self) -> bool {
+        !self.seeded
+    }
+}
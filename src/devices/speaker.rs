@@ -0,0 +1,41 @@
+use crate::electron_2::Device;
+use std::sync::{Arc, Mutex};
+
+/// The most clocks kept for the window to play. Without a window emptying it the buffer would
+/// only grow, so past this the oldest are dropped.
+const MAX_PENDING: usize = 1 << 20;
+
+/// `--speaker`: one bit of a port, sampled every clock, as a 1-bit audio stream for the window to
+/// play. Writes to the port set the level; the samples are its level at the start of each clock.
+pub struct Speaker {
+    port: u8,
+    bit: u8,
+    level: bool,
+    samples: Arc<Mutex<Vec<bool>>>,
+}
+
+impl Speaker {
+    /// The device, and the samples it collects for the window to take.
+    pub fn new(port: u8, bit: u8) -> (Self, Arc<Mutex<Vec<bool>>>) {
+        let samples = Arc::new(Mutex::new(Vec::new()));
+        (Self { port, bit, level: false, samples: samples.clone() }, samples)
+    }
+}
+
+impl Device for Speaker {
+    fn claims(&self, port: u8) -> bool {
+        port == self.port
+    }
+
+    fn write(&mut self, _port: u8, value: u8) {
+        self.level = value & (1 << self.bit) != 0;
+    }
+
+    fn tick(&mut self) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == MAX_PENDING {
+            samples.drain(..MAX_PENDING / 2);
+        }
+        samples.push(self.level);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::electron_2::Device;
+
+pub const COUNTER_PORT: u8 = 14;
+pub const PRESCALER_PORT: u8 = 15;
+
+/// Free-running 8-bit counter driven by the CPU clock. It advances once every N clocks, where N
+/// is the prescaler (default 1, 0 stops it), and wraps from 255 to 0. Writing `COUNTER_PORT`
+/// reloads the count and restarts the prescaler, so `OUT %14 R0` starts a fresh measurement.
+pub struct Timer {
+    count: u8,
+    prescaler: u8,
+    ticks: u8,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self { count: 0, prescaler: 1, ticks: 0 }
+    }
+}
+
+impl Device for Timer {
+    fn claims(&self, port: u8) -> bool {
+        port == COUNTER_PORT || port == PRESCALER_PORT
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        if port == COUNTER_PORT { self.count } else { self.prescaler }
+    }
+
+    fn write(&mut self, port: u8, value: u8) {
+        if port == COUNTER_PORT {
+            self.count = value;
+        } else {
+            self.prescaler = value;
+        }
+        self.ticks = 0;
+    }
+
+    fn tick(&mut self) {
+        if self.prescaler == 0 {
+            return;
+        }
+        self.ticks += 1;
+        if self.ticks >= self.prescaler {
+            self.ticks = 0;
+            self.count = self.count.wrapping_add(1);
+        }
+    }
+}
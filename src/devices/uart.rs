@@ -0,0 +1,82 @@
+use crate::electron_2::Device;
+use std::io::{Read, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+pub const DATA_PORT: u8 = 8;
+pub const STATUS_PORT: u8 = 9;
+
+/// Status bits read from `STATUS_PORT`.
+pub const RX_READY: u8 = 0b001;
+pub const TX_READY: u8 = 0b010;
+pub const RX_CLOSED: u8 = 0b100;
+
+/// Serial port bridged to stdio. Writing `DATA_PORT` sends a byte to stdout; reading it takes the
+/// next byte received on stdin (0 if none). `STATUS_PORT` reports `RX_READY`, `TX_READY` (always
+/// set) and `RX_CLOSED` once stdin hits end of file.
+pub struct Uart {
+    rx: Receiver<u8>,
+    pending: Option<u8>,
+    closed: bool,
+}
+
+impl Default for Uart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Uart {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for byte in std::io::stdin().lock().bytes() {
+                let Ok(byte) = byte else { break };
+                if tx.send(byte).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { rx, pending: None, closed: false }
+    }
+
+    fn poll(&mut self) {
+        if self.pending.is_none() {
+            match self.rx.try_recv() {
+                Ok(byte) => self.pending = Some(byte),
+                Err(TryRecvError::Disconnected) => self.closed = true,
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+    }
+}
+
+impl Device for Uart {
+    fn claims(&self, port: u8) -> bool {
+        port == DATA_PORT || port == STATUS_PORT
+    }
+
+    fn read(&mut self, port: u8) -> u8 {
+        self.poll();
+        if port == DATA_PORT {
+            self.pending.take().unwrap_or(0)
+        } else {
+            let mut status = TX_READY;
+            if self.pending.is_some() { status |= RX_READY; }
+            if self.closed { status |= RX_CLOSED; }
+            status
+        }
+    }
+
+    /// What arrives on stdin, and when, is up to the host.
+    fn host_dependent(&self) -> bool {
+        true
+    }
+
+    fn write(&mut self, port: u8, value: u8) {
+        if port == DATA_PORT {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(&[value]);
+            let _ = stdout.flush();
+        }
+    }
+}
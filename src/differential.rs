@@ -0,0 +1,197 @@
+//! Runs programs from the instruction subset V1 and V2 share on both emulators and compares
+//! registers and display ports after every clock, so V2 changes that break V1 behaviour show up.
+//!
+//! The subset is `IMM`, `MOV`, `ADD` and `ADDC` with the `S`, `U` or `X` prefix, `SHR`, `NOT`,
+//! `OUT`, `JMP` and `BIE`, with two rules the architectures don't agree on: V1 executes the
+//! instruction after a taken branch while V2 flushes it, so that slot holds a `NOOP`; and V2
+//! reads registers a clock later, so no instruction reads a register the one before it wrote
+//! (the V2 parser's RAW warning). Plain `ADD` and `ADDC` are left out, since V1 discards their
+//! result. The accumulator and flags are compared too.
+
+use crate::electron_2::isa::OPERATIONS;
+use crate::electron_2::Emulator as EmulatorV2;
+use crate::emulator::Emulator as EmulatorV1;
+use crate::parser::{Instruction, Program, ProgramLoader};
+
+/// V1's ROM size; programs are padded with NOOPs to fill it, as the loader does.
+const V1_ROM: usize = 32;
+
+fn load_v1(source: &str) -> EmulatorV1 {
+    let (mut instructions, errors, _) = ProgramLoader::compile(source);
+    assert!(errors.is_empty(), "V1 rejected the program: {}", errors[0].message);
+    instructions.resize(V1_ROM, Instruction::none());
+    EmulatorV1::new(Program { instructions })
+}
+
+/// Clocks both emulators `clocks` times and panics at the first clock where they disagree.
+/// Returns the V2 emulator for checks on the final state.
+fn assert_same(source: &str, clocks: usize) -> EmulatorV2 {
+    let mut v1 = load_v1(source);
+    let mut v2 = EmulatorV2::new(source.to_string());
+    assert!(v2.errors.is_empty(), "V2 rejected the program: {}", v2.errors[0]);
+    // Unreachable code is fine here: the harness's own test puts some in a branch's shadow. So
+    // is reading a register before writing it, since both reset registers to 0.
+    let flow = |w: &&String| w.contains("Unreachable code") || w.contains("is never used") || w.contains("before anything writes it");
+    if let Some(warning) = v2.warnings.iter().find(|w| !flow(w)) {
+        panic!("outside the shared subset: {warning}");
+    }
+
+    for clock in 1..=clocks {
+        v1.clock();
+        v2.clock();
+        let v1_regs: Vec<u8> = (0..8).map(|r| v1.registers.read(r)).collect();
+        let v2_regs: Vec<u8> = (0..8).map(|r| v2.registers.read(r)).collect();
+        assert_eq!(v1_regs, v2_regs, "registers differ after clock {clock}");
+        assert_eq!(v1.ports.out, v2.ports_out, "ports differ after clock {clock}");
+        assert_eq!(v1.alu.accumalator, v2.alu.accumulator, "accumulators differ after clock {clock}");
+        let (f1, f2) = (&v1.alu.flags, &v2.alu.flags);
+        assert_eq!(
+            [f1.equals, f1.greater, f1.less, f1.overflow],
+            [f2.equals, f2.greater, f2.less, f2.overflow],
+            "flags (equals, greater, less, overflow) differ after clock {clock}"
+        );
+    }
+    v2
+}
+
+#[test]
+fn data_moves_and_alu_results_match() {
+    let source = "\
+IMM R1 200
+IMM R2 55
+NOOP
+MOV R3 R1
+SADD R4 R2
+UADD R5 R0
+SHR R6 R1
+NOT R7 R2
+OUT %0 R3
+OUT %1 R4
+OUT %2 R5
+OUT %3 R6
+OUT %4 R7
+JMP 13
+NOOP";
+    let v2 = assert_same(source, 40);
+    assert_eq!(v2.ports_out[..5], [200, 55, 55, 100, 200]);
+}
+
+#[test]
+fn overflowing_add_wraps_the_same_way() {
+    let source = "\
+IMM R1 250
+IMM R2 10
+NOOP
+SADD R3 R1
+UADD R4 R2
+OUT %0 R3
+OUT %1 R4
+JMP 7
+NOOP";
+    let v2 = assert_same(source, 30);
+    assert_eq!(v2.ports_out[..2], [250, 4]);
+}
+
+/// Sums that land on 255, 256 and 0, and `ADDC` taking the overflow of the sum before it as
+/// its carry, then dropping it again.
+#[test]
+fn add_boundaries_set_the_same_flags() {
+    let source = "\
+IMM R1 255
+IMM R2 1
+IMM R3 128
+SADD R1 R2
+SADDC R3 R3
+SADDC R2 R0
+UADD R4 R3
+XADD R1
+NOT R5 R0
+OUT %0 R1
+SHR R6 R5
+OUT %1 R2
+OUT %2 R3
+OUT %3 R4
+OUT %4 R5
+OUT %5 R6
+JMP 16
+NOOP";
+    let v2 = assert_same(source, 50);
+    assert_eq!(v2.ports_out[..6], [0, 2, 1, 3, 255, 127]);
+}
+
+/// V1 has no `SUB`, so V2's borrow cases are checked against assertions instead: a result
+/// below 0 wraps and sets the overflow flag, as a sum above 255 does.
+#[test]
+fn sub_boundaries_wrap_and_set_overflow() {
+    let source = "\
+IMM R1 0
+IMM R2 1
+IMM R3 255
+SSUB R1 R2 ; ASSERT R1 == 255
+NOOP ; ASSERT OVERFLOW == true
+SSUB R2 R2 ; ASSERT R2 == 0
+NOOP ; ASSERT EQUALS == true
+NOOP ; ASSERT OVERFLOW == false
+SSUB R3 R1 ; ASSERT R3 == 0
+NOOP ; ASSERT EQUALS == true
+SUB R2 R3 ; ASSERT R2 == 0
+NOOP ; ASSERT OVERFLOW == false
+XSUB R1 ; ASSERT ACC == 1
+NOOP ; ASSERT OVERFLOW == true
+NOOP ; ASSERT LESS == true
+HLT";
+    let mut v2 = EmulatorV2::new(source.to_string());
+    assert!(v2.errors.is_empty(), "V2 rejected the program: {}", v2.errors[0]);
+    while !v2.halted && v2.fault.is_none() {
+        v2.clock();
+    }
+    assert_eq!(v2.fault, None);
+    assert_eq!(v2.stats.assertions, 12, "not every assertion ran");
+}
+
+#[test]
+fn counting_loop_with_bie_matches() {
+    let source = "\
+IMM R1 0
+IMM R2 1
+IMM R3 4
+SADD R1 R2
+XADD R3
+BIE 9
+NOOP
+JMP 3
+NOOP
+OUT %0 R1
+NOOP
+JMP 11
+NOOP";
+    let v2 = assert_same(source, 80);
+    assert_eq!(v2.ports_out[0], 4);
+}
+
+/// Guards the harness itself: an instruction in the slot after a taken branch runs on V1 only.
+#[test]
+#[should_panic(expected = "registers differ")]
+fn branch_delay_slot_is_detected() {
+    let source = "\
+JMP 2
+IMM R1 9
+NOOP
+JMP 3
+NOOP";
+    assert_same(source, 10);
+}
+
+/// The instruction table `electron isa` prints marks what V1 has; both assemblers must agree
+/// with it, and every example must assemble to its own operation.
+#[test]
+fn isa_table_matches_both_assemblers() {
+    for (opcode, info) in OPERATIONS.iter().enumerate() {
+        assert_eq!(info.operation as usize, opcode, "{} is out of opcode order", info.mnemonic);
+        let v2 = EmulatorV2::new(info.example.to_string());
+        assert!(v2.errors.is_empty(), "V2 rejected `{}`: {}", info.example, v2.errors[0]);
+        assert_eq!(v2.instructions[0].operation, info.operation, "`{}` is another operation", info.example);
+        let (_, errors, _) = ProgramLoader::compile(info.example);
+        assert_eq!(errors.is_empty(), info.v1, "V1 {} `{}`", if info.v1 { "rejected" } else { "accepted" }, info.example);
+    }
+}
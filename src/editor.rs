@@ -0,0 +1,377 @@
+//! E: the program's source, editable in the V2 window. Each edit is assembled again on a thread
+//! of its own, the errors and warnings shown beside their lines, and Ctrl+Enter loads the edited
+//! program in place of the running one; Ctrl+S writes it to the program file as well.
+
+use crate::arch::Arch;
+use crate::transport;
+use crate::window::Edit;
+use crate::WINDOW_SIZE;
+use raylib::prelude::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+const FONT_SIZE: i32 = 20;
+const LINE_HEIGHT: i32 = 24;
+const PADDING: i32 = 12;
+const GUTTER_WIDTH: i32 = 48;
+const BACKGROUND: Color = Color::new(10, 10, 20, 245);
+const TITLE: Color = Color::new(140, 170, 255, 255);
+const TEXT: Color = Color::new(230, 230, 230, 255);
+const LINE_NUMBER: Color = Color::new(120, 120, 140, 255);
+const CURSOR: Color = Color::new(255, 200, 60, 255);
+const CURRENT_LINE: Color = Color::new(35, 35, 60, 255);
+const ERROR: Color = Color::new(240, 80, 80, 255);
+const WARNING: Color = Color::new(240, 190, 60, 255);
+const FINE: Color = Color::new(90, 230, 120, 255);
+const INDENT: &str = "    ";
+/// Held keys repeat after this long, then once every `REPEAT_RATE`.
+const REPEAT_DELAY: Duration = Duration::from_millis(400);
+const REPEAT_RATE: Duration = Duration::from_millis(35);
+/// The keys that move the cursor or delete, which repeat while held. Typed characters repeat
+/// on their own.
+const REPEATING: [KeyboardKey; 10] = [
+    KeyboardKey::KEY_LEFT,
+    KeyboardKey::KEY_RIGHT,
+    KeyboardKey::KEY_UP,
+    KeyboardKey::KEY_DOWN,
+    KeyboardKey::KEY_BACKSPACE,
+    KeyboardKey::KEY_DELETE,
+    KeyboardKey::KEY_ENTER,
+    KeyboardKey::KEY_TAB,
+    KeyboardKey::KEY_PAGE_UP,
+    KeyboardKey::KEY_PAGE_DOWN,
+];
+
+/// A text to assemble, or the notes on one, with the edit it was made at.
+type Check<T> = (u64, T);
+
+/// An error or warning on a line, counted from 0.
+struct Note {
+    line: usize,
+    error: bool,
+    message: String,
+}
+
+pub struct Editor {
+    lines: Vec<Vec<char>>,
+    /// The line and the character in it the cursor is before.
+    cursor: (usize, usize),
+    /// The first line shown.
+    first: usize,
+    /// Edited since it was opened or last loaded.
+    pub changed: bool,
+    /// Counts the edits, so a check that finishes after a newer edit is told apart.
+    version: u64,
+    checked: u64,
+    notes: Vec<Note>,
+    requests: Sender<Check<String>>,
+    results: Receiver<Check<Vec<Note>>>,
+    /// The key being held and when it next repeats.
+    held: Option<(KeyboardKey, Instant)>,
+    /// Why Ctrl+Enter did nothing, until the next edit.
+    refused: Option<&'static str>,
+}
+
+impl Editor {
+    pub fn new(code: &str) -> Self {
+        let (requests, results) = spawn_checker();
+        let mut editor = Self {
+            lines: Vec::new(),
+            cursor: (0, 0),
+            first: 0,
+            changed: false,
+            version: 0,
+            checked: 0,
+            notes: Vec::new(),
+            requests,
+            results,
+            held: None,
+            refused: None,
+        };
+        editor.set_text(code);
+        editor
+    }
+
+    /// Replaces the text, as when the program was reloaded from its file, keeping the cursor
+    /// where it was as far as the new text goes.
+    pub fn set_text(&mut self, code: &str) {
+        self.lines = code.lines().map(|line| line.chars().collect()).collect();
+        if self.lines.is_empty() {
+            self.lines.push(Vec::new());
+        }
+        self.cursor.0 = self.cursor.0.min(self.lines.len() - 1);
+        self.cursor.1 = self.cursor.1.min(self.lines[self.cursor.0].len());
+        self.changed = false;
+        self.check();
+    }
+
+    fn text(&self) -> String {
+        let mut text: String = self.lines.iter().map(|line| line.iter().collect::<String>() + "\n").collect();
+        text.truncate(text.len() - 1);
+        text
+    }
+
+    /// Sends the text to be assembled. Only the newest text is checked when typing gets ahead.
+    fn check(&mut self) {
+        self.version += 1;
+        let _ = self.requests.send((self.version, self.text()));
+    }
+
+    fn edited(&mut self) {
+        self.changed = true;
+        self.refused = None;
+        self.check();
+    }
+
+    /// Whether `key` was pressed this frame, or has been held long enough to repeat.
+    fn repeated(&mut self, rl: &RaylibHandle, key: KeyboardKey) -> bool {
+        let now = Instant::now();
+        if rl.is_key_pressed(key) {
+            self.held = Some((key, now + REPEAT_DELAY));
+            return true;
+        }
+        match self.held {
+            Some((held, at)) if held == key && rl.is_key_down(key) && now >= at => {
+                self.held = Some((key, now + REPEAT_RATE));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Types, moves the cursor and takes the checks that have finished. `mouse` is the pointer
+    /// relative to where the editor is drawn. Returns the program to load when Ctrl+Enter or
+    /// Ctrl+S is pressed.
+    pub fn handle_input(&mut self, rl: &RaylibHandle, mouse: Vector2) -> Option<Edit> {
+        while let Ok((version, notes)) = self.results.try_recv() {
+            self.checked = version;
+            self.notes = notes;
+        }
+        let control = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        // `changed` stays set until the program loads and comes back through `set_text`.
+        if control && rl.is_key_pressed(KeyboardKey::KEY_S) {
+            return Some(Edit::Save(self.text()));
+        }
+        if control && rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            if self.checked == self.version && self.notes.iter().any(|note| note.error) {
+                self.refused = Some("Fix the errors first: the running program is kept.");
+                return None;
+            }
+            return Some(Edit::Apply(self.text()));
+        }
+        // Ctrl+S and the like may come through as characters too.
+        for c in typed() {
+            if !control && !c.is_control() {
+                let (line, column) = self.cursor;
+                self.lines[line].insert(column, c);
+                self.cursor.1 += 1;
+                self.edited();
+            }
+        }
+        let rows = rows();
+        for key in REPEATING {
+            if control || !self.repeated(rl, key) {
+                continue;
+            }
+            let (line, column) = self.cursor;
+            match key {
+                KeyboardKey::KEY_LEFT if column > 0 => self.cursor.1 -= 1,
+                KeyboardKey::KEY_LEFT if line > 0 => self.cursor = (line - 1, self.lines[line - 1].len()),
+                KeyboardKey::KEY_RIGHT if column < self.lines[line].len() => self.cursor.1 += 1,
+                KeyboardKey::KEY_RIGHT if line + 1 < self.lines.len() => self.cursor = (line + 1, 0),
+                KeyboardKey::KEY_UP => self.move_lines(-1),
+                KeyboardKey::KEY_DOWN => self.move_lines(1),
+                KeyboardKey::KEY_PAGE_UP => self.move_lines(-rows),
+                KeyboardKey::KEY_PAGE_DOWN => self.move_lines(rows),
+                KeyboardKey::KEY_BACKSPACE if column > 0 => {
+                    self.lines[line].remove(column - 1);
+                    self.cursor.1 -= 1;
+                    self.edited();
+                }
+                KeyboardKey::KEY_BACKSPACE if line > 0 => {
+                    let rest = self.lines.remove(line);
+                    self.cursor = (line - 1, self.lines[line - 1].len());
+                    self.lines[line - 1].extend(rest);
+                    self.edited();
+                }
+                KeyboardKey::KEY_DELETE if column < self.lines[line].len() => {
+                    self.lines[line].remove(column);
+                    self.edited();
+                }
+                KeyboardKey::KEY_DELETE if line + 1 < self.lines.len() => {
+                    let next = self.lines.remove(line + 1);
+                    self.lines[line].extend(next);
+                    self.edited();
+                }
+                // The new line starts indented as far as the one it was split from.
+                KeyboardKey::KEY_ENTER => {
+                    let rest = self.lines[line].split_off(column);
+                    let indent: Vec<char> = self.lines[line].iter().take_while(|c| c.is_whitespace()).copied().collect();
+                    self.cursor = (line + 1, indent.len());
+                    self.lines.insert(line + 1, indent.into_iter().chain(rest).collect());
+                    self.edited();
+                }
+                KeyboardKey::KEY_TAB => {
+                    self.lines[line].splice(column..column, INDENT.chars());
+                    self.cursor.1 += INDENT.len();
+                    self.edited();
+                }
+                _ => {}
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_HOME) {
+            self.cursor.1 = 0;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_END) {
+            self.cursor.1 = self.lines[self.cursor.0].len();
+        }
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            self.click(mouse);
+        }
+        let wheel = rl.get_mouse_wheel_move();
+        if wheel != 0.0 {
+            let last_first = self.lines.len().saturating_sub(rows as usize);
+            self.first = if wheel > 0.0 { self.first.saturating_sub(3) } else { (self.first + 3).min(last_first) };
+        } else {
+            // Keep the cursor in view as it moves.
+            self.first = self.first.min(self.cursor.0).max((self.cursor.0 + 1).saturating_sub(rows as usize));
+        }
+        None
+    }
+
+    /// Moves the cursor `by` lines, keeping its column where the line is long enough.
+    fn move_lines(&mut self, by: i32) {
+        let line = (self.cursor.0 as i64 + i64::from(by)).clamp(0, self.lines.len() as i64 - 1) as usize;
+        self.cursor = (line, self.cursor.1.min(self.lines[line].len()));
+    }
+
+    /// Puts the cursor at the character nearest the point clicked.
+    fn click(&mut self, mouse: Vector2) {
+        let row = (mouse.y as i32 - text_top()).div_euclid(LINE_HEIGHT);
+        if !(0..rows()).contains(&row) {
+            return;
+        }
+        let line = (self.first + row as usize).min(self.lines.len() - 1);
+        let x = mouse.x as i32 - PADDING - GUTTER_WIDTH;
+        let text = &self.lines[line];
+        let column = (0..=text.len()).min_by_key(|column| (measure_text(&text[..*column].iter().collect::<String>(), FONT_SIZE) - x).abs());
+        self.cursor = (line, column.unwrap_or(0));
+    }
+
+    /// Fills the canvas above the transport bar: the lines with their numbers, a mark for each
+    /// one with an error or warning and its message after it, and at the bottom the message on
+    /// the cursor's line or how many there are.
+    pub fn draw(&self, d: &mut impl RaylibDraw) {
+        d.draw_rectangle(0, 0, WINDOW_SIZE.0, WINDOW_SIZE.1 - transport::BAR_HEIGHT, BACKGROUND);
+        let title = if self.changed { "Editing (changed): Ctrl+Enter applies, Ctrl+S saves, Esc closes" } else { "Editing: Ctrl+Enter applies, Ctrl+S saves, Esc closes" };
+        d.draw_text(title, PADDING, PADDING, FONT_SIZE, TITLE);
+        let width = WINDOW_SIZE.0 - PADDING * 2 - GUTTER_WIDTH;
+        for (i, text) in self.lines.iter().enumerate().skip(self.first).take(rows() as usize) {
+            let y = text_top() + (i - self.first) as i32 * LINE_HEIGHT;
+            if i == self.cursor.0 {
+                d.draw_rectangle(PADDING, y - 2, WINDOW_SIZE.0 - PADDING * 2, LINE_HEIGHT, CURRENT_LINE);
+            }
+            let note = self.notes.iter().filter(|note| note.line == i).min_by_key(|note| !note.error);
+            let number_color = note.map_or(LINE_NUMBER, |note| if note.error { ERROR } else { WARNING });
+            d.draw_text(&(i + 1).to_string(), PADDING, y, FONT_SIZE, number_color);
+            let text: String = text.iter().collect();
+            d.draw_text(&text, PADDING + GUTTER_WIDTH, y, FONT_SIZE, TEXT);
+            if i == self.cursor.0 {
+                let before: String = self.lines[i][..self.cursor.1].iter().collect();
+                let x = PADDING + GUTTER_WIDTH + measure_text(&before, FONT_SIZE);
+                d.draw_rectangle(x, y - 1, 2, LINE_HEIGHT - 2, CURSOR);
+            }
+            if let Some(note) = note {
+                let x = PADDING + GUTTER_WIDTH + measure_text(&text, FONT_SIZE) + PADDING * 2;
+                let room = PADDING + GUTTER_WIDTH + width - x;
+                d.draw_text(&fit(&note.message, room), x, y, FONT_SIZE, number_color);
+            }
+        }
+        let errors = self.notes.iter().filter(|note| note.error).count();
+        let warnings = self.notes.len() - errors;
+        let (status, color) = if self.checked != self.version {
+            ("Assembling...".to_string(), LINE_NUMBER)
+        } else if let Some(reason) = self.refused {
+            (reason.to_string(), ERROR)
+        } else {
+            match self.notes.iter().find(|note| note.line == self.cursor.0) {
+                Some(note) => (note.message.clone(), if note.error { ERROR } else { WARNING }),
+                None if errors + warnings == 0 => ("Assembles with no errors or warnings.".to_string(), FINE),
+                None => (format!("{errors} errors, {warnings} warnings."), if errors > 0 { ERROR } else { WARNING }),
+            }
+        };
+        let y = WINDOW_SIZE.1 - transport::BAR_HEIGHT - PADDING - FONT_SIZE;
+        d.draw_text(&fit(&status, WINDOW_SIZE.0 - PADDING * 2), PADDING, y, FONT_SIZE, color);
+    }
+}
+
+/// The characters typed since the last frame. raylib 3.7's bindings read keys but not the
+/// characters they make, which depend on Shift and the keyboard layout.
+fn typed() -> Vec<char> {
+    // SAFETY: GetCharPressed only takes from raylib's queue of typed characters, which the
+    // window thread (this one) fills between frames.
+    std::iter::from_fn(|| match unsafe { raylib::ffi::GetCharPressed() } {
+        0 => None,
+        code => Some(u32::try_from(code).ok().and_then(char::from_u32).unwrap_or(char::REPLACEMENT_CHARACTER)),
+    })
+    .collect()
+}
+
+/// Drops the characters typed so far, like the E that opened the editor.
+pub fn discard_typed() {
+    typed();
+}
+
+fn text_top() -> i32 {
+    PADDING * 2 + LINE_HEIGHT
+}
+
+/// How many lines fit between the title and the status line.
+fn rows() -> i32 {
+    (WINDOW_SIZE.1 - transport::BAR_HEIGHT - text_top() - PADDING * 2 - LINE_HEIGHT) / LINE_HEIGHT
+}
+
+/// `text` cut to fit in `width` pixels, with "..." where it was cut.
+fn fit(text: &str, width: i32) -> String {
+    if measure_text(text, FONT_SIZE) <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.to_string();
+    while !cut.is_empty() && measure_text(&format!("{cut}..."), FONT_SIZE) > width {
+        cut.pop();
+    }
+    format!("{cut}...")
+}
+
+/// The thread that assembles the text sent to it, until the editor that holds the other ends
+/// is dropped.
+fn spawn_checker() -> (Sender<Check<String>>, Receiver<Check<Vec<Note>>>) {
+    let (requests, inbox) = mpsc::channel::<Check<String>>();
+    let (outbox, results) = mpsc::channel();
+    std::thread::spawn(move || {
+        while let Ok(mut request) = inbox.recv() {
+            while let Ok(newer) = inbox.try_recv() {
+                request = newer;
+            }
+            let (version, text) = request;
+            let assembled = Arch::Electron2.assemble(&text);
+            let notes = [(true, assembled.errors), (false, assembled.warnings)]
+                .into_iter()
+                .flat_map(|(error, diagnostics)| {
+                    diagnostics.into_iter().map(move |diagnostic| Note {
+                        line: diagnostic.line.unwrap_or(1).saturating_sub(1),
+                        error,
+                        message: match diagnostic.code {
+                            Some(code) => code.tag(&diagnostic.message),
+                            None => diagnostic.message,
+                        },
+                    })
+                })
+                .collect();
+            if outbox.send((version, notes)).is_err() {
+                break;
+            }
+        }
+    });
+    (requests, results)
+}
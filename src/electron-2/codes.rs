@@ -0,0 +1,261 @@
+//! Stable codes for the assembler's errors (`E001`) and warnings (`W001`), and the longer
+//! explanation `electron explain <code>` prints for each. A message carries its code in brackets
+//! after the location, e.g. `Line 4, column 8: [W003] RAW Hazard. ...`. Warnings also have a
+//! name, such as `raw_hazard`, for `; @allow(raw_hazard)` to drop them from a line.
+
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Code {
+    UnknownOperation,
+    BadOperand,
+    BadDirective,
+    DuplicateName,
+    DataOverflow,
+    BadAssertion,
+    WriteToZero,
+    ImmediateRange,
+    RawHazard,
+    PortRange,
+    RamRange,
+    UnusedLabel,
+    Unreachable,
+    CrossBank,
+    UninitializedRead,
+    DeadStore,
+}
+
+pub struct Explanation {
+    /// One line: what the message means.
+    pub summary: &'static str,
+    /// Why it happens, in terms of the machine.
+    pub details: &'static str,
+    /// A program that gets the message, and the same program fixed.
+    pub example: &'static str,
+    pub fixed: &'static str,
+}
+
+impl Code {
+    pub const ALL: [Code; 16] = [
+        Code::UnknownOperation,
+        Code::BadOperand,
+        Code::BadDirective,
+        Code::DuplicateName,
+        Code::DataOverflow,
+        Code::BadAssertion,
+        Code::WriteToZero,
+        Code::ImmediateRange,
+        Code::RawHazard,
+        Code::PortRange,
+        Code::RamRange,
+        Code::UnusedLabel,
+        Code::Unreachable,
+        Code::CrossBank,
+        Code::UninitializedRead,
+        Code::DeadStore,
+    ];
+
+    /// `E001` and up for errors, `W001` and up for warnings. Never reused or renumbered.
+    pub fn id(self) -> &'static str {
+        match self {
+            Code::UnknownOperation => "E001",
+            Code::BadOperand => "E002",
+            Code::BadDirective => "E003",
+            Code::DuplicateName => "E004",
+            Code::DataOverflow => "E005",
+            Code::BadAssertion => "E006",
+            Code::WriteToZero => "W001",
+            Code::ImmediateRange => "W002",
+            Code::RawHazard => "W003",
+            Code::PortRange => "W004",
+            Code::RamRange => "W005",
+            Code::UnusedLabel => "W006",
+            Code::Unreachable => "W007",
+            Code::CrossBank => "W008",
+            Code::UninitializedRead => "W009",
+            Code::DeadStore => "W010",
+        }
+    }
+
+    /// What `@allow` calls a warning. Errors can't be allowed, so they have none.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Code::WriteToZero => Some("r0_write"),
+            Code::ImmediateRange => Some("immediate_range"),
+            Code::RawHazard => Some("raw_hazard"),
+            Code::PortRange => Some("port_range"),
+            Code::RamRange => Some("ram_range"),
+            Code::UnusedLabel => Some("unused_label"),
+            Code::Unreachable => Some("unreachable"),
+            Code::CrossBank => Some("cross_bank"),
+            Code::UninitializedRead => Some("uninitialized_read"),
+            Code::DeadStore => Some("dead_store"),
+            _ => None,
+        }
+    }
+
+    /// By code or warning name, case insensitive, so `electron explain w003` and
+    /// `electron explain raw_hazard` work too.
+    pub fn find(id: &str) -> Option<Code> {
+        let id = id.trim();
+        Self::ALL.into_iter().find(|code| code.id().eq_ignore_ascii_case(id) || code.name().is_some_and(|name| name.eq_ignore_ascii_case(id)))
+    }
+
+    /// `message` with this code in front, as the assembler reports it.
+    pub fn tag(self, message: impl fmt::Display) -> String {
+        format!("[{}] {message}", self.id())
+    }
+
+    pub fn explanation(self) -> Explanation {
+        match self {
+            Code::UnknownOperation => Explanation {
+                summary: "The first word of an instruction isn't an operation.",
+                details: "Each instruction line starts with an operation such as `ADD` or `JMP`, after an optional \
+                          `label:`. The word wasn't one of them, nor one of the ALU operations (`ADD`, `ADDC`, `SUB`, \
+                          `OR`, `XOR`, `AND`) with an `S`, `U` or `X` prefix. `electron isa` lists every operation.",
+                example: "JPM 0",
+                fixed: "JMP 0",
+            },
+            Code::BadOperand => Explanation {
+                summary: "An operand isn't a register, number, address, port or known label.",
+                details: "Operands are registers (`R3` or `$3`), numbers (`42`, `0x2A`, `0b101010` or `'*'`), RAM \
+                          addresses (`#3` or `@3`), ports (`%13`), or labels and `.ascii` names defined somewhere in \
+                          the file. Labels match regardless of case unless the file has `.casesensitive`. A \
+                          branch's target must be an address the PC can reach, 0-254.",
+                example: "IMM R1 0x2G",
+                fixed: "IMM R1 0x2F",
+            },
+            Code::BadDirective => Explanation {
+                summary: "A directive isn't one the assembler knows, or its arguments are wrong.",
+                details: "The directives are `.autonop`, `.casesensitive`, `.forwarding` and `.strict`, which take nothing; \
+                          `.port <port or range> \"label\"` for display ports %0-%7; `.ascii NAME \"text\"`; \
+                          `.word A, B, ...` with numbers or labels; `.stack #base`, optionally followed by `up` \
+                          or `down`; and `.watchdog <clocks>`, optionally followed by `clear`. An `@allow(...)` \
+                          comment lists warnings by name or code, such as `raw_hazard` or `W001`.",
+                example: ".port 9 \"Status\"",
+                fixed: ".port 7 \"Status\"",
+            },
+            Code::DuplicateName => Explanation {
+                summary: "An `.ascii` name is already a label or an earlier `.ascii` name.",
+                details: "`.ascii NAME \"text\"` makes `NAME` (where the text starts in RAM) and `NAME_LEN` (its \
+                          length) into constants that operands can use like labels, so the name has to be free.",
+                example: "LOOP: JMP LOOP\n.ascii LOOP \"HI\"",
+                fixed: "LOOP: JMP LOOP\n.ascii GREETING \"HI\"",
+            },
+            Code::DataOverflow => Explanation {
+                summary: "The `.ascii` data doesn't fit in RAM.",
+                details: "Every `.ascii` text is put in RAM, one after the other from #0, at each reset. RAM has 16 \
+                          bytes, which the stack shares (from #15 down unless `.stack` moves it), so all the texts \
+                          together can take at most 16.",
+                example: ".ascii MSG \"HELLO, WORLD! HOW ARE YOU?\"",
+                fixed: ".ascii MSG \"HELLO, WORLD!\"",
+            },
+            Code::BadAssertion => Explanation {
+                summary: "An `; ASSERT` comment can't be read.",
+                details: "An assertion is `ASSERT <field> <op> <value>`. The field is a register (`R1`), RAM address \
+                          (`#3`), port (`%0`), `ACC`, `PC`, `SP` or a flag (`EQUALS`, `GREATER`, `LESS`, `OVERFLOW`); \
+                          the op is `==`, `!=`, `<`, `<=`, `>` or `>=`; and the value is a number, `true`, `false` or \
+                          another field.",
+                example: "IMM R1 42 ; ASSERT R1 = 42",
+                fixed: "IMM R1 42 ; ASSERT R1 == 42",
+            },
+            Code::WriteToZero => Explanation {
+                summary: "The instruction writes R0, which always reads 0.",
+                details: "R0 is the zero register: writes to it are dropped. To compare two values without keeping \
+                          the result, use the `X` prefix, which only sets the accumulator and the flags.",
+                example: "SUB R0 R2",
+                fixed: "XSUB R2",
+            },
+            Code::ImmediateRange => Explanation {
+                summary: "A number doesn't fit in a byte.",
+                details: "Registers, RAM and ports hold 8 bits, so a value outside 0-255 is wrapped: 256 becomes 0 and \
+                          -1 becomes 255. Larger numbers need two bytes, added with `ADD` and `ADDC`.",
+                example: "IMM R1 300",
+                fixed: "IMM R1 44 ; 300 - 256",
+            },
+            Code::RawHazard => Explanation {
+                summary: "The instruction reads a register the instruction right before it writes (read after write).",
+                details: "Electron V2 has four pipeline stages: fetch, decode, execute and writeback. An instruction \
+                          reads its registers in execute, in the same clock as the one before it writes its result in \
+                          writeback, so it still sees the register's old value. Put a `NOOP` or an unrelated \
+                          instruction between the two. `.autonop` has the assembler insert the NOOPs, `.forwarding` \
+                          runs the program with a bypass from writeback to execute, and `--interlock` makes the \
+                          emulator stall for a clock instead.",
+                example: "IMM R1 5\nADD R2 R1",
+                fixed: "IMM R1 5\nNOOP\nADD R2 R1",
+            },
+            Code::PortRange => Explanation {
+                summary: "A port number is outside 0-255.",
+                details: "`OUT` and `INP` reach ports %0-%255: %0-%7 are the display and the rest are devices. Port \
+                          numbers are cut to a byte, so this reaches some other port.",
+                example: "OUT %300 R1",
+                fixed: "OUT %3 R1",
+            },
+            Code::RamRange => Explanation {
+                summary: "A RAM address is outside #0-#15.",
+                details: "RAM has 16 bytes. `STORE` outside it does nothing and `LOAD` leaves the register as it was, \
+                          except under `--von-neumann`, where #16 and up are the program's own bytes.",
+                example: "STORE #16 R1",
+                fixed: "STORE #15 R1",
+            },
+            Code::UnusedLabel => Explanation {
+                summary: "Nothing refers to the label.",
+                details: "No branch, call, `IMM` or `.word` names it. That's often a typo in the branch that was meant \
+                          to use it, or code left over from an earlier version.",
+                example: "START: IMM R1 1\nHLT",
+                fixed: "IMM R1 1\nHLT",
+            },
+            Code::Unreachable => Explanation {
+                summary: "No path from the start of the program reaches this code.",
+                details: "The assembler follows every branch from address 0, and from labels loaded as data for a \
+                          `RET` to jump to. Code after a `JMP`, `RET` or `HLT` that nothing branches to never runs. \
+                          Runs of only `NOOP`s are left alone, since they're the padding after a branch.",
+                example: "JMP END\nIMM R1 1\nEND: HLT",
+                fixed: "BIE END\nIMM R1 1\nEND: HLT",
+            },
+            Code::CrossBank => Explanation {
+                summary: "A `STORE` or `LOAD` likely reaches a different RAM bank than meant.",
+                details: "`BANK n` (or a write to port %19) picks which of the 16 RAM banks `STORE` and `LOAD` reach, \
+                          but `.ascii` text and the stack stay in bank 0. The assembler follows the bank along every \
+                          path from address 0 and warns when an address holding `.ascii` text is used with another \
+                          bank selected, or when a `LOAD` reads an address that's only stored in a different bank. \
+                          Select the right bank first, or copy the data across.",
+                example: "IMM R1 7\nBANK 1\nSTORE #3 R1\nBANK 0\nLOAD R2 #3",
+                fixed: "IMM R1 7\nBANK 1\nSTORE #3 R1\nLOAD R2 #3",
+            },
+            Code::UninitializedRead => Explanation {
+                summary: "A register is read on a path where nothing has written it yet.",
+                details: "The assembler follows every path from address 0 and notes which registers each one has \
+                          written. A register read before any write holds whatever reset left there, 0, which is \
+                          rarely what was meant: often a typo in the register, or a branch that skips the code \
+                          setting it. Only the first such read on a path is reported. `PUSH` (saving a register) \
+                          and the arguments `SYS` may not use don't count, and after a `CALL` the subroutine is \
+                          taken to have written everything.",
+                example: "IMM R1 5\nBIE SKIP\nIMM R2 1\nSKIP: ADD R1 R2",
+                fixed: "IMM R1 5\nIMM R2 0\nBIE SKIP\nIMM R2 1\nSKIP: ADD R1 R2",
+            },
+            Code::DeadStore => Explanation {
+                summary: "A value is written and then overwritten before anything reads it.",
+                details: "Within a run of instructions that nothing branches into or out of, the register or RAM \
+                          address is written again with no read in between, so the first write only costs a slot \
+                          in ROM and a clock. Reads include the stack operations, `SYS` arguments and `PUSHA`; RAM \
+                          also counts as read by anything that could reach it behind the program's back, such as \
+                          a port write the DMA engine might act on or a `BANK` change. This is separate from a RAW \
+                          hazard, which is about a read that comes too soon.",
+                example: "IMM R1 5\nIMM R1 6\nOUT %0 R1",
+                fixed: "IMM R1 6\nOUT %0 R1",
+            },
+        }
+    }
+}
+
+/// Splits `[W003] text` into the code and the text. Messages without a code come back whole.
+pub fn split_code(message: &str) -> (Option<Code>, &str) {
+    let Some((id, text)) = message.strip_prefix('[').and_then(|rest| rest.split_once("] ")) else {
+        return (None, message);
+    };
+    match Code::find(id) {
+        Some(code) => (Some(code), text),
+        None => (None, message),
+    }
+}
@@ -0,0 +1,76 @@
+use super::{Instruction, Operand, OperandType, Operation, OperationArgs};
+use std::collections::HashMap;
+
+/// Turns parsed instructions back into canonical assembly that the parser reads back to the same
+/// instructions.
+pub struct Disassembler;
+
+impl Disassembler {
+    /// One instruction, e.g. `UADD R5 R0` or `INP R1 %13`.
+    pub fn instruction(instr: &Instruction) -> String {
+        Self::format(instr, &HashMap::new())
+    }
+
+    /// A whole program, one instruction per line. Addresses that `labels` names get a label line,
+    /// and branch targets are written as those labels.
+    pub fn program(instructions: &[Instruction], labels: &HashMap<String, i32>) -> String {
+        let mut names: HashMap<i32, &str> = HashMap::new();
+        for (name, &address) in labels {
+            // Several labels can mark one address; pick one consistently.
+            let entry = names.entry(address).or_insert(name);
+            if name.as_str() < *entry {
+                *entry = name;
+            }
+        }
+
+        let mut out = String::new();
+        for instr in instructions {
+            if let Some(name) = names.get(&instr.address) {
+                out.push_str(&format!("{name}:\n"));
+            }
+            out.push_str(&Self::format(instr, &names));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn format(instr: &Instruction, names: &HashMap<i32, &str>) -> String {
+        let prefix = match instr.args {
+            OperationArgs::None => "",
+            OperationArgs::S => "S",
+            OperationArgs::U => "U",
+            OperationArgs::X => "X",
+        };
+        let mut text = format!("{prefix}{}", instr.operation.get_name());
+
+        let (needs_a, needs_b) = instr.operation.operands(instr.args);
+        let is_branch = matches!(
+            instr.operation,
+            Operation::JMP | Operation::CALL | Operation::BIE | Operation::BIG | Operation::BIL | Operation::BIO
+        );
+        if needs_a {
+            match names.get(&instr.a.data) {
+                Some(name) if is_branch && instr.a.type_ == OperandType::Immediate => {
+                    text.push(' ');
+                    text.push_str(name);
+                }
+                _ => text.push_str(&format!(" {}", Self::operand(&instr.a))),
+            }
+        }
+        // `INP Rx` (wait for the user) has no B; only the port form spells it out.
+        let has_b = needs_b && (instr.operation != Operation::INP || instr.b.type_ == OperandType::Port);
+        if has_b {
+            text.push_str(&format!(" {}", Self::operand(&instr.b)));
+        }
+        text
+    }
+
+    fn operand(operand: &Operand) -> String {
+        match operand.type_ {
+            OperandType::Register => format!("R{}", operand.data),
+            OperandType::MemoryAddress => format!("#{}", operand.data),
+            OperandType::Port => format!("%{}", operand.data),
+            OperandType::Immediate => operand.data.to_string(),
+        }
+    }
+}
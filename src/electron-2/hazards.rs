@@ -0,0 +1,82 @@
+//! The warnings about an instruction that conflicts with the one right before it, tied to both
+//! instructions: a RAW hazard (W003), where it reads a register the one before hasn't written
+//! back yet, and code in a branch's shadow (W007), behind a `JMP`, `RET` or `HLT` that never
+//! continues to it. The window's source panel marks them and lights both up while either is in
+//! the pipeline, and `electron assemble` notes them in its listing.
+
+use super::codes::{split_code, Code};
+use super::parser::{split_location, Parser};
+use super::{Emulator, Instruction};
+
+pub struct Hazard {
+    /// `RawHazard` or `Unreachable`.
+    pub code: Code,
+    /// The instruction the warning is on.
+    pub address: i32,
+    /// The one before it: the write that's read too soon, or the branch it's hidden behind.
+    pub cause: i32,
+    /// The register read too soon, for a RAW hazard.
+    pub register: Option<i32>,
+}
+
+impl Hazard {
+    /// A few words for the instruction the warning is on.
+    pub fn note(&self) -> String {
+        match self.register {
+            Some(register) => format!("reads R{register} before instruction {} writes it back", self.cause),
+            None => format!("never runs: instruction {} doesn't continue here", self.cause),
+        }
+    }
+
+    /// A few words for the instruction before it.
+    pub fn cause_note(&self) -> String {
+        match self.register {
+            Some(register) => format!("instruction {} reads R{register} before this writes it back", self.address),
+            None => format!("instruction {} after this never runs", self.address),
+        }
+    }
+
+    /// Whether `address` is one of the two.
+    pub fn involves(&self, address: i32) -> bool {
+        address >= 0 && (address == self.address || address == self.cause)
+    }
+}
+
+impl Emulator {
+    /// The loaded program's hazards, in address order. The ROM's aren't the program's to fix,
+    /// and `.forwarding` leaves no RAW hazards; `.autonop` has already put NOOPs between them.
+    /// Only the ones still warned about count, so a line's `@allow(raw_hazard)` leaves it out.
+    pub fn hazards(&self) -> Vec<Hazard> {
+        let mut hazards = Vec::new();
+        let warned = |code: Code| -> Vec<usize> {
+            self.warnings
+                .iter()
+                .filter_map(|warning| match split_location(warning) {
+                    (Some(line), _, text) if split_code(text).0 == Some(code) => Some(line),
+                    _ => None,
+                })
+                .collect()
+        };
+        if !self.forwarding {
+            let raw_lines = warned(Code::RawHazard);
+            for pair in self.instructions.windows(2) {
+                let [before, instr] = pair else { continue };
+                if self.in_rom(instr.address) || !raw_lines.contains(&(instr.source_line as usize)) {
+                    continue;
+                }
+                if let Some(register) = Parser::raw_hazard(before, instr) {
+                    hazards.push(Hazard { code: Code::RawHazard, address: instr.address, cause: before.address, register: Some(register) });
+                }
+            }
+        }
+        // The flow pass only reports the line each unreachable run starts on.
+        for line in warned(Code::Unreachable) {
+            let first = self.instructions.iter().find(|instr| instr.source_line == line as i32 && !self.in_rom(instr.address));
+            if let Some(Instruction { address, .. }) = first.filter(|instr| instr.address > 0) {
+                hazards.push(Hazard { code: Code::Unreachable, address: *address, cause: address - 1, register: None });
+            }
+        }
+        hazards.sort_by_key(|hazard| hazard.address);
+        hazards
+    }
+}
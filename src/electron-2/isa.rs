@@ -0,0 +1,204 @@
+//! What each operation is: its mnemonic, operands, prefixes, effect on the flags and a short
+//! description. The parser reads its mnemonics and operand counts from here, the ALU which
+//! operations set the flags, and `electron isa` prints it as the instruction-set reference, so
+//! the three can't drift apart.
+
+use super::{Operation, OperationArgs};
+
+pub struct OperationInfo {
+    pub operation: Operation,
+    pub mnemonic: &'static str,
+    /// Other spellings the parser accepts.
+    pub aliases: &'static [&'static str],
+    /// Operands A and B as written: `Rx`/`Ry` (a register), `imm` (a number or `.ascii`
+    /// constant), `off` (a number of bytes back into the stack from SP), `mask` (a byte with bit
+    /// n set for Rn), `#addr` (RAM), `%port` or `label` (a label or instruction address). An
+    /// operand in brackets may be left out.
+    pub operands: [Option<&'static str>; 2],
+    /// Takes the `S`, `U` and `X` prefixes; under `X` it has no operand A.
+    pub prefixes: bool,
+    /// Writes operand A, a register, when it writes back (not under `X`).
+    pub writes_register: bool,
+    /// Puts its result in the accumulator and sets the four flags from its inputs.
+    pub sets_flags: bool,
+    /// Also part of the Electron V1 instruction set.
+    pub v1: bool,
+    pub group: Group,
+    pub description: &'static str,
+    /// One line that assembles on its own (on V1 too, for V1 operations).
+    pub example: &'static str,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Group {
+    Assignment,
+    MathAndLogic,
+    Memory,
+    FlowControl,
+    SystemAndIo,
+}
+
+impl Group {
+    pub const ALL: [Group; 5] = [Group::Assignment, Group::MathAndLogic, Group::Memory, Group::FlowControl, Group::SystemAndIo];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Group::Assignment => "Assignments",
+            Group::MathAndLogic => "Math & Logic",
+            Group::Memory => "Memory (RAM)",
+            Group::FlowControl => "Flow Control",
+            Group::SystemAndIo => "System & I/O",
+        }
+    }
+}
+
+const fn op(
+    operation: Operation,
+    mnemonic: &'static str,
+    operands: [Option<&'static str>; 2],
+    group: Group,
+    description: &'static str,
+    example: &'static str,
+) -> OperationInfo {
+    OperationInfo {
+        operation,
+        mnemonic,
+        aliases: &[],
+        operands,
+        prefixes: false,
+        writes_register: false,
+        sets_flags: false,
+        v1: false,
+        group,
+        description,
+        example,
+    }
+}
+
+const NONE: [Option<&str>; 2] = [None, None];
+const RX: [Option<&str>; 2] = [Some("Rx"), None];
+const RX_RY: [Option<&str>; 2] = [Some("Rx"), Some("Ry")];
+const LABEL: [Option<&str>; 2] = [Some("label"), None];
+
+/// The ALU operations that combine Rx (or the accumulator) with Ry.
+const fn alu(operation: Operation, mnemonic: &'static str, description: &'static str, example: &'static str) -> OperationInfo {
+    OperationInfo { prefixes: true, writes_register: true, sets_flags: true, ..op(operation, mnemonic, RX_RY, Group::MathAndLogic, description, example) }
+}
+
+/// In opcode order, so `Operation::info` can index it.
+pub const OPERATIONS: [OperationInfo; 32] = [
+    OperationInfo { aliases: &["NOP"], v1: true, ..op(Operation::NOOP, "NOOP", NONE, Group::SystemAndIo, "Does nothing for a clock.", "NOOP") },
+    OperationInfo { writes_register: true, v1: true, ..op(Operation::IMM, "IMM", [Some("Rx"), Some("imm")], Group::Assignment, "Sets Rx to imm.", "IMM R1 42") },
+    OperationInfo { writes_register: true, v1: true, ..op(Operation::MOV, "MOV", RX_RY, Group::Assignment, "Copies Ry into Rx.", "MOV R2 R1") },
+    OperationInfo { v1: true, ..alu(Operation::ADD, "ADD", "Rx = Rx + Ry.", "ADD R1 R2") },
+    OperationInfo { v1: true, ..alu(Operation::ADDC, "ADDC", "Rx = Rx + Ry + the overflow flag, for sums wider than a byte.", "ADDC R1 R2") },
+    alu(Operation::SUB, "SUB", "Rx = Rx - Ry.", "SUB R1 R2"),
+    alu(Operation::OR, "OR", "Rx = Rx | Ry (bitwise OR).", "OR R1 R2"),
+    alu(Operation::XOR, "XOR", "Rx = Rx ^ Ry (bitwise XOR).", "XOR R1 R2"),
+    alu(Operation::AND, "AND", "Rx = Rx & Ry (bitwise AND).", "AND R1 R2"),
+    OperationInfo { writes_register: true, sets_flags: true, v1: true, ..op(Operation::SHR, "SHR", RX_RY, Group::MathAndLogic, "Rx = Ry shifted right by one bit.", "SHR R1 R2") },
+    OperationInfo { writes_register: true, sets_flags: true, v1: true, ..op(Operation::NOT, "NOT", RX_RY, Group::MathAndLogic, "Rx = Ry with every bit inverted.", "NOT R1 R2") },
+    OperationInfo { v1: true, ..op(Operation::OUT, "OUT", [Some("%port"), Some("Ry")], Group::SystemAndIo, "Sends Ry to the port (%0-%255); %0-%7 are the display.", "OUT %0 R1") },
+    op(Operation::ROUT, "ROUT", RX_RY, Group::SystemAndIo, "Sends Ry to the port numbered by Rx.", "ROUT R1 R2"),
+    OperationInfo {
+        writes_register: true,
+        ..op(Operation::INP, "INP", [Some("Rx"), Some("[%port]")], Group::SystemAndIo, "Waits for a typed value and puts it in Rx; with a port, reads the port into Rx without waiting.", "INP R1 %13")
+    },
+    OperationInfo { v1: true, ..op(Operation::JMP, "JMP", LABEL, Group::FlowControl, "Continues at the label.", "JMP 0") },
+    OperationInfo { v1: true, ..op(Operation::BIE, "BIE", LABEL, Group::FlowControl, "Jumps if the last ALU operation's inputs were equal.", "BIE 0") },
+    op(Operation::BIG, "BIG", LABEL, Group::FlowControl, "Jumps if the first input of the last ALU operation was greater.", "BIG 0"),
+    op(Operation::BIL, "BIL", LABEL, Group::FlowControl, "Jumps if the first input of the last ALU operation was less.", "BIL 0"),
+    op(Operation::BIO, "BIO", LABEL, Group::FlowControl, "Jumps if the last ALU operation overflowed.", "BIO 0"),
+    op(Operation::STORE, "STORE", [Some("#addr"), Some("Ry")], Group::Memory, "Saves Ry at the RAM address (#0-#15).", "STORE #3 R1"),
+    OperationInfo { writes_register: true, ..op(Operation::LOAD, "LOAD", [Some("Rx"), Some("#addr")], Group::Memory, "Loads the RAM address into Rx.", "LOAD R1 #3") },
+    op(Operation::PUSH, "PUSH", RX, Group::SystemAndIo, "Saves Rx on the stack, which grows down from #15 (or as `.stack` says) and shares RAM.", "PUSH R1"),
+    OperationInfo { writes_register: true, ..op(Operation::POP, "POP", RX, Group::SystemAndIo, "Takes the top of the stack into Rx.", "POP R1") },
+    op(Operation::CALL, "CALL", LABEL, Group::FlowControl, "Pushes the address after it and continues at the label.", "CALL 0"),
+    op(Operation::RET, "RET", NONE, Group::FlowControl, "Pops the address CALL pushed and continues there.", "RET"),
+    OperationInfo { aliases: &["HALT"], ..op(Operation::HLT, "HLT", NONE, Group::SystemAndIo, "Stops the clock once everything before it has finished.", "HLT") },
+    OperationInfo {
+        writes_register: true,
+        ..op(Operation::LOADS, "LOADS", [Some("Rx"), Some("off")], Group::Memory, "Loads the stack byte off places back from SP into Rx; 1 is the top of the stack.", "LOADS R1 1")
+    },
+    op(Operation::STORES, "STORES", [Some("Rx"), Some("off")], Group::Memory, "Saves Rx in the stack byte off places back from SP, as LOADS reads it.", "STORES R1 1"),
+    op(
+        Operation::PUSHA,
+        "PUSHA",
+        [Some("[mask]"), None],
+        Group::SystemAndIo,
+        "Pushes R1-R7, or the registers in mask, from R1 up, one a clock; the instructions behind it wait.",
+        "PUSHA 0b00000110",
+    ),
+    op(Operation::POPA, "POPA", [Some("[mask]"), None], Group::SystemAndIo, "Pops what PUSHA with the same mask pushed, from R7 down, one a clock.", "POPA 0b00000110"),
+    op(
+        Operation::SYS,
+        "SYS",
+        [Some("imm"), None],
+        Group::SystemAndIo,
+        "Calls host service imm with R1 and R2 as arguments; a result goes in R1 at writeback, so wait a clock to read it. See the README for the services.",
+        "SYS 1",
+    ),
+    op(
+        Operation::BANK,
+        "BANK",
+        [Some("imm"), None],
+        Group::Memory,
+        "Selects which of the 16 RAM banks STORE and LOAD reach from the next instruction; the stack and `.ascii` text stay in bank 0.",
+        "BANK 1",
+    ),
+];
+
+impl Operation {
+    pub fn info(&self) -> &'static OperationInfo {
+        &OPERATIONS[*self as usize]
+    }
+
+    /// Whether the instruction, as `args` makes it, takes operands A and B. Optional ones count:
+    /// `INP Rx` waits for the user and `INP Rx %port` reads the port bus.
+    pub fn operands(&self, args: OperationArgs) -> (bool, bool) {
+        let info = self.info();
+        let [a, b] = info.operands;
+        (a.is_some() && !(info.prefixes && args == OperationArgs::X), b.is_some())
+    }
+}
+
+/// The reference `electron isa` prints, in Markdown.
+pub fn reference() -> String {
+    let mut out = String::from("# Electron V2 Instruction Set\n\n");
+    out.push_str("Generated by `electron isa` from the table the assembler and emulator use.\n\n");
+    out.push_str(
+        "Operands: `Rx` and `Ry` are registers R0-R7 (R0 always reads 0), `imm` an 8-bit number, `off` a number of \
+         bytes back into the stack from SP (1 is the top), `mask` a byte with bit n set for each register Rn, `#addr` a RAM address, `%port` a port and `label` a label or \
+         instruction address. An operand in brackets may be left out.\n\n",
+    );
+    out.push_str(
+        "Prefixes: `S` is the same as no prefix; `U` takes the accumulator in place of Rx (`UADD R1 R2` is \
+         R1 = ACC + R2); `X` only updates the accumulator and flags and has no Rx (`XSUB R2` is ACC - R2).\n\n",
+    );
+    out.push_str(
+        "Flags: the operations marked set the accumulator to their result and the EQUALS, GREATER and LESS flags \
+         by comparing their two inputs, and OVERFLOW when the result doesn't fit in a byte.\n",
+    );
+    for group in Group::ALL {
+        out.push_str(&format!("\n## {}\n\n", group.title()));
+        out.push_str("| Instruction | Operands | Prefixes | Flags | V1 | Description | Example |\n");
+        out.push_str("| :--- | :--- | :--- | :--- | :--- | :--- | :--- |\n");
+        for info in OPERATIONS.iter().filter(|info| info.group == group) {
+            let mut names = format!("`{}`", info.mnemonic);
+            for alias in info.aliases {
+                names.push_str(&format!(" (`{alias}`)"));
+            }
+            let operands: Vec<&str> = info.operands.iter().flatten().copied().collect();
+            let operands = if operands.is_empty() { "-".to_string() } else { format!("`{}`", operands.join(" ")) };
+            out.push_str(&format!(
+                "| {names} | {operands} | {} | {} | {} | {} | `{}` |\n",
+                if info.prefixes { "S, U, X" } else { "-" },
+                if info.sets_flags { "E G L O" } else { "-" },
+                if info.v1 { "yes" } else { "-" },
+                info.description.replace('|', "\\|"),
+                info.example,
+            ));
+        }
+    }
+    out
+}
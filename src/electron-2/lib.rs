@@ -1,21 +1,52 @@
+pub mod codes;
+pub mod disassembler;
+pub mod hazards;
+pub mod isa;
 pub mod parser;
 use parser::Parser;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
 
 // --- Enums & Types ---
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Operation {
     NOOP, IMM, MOV, ADD, ADDC, SUB, OR, XOR, AND, SHR, NOT,
     OUT, ROUT, INP, JMP, BIE, BIG, BIL, BIO, STORE, LOAD,
-    PUSH, POP, CALL, RET
+    PUSH, POP, CALL, RET, HLT, LOADS, STORES, PUSHA, POPA, SYS, BANK
 }
 
 impl Operation {
+    /// In declaration order, which is also the opcode order of the `--von-neumann` encoding.
+    const ALL: [Operation; 32] = [
+        Operation::NOOP, Operation::IMM, Operation::MOV, Operation::ADD, Operation::ADDC, Operation::SUB,
+        Operation::OR, Operation::XOR, Operation::AND, Operation::SHR, Operation::NOT, Operation::OUT,
+        Operation::ROUT, Operation::INP, Operation::JMP, Operation::BIE, Operation::BIG, Operation::BIL,
+        Operation::BIO, Operation::STORE, Operation::LOAD, Operation::PUSH, Operation::POP, Operation::CALL,
+        Operation::RET, Operation::HLT, Operation::LOADS, Operation::STORES, Operation::PUSHA, Operation::POPA,
+        Operation::SYS, Operation::BANK,
+    ];
+
     pub fn get_name(&self) -> String {
         format!("{:?}", self)
     }
 }
 
+/// Port %19 reads and selects the RAM bank, like `BANK n` but for a bank worked out at run time.
+pub const BANK_PORT: i32 = 19;
+/// RAM banks `STORE` and `LOAD` can reach, 16 bytes each.
+pub const BANKS: usize = 16;
+
+/// The registers a `PUSHA` or `POPA` with operand `mask` moves: bit n for Rn. R0's bit is
+/// ignored, and a mask without any of R1-R7 (or none at all) means all of them.
+pub(crate) fn register_mask(mask: i32) -> u8 {
+    match mask as u8 & 0xFE {
+        0 => 0xFE,
+        mask => mask,
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum OperationArgs {
     None, S, U, X
@@ -49,6 +80,9 @@ pub struct Instruction {
     pub b: Operand,
     pub address: i32,
     pub source_line: i32,
+    /// Where operands A and B start in the source line, counted from 1; 0 for an operand the
+    /// line doesn't have.
+    pub columns: [usize; 2],
 }
 
 impl Instruction {
@@ -60,12 +94,51 @@ impl Instruction {
             b: Operand::new(OperandType::Immediate, 0),
             address: -1,
             source_line: 0,
+            columns: [0; 2],
+        }
+    }
+
+    /// The four bytes this instruction takes in `--von-neumann` memory: the opcode with the
+    /// S/U/X suffix in bits 5-6, the operand types (A in bits 0-1, B in bits 2-3), then the
+    /// operand values, each cut to a byte.
+    pub fn encode(&self) -> [u8; 4] {
+        let args = match self.args {
+            OperationArgs::None => 0,
+            OperationArgs::S => 1,
+            OperationArgs::U => 2,
+            OperationArgs::X => 3,
+        };
+        [
+            self.operation as u8 | args << 5,
+            self.a.type_ as u8 | (self.b.type_ as u8) << 2,
+            self.a.data as u8,
+            self.b.data as u8,
+        ]
+    }
+
+    /// Reads back what `encode` wrote; None for bytes that aren't an instruction.
+    pub fn decode(bytes: [u8; 4], address: i32, source_line: i32) -> Option<Self> {
+        let operation = *Operation::ALL.get((bytes[0] & 0x1F) as usize)?;
+        let args = [OperationArgs::None, OperationArgs::S, OperationArgs::U, OperationArgs::X][(bytes[0] >> 5 & 3) as usize];
+        let operand_type = |bits: u8| [OperandType::Register, OperandType::MemoryAddress, OperandType::Immediate, OperandType::Port][(bits & 3) as usize];
+        if bytes[0] & 0x80 != 0 || bytes[1] & 0xF0 != 0 {
+            return None;
         }
+        Some(Self {
+            operation,
+            args,
+            a: Operand::new(operand_type(bytes[1]), bytes[2] as i32),
+            b: Operand::new(operand_type(bytes[1] >> 2), bytes[3] as i32),
+            address,
+            source_line,
+            columns: [0; 2],
+        })
     }
 }
 
 // --- Components ---
 
+#[derive(Clone)]
 pub struct Registers {
     pub regs: [u8; 8],
     pub next_regs: [u8; 8],
@@ -107,11 +180,18 @@ impl Registers {
         }
     }
     
+    /// The register file as it will be after this clock, with the writes made so far in it.
+    /// Forwarding reads from this.
+    pub fn forwarded(&self) -> Registers {
+        Registers { regs: self.next_regs, next_regs: self.next_regs }
+    }
+
     pub fn get_all(&self) -> Vec<u8> {
         self.regs.to_vec()
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct AluFlags {
     pub equals: bool,
     pub greater: bool,
@@ -119,6 +199,8 @@ pub struct AluFlags {
     pub overflow: bool,
 }
 
+#[derive(Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct ALU {
     pub accumulator: u8,
     pub flags: AluFlags,
@@ -163,7 +245,7 @@ impl ALU {
             Operation::AND => result = (a_data as i32) & (b_data as i32),
             Operation::SHR => result = (b_data as i32) >> 1,
             Operation::NOT => result = (!b_data as i32) & 0xFF,
-            Operation::INP => {
+            Operation::INP if instr.b.type_ != OperandType::Port => {
                 *waiting_for_input = true;
                 *input_register = instr.a.data;
                 result = 0;
@@ -172,13 +254,7 @@ impl ALU {
         }
 
         // Store Accumulator
-        let is_alu_op = matches!(op, 
-            Operation::ADD | Operation::ADDC | Operation::SUB | 
-            Operation::OR | Operation::XOR | Operation::AND | 
-            Operation::SHR | Operation::NOT
-        );
-
-        if is_alu_op {
+        if op.info().sets_flags {
             // Flags
             self.flags.equals = a_data == b_data;
             self.flags.greater = a_data > b_data;
@@ -190,6 +266,576 @@ impl ALU {
     }
 }
 
+// --- Snapshots ---
+
+/// A named piece of architectural state, spelled the way operands are written in assembly
+/// (`R1`, `$1`, `#3`, `@3`, `%0`) or by name (`ACC`, `PC`, `SP`, `EQUALS`, `GREATER`, `LESS`,
+/// `OVERFLOW`). Case insensitive.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StateField {
+    Register(usize),
+    Ram(usize),
+    Port(usize),
+    Accumulator,
+    Pc,
+    Sp,
+    Equals,
+    Greater,
+    Less,
+    Overflow,
+}
+
+impl StateField {
+    pub fn parse(s: &str) -> Option<StateField> {
+        let upper = s.trim().to_uppercase();
+        let index = |rest: &str, len: usize| rest.parse::<usize>().ok().filter(|i| *i < len);
+        match upper.as_str() {
+            "ACC" => Some(StateField::Accumulator),
+            "PC" => Some(StateField::Pc),
+            "SP" => Some(StateField::Sp),
+            "EQUALS" => Some(StateField::Equals),
+            "GREATER" => Some(StateField::Greater),
+            "LESS" => Some(StateField::Less),
+            "OVERFLOW" => Some(StateField::Overflow),
+            _ => {
+                let (first, rest) = upper.split_at(upper.chars().next()?.len_utf8());
+                match first {
+                    "R" | "$" => index(rest, 8).map(StateField::Register),
+                    "#" | "@" => index(rest, 16).map(StateField::Ram),
+                    "%" => index(rest, 8).map(StateField::Port),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// The canonical spelling, e.g. `R1`, `#3` or `ACC`.
+    pub fn name(&self) -> String {
+        match self {
+            StateField::Register(i) => format!("R{i}"),
+            StateField::Ram(i) => format!("#{i}"),
+            StateField::Port(i) => format!("%{i}"),
+            StateField::Accumulator => "ACC".to_string(),
+            StateField::Pc => "PC".to_string(),
+            StateField::Sp => "SP".to_string(),
+            StateField::Equals => "EQUALS".to_string(),
+            StateField::Greater => "GREATER".to_string(),
+            StateField::Less => "LESS".to_string(),
+            StateField::Overflow => "OVERFLOW".to_string(),
+        }
+    }
+}
+
+/// A copy of the architectural state at one point in time, for frontends that want to look at
+/// state without holding on to the emulator.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub pc: i32,
+    pub sp: i32,
+    pub registers: [u8; 8],
+    pub accumulator: u8,
+    pub flags: AluFlags,
+    pub ram: [u8; 16],
+    pub ports_out: [u8; 8],
+    /// Fetch, decode, execute and writeback registers, in that order.
+    pub pipeline: [Instruction; 4],
+    pub waiting_for_input: bool,
+}
+
+/// Everything a clock can change inside the core, for frontends that rewind. Unlike `Snapshot`
+/// it includes in-flight register writes and counters. Devices keep their own state, which a
+/// checkpoint doesn't cover.
+#[derive(Clone)]
+pub struct Checkpoint {
+    pc: i32,
+    sp: i32,
+    pipeline: [Instruction; 4],
+    registers: Registers,
+    alu: ALU,
+    ports_out: [u8; 8],
+    ram: [u8; 16],
+    stack: [u8; 16],
+    bank: u8,
+    banks: [[u8; 16]; BANKS - 1],
+    dma: Dma,
+    watchdog_clocks: u32,
+    watchdog_resets: u8,
+    transfer: u8,
+    code_memory: Vec<u8>,
+    waiting_for_input: bool,
+    input_register: i32,
+    fault: Option<String>,
+    halted: bool,
+    exit_code: Option<u8>,
+    stats: Stats,
+    replay_pos: usize,
+    recorded: usize,
+}
+
+impl Snapshot {
+    pub fn get(&self, field: StateField) -> i32 {
+        match field {
+            StateField::Register(i) => if i == 0 { 0 } else { self.registers[i] as i32 },
+            StateField::Ram(i) => self.ram[i] as i32,
+            StateField::Port(i) => self.ports_out[i] as i32,
+            StateField::Accumulator => self.accumulator as i32,
+            StateField::Pc => self.pc,
+            StateField::Sp => self.sp,
+            StateField::Equals => self.flags.equals as i32,
+            StateField::Greater => self.flags.greater as i32,
+            StateField::Less => self.flags.less as i32,
+            StateField::Overflow => self.flags.overflow as i32,
+        }
+    }
+
+    /// `get` by name, e.g. `snapshot.query("%3")` or `snapshot.query("acc")`.
+    pub fn query(&self, name: &str) -> Option<i32> {
+        StateField::parse(name).map(|field| self.get(field))
+    }
+}
+
+// --- Assertions ---
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    pub fn parse(s: &str) -> Option<Comparison> {
+        match s {
+            "==" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            _ => None,
+        }
+    }
+
+    pub fn holds(self, left: i32, right: i32) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+/// The right-hand side of an assertion: a number or another piece of state.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AssertValue {
+    Number(i32),
+    Field(StateField),
+}
+
+/// A `; ASSERT <field> <op> <value>` comment. It is checked once every instruction up to and
+/// including `after` has written back and before the next one executes, so it sees the state
+/// the source order suggests.
+#[derive(Clone, Debug)]
+pub struct Assertion {
+    /// Address of the last instruction at or above the comment; -1 checks before the first clock.
+    pub after: i32,
+    pub source_line: i32,
+    /// The condition as written, for failure messages.
+    pub text: String,
+    pub field: StateField,
+    pub comparison: Comparison,
+    pub value: AssertValue,
+}
+
+/// Where the instruction at an address came from, for frontends that show the source of
+/// what's in each pipeline stage.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    /// `Emulator::source_file` when the program was loaded.
+    pub file: Arc<str>,
+    pub source_line: i32,
+    /// The line as written, trimmed.
+    pub text: String,
+    /// Put there by `.autonop` ahead of the instruction on the same line, not written by hand.
+    pub generated: bool,
+}
+
+/// `--rom`: library routines assembled ahead of every program, so they sit at the same
+/// addresses whatever program follows. Labels resolve across both, and execution starts at the
+/// ROM's address 0, so a ROM begins with its reset code (often `JMP MAIN` to a label the program
+/// defines).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rom {
+    pub file: String,
+    pub source: String,
+}
+
+// --- Directives ---
+
+/// A `.port <n> "text"` or `.port <first>-<last> "text"` line naming one display port or a
+/// group of them, for the frontends to show next to the LEDs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortLabel {
+    pub first: u8,
+    pub last: u8,
+    pub text: String,
+}
+
+/// Where the stack starts and which way it grows, from a `.stack` line or `--stack`. SP starts
+/// at `base` and names the next free byte; each push moves it one byte the way the stack grows,
+/// wrapping round the 16 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackLayout {
+    pub base: u8,
+    pub grows_up: bool,
+}
+
+impl Default for StackLayout {
+    /// #15 down, as on the hardware.
+    fn default() -> Self {
+        StackLayout { base: 15, grows_up: false }
+    }
+}
+
+impl StackLayout {
+    /// Reads `#12`, `#12 down` or `#0 up`. The `#` may be left out and a comma may stand in for
+    /// the space, so `--stack 0,up` works without quotes.
+    pub fn parse(text: &str) -> Result<StackLayout, String> {
+        let usage = || {
+            format!("\"{}\" is not a stack base (#0-#15) optionally followed by `up` or `down`, e.g. `#12` or `#0 up`", text.trim())
+        };
+        let mut words = text.split([' ', '\t', ',']).filter(|word| !word.is_empty());
+        let base = words
+            .next()
+            .and_then(|word| word.trim_start_matches(['#', '@']).parse::<u8>().ok())
+            .filter(|base| *base < 16)
+            .ok_or_else(usage)?;
+        let grows_up = match words.next().map(str::to_ascii_lowercase).as_deref() {
+            None | Some("down") => false,
+            Some("up") => true,
+            Some(_) => return Err(usage()),
+        };
+        if words.next().is_some() {
+            return Err(usage());
+        }
+        Ok(StackLayout { base, grows_up })
+    }
+
+    /// The byte `offset` places back into the stack from `sp`, wrapping round the 16 bytes:
+    /// 1 is the last one pushed, 2 the one before it, and -1 the next free byte after SP's.
+    fn offset(&self, sp: i32, offset: i32) -> i32 {
+        // Wrapped first, so no offset an operand can hold overflows.
+        let offset = offset.rem_euclid(16);
+        (sp + if self.grows_up { -offset } else { offset }).rem_euclid(16)
+    }
+
+    /// One byte the way the stack grows from `sp`.
+    fn grow(&self, sp: i32) -> i32 {
+        self.offset(sp, -1)
+    }
+
+    /// One byte back from `sp`, the way the stack shrinks.
+    fn shrink(&self, sp: i32) -> i32 {
+        self.offset(sp, 1)
+    }
+}
+
+impl std::fmt::Display for StackLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "#{} {}", self.base, if self.grows_up { "up" } else { "down" })
+    }
+}
+
+pub const WATCHDOG_PORT: i32 = 36;
+
+/// From a `.watchdog` line or `--watchdog`: unless the program writes `WATCHDOG_PORT` ("kicks"
+/// it) at least once every `timeout` clocks, the watchdog restarts it from address 0. The
+/// registers, RAM and ports are kept, so the program can tell it was restarted, unless `clear`
+/// puts them back as at power-on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchdog {
+    pub timeout: u32,
+    pub clear: bool,
+}
+
+impl Watchdog {
+    /// Reads `500` or `500 clear`; a comma may stand in for the space, as in `--watchdog 500,clear`.
+    pub fn parse(text: &str) -> Result<Watchdog, String> {
+        let usage = || {
+            format!("\"{}\" is not a number of clocks (1 or more) optionally followed by `clear`, e.g. `500` or `500 clear`", text.trim())
+        };
+        let mut words = text.split([' ', '\t', ',']).filter(|word| !word.is_empty());
+        let timeout = words.next().and_then(|word| word.parse::<u32>().ok()).filter(|timeout| *timeout > 0).ok_or_else(usage)?;
+        let clear = match words.next().map(str::to_ascii_lowercase).as_deref() {
+            None => false,
+            Some("clear") => true,
+            Some(_) => return Err(usage()),
+        };
+        if words.next().is_some() {
+            return Err(usage());
+        }
+        Ok(Watchdog { timeout, clear })
+    }
+}
+
+// --- Devices ---
+
+/// A peripheral on the port bus. Ports 0-7 drive the display; devices answer on their own port
+/// numbers (see the peripheral map in ARCHITECTURE-v2.md). Programs write with `OUT`/`ROUT` and
+/// read with `INP Rx %port`.
+pub trait Device: Send {
+    /// Whether this device answers reads and writes on `port`.
+    fn claims(&self, port: u8) -> bool;
+    fn read(&mut self, _port: u8) -> u8 { 0 }
+    fn write(&mut self, _port: u8, _value: u8) {}
+    /// Called once per clock, before the pipeline advances.
+    fn tick(&mut self) {}
+    /// Whether reads depend on the host (wall clock, unseeded randomness). Deterministic mode
+    /// refuses to read such a device.
+    fn host_dependent(&self) -> bool { false }
+}
+
+pub const DMA_SOURCE_PORT: i32 = 32;
+pub const DMA_DESTINATION_PORT: i32 = 33;
+pub const DMA_LENGTH_PORT: i32 = 34;
+pub const DMA_CONTROL_PORT: i32 = 35;
+
+/// Writing one of these to `DMA_CONTROL_PORT` starts a transfer; 0 stops it.
+pub const DMA_COPY: u8 = 1;
+pub const DMA_TO_PORT: u8 = 2;
+pub const DMA_TO_PORTS: u8 = 3;
+
+/// The DMA engine on ports %32-%35, built into the core since it reaches RAM. Once started it
+/// moves a byte a clock from the selected bank: to RAM (`DMA_COPY`), to one port
+/// (`DMA_TO_PORT`), or to consecutive ports (`DMA_TO_PORTS`, e.g. eight bytes to the display).
+/// Each byte takes the memory bus for its clock, so the pipeline waits meanwhile (cycle stealing).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dma {
+    /// The next byte to read; RAM addresses wrap round the bank.
+    pub source: u8,
+    /// The next RAM address or port to write.
+    pub destination: u8,
+    /// Bytes left to move.
+    pub length: u8,
+    /// The running transfer, 0 when idle.
+    pub mode: u8,
+}
+
+impl Dma {
+    pub fn busy(&self) -> bool {
+        self.mode != 0 && self.length > 0
+    }
+
+    fn read(&self, port: i32) -> u8 {
+        match port {
+            DMA_SOURCE_PORT => self.source,
+            DMA_DESTINATION_PORT => self.destination,
+            DMA_LENGTH_PORT => self.length,
+            _ => u8::from(self.busy()),
+        }
+    }
+
+    fn write(&mut self, port: i32, value: u8) {
+        match port {
+            DMA_SOURCE_PORT => self.source = value % 16,
+            DMA_DESTINATION_PORT => self.destination = value,
+            DMA_LENGTH_PORT => self.length = value,
+            _ => self.mode = if (DMA_COPY..=DMA_TO_PORTS).contains(&value) { value } else { 0 },
+        }
+    }
+}
+
+// --- Host Services ---
+
+/// What a host service answers a `SYS`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SysResult {
+    Done,
+    /// Put the value in R1.
+    Return(u8),
+    /// Wait for a typed value for R1, as `INP R1` does. Frontends answer with `resolve_input`,
+    /// and `--record` and deterministic mode treat it as the `INP` it is.
+    Input,
+    /// Stop as `HLT` does, with the value as the program's exit code.
+    Exit(u8),
+    /// The service can't be done: no service has that number, say. The core faults.
+    Fault(String),
+}
+
+/// The services a frontend offers programs through `SYS n`, so they can print, read and sleep
+/// without a peripheral for each. R1 and R2 carry the arguments by convention.
+pub trait Syscalls: Send {
+    /// Runs service `number` with R0-R7 as `SYS` reads them, and RAM to read text from.
+    fn call(&mut self, number: u8, registers: [u8; 8], ram: &[u8; 16]) -> SysResult;
+}
+
+// --- Record & Replay ---
+
+/// A value that came from outside the core: a typed answer to a blocking `INP`, or a read of a
+/// host-dependent device port. `cycle` is `stats.cycles` when the value arrived.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputEvent {
+    Inp { cycle: u64, value: u8 },
+    Port { cycle: u64, port: u8, value: u8 },
+}
+
+impl InputEvent {
+    /// Parses one line as written by `Display`: `inp <cycle> <value>` or
+    /// `port <cycle> <port> <value>`.
+    pub fn parse(line: &str) -> Result<InputEvent, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let cycle = |i: usize| words.get(i).and_then(|w| w.parse::<u64>().ok());
+        let byte = |i: usize| words.get(i).and_then(|w| w.parse::<u8>().ok());
+        let event = match words[..] {
+            ["inp", ..] if words.len() == 3 => cycle(1).zip(byte(2)).map(|(cycle, value)| InputEvent::Inp { cycle, value }),
+            ["port", ..] if words.len() == 4 => cycle(1)
+                .zip(byte(2))
+                .zip(byte(3))
+                .map(|((cycle, port), value)| InputEvent::Port { cycle, port, value }),
+            _ => None,
+        };
+        event.ok_or(format!("expected `inp <clock> <value>` or `port <clock> <port> <value>`, got \"{line}\""))
+    }
+}
+
+impl std::fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            InputEvent::Inp { cycle, value } => write!(f, "inp {cycle} {value}"),
+            InputEvent::Port { cycle, port, value } => write!(f, "port {cycle} {port} {value}"),
+        }
+    }
+}
+
+// --- Statistics ---
+
+/// Running totals since the program was loaded.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Stats {
+    /// Clocks that advanced the pipeline (clocks spent waiting on `INP` don't count).
+    pub cycles: u64,
+    /// Instructions that completed the writeback stage.
+    pub retired: u64,
+    /// Instructions that entered the execute stage; bubbles don't count.
+    pub executed: u64,
+    /// Taken branches, including `JMP`, `CALL` and `RET`.
+    pub branches: u64,
+    /// Instructions discarded from the pipeline by taken branches.
+    pub flushed: u64,
+    /// Clocks the interlock held an instruction in decode for a RAW hazard, a `PUSHA` or `POPA`
+    /// held the pipeline behind it, or the DMA engine had the memory bus.
+    pub stalls: u64,
+    /// `ASSERT` checks that held.
+    pub assertions: u64,
+    /// Clocks the DMA engine took the memory bus for; they're stalls too.
+    pub dma: u64,
+}
+
+impl Stats {
+    /// Instructions retired per cycle.
+    pub fn ipc(&self) -> f64 {
+        if self.cycles == 0 { 0.0 } else { self.retired as f64 / self.cycles as f64 }
+    }
+}
+
+// --- Tracing ---
+
+/// The instruction a stage just took in, at trace level; bubbles are skipped.
+fn trace_stage(stage: &'static str, instruction: &Instruction) {
+    if instruction.address >= 0 {
+        tracing::trace!(stage, address = instruction.address, instruction = %disassembler::Disassembler::instruction(instruction));
+    }
+}
+
+// --- Hooks ---
+
+/// Closures attached with `Emulator::on_clock`, `on_port_write`, `on_register_write` and
+/// `on_branch`, for instrumentation that shouldn't need a fork of the core. Peripherals that
+/// answer reads are `Device`s instead.
+#[derive(Default)]
+pub struct Hooks {
+    clock: Vec<ClockHook>,
+    port_write: Vec<Box<dyn FnMut(u8, u8) + Send>>,
+    register_write: Vec<Box<dyn FnMut(u8, u8) + Send>>,
+    branch: Vec<Box<dyn FnMut(i32, i32) + Send>>,
+}
+
+type ClockHook = Box<dyn FnMut(&Emulator) + Send>;
+
+// --- Events ---
+
+/// Something a clock did, queued in `Emulator::events` for a frontend to drain each frame and
+/// animate (flash a port that changed, say) without diffing the whole state.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Event {
+    /// From `OUT`, `ROUT` or `write_port`.
+    PortWrite { port: u8, value: u8 },
+    /// Writeback wrote R1-R7.
+    RegisterWrite { register: u8, value: u8 },
+    /// `STORE` to RAM, or to code memory under `von_neumann`.
+    MemoryWrite { address: i32, value: u8 },
+    /// `PUSH`, or the return address `CALL` saves. The address is in RAM, or in `stack` under
+    /// `separate_stack`.
+    StackPush { address: i32, value: u8 },
+    /// `POP`, or the return address `RET` takes, addressed as `StackPush` is.
+    StackPop { address: i32, value: u8 },
+    /// Execute took a jump, call, return or branch from the instruction at `from`.
+    Branch { from: i32, to: i32 },
+    /// A taken branch threw away the instruction fetched behind it.
+    Flush { address: i32 },
+    /// `INP` is waiting for a typed value.
+    WaitingForInput,
+    /// The watchdog wasn't kicked in time and restarted the program from address 0.
+    WatchdogReset,
+    Halted,
+    Faulted(String),
+}
+
+/// The events not yet drained, oldest first. It stays off, and costs nothing, until `enable`;
+/// past its capacity the oldest events are dropped, so a frontend that stops draining can't
+/// grow it without bound.
+#[derive(Default)]
+pub struct EventQueue {
+    events: VecDeque<Event>,
+    capacity: usize,
+    /// Events dropped for lack of room since the queue was enabled.
+    pub dropped: u64,
+}
+
+impl EventQueue {
+    /// Starts queueing, keeping at most `capacity` events. 0 turns the queue off again.
+    pub fn enable(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.events.truncate(capacity);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    fn push(&mut self, event: Event) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(event);
+    }
+
+    /// Takes every queued event, oldest first.
+    pub fn drain(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.events.drain(..)
+    }
+}
+
 // --- Emulator ---
 
 pub struct Emulator {
@@ -206,10 +852,109 @@ pub struct Emulator {
     pub alu: ALU,
     pub ports_out: [u8; 8],
     pub ram: [u8; 16],
+    /// Where `PUSH`, `POP`, `CALL` and `RET` keep the stack under `separate_stack`; unused
+    /// otherwise.
+    pub stack: [u8; 16],
+    /// The bank `STORE` and `LOAD` reach, set by `BANK n` or a write to `BANK_PORT`. Bank 0 is
+    /// `ram`, where the stack and `.ascii` text stay whichever bank is selected.
+    pub bank: u8,
+    /// Banks 1-15; cleared at every reset.
+    pub banks: [[u8; 16]; BANKS - 1],
+    pub dma: Dma,
+    /// The registers the `PUSHA` or `POPA` in writeback has still to move, as its mask. It
+    /// moves one a clock, and the instructions behind it wait until this is empty.
+    pub transfer: u8,
+    pub devices: Vec<Box<dyn Device>>,
+    /// What `SYS` calls; without any, `SYS` faults. Kept across loads and resets, like `devices`.
+    pub syscalls: Option<Box<dyn Syscalls>>,
+
+    /// Label name (upper-cased unless `.casesensitive`) to instruction address, from the last
+    /// loaded program.
+    pub labels: HashMap<String, i32>,
+    /// Instruction addresses that stop a debugger run when they reach the execute stage.
+    pub breakpoints: BTreeSet<i32>,
+    /// `ASSERT` comments from the last loaded program. A failing one sets `fault`.
+    pub assertions: Vec<Assertion>,
+    /// `.port` labels from the last loaded program.
+    pub port_labels: Vec<PortLabel>,
+    /// `.ascii` bytes from the last loaded program, put in RAM from address 0 at every reset.
+    pub ram_data: Vec<u8>,
+    /// Name of the file the program came from; set it before `load_program` so the source map
+    /// carries it. Empty for programs that aren't from a file.
+    pub source_file: String,
+    /// The source of each instruction, indexed by address.
+    pub source_map: Vec<SourceLocation>,
+    /// Assembled in front of each program `load_program` loads; loads and resets keep it.
+    /// Messages, assertions and the source map give the program's own line numbers, and the
+    /// ROM's messages name its file.
+    pub rom: Option<Rom>,
+    /// The first address after the ROM; 0 without one.
+    pub rom_end: i32,
 
     pub waiting_for_input: bool,
     pub input_register: i32,
 
+    /// `--von-neumann`: the program also sits in memory as bytes, four per instruction from
+    /// address 16 up, where `STORE` and `LOAD` reach it and fetch decodes it. Set it with
+    /// `set_von_neumann`.
+    pub von_neumann: bool,
+    /// The program's bytes under `von_neumann`, empty otherwise. Each run starts from the
+    /// program as loaded.
+    pub code_memory: Vec<u8>,
+
+    /// Execute reads the registers with this clock's writeback already in them (a bypass from
+    /// writeback to execute), so an instruction sees the result of the one right before it.
+    /// Loaded from the program's `.forwarding` line.
+    pub forwarding: bool,
+    /// `--interlock`: when the instruction about to execute reads a register the one writing
+    /// back is writing, hold it in decode for a clock (a bubble goes to execute) instead of
+    /// letting it read the old value. Frontends can flip it mid-run.
+    pub interlock: bool,
+    /// `--strict`: every warning is an error, as under a `.strict` line, so a program with any
+    /// doesn't load. Set it before `load_program`.
+    pub strict: bool,
+    /// `--separate-stack`: the stack gets `stack`, 16 bytes of its own, instead of sharing RAM
+    /// from #15 down, so `PUSH` and `CALL` can't overwrite the program's data. Off by default,
+    /// as on the hardware. Loads and resets keep it.
+    pub separate_stack: bool,
+    /// Loaded from the program's `.stack` line; #15 down without one.
+    pub program_stack: StackLayout,
+    /// `--stack`: used in place of the program's `.stack` line. Loads and resets keep it; SP
+    /// moves to its base at the next reset.
+    pub stack_override: Option<StackLayout>,
+    /// Loaded from the program's `.watchdog` line.
+    pub program_watchdog: Option<Watchdog>,
+    /// `--watchdog`: used in place of the program's `.watchdog` line. Loads and resets keep it.
+    pub watchdog_override: Option<Watchdog>,
+    /// Clocks since the watchdog was last kicked, or since the run started.
+    pub watchdog_clocks: u32,
+    /// Times the watchdog has restarted the program since the last reset; reads of
+    /// `WATCHDOG_PORT` give it, so a program can tell a restart from a cold start.
+    pub watchdog_resets: u8,
+
+    /// Refuse anything that could make two runs diverge (host input, wall clock, unseeded RNG).
+    pub deterministic: bool,
+    /// Set when the program does something the current mode forbids. The clock stops until reload.
+    pub fault: Option<String>,
+    /// Set once `HLT` executes; the clock stops until reload.
+    pub halted: bool,
+    /// The exit code of the `SYS` that stopped the program, if one did.
+    pub exit_code: Option<u8>,
+
+    /// When set, every outside input is appended here so the run can be replayed.
+    pub recording: Option<Vec<InputEvent>>,
+    /// Outside inputs to use instead of asking the host, and how many have been used. Once
+    /// they run out the host is asked again; a mismatch with the program sets `fault`.
+    pub replay: Option<Vec<InputEvent>>,
+    pub replay_pos: usize,
+
+    pub stats: Stats,
+    /// Kept across loads and resets, like `devices`.
+    hooks: Hooks,
+    /// Off until a frontend enables it. Loads and resets don't clear it, so the frontend still
+    /// sees what ran before them.
+    pub events: EventQueue,
+
     // Diagnostics
     pub errors: Vec<String>,
     pub warnings: Vec<String>,
@@ -229,30 +974,170 @@ impl Emulator {
             alu: ALU::new(),
             ports_out: [0; 8],
             ram: [0; 16],
+            stack: [0; 16],
+            bank: 0,
+            banks: [[0; 16]; BANKS - 1],
+            dma: Dma::default(),
+            transfer: 0,
+            devices: Vec::new(),
+            syscalls: None,
+            labels: HashMap::new(),
+            breakpoints: BTreeSet::new(),
+            assertions: Vec::new(),
+            port_labels: Vec::new(),
+            ram_data: Vec::new(),
+            source_file: String::new(),
+            source_map: Vec::new(),
+            rom: None,
+            rom_end: 0,
             waiting_for_input: false,
             input_register: 0,
+            von_neumann: false,
+            code_memory: Vec::new(),
+            forwarding: false,
+            interlock: false,
+            strict: false,
+            separate_stack: false,
+            program_stack: StackLayout::default(),
+            stack_override: None,
+            program_watchdog: None,
+            watchdog_override: None,
+            watchdog_clocks: 0,
+            watchdog_resets: 0,
+            deterministic: false,
+            recording: None,
+            replay: None,
+            replay_pos: 0,
+            fault: None,
+            halted: false,
+            exit_code: None,
+            stats: Stats::default(),
+            hooks: Hooks::default(),
+            events: EventQueue::default(),
             errors: Vec::new(),
             warnings: Vec::new(),
         };
-        emu.load_program(code);
+        // The errors stay in `errors`; frontends that run the program check `load_program`.
+        let _ = emu.load_program(code);
         emu
     }
 
-    pub fn load_program(&mut self, code: String) {
+    /// Assembles `code` in place of the loaded program. The errors are also kept in `errors`,
+    /// but a program with any is only partly loaded and must not be run.
+    pub fn load_program(&mut self, code: String) -> Result<(), Vec<String>> {
         self.instructions.clear();
         self.errors.clear();
         self.warnings.clear();
         self.pc = 0;
-        self.sp = 15;
+        let rom_lines = self.rom.as_ref().map_or(0, |rom| rom.source.trim_end().lines().count() as i32);
+        let code = match &self.rom {
+            Some(rom) => format!("{}\n{code}", rom.source.trim_end()),
+            None => code,
+        };
+        let (data, data_errors) = Parser::scan_data(&code);
+        self.ram_data = data;
+        self.program_stack = Parser::stack_layout(&code);
+        self.program_watchdog = Parser::watchdog(&code);
+        let strict = self.strict || Parser::strict(&code);
         self.reset_state();
 
-        let (instrs, errs, warns) = Parser::parse(code);
+        self.labels = Parser::scan_labels(&code);
+        let (assertions, assert_errors) = Parser::scan_assertions(&code);
+        self.assertions = assertions;
+        self.port_labels = Parser::scan_port_labels(&code);
+        self.forwarding = Parser::forwarding(&code);
+        let (instrs, errs, warns) = Parser::parse(code.clone());
         self.instructions = instrs;
+        self.source_map = self.map_source(&code);
+        self.load_code_memory();
         self.errors = errs;
+        self.errors.extend(assert_errors);
+        self.errors.extend(data_errors);
         self.warnings = warns;
+        self.place_rom(rom_lines);
+        if strict {
+            self.errors.append(&mut self.warnings);
+        }
+        if self.errors.is_empty() { Ok(()) } else { Err(self.errors.clone()) }
+    }
+
+    /// Gives the program's instructions, assertions and messages back its own line numbers
+    /// after `load_program` assembled it behind `rom_lines` lines of ROM. The ROM's errors name
+    /// its file, and its warnings are dropped: they're about the library, not the program.
+    fn place_rom(&mut self, rom_lines: i32) {
+        self.rom_end = self.instructions.iter().take_while(|instr| instr.source_line <= rom_lines).count() as i32;
+        let Some(rom) = &self.rom else { return };
+        let rom_file: Arc<str> = rom.file.as_str().into();
+        for instr in &mut self.instructions[self.rom_end as usize..] {
+            instr.source_line -= rom_lines;
+        }
+        for (address, source) in self.source_map.iter_mut().enumerate() {
+            if (address as i32) < self.rom_end {
+                source.file = rom_file.clone();
+            } else {
+                source.source_line -= rom_lines;
+            }
+        }
+        for assertion in self.assertions.iter_mut().filter(|assertion| assertion.source_line > rom_lines) {
+            assertion.source_line -= rom_lines;
+        }
+        let relocate = |message: &String| match parser::split_location(message) {
+            (Some(line), _, _) if line as i32 <= rom_lines => format!("{}: {message}", rom.file),
+            (Some(line), column, text) => format!("{}: {text}", parser::location(line as i32 - rom_lines, column)),
+            (None, ..) => message.clone(),
+        };
+        let in_program = |message: &&String| parser::split_location(message).0.is_none_or(|line| line as i32 > rom_lines);
+        self.errors = self.errors.iter().map(relocate).collect();
+        self.warnings = self.warnings.iter().filter(in_program).map(relocate).collect();
+    }
+
+    /// Whether the instruction at `address` is the ROM's rather than the program's.
+    pub fn in_rom(&self, address: i32) -> bool {
+        (0..self.rom_end).contains(&address)
     }
     
+    /// A line assembles to one instruction (or a `.word` line to one per value), so a NOOP that
+    /// shares its line with the next instruction is one that `.autonop` put in front of it.
+    fn map_source(&self, code: &str) -> Vec<SourceLocation> {
+        let file: Arc<str> = self.source_file.as_str().into();
+        let lines: Vec<&str> = code.lines().collect();
+        self.instructions
+            .iter()
+            .enumerate()
+            .map(|(i, instr)| SourceLocation {
+                file: file.clone(),
+                source_line: instr.source_line,
+                text: lines.get((instr.source_line as usize).wrapping_sub(1)).map_or("", |line| line.trim()).to_string(),
+                generated: instr.operation == Operation::NOOP
+                    && self.instructions.get(i + 1).is_some_and(|next| next.source_line == instr.source_line),
+            })
+            .collect()
+    }
+
+    /// The source of the instruction at `address`, if there is one there.
+    pub fn source_at(&self, address: i32) -> Option<&SourceLocation> {
+        usize::try_from(address).ok().and_then(|address| self.source_map.get(address))
+    }
+
+    /// Starts the loaded program over without parsing it again. Breakpoints and devices stay.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.reset_state();
+    }
+
+    /// `--watchdog`, else the program's `.watchdog`; None leaves the program to run as long as it
+    /// likes.
+    pub fn watchdog(&self) -> Option<Watchdog> {
+        self.watchdog_override.or(self.program_watchdog)
+    }
+
+    /// Where the stack starts and which way it grows: `--stack`, else the program's `.stack`.
+    pub fn stack_layout(&self) -> StackLayout {
+        self.stack_override.unwrap_or(self.program_stack)
+    }
+
     fn reset_state(&mut self) {
+        self.sp = self.stack_layout().base as i32;
         self.registers = Registers::new();
         self.alu = ALU::new();
         self.fetch_reg = Instruction::none();
@@ -261,31 +1146,457 @@ impl Emulator {
         self.writeback_reg = Instruction::none();
         self.ports_out = [0; 8];
         self.ram = [0; 16];
+        self.ram[..self.ram_data.len()].copy_from_slice(&self.ram_data);
+        self.transfer = 0;
+        self.exit_code = None;
+        self.stack = [0; 16];
+        self.bank = 0;
+        self.banks = [[0; 16]; BANKS - 1];
+        self.dma = Dma::default();
+        self.watchdog_clocks = 0;
+        self.watchdog_resets = 0;
+        self.load_code_memory();
         self.waiting_for_input = false;
+        self.fault = None;
+        self.halted = false;
+        self.stats = Stats::default();
+        // A fresh run replays from the start and records afresh.
+        self.replay_pos = 0;
+        if let Some(events) = &mut self.recording {
+            events.clear();
+        }
+    }
+
+    /// Clocks until the next instruction reaches EXECUTE, passing over however many clocks of
+    /// bubbles and stalls come first, or until the core stops. Returns the number of clocks run.
+    pub fn step(&mut self) -> u64 {
+        self.step_with(|_| {})
+    }
+
+    /// `step`, calling `after_clock` after each of its clocks.
+    pub fn step_with(&mut self, mut after_clock: impl FnMut(&Self)) -> u64 {
+        let start = self.stats.cycles;
+        let executed = self.stats.executed;
+        // With no program nothing ever reaches EXECUTE, so one clock is all there is to step.
+        let empty = self.instructions.is_empty() && self.code_memory.is_empty();
+        loop {
+            self.clock();
+            after_clock(self);
+            let stopped = self.waiting_for_input || self.fault.is_some() || self.halted;
+            if stopped || empty || self.stats.executed > executed {
+                return self.stats.cycles - start;
+            }
+        }
     }
 
     pub fn clock(&mut self) {
-        if self.waiting_for_input { return; }
+        if self.waiting_for_input || self.fault.is_some() || self.halted { return; }
 
+        let _span = tracing::trace_span!("clock", cycle = self.stats.cycles).entered();
+        self.clock_pipeline();
+        if !self.hooks.clock.is_empty() {
+            // Out of `self` while they run, since they see all of it.
+            let mut hooks = std::mem::take(&mut self.hooks.clock);
+            for hook in &mut hooks {
+                hook(self);
+            }
+            self.hooks.clock = hooks;
+        }
+        if let Some(fault) = &self.fault {
+            tracing::warn!(%fault, "fault");
+            self.events.push(Event::Faulted(fault.clone()));
+        } else if self.halted {
+            tracing::info!(cycles = self.stats.cycles, "halted");
+            self.events.push(Event::Halted);
+        } else if self.waiting_for_input {
+            tracing::debug!("waiting for INP");
+            self.events.push(Event::WaitingForInput);
+        }
+    }
+
+    fn clock_pipeline(&mut self) {
         self.registers.begin_cycle();
+        for device in self.devices.iter_mut() {
+            device.tick();
+        }
+
+        if self.stats.cycles == 0 {
+            self.check_assertions(-1);
+        }
+
+        if let Some(watchdog) = self.watchdog() {
+            self.watchdog_clocks += 1;
+            if self.watchdog_clocks > watchdog.timeout {
+                self.watchdog_reset(watchdog.clear);
+            }
+        }
+
+        // The DMA engine has the memory bus this clock, so nothing in the pipeline moves.
+        if self.dma.busy() {
+            self.dma_step();
+            self.stats.stalls += 1;
+            self.stats.dma += 1;
+            self.registers.end_cycle();
+            self.stats.cycles += 1;
+            return;
+        }
 
         // Pipeline (Reverse)
-        self.write_back_stage();
+        if self.transfer == 0 {
+            self.write_back_stage();
+        } else {
+            self.transfer_step();
+        }
+        trace_stage("writeback", &self.writeback_reg);
+        // A `PUSHA` or `POPA` with registers left holds everything behind it, as a stall does.
+        if self.transfer != 0 {
+            self.execute_reg = Instruction::none();
+            trace_stage("execute", &self.execute_reg);
+            self.stats.stalls += 1;
+            self.registers.end_cycle();
+            self.stats.cycles += 1;
+            return;
+        }
+        // Between writeback and execute the state is exactly "everything up to the retiring
+        // instruction has happened", which is what an ASSERT under it means.
+        if self.writeback_reg.address >= 0 {
+            self.check_assertions(self.writeback_reg.address);
+        }
+        if self.fault.is_some() {
+            self.registers.end_cycle();
+            self.stats.cycles += 1;
+            return;
+        }
+        // Stalling: decode and fetch hold their instructions and the PC stays put.
+        if self.interlock && !self.forwarding && Parser::raw_hazard(&self.writeback_reg, &self.decode_reg).is_some() {
+            self.execute_reg = Instruction::none();
+            trace_stage("execute", &self.execute_reg);
+            self.stats.stalls += 1;
+            self.registers.end_cycle();
+            self.stats.cycles += 1;
+            return;
+        }
         self.execute_stage();
+        trace_stage("execute", &self.execute_reg);
         self.decode_stage();
+        trace_stage("decode", &self.decode_reg);
         self.fetch_stage();
+        trace_stage("fetch", &self.fetch_reg);
 
         self.increment_pc();
         self.registers.end_cycle();
+        self.stats.cycles += 1;
+
+        // A live answer arrives after the clock that parked on INP, so a replayed one does too.
+        if self.waiting_for_input {
+            if let Some(value) = self.replayed(None) {
+                self.resolve_input(value as i32);
+            }
+        }
+    }
+
+    /// Calls `hook` after every clock that ran, with the core as that clock left it.
+    pub fn on_clock(&mut self, hook: impl FnMut(&Emulator) + Send + 'static) {
+        self.hooks.clock.push(Box::new(hook));
+    }
+
+    /// Calls `hook(port, value)` on every port write, from `OUT` and `ROUT` in writeback or
+    /// from `write_port`, after the display and the devices have it.
+    pub fn on_port_write(&mut self, hook: impl FnMut(u8, u8) + Send + 'static) {
+        self.hooks.port_write.push(Box::new(hook));
+    }
+
+    /// Calls `hook(register, value)` when writeback writes R1-R7. The value is in the
+    /// registers from the end of the clock.
+    pub fn on_register_write(&mut self, hook: impl FnMut(u8, u8) + Send + 'static) {
+        self.hooks.register_write.push(Box::new(hook));
+    }
+
+    /// Calls `hook(from, to)` when execute takes a jump, call, return or branch: the address of
+    /// the instruction and the one fetched next.
+    pub fn on_branch(&mut self, hook: impl FnMut(i32, i32) + Send + 'static) {
+        self.hooks.branch.push(Box::new(hook));
     }
 
     pub fn resolve_input(&mut self, val: i32) {
         if self.waiting_for_input {
             self.alu.accumulator = (val & 0xFF) as u8;
             self.waiting_for_input = false;
+            if let Some(events) = &mut self.recording {
+                events.push(InputEvent::Inp { cycle: self.stats.cycles, value: self.alu.accumulator });
+            }
         }
     }
+
+    fn replay_pending(&self) -> bool {
+        self.replay.as_ref().is_some_and(|events| self.replay_pos < events.len())
+    }
+
+    /// The next recorded input, if it is the one the program wants now: a port read for
+    /// `Some(port)`, a typed `INP` answer for None. Sets `fault` when the recording disagrees.
+    fn replayed(&mut self, port: Option<u8>) -> Option<u8> {
+        let event = *self.replay.as_ref()?.get(self.replay_pos)?;
+        let cycle = self.stats.cycles;
+        let value = match (event, port) {
+            (InputEvent::Inp { cycle: c, value }, None) if c == cycle => value,
+            (InputEvent::Port { cycle: c, port: p, value }, Some(port)) if c == cycle && p == port => value,
+            _ => {
+                let wanted = match port {
+                    Some(port) => format!("read port %{port}"),
+                    None => "waited for INP".to_string(),
+                };
+                self.fault = Some(format!(
+                    "Replay diverged at clock {cycle}: the program {wanted}, but the recording has `{event}` next."
+                ));
+                return None;
+            }
+        };
+        self.replay_pos += 1;
+        Some(value)
+    }
     
+    /// Checks the assertions placed after `address`, faulting on the first one that fails.
+    fn check_assertions(&mut self, address: i32) {
+        if self.fault.is_some() || !self.assertions.iter().any(|a| a.after == address) {
+            return;
+        }
+        // Writeback has already landed in next_regs this clock.
+        let mut state = self.snapshot();
+        state.registers = self.registers.next_regs;
+        for assertion in self.assertions.iter().filter(|a| a.after == address) {
+            let left = state.get(assertion.field);
+            let right = match assertion.value {
+                AssertValue::Number(n) => n,
+                AssertValue::Field(field) => state.get(field),
+            };
+            if !assertion.comparison.holds(left, right) {
+                let found = match assertion.value {
+                    AssertValue::Number(_) => format!("{} is {left}", assertion.field.name()),
+                    AssertValue::Field(field) => format!("{} is {left}, {} is {right}", assertion.field.name(), field.name()),
+                };
+                self.fault = Some(format!("Line {}: ASSERT {} failed ({found}).", assertion.source_line, assertion.text));
+                return;
+            }
+            self.stats.assertions += 1;
+        }
+    }
+
+    /// The `.port` label covering display port `port`, if any.
+    pub fn port_label(&self, port: u8) -> Option<&PortLabel> {
+        self.port_labels.iter().find(|label| (label.first..=label.last).contains(&port))
+    }
+
+    /// True when the instruction now in the execute stage has a breakpoint on it.
+    pub fn at_breakpoint(&self) -> bool {
+        self.execute_reg.address >= 0 && self.breakpoints.contains(&self.execute_reg.address)
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            pc: self.pc,
+            sp: self.sp,
+            pipeline: [
+                self.fetch_reg.clone(),
+                self.decode_reg.clone(),
+                self.execute_reg.clone(),
+                self.writeback_reg.clone(),
+            ],
+            registers: self.registers.clone(),
+            alu: self.alu.clone(),
+            ports_out: self.ports_out,
+            ram: self.ram,
+            stack: self.stack,
+            bank: self.bank,
+            banks: self.banks,
+            dma: self.dma,
+            watchdog_clocks: self.watchdog_clocks,
+            watchdog_resets: self.watchdog_resets,
+            transfer: self.transfer,
+            code_memory: self.code_memory.clone(),
+            waiting_for_input: self.waiting_for_input,
+            input_register: self.input_register,
+            fault: self.fault.clone(),
+            halted: self.halted,
+            exit_code: self.exit_code,
+            stats: self.stats,
+            replay_pos: self.replay_pos,
+            recorded: self.recording.as_ref().map_or(0, |events| events.len()),
+        }
+    }
+
+    /// Puts the core back the way it was when `checkpoint` was taken.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        let c = checkpoint.clone();
+        self.pc = c.pc;
+        self.sp = c.sp;
+        [self.fetch_reg, self.decode_reg, self.execute_reg, self.writeback_reg] = c.pipeline;
+        self.registers = c.registers;
+        self.alu = c.alu;
+        self.ports_out = c.ports_out;
+        self.ram = c.ram;
+        self.stack = c.stack;
+        self.bank = c.bank;
+        self.banks = c.banks;
+        self.dma = c.dma;
+        self.watchdog_clocks = c.watchdog_clocks;
+        self.watchdog_resets = c.watchdog_resets;
+        self.transfer = c.transfer;
+        self.code_memory = c.code_memory;
+        self.waiting_for_input = c.waiting_for_input;
+        self.input_register = c.input_register;
+        self.fault = c.fault;
+        self.halted = c.halted;
+        self.exit_code = c.exit_code;
+        self.stats = c.stats;
+        self.replay_pos = c.replay_pos;
+        if let Some(events) = &mut self.recording {
+            events.truncate(c.recorded);
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            sp: self.sp,
+            registers: self.registers.regs,
+            accumulator: self.alu.accumulator,
+            flags: self.alu.flags,
+            ram: self.ram,
+            ports_out: self.ports_out,
+            pipeline: [
+                self.fetch_reg.clone(),
+                self.decode_reg.clone(),
+                self.execute_reg.clone(),
+                self.writeback_reg.clone(),
+            ],
+            waiting_for_input: self.waiting_for_input,
+        }
+    }
+
+    /// Reads a port: display ports read back their latched value, `BANK_PORT` the bank, the DMA
+    /// ports the engine's state and `WATCHDOG_PORT` the watchdog's restarts, otherwise the first
+    /// device claiming the port answers and everything else reads 0. Devices may still claim
+    /// display ports to observe writes to them.
+    pub fn read_port(&mut self, port: i32) -> u8 {
+        if !(0..=255).contains(&port) { return 0; }
+        if port < 8 { return self.ports_out[port as usize]; }
+        if port == BANK_PORT { return self.bank; }
+        if (DMA_SOURCE_PORT..=DMA_CONTROL_PORT).contains(&port) { return self.dma.read(port); }
+        if port == WATCHDOG_PORT { return self.watchdog_resets; }
+        match self.devices.iter_mut().find(|d| d.claims(port as u8)) {
+            Some(device) => device.read(port as u8),
+            None => 0,
+        }
+    }
+
+    fn port_is_host_dependent(&self, port: i32) -> bool {
+        (8..=255).contains(&port)
+            && self.devices.iter().find(|d| d.claims(port as u8)).is_some_and(|d| d.host_dependent())
+    }
+
+    /// Writes a port: ports 0-7 update the display, `BANK_PORT` selects the bank, the DMA ports
+    /// set up the engine, `WATCHDOG_PORT` kicks the watchdog, and every device claiming the port
+    /// sees it.
+    pub fn write_port(&mut self, port: i32, value: u8) {
+        if !(0..=255).contains(&port) { return; }
+        tracing::debug!(port, value, "port write");
+        if port < 8 {
+            self.ports_out[port as usize] = value;
+        }
+        if port == BANK_PORT {
+            self.bank = value % BANKS as u8;
+        }
+        if (DMA_SOURCE_PORT..=DMA_CONTROL_PORT).contains(&port) {
+            self.dma.write(port, value);
+        }
+        if port == WATCHDOG_PORT {
+            self.watchdog_clocks = 0;
+        }
+        for device in self.devices.iter_mut().filter(|d| d.claims(port as u8)) {
+            device.write(port as u8, value);
+        }
+        for hook in &mut self.hooks.port_write {
+            hook(port as u8, value);
+        }
+        self.events.push(Event::PortWrite { port: port as u8, value });
+    }
+
+    /// FNV-1a hash over all architectural state. Two runs that agree on this after the same
+    /// number of clocks are bit-identical.
+    pub fn state_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        feed(&self.pc.to_le_bytes());
+        feed(&self.sp.to_le_bytes());
+        feed(&self.registers.regs);
+        feed(&[self.alu.accumulator]);
+        let flags = &self.alu.flags;
+        feed(&[flags.equals as u8, flags.greater as u8, flags.less as u8, flags.overflow as u8]);
+        feed(&self.ram);
+        feed(&self.code_memory);
+        if self.separate_stack {
+            feed(&self.stack);
+        }
+        feed(&[self.transfer, self.bank]);
+        feed(self.banks.as_flattened());
+        feed(&[self.dma.source, self.dma.destination, self.dma.length, self.dma.mode]);
+        feed(&self.watchdog_clocks.to_le_bytes());
+        feed(&[self.watchdog_resets]);
+        feed(&self.ports_out);
+        for stage in [&self.fetch_reg, &self.decode_reg, &self.execute_reg, &self.writeback_reg] {
+            feed(&stage.address.to_le_bytes());
+        }
+        feed(&[self.waiting_for_input as u8]);
+        hash
+    }
+
+    // --- Von Neumann Mode ---
+
+    /// Turns `von_neumann` on or off and starts the program over.
+    pub fn set_von_neumann(&mut self, on: bool) {
+        self.von_neumann = on;
+        self.reset();
+    }
+
+    fn load_code_memory(&mut self) {
+        self.code_memory = if self.von_neumann {
+            self.instructions.iter().flat_map(Instruction::encode).collect()
+        } else {
+            Vec::new()
+        };
+    }
+
+    /// The instruction at `address` as fetch would see it: decoded from `code_memory` under
+    /// `von_neumann`, where it may have been overwritten. Err for bytes that don't decode.
+    pub fn instruction_at(&self, address: i32) -> Result<Option<Instruction>, String> {
+        let Ok(index) = usize::try_from(address) else { return Ok(None) };
+        if !self.von_neumann {
+            return Ok(self.instructions.get(index).cloned());
+        }
+        let Some(bytes) = self.code_memory.get(index * 4..index * 4 + 4) else { return Ok(None) };
+        let source_line = self.source_at(address).map_or(0, |source| source.source_line);
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Instruction::decode(bytes, address, source_line)
+            .map(Some)
+            .ok_or_else(|| format!("Address {address} holds {bytes:?}, which is not an instruction."))
+    }
+
+    /// `STORE` and `LOAD` at `#16` and up under `von_neumann`. Stores past the end of the
+    /// program grow it (with zeros, which decode as NOOPs) up to the 255 addresses the PC
+    /// reaches.
+    fn code_byte(&mut self, address: i32) -> Option<&mut u8> {
+        let offset = usize::try_from(address - 16).ok().filter(|offset| *offset < 255 * 4)?;
+        if offset >= self.code_memory.len() {
+            self.code_memory.resize((offset / 4 + 1) * 4, 0);
+        }
+        self.code_memory.get_mut(offset)
+    }
+
     // --- Internal Pipeline ---
     fn increment_pc(&mut self) {
         self.pc += 1;
@@ -293,11 +1604,13 @@ impl Emulator {
     }
 
     fn fetch_stage(&mut self) {
-        if self.pc >= 0 && (self.pc as usize) < self.instructions.len() {
-            self.fetch_reg = self.instructions[self.pc as usize].clone();
-        } else {
-            self.fetch_reg = Instruction::none();
-        }
+        self.fetch_reg = match self.instruction_at(self.pc) {
+            Ok(instr) => instr.unwrap_or_else(Instruction::none),
+            Err(error) => {
+                self.fault = Some(error);
+                Instruction::none()
+            }
+        };
     }
 
     fn decode_stage(&mut self) {
@@ -306,98 +1619,321 @@ impl Emulator {
 
     fn execute_stage(&mut self) {
         self.execute_reg = self.decode_reg.clone();
+        if self.execute_reg.address >= 0 {
+            self.stats.executed += 1;
+        }
         let op = self.execute_reg.operation;
 
         // Branching
-        let mut take_branch = false;
-        if op == Operation::JMP { take_branch = true; }
-        else if op == Operation::CALL { take_branch = true; }
-        else if op == Operation::BIE && self.alu.flags.equals { take_branch = true; }
-        else if op == Operation::BIG && self.alu.flags.greater { take_branch = true; }
-        else if op == Operation::BIO && self.alu.flags.overflow { take_branch = true; }
-        else if op == Operation::BIL && self.alu.flags.less { take_branch = true; }
-        else if op == Operation::RET {
+        let mut take_branch = match op {
+            Operation::JMP | Operation::CALL => true,
+            Operation::BIE => self.alu.flags.equals,
+            Operation::BIG => self.alu.flags.greater,
+            Operation::BIO => self.alu.flags.overflow,
+            Operation::BIL => self.alu.flags.less,
+            _ => false,
+        };
+        if op == Operation::RET {
             take_branch = true;
-            self.sp += 1;
-            if self.sp > 15 { self.sp = 0; }
-            let ret_addr = self.ram[self.sp as usize];
+            let ret_addr = self.pop();
             self.execute_reg.a.data = ret_addr as i32; // Hack to use common branch logic
         }
 
+        // HLT stops here: everything older has already written back this clock, and nothing
+        // younger gets to execute. It counts as retired since it never reaches writeback.
+        if op == Operation::HLT {
+            self.stats.retired += 1;
+            self.check_assertions(self.execute_reg.address);
+            self.halted = self.fault.is_none();
+            return;
+        }
+
         if take_branch {
+            self.stats.branches += 1;
+            if self.fetch_reg.address >= 0 {
+                self.stats.flushed += 1;
+                self.events.push(Event::Flush { address: self.fetch_reg.address });
+            }
             self.pc = self.execute_reg.a.data;
             self.fetch_reg = Instruction::none(); // Flush
+            for hook in &mut self.hooks.branch {
+                hook(self.execute_reg.address, self.pc);
+            }
+            self.events.push(Event::Branch { from: self.execute_reg.address, to: self.pc });
+        }
+
+        // Writeback ran first this clock, so its result is already in `next_regs`.
+        let registers = if self.forwarding { self.registers.forwarded() } else { self.registers.clone() };
+        self.alu.execute(&registers, &self.execute_reg, &mut self.input_register, &mut self.waiting_for_input);
+
+        // INP with a port operand reads the bus instead of waiting for the user.
+        if op == Operation::INP && self.execute_reg.b.type_ == OperandType::Port {
+            let port = self.execute_reg.b.data;
+            let from_host = self.port_is_host_dependent(port);
+            let replayed = if from_host { self.replayed(Some(port as u8)) } else { None };
+            if self.fault.is_some() {
+                return;
+            }
+            let value = match replayed {
+                Some(value) => value,
+                None if from_host && self.deterministic => {
+                    self.fault = Some(format!(
+                        "Line {}: Port %{} depends on the host, which is not allowed in deterministic mode.",
+                        self.execute_reg.source_line, port
+                    ));
+                    return;
+                }
+                None => self.read_port(port),
+            };
+            if from_host {
+                if let Some(events) = &mut self.recording {
+                    events.push(InputEvent::Port { cycle: self.stats.cycles, port: port as u8, value });
+                }
+            }
+            self.alu.accumulator = value;
+        }
+        if op == Operation::SYS {
+            self.syscall(&registers);
+        }
+
+        if self.waiting_for_input && self.deterministic && !self.replay_pending() {
+            self.fault = Some(format!(
+                "Line {}: {} waits for host input, which is not allowed in deterministic mode.",
+                self.execute_reg.source_line,
+                op.get_name()
+            ));
         }
+    }
+
+    /// Runs the `SYS` in execute. A result goes through the accumulator to R1 at writeback, as
+    /// `INP`'s does, with operand B marked so writeback knows there is one; a typed one waits
+    /// like `INP R1`. An exit stops the clock the way `HLT` does.
+    fn syscall(&mut self, registers: &Registers) {
+        let number = self.execute_reg.a.data as u8;
+        let line = self.execute_reg.source_line;
+        let Some(syscalls) = &mut self.syscalls else {
+            self.fault = Some(format!("Line {line}: SYS {number} needs host services, and none are attached."));
+            return;
+        };
+        let arguments = std::array::from_fn(|i| registers.read(i as i32));
+        match syscalls.call(number, arguments, &self.ram) {
+            SysResult::Done => {}
+            SysResult::Return(value) => {
+                self.alu.accumulator = value;
+                self.execute_reg.b = Operand::new(OperandType::Register, 1);
+            }
+            SysResult::Input => {
+                self.waiting_for_input = true;
+                self.input_register = 1;
+                self.execute_reg.b = Operand::new(OperandType::Register, 1);
+            }
+            SysResult::Exit(code) => {
+                self.exit_code = Some(code);
+                self.stats.retired += 1;
+                self.check_assertions(self.execute_reg.address);
+                self.halted = self.fault.is_none();
+            }
+            SysResult::Fault(message) => self.fault = Some(format!("Line {line}: SYS {number}: {message}")),
+        }
+    }
 
-        self.alu.execute(&self.registers, &self.execute_reg, &mut self.input_register, &mut self.waiting_for_input);
+    /// A register write from writeback, seen by the `on_register_write` hooks and the events.
+    fn write_register(&mut self, register: i32, value: u8) {
+        self.registers.write(register, value);
+        if (1..8).contains(&register) {
+            for hook in &mut self.hooks.register_write {
+                hook(register as u8, value);
+            }
+            self.events.push(Event::RegisterWrite { register: register as u8, value });
+        }
     }
 
     fn write_back_stage(&mut self) {
         self.writeback_reg = self.execute_reg.clone();
+        if self.writeback_reg.address >= 0 { self.stats.retired += 1; }
         let op = self.writeback_reg.operation;
         let a = self.writeback_reg.a.data;
         let b = self.writeback_reg.b.data;
         let address = self.writeback_reg.address;
 
         match op {
-            Operation::IMM => self.registers.write(a, b as u8),
+            Operation::IMM => self.write_register(a, b as u8),
             Operation::MOV => {
                 let val = self.registers.read(b);
-                self.registers.write(a, val);
+                self.write_register(a, val);
             },
             Operation::ADD | Operation::ADDC | Operation::SUB | 
             Operation::OR | Operation::XOR | Operation::AND => {
                 let args = self.writeback_reg.args;
                 if args == OperationArgs::S || args == OperationArgs::U || args == OperationArgs::None {
-                    self.registers.write(a, self.alu.accumulator);
+                    self.write_register(a, self.alu.accumulator);
                 }
             },
             Operation::SHR | Operation::NOT => {
-                self.registers.write(a, self.alu.accumulator);
+                self.write_register(a, self.alu.accumulator);
             },
             Operation::INP => {
-                self.registers.write(a, self.alu.accumulator);
+                self.write_register(a, self.alu.accumulator);
             },
             Operation::OUT => {
-                if a < 8 {
-                    self.ports_out[a as usize] = self.registers.read(b);
-                }
+                self.write_port(a, self.registers.read(b));
             },
             Operation::ROUT => {
-                if self.registers.read(a) < 8 {
-                    self.ports_out[self.registers.read(a) as usize] = self.registers.read(b);
-                }
+                self.write_port(self.registers.read(a) as i32, self.registers.read(b));
             },
-            Operation::STORE => {
-                if a < 16 {
-                    self.ram[a as usize] = self.registers.read(b);
+            Operation::STORE if (0..16).contains(&a) => {
+                let value = self.registers.read(b);
+                self.data_bank()[a as usize] = value;
+                if self.bank == 0 {
+                    self.events.push(Event::MemoryWrite { address: a, value });
                 }
             },
-            Operation::LOAD => {
-                if b < 16 {
-                    self.registers.write(a, self.ram[b as usize]);
+            Operation::LOAD if (0..16).contains(&b) => {
+                let value = self.data_bank()[b as usize];
+                self.write_register(a, value);
+            },
+            Operation::STORE if self.von_neumann => {
+                let value = self.registers.read(b);
+                if let Some(byte) = self.code_byte(a) {
+                    *byte = value;
+                    self.events.push(Event::MemoryWrite { address: a, value });
                 }
             },
+            Operation::LOAD if self.von_neumann => {
+                let offset = usize::try_from(b - 16).ok();
+                let value = offset.and_then(|offset| self.code_memory.get(offset)).copied().unwrap_or(0);
+                self.write_register(a, value);
+            },
             Operation::PUSH => {
-                if self.sp >= 0 {
-                    self.ram[self.sp as usize] = self.registers.read(a);
-                    self.sp -= 1;
-                    if self.sp < 0 { self.sp = 15; }
-                }
+                self.push(self.registers.read(a));
             },
             Operation::POP => {
-                self.sp += 1;
-                if self.sp > 15 { self.sp = 0; }
-                self.registers.write(a, self.ram[self.sp as usize]);
+                let value = self.pop();
+                self.write_register(a, value);
             },
             Operation::CALL => {
-                if self.sp >= 0 {
-                    self.ram[self.sp as usize] = (address + 1) as u8;
-                    self.sp -= 1;
-                    if self.sp < 0 { self.sp = 15; }
+                self.push((address + 1) as u8);
+            },
+            Operation::LOADS => {
+                let at = self.stack_layout().offset(self.sp, b);
+                let value = self.stack_memory()[at as usize];
+                self.write_register(a, value);
+            },
+            Operation::SYS if self.writeback_reg.b.type_ == OperandType::Register => {
+                self.write_register(1, self.alu.accumulator);
+            },
+            Operation::BANK => self.bank = (a as u8) % BANKS as u8,
+            Operation::PUSHA | Operation::POPA => {
+                self.transfer = register_mask(a);
+                self.transfer_step();
+            },
+            Operation::STORES => {
+                let at = self.stack_layout().offset(self.sp, b);
+                let value = self.registers.read(a);
+                self.stack_memory()[at as usize] = value;
+                if !self.separate_stack {
+                    self.events.push(Event::MemoryWrite { address: at, value });
                 }
             },
             _ => {}
         }
     }
+
+    /// Moves the next register of the `PUSHA` or `POPA` in writeback: `PUSHA` from R1 up and
+    /// `POPA` from R7 down, so a `POPA` undoes the `PUSHA` with the same mask.
+    fn transfer_step(&mut self) {
+        let push = self.writeback_reg.operation == Operation::PUSHA;
+        let register = if push { self.transfer.trailing_zeros() } else { 7 - self.transfer.leading_zeros() };
+        self.transfer &= !(1 << register);
+        if push {
+            self.push(self.registers.read(register as i32));
+        } else {
+            let value = self.pop();
+            self.write_register(register as i32, value);
+        }
+    }
+
+    /// What the watchdog does when it bites: the pipeline empties and fetch starts again from
+    /// address 0, in this same clock. Under `clear` the rest of the state goes back to how a reset
+    /// leaves it, but the count of restarts stays, and so do the stats and any recording.
+    fn watchdog_reset(&mut self, clear: bool) {
+        tracing::warn!(clocks = self.watchdog_clocks - 1, "watchdog reset");
+        self.pc = 0;
+        self.fetch_reg = Instruction::none();
+        self.decode_reg = Instruction::none();
+        self.execute_reg = Instruction::none();
+        self.writeback_reg = Instruction::none();
+        self.transfer = 0;
+        self.dma = Dma::default();
+        if clear {
+            self.sp = self.stack_layout().base as i32;
+            self.registers = Registers::new();
+            self.registers.begin_cycle();
+            self.alu = ALU::new();
+            self.ports_out = [0; 8];
+            self.ram = [0; 16];
+            self.ram[..self.ram_data.len()].copy_from_slice(&self.ram_data);
+            self.stack = [0; 16];
+            self.bank = 0;
+            self.banks = [[0; 16]; BANKS - 1];
+            self.load_code_memory();
+        }
+        self.watchdog_clocks = 1;
+        self.watchdog_resets = self.watchdog_resets.saturating_add(1);
+        self.events.push(Event::WatchdogReset);
+    }
+
+    /// Moves the DMA engine's next byte.
+    fn dma_step(&mut self) {
+        let Dma { source, destination, mode, .. } = self.dma;
+        let value = self.data_bank()[source as usize];
+        if mode == DMA_COPY {
+            let address = destination % 16;
+            self.data_bank()[address as usize] = value;
+            if self.bank == 0 {
+                self.events.push(Event::MemoryWrite { address: address as i32, value });
+            }
+        } else {
+            self.write_port(destination as i32, value);
+        }
+        self.dma.source = (source + 1) % 16;
+        if mode != DMA_TO_PORT {
+            self.dma.destination = destination.wrapping_add(1);
+        }
+        // A stream to the engine's own ports can change the length under it.
+        self.dma.length = self.dma.length.saturating_sub(1);
+        if self.dma.length == 0 {
+            self.dma.mode = 0;
+        }
+    }
+
+    /// The bank `STORE` and `LOAD` reach.
+    fn data_bank(&mut self) -> &mut [u8; 16] {
+        match self.bank {
+            0 => &mut self.ram,
+            bank => &mut self.banks[bank as usize - 1],
+        }
+    }
+
+    /// The memory the stack is in: `stack` under `separate_stack`, RAM otherwise.
+    fn stack_memory(&mut self) -> &mut [u8; 16] {
+        if self.separate_stack { &mut self.stack } else { &mut self.ram }
+    }
+
+    /// Saves `value` where SP points and moves SP the way the stack grows.
+    fn push(&mut self, value: u8) {
+        let address = self.sp;
+        self.stack_memory()[address as usize] = value;
+        self.events.push(Event::StackPush { address, value });
+        self.sp = self.stack_layout().grow(address);
+    }
+
+    /// Moves SP back the way the stack shrinks and takes the byte there.
+    fn pop(&mut self) -> u8 {
+        let address = self.stack_layout().shrink(self.sp);
+        self.sp = address;
+        let value = self.stack_memory()[address as usize];
+        self.events.push(Event::StackPop { address, value });
+        value
+    }
 }
\ No newline at end of file
@@ -1,57 +1,202 @@
-use super::{Instruction, Operation, OperationArgs, Operand, OperandType};
-use std::collections::HashMap;
+use super::codes::{split_code, Code};
+use super::isa::{Group, OPERATIONS};
+use super::{register_mask, AssertValue, BANKS, BANK_PORT, Assertion, Comparison, Instruction, Operation, OperationArgs, Operand, OperandType, PortLabel, StackLayout, StateField, Watchdog};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 pub struct Parser;
 
+enum Directive {
+    /// Put a NOOP in wherever the RAW hazard check would warn, instead of warning.
+    AutoNop,
+    /// Labels keep the case they're written in, and `Loop` and `LOOP` are different labels.
+    CaseSensitive,
+    /// The program runs with forwarding, so RAW hazards can't happen and aren't warned about.
+    Forwarding,
+    /// Every warning is an error, so the program only assembles without any.
+    Strict,
+    Port(PortLabel),
+    /// `.ascii NAME "text"`: the text's bytes go in RAM, and `NAME` and `NAME_LEN` become
+    /// constants for where they start and how many there are.
+    Ascii { name: String, text: String },
+    /// `.word A, B, ...`: one word of instruction memory per value, for jump tables. Instruction
+    /// memory holds decoded instructions rather than bytes, so each word is a `JMP` to its value.
+    Word(Vec<String>),
+    /// `.stack #12` or `.stack #0 up`: where SP starts and which way the stack grows.
+    Stack(StackLayout),
+    /// `.watchdog 500` or `.watchdog 500 clear`: restart the program unless it kicks the
+    /// watchdog port that often.
+    Watchdog(Watchdog),
+}
+
+const RAM_SIZE: usize = 16;
+
+/// Every directive, with what it does, for the editor tools. `parse_directive` reads them.
+pub const DIRECTIVES: [(&str, &str); 9] = [
+    (".autonop", "Put a NOOP in wherever a RAW hazard would be, instead of warning."),
+    (".casesensitive", "Labels keep their case, so `Loop` and `LOOP` are different labels."),
+    (".forwarding", "Run with a bypass from writeback to execute, so RAW hazards can't happen."),
+    (".strict", "Make every warning an error, so the program only assembles without hazards or other warnings."),
+    (".port", "Name a display port or range in the dashboard: `.port 3 \"Hundreds digit\"`."),
+    (".ascii", "Put text in RAM from #0: `.ascii NAME \"text\"` makes `NAME` and `NAME_LEN` constants."),
+    (".word", "One jump-table entry per value: `.word IDLE, RUN`."),
+    (".stack", "Where the stack starts and which way it grows: `.stack #12` or `.stack #0 up`."),
+    (".watchdog", "Restart from address 0 unless %36 is written every N clocks: `.watchdog 500` or `.watchdog 500 clear`."),
+];
+
+/// The characters an operand starts with to say what it is: `R3`/`$3` is a register, `#3`/`@3`
+/// a RAM address and `%3` a port. Anything else is a number or a label.
+pub const OPERAND_PREFIXES: [(char, OperandType); 5] = [
+    ('R', OperandType::Register),
+    ('$', OperandType::Register),
+    ('#', OperandType::MemoryAddress),
+    ('@', OperandType::MemoryAddress),
+    ('%', OperandType::Port),
+];
+
+/// Why a line didn't assemble, with the column of the token at fault when there is one.
+#[derive(Debug)]
+pub struct SyntaxError {
+    /// Counted in characters from 1.
+    pub column: Option<usize>,
+    pub code: Code,
+    pub message: String,
+}
+
+impl SyntaxError {
+    fn at(column: usize, code: Code, message: String) -> Self {
+        Self { column: Some(column), code, message }
+    }
+}
+
+/// The message with its code, e.g. `[E001] Invalid operation: JPM (did you mean JMP?)`.
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code.tag(&self.message))
+    }
+}
+
+/// " (did you mean X?)" with the candidate closest to `word`, if one is close enough to be
+/// a typo: at most two edits away, and fewer edits than the word has letters.
+fn did_you_mean(word: &str, candidates: impl Iterator<Item = String>) -> String {
+    let limit = 2.min(word.chars().count().saturating_sub(1));
+    candidates
+        .map(|candidate| (edit_distance(word, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min()
+        .map(|(_, candidate)| format!(" (did you mean {candidate}?)"))
+        .unwrap_or_default()
+}
+
+/// Edits (insertions, deletions, substitutions and swaps of neighbours) that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1).min(row[j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// `register`'s bit in a set of registers. Numbers outside R0-R7 read 0 and drop writes, as R0
+/// does, so they get no bit and are never reported as unwritten.
+fn register_bit(register: i32) -> u8 {
+    if (0..8).contains(&register) { 1 << register } else { 0 }
+}
+
+/// `Line N` or `Line N, column C`: how every assembler message starts.
+pub fn location(line: i32, column: Option<usize>) -> String {
+    match column {
+        Some(column) => format!("Line {line}, column {column}"),
+        None => format!("Line {line}"),
+    }
+}
+
+/// Splits a message that starts with `location` back into the line, the column and the rest.
+pub fn split_location(message: &str) -> (Option<usize>, Option<usize>, &str) {
+    let Some((place, text)) = message.strip_prefix("Line ").and_then(|rest| rest.split_once(": ")) else {
+        return (None, None, message);
+    };
+    let (line, column) = match place.split_once(", column ") {
+        Some((line, column)) => (line, column.parse().ok()),
+        None => (place, None),
+    };
+    match line.parse() {
+        Ok(line) => (Some(line), column, text),
+        Err(_) => (None, None, message),
+    }
+}
+
 impl Parser {
     pub fn parse(code: String) -> (Vec<Instruction>, Vec<String>, Vec<String>) {
         let lines: Vec<&str> = code.lines().collect();
         let mut instructions = Vec::new();
         let mut errors = Vec::new();
         let mut warnings = Vec::new();
-        let mut labels = HashMap::new();
-        let mut addr_counter = 0;
 
         // Pass 0: Scan labels
-        for line in &lines {
-            let clean = line.split(';').next().unwrap_or("").trim().to_uppercase();
-            if let Some(idx) = clean.find(':') {
-                if let Some(label) = clean.get(0..idx) {
-                    if !label.contains(' ') {
-                        labels.insert(label.to_string(), addr_counter);
-                    }
-                }
-                let after = clean.get(idx+1..).unwrap_or("").trim();
-                if !after.is_empty() {
-                    addr_counter += 1;
-                }
-            } else if !clean.is_empty() {
-                addr_counter += 1;
-            }
+        let labels = Self::scan_labels(&code);
+        let auto_nop = Self::auto_nop(&code);
+        let forwarding = Self::forwarding(&code);
+        let case_sensitive = Self::case_sensitive(&code);
+        // Operands can also name `.ascii` data; the flow pass only cares about code labels.
+        let mut names = labels.clone();
+        for (name, value) in Self::data_names(&code) {
+            names.entry(name).or_insert(value);
         }
 
         // Pass 1: Parse
-        addr_counter = 0;
+        let mut addr_counter = 0;
         for (i, line) in lines.iter().enumerate() {
             let source_line = (i + 1) as i32;
-            match Self::parse_line(line, addr_counter, source_line, &labels) {
-                Ok(Some(instr)) => {
+            if let Some(values) = Self::word_values(line) {
+                for value in values {
+                    match Self::parse_operand(&value, &names, case_sensitive) {
+                        Ok(target) if target.type_ == OperandType::Immediate => instructions.push(Instruction {
+                            operation: Operation::JMP,
+                            a: target,
+                            address: addr_counter,
+                            source_line,
+                            ..Instruction::none()
+                        }),
+                        Ok(_) => errors.push(format!("{}: {}", location(source_line, None), Code::BadDirective.tag(format!("`.word` takes numbers and labels, not \"{}\"", value)))),
+                        Err(e) => errors.push(format!("{}: {}", location(source_line, None), Code::BadOperand.tag(e))),
+                    }
+                    addr_counter += 1;
+                }
+                continue;
+            }
+            match Self::parse_line(line, addr_counter, source_line, &names, case_sensitive) {
+                Ok(Some(mut instr)) => {
                     // 1. Static Warnings
                     let mut warns = Self::check_warnings(&instr, source_line);
                     
                     // 2. DYNAMIC HAZARD CHECK (Read-After-Write)
-                    if let Some(prev) = instructions.last() {
-                         // Check if previous instruction writes to a register
-                        if let Some(written_reg) = Self::get_write_register(prev) {
-                            // Check if current instruction reads that same register
-                            let read_regs = Self::get_read_registers(&instr);
-                            if read_regs.contains(&written_reg) {
-                                warns.push(format!(
-                                    "Line {}: RAW Hazard. Reading R{} immediately after writing may yield old value due to pipeline latency. Insert a NOOP.", 
-                                    source_line, written_reg
-                                ));
-                            }
+                    match instructions.last().and_then(|prev| Self::raw_hazard(prev, &instr)).filter(|_| !forwarding) {
+                        // `.autonop`: the NOOP goes in, and the instruction moves down one.
+                        Some(_) if auto_nop => {
+                            instructions.push(Instruction { address: addr_counter, source_line, ..Instruction::none() });
+                            addr_counter += 1;
+                            instr.address = addr_counter;
                         }
+                        Some(written_reg) => {
+                            // Point at the operand that reads it; B is the usual source.
+                            let column = if instr.b.type_ == OperandType::Register && instr.b.data == written_reg { instr.columns[1] } else { instr.columns[0] };
+                            warns.push(format!(
+                                "{}: {}",
+                                location(source_line, Some(column).filter(|c| *c > 0)),
+                                Code::RawHazard.tag(format!("RAW Hazard. Reading R{} immediately after writing may yield old value due to pipeline latency. Insert a NOOP.", written_reg))
+                            ));
+                        }
+                        None => {}
                     }
 
                     if !warns.is_empty() {
@@ -62,27 +207,725 @@ impl Parser {
                 },
                 Ok(None) => {}, // Empty or comment or just label
                 Err(e) => {
-                    errors.push(format!("Line {}: {}", source_line, e));
+                    errors.push(format!("{}: {}", location(source_line, e.column), e));
                 }
             }
         }
 
+        // Pass 2: Flow analysis, which needs the whole program.
+        if errors.is_empty() {
+            warnings.extend(Self::check_flow(&lines, &instructions, &labels, case_sensitive));
+            warnings.extend(Self::check_banks(&instructions, Self::scan_data(&code).0.len() as i32));
+            warnings.extend(Self::check_dead_stores(&instructions, &labels));
+        }
+
+        let (allowed, allow_errors) = Self::scan_allows(&lines);
+        errors.extend(allow_errors);
+        warnings.retain(|warning| {
+            let (Some(line), _, text) = split_location(warning) else { return true };
+            let (Some(code), _) = split_code(text) else { return true };
+            !allowed.get(&line).is_some_and(|codes| codes.contains(&code))
+        });
+
         (instructions, errors, warnings)
     }
 
-    fn check_warnings(instr: &Instruction, line: i32) -> Vec<String> {
+    /// The warnings each line's `; @allow(raw_hazard, W001)` comment drops, by line, and an
+    /// error for anything listed that isn't a warning's name or code.
+    fn scan_allows(lines: &[&str]) -> (HashMap<usize, Vec<Code>>, Vec<String>) {
+        let mut allowed = HashMap::new();
+        let mut errors = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            if Self::is_directive(line) {
+                continue;
+            }
+            let Some((_, comment)) = line.split_once(';') else { continue };
+            let Some((_, list)) = comment.split_once("@allow(") else { continue };
+            let Some((list, _)) = list.split_once(')') else {
+                errors.push(format!("Line {}: {}", i + 1, Code::BadDirective.tag("`@allow(` needs a closing `)`")));
+                continue;
+            };
+            let codes: &mut Vec<Code> = allowed.entry(i + 1).or_default();
+            for name in list.split(',').map(str::trim) {
+                match Code::find(name).filter(|code| code.name().is_some()) {
+                    Some(code) => codes.push(code),
+                    None => {
+                        let names = Code::ALL.into_iter().filter_map(|code| code.name().map(str::to_string));
+                        errors.push(format!(
+                            "Line {}: {}",
+                            i + 1,
+                            Code::BadDirective.tag(format!("`@allow` takes warnings, such as `raw_hazard` or `W003`, not \"{}\"{}", name, did_you_mean(name, names)))
+                        ));
+                    }
+                }
+            }
+        }
+        (allowed, errors)
+    }
+
+    /// Warns about labels nothing refers to, and about the first instruction of each run that no
+    /// path from address 0 reaches, such as code after a `JMP` that nothing branches to. Runs of
+    /// only `NOOP`s are left alone: that's the padding that goes after a branch. The same entry
+    /// points feed `check_uninitialized`.
+    fn check_flow(lines: &[&str], instructions: &[Instruction], labels: &HashMap<String, i32>, case_sensitive: bool) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut defined = Vec::new();
+        let mut used = HashSet::new();
+        // Besides address 0, code runs from addresses loaded as data: `IMM R1 LABEL`, then
+        // `PUSH R1` and `RET` jumps there.
+        let mut roots = vec![0];
+        for (i, line) in lines.iter().enumerate() {
+            if Self::is_directive(line) {
+                // A jump table is entered by computed address, at any of its words.
+                if let Some(values) = Self::word_values(line) {
+                    used.extend(values.iter().map(|value| Self::label_name(value, case_sensitive)));
+                    roots.extend(instructions.iter().filter(|instr| instr.source_line == i as i32 + 1).map(|instr| instr.address));
+                }
+                continue;
+            }
+            let code = line.split(';').next().unwrap_or("");
+            let body = match code.find(':') {
+                Some(idx) => {
+                    let name = code[..idx].trim();
+                    if !name.is_empty() && !name.contains(' ') {
+                        defined.push((i + 1, Self::label_name(name, case_sensitive)));
+                    }
+                    &code[idx + 1..]
+                }
+                None => code,
+            };
+            let mut tokens = body.split_whitespace();
+            let branches = tokens.next().and_then(|op| Self::match_op(&op.to_uppercase())).is_some_and(Self::is_branch);
+            for name in tokens.map(|token| Self::label_name(token, case_sensitive)).filter(|name| labels.contains_key(name)) {
+                if !branches {
+                    roots.push(labels[&name]);
+                }
+                used.insert(name);
+            }
+        }
+        for (line, name) in defined.iter().filter(|(_, name)| !used.contains(name)) {
+            warnings.push(format!("Line {}: {}", line, Code::UnusedLabel.tag(format!("Label {} is never used.", name))));
+        }
+        warnings.extend(Self::check_uninitialized(instructions, &roots));
+
+        let mut reached = vec![false; instructions.len()];
+        let mut pending: Vec<i32> = roots;
+        while let Some(address) = pending.pop() {
+            let Some(instr) = instructions.get(address as usize).filter(|_| address >= 0 && !reached[address as usize]) else {
+                continue;
+            };
+            reached[address as usize] = true;
+            if Self::is_branch(instr.operation) {
+                pending.push(instr.a.data);
+            }
+            if !matches!(instr.operation, Operation::JMP | Operation::RET | Operation::HLT) {
+                pending.push(address + 1);
+            }
+        }
+        let mut runs = instructions.iter().enumerate().peekable();
+        while let Some((address, instr)) = runs.next() {
+            if reached[address] {
+                continue;
+            }
+            let mut padding = instr.operation == Operation::NOOP;
+            while let Some((_, next)) = runs.next_if(|(next, _)| !reached[*next]) {
+                padding &= next.operation == Operation::NOOP;
+            }
+            if !padding {
+                warnings.push(format!(
+                    "Line {}: {}",
+                    instr.source_line,
+                    Code::Unreachable.tag("Unreachable code. Nothing branches here, and the instruction before never continues to it.")
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Follows which registers are written along every path from address 0, and warns where one
+    /// is read on a path that hasn't written it. The other `roots` are entered with registers
+    /// set by whoever computed the jump, and a `CALL` returns with them set by the subroutine,
+    /// so all of them count as written there. Once warned about, a register counts as written.
+    fn check_uninitialized(instructions: &[Instruction], roots: &[i32]) -> Vec<String> {
+        // None until a path reaches the instruction, then a bit per register every path to it
+        // has written. R0 always reads 0, so it's always written.
+        let mut written: Vec<Option<u8>> = vec![None; instructions.len()];
+        let mut reported = vec![0u8; instructions.len()];
+        let mut pending: Vec<(i32, u8)> = roots.iter().map(|&root| (root, if root == 0 { 1 } else { 0xFF })).collect();
+        while let Some((address, state)) = pending.pop() {
+            let Some(instr) = usize::try_from(address).ok().and_then(|address| instructions.get(address)) else {
+                continue;
+            };
+            let state = match written[address as usize] {
+                None => state,
+                Some(seen) if seen & state == seen => continue,
+                Some(seen) => seen & state,
+            };
+            written[address as usize] = Some(state);
+            // Saving a register doesn't use its value, and services differ in the arguments they take.
+            let reads = match instr.operation {
+                Operation::PUSH | Operation::SYS => Vec::new(),
+                _ => Self::get_read_registers(instr),
+            };
+            let unwritten = reads.iter().fold(0u8, |bits, register| bits | register_bit(*register)) & !state;
+            reported[address as usize] |= unwritten;
+            let writes = match instr.operation {
+                Operation::POPA => register_mask(instr.a.data),
+                Operation::SYS => 1 << 1,
+                _ => Self::get_write_register(instr).map_or(0, register_bit),
+            };
+            let after = state | unwritten | writes;
+            if Self::is_branch(instr.operation) {
+                pending.push((instr.a.data, after));
+            }
+            if !matches!(instr.operation, Operation::JMP | Operation::RET | Operation::HLT) {
+                pending.push((address + 1, if instr.operation == Operation::CALL { 0xFF } else { after }));
+            }
+        }
+
+        let mut warnings = Vec::new();
+        for (instr, unwritten) in instructions.iter().zip(reported) {
+            for register in (1..8).filter(|register| unwritten & 1 << register != 0) {
+                let column = if instr.b.type_ == OperandType::Register && instr.b.data == register { instr.columns[1] } else { instr.columns[0] };
+                warnings.push(format!(
+                    "{}: {}",
+                    location(instr.source_line, Some(column).filter(|c| *c > 0)),
+                    Code::UninitializedRead.tag(format!("R{register} is read before anything writes it on some path from the start, so it may still be 0 from reset."))
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Warns where a register or RAM address is written and then written again before anything
+    /// reads it, within a basic block: a run that's only entered at its top (a label or branch
+    /// target) and only left at its bottom (a branch, `RET` or `HLT`). What a value does after the
+    /// block ends isn't known, so it's never warned about.
+    fn check_dead_stores(instructions: &[Instruction], labels: &HashMap<String, i32>) -> Vec<String> {
+        let mut starts: HashSet<i32> = labels.values().copied().collect();
+        starts.extend(instructions.iter().filter(|instr| Self::is_branch(instr.operation)).map(|instr| instr.a.data));
+        let mut warnings = Vec::new();
+        // The unread write to each register and RAM address in this block.
+        let mut registers: HashMap<i32, &Instruction> = HashMap::new();
+        let mut ram: HashMap<i32, &Instruction> = HashMap::new();
+        for instr in instructions {
+            if starts.contains(&instr.address) {
+                registers.clear();
+                ram.clear();
+            }
+            let mut reads = Self::get_read_registers(instr);
+            if instr.operation == Operation::PUSHA {
+                reads.extend((1..8).filter(|register| register_mask(instr.a.data) & 1 << register != 0));
+            }
+            for register in reads {
+                registers.remove(&register);
+            }
+            let writes: Vec<i32> = match instr.operation {
+                Operation::POPA => (1..8).filter(|register| register_mask(instr.a.data) & 1 << register != 0).collect(),
+                _ => Self::get_write_register(instr).into_iter().filter(|register| *register != 0).collect(),
+            };
+            for register in writes {
+                if let Some(dead) = registers.insert(register, instr) {
+                    // The accumulator and flags may be what the ALU operation was for.
+                    let info = dead.operation.info();
+                    let hint = if info.prefixes { format!(" If only the accumulator and flags are needed, X{} doesn't write R{register}.", info.mnemonic) } else { String::new() };
+                    warnings.push(format!(
+                        "{}: {}",
+                        location(dead.source_line, Some(dead.columns[0]).filter(|c| *c > 0)),
+                        Code::DeadStore.tag(format!("R{register} is written here, then again on line {} before anything reads it.{hint}", instr.source_line))
+                    ));
+                }
+            }
+            match instr.operation {
+                Operation::STORE if (0..RAM_SIZE as i32).contains(&instr.a.data) => {
+                    if let Some(dead) = ram.insert(instr.a.data, instr) {
+                        warnings.push(format!(
+                            "{}: {}",
+                            location(dead.source_line, Some(dead.columns[0]).filter(|c| *c > 0)),
+                            Code::DeadStore.tag(format!("#{} is stored here, then again on line {} before anything loads it.", instr.a.data, instr.source_line))
+                        ));
+                    }
+                }
+                Operation::LOAD => {
+                    ram.remove(&instr.b.data);
+                }
+                // Arithmetic only touches registers; anything else might read RAM.
+                Operation::NOOP | Operation::IMM | Operation::MOV | Operation::STORE => {}
+                _ if instr.operation.info().group == Group::MathAndLogic => {}
+                _ => ram.clear(),
+            }
+            if Self::is_branch(instr.operation) || matches!(instr.operation, Operation::RET | Operation::HLT) {
+                registers.clear();
+                ram.clear();
+            }
+        }
+        warnings
+    }
+
+    /// Follows the bank `BANK` selects along every path from address 0, and warns where a `STORE`
+    /// or `LOAD` likely reaches the wrong one: `.ascii` text (always in bank 0) with another bank
+    /// selected, or an address only ever stored in some other bank. Where paths disagree, after a
+    /// `CALL` and after a bank port write the bank isn't known, and nothing there is warned about.
+    fn check_banks(instructions: &[Instruction], data_len: i32) -> Vec<String> {
+        // None until a path reaches the instruction, then the bank it runs with if it's known.
+        let mut banks: Vec<Option<Option<u8>>> = vec![None; instructions.len()];
+        let mut pending = vec![(0, Some(0))];
+        while let Some((address, bank)) = pending.pop() {
+            let Some(instr) = usize::try_from(address).ok().and_then(|address| instructions.get(address)) else {
+                continue;
+            };
+            let bank = match banks[address as usize] {
+                None => bank,
+                Some(seen) if seen == bank || seen.is_none() => continue,
+                Some(_) => None,
+            };
+            banks[address as usize] = Some(bank);
+            let after = match instr.operation {
+                Operation::BANK => Some(instr.a.data as u8 % BANKS as u8),
+                Operation::CALL | Operation::ROUT => None,
+                Operation::OUT if instr.a.data == BANK_PORT => None,
+                _ => bank,
+            };
+            if Self::is_branch(instr.operation) {
+                pending.push((instr.a.data, if instr.operation == Operation::CALL { bank } else { after }));
+            }
+            if !matches!(instr.operation, Operation::JMP | Operation::RET | Operation::HLT) {
+                pending.push((address + 1, after));
+            }
+        }
+
+        // Where each address is stored, by bank and line; `.ascii` text counts as stored in bank 0.
+        let accesses: Vec<(&Instruction, u8, i32, usize)> = instructions
+            .iter()
+            .zip(&banks)
+            .filter_map(|(instr, bank)| {
+                let (address, column) = match instr.operation {
+                    Operation::STORE => (instr.a.data, instr.columns[0]),
+                    Operation::LOAD => (instr.b.data, instr.columns[1]),
+                    _ => return None,
+                };
+                Some((instr, (*bank)??, address, column)).filter(|_| (0..16).contains(&address))
+            })
+            .collect();
+        let stored_anywhere: HashSet<i32> = instructions
+            .iter()
+            .zip(&banks)
+            .filter(|(instr, bank)| instr.operation == Operation::STORE && bank.is_some_and(|bank| bank.is_none()))
+            .map(|(instr, _)| instr.a.data)
+            .collect();
+        let stores: Vec<(u8, i32, i32)> = accesses
+            .iter()
+            .filter(|(instr, ..)| instr.operation == Operation::STORE)
+            .map(|(instr, bank, address, _)| (*bank, *address, instr.source_line))
+            .chain((0..data_len).map(|address| (0, address, 0)))
+            .collect();
+
+        let mut warnings = Vec::new();
+        for (instr, bank, address, column) in accesses {
+            let at = location(instr.source_line, Some(column).filter(|c| *c > 0));
+            if bank != 0 && address < data_len {
+                warnings.push(format!(
+                    "{at}: {}",
+                    Code::CrossBank.tag(format!("#{address} is `.ascii` text, which is in bank 0, but bank {bank} is selected here."))
+                ));
+            } else if instr.operation == Operation::LOAD
+                && !stored_anywhere.contains(&address)
+                && !stores.iter().any(|(stored, at, _)| (*stored, *at) == (bank, address))
+            {
+                if let Some((other, _, line)) = stores.iter().find(|(_, at, _)| *at == address) {
+                    warnings.push(format!(
+                        "{at}: {}",
+                        Code::CrossBank.tag(format!("Nothing stores #{address} in bank {bank}, which is selected here; line {line} stores it in bank {other}."))
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// The instructions that may jump to the address in operand A.
+    fn is_branch(op: Operation) -> bool {
+        matches!(op, Operation::JMP | Operation::BIE | Operation::BIG | Operation::BIL | Operation::BIO | Operation::CALL)
+    }
+
+    /// The register `instr` reads right after `prev` wrote it, which it would read stale.
+    pub(crate) fn raw_hazard(prev: &Instruction, instr: &Instruction) -> Option<i32> {
+        Self::get_write_register(prev).filter(|written_reg| Self::get_read_registers(instr).contains(written_reg))
+    }
+
+    /// Whether any line of `code` is a directive `wanted` accepts.
+    fn has_directive(code: &str, wanted: fn(&Directive) -> bool) -> bool {
+        code.lines().filter(|line| Self::is_directive(line)).any(|line| Self::parse_directive(line).is_ok_and(|directive| wanted(&directive)))
+    }
+
+    /// Whether the program asks for `.autonop`.
+    fn auto_nop(code: &str) -> bool {
+        Self::has_directive(code, |directive| matches!(directive, Directive::AutoNop))
+    }
+
+    /// Whether the program asks for `.forwarding`.
+    pub fn forwarding(code: &str) -> bool {
+        Self::has_directive(code, |directive| matches!(directive, Directive::Forwarding))
+    }
+
+    /// Whether the program asks for `.strict`.
+    pub fn strict(code: &str) -> bool {
+        Self::has_directive(code, |directive| matches!(directive, Directive::Strict))
+    }
+
+    /// The program's `.stack` layout, or #15 down without one. With more than one, the last wins.
+    pub fn stack_layout(code: &str) -> StackLayout {
+        code.lines()
+            .rev()
+            .filter(|line| Self::is_directive(line))
+            .filter_map(|line| match Self::parse_directive(line) {
+                Ok(Directive::Stack(layout)) => Some(layout),
+                _ => None,
+            })
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// The program's `.watchdog`, if it has one. With more than one, the last wins.
+    pub fn watchdog(code: &str) -> Option<Watchdog> {
+        code.lines()
+            .rev()
+            .filter(|line| Self::is_directive(line))
+            .find_map(|line| match Self::parse_directive(line) {
+                Ok(Directive::Watchdog(watchdog)) => Some(watchdog),
+                _ => None,
+            })
+    }
+
+    /// Whether the program asks for `.casesensitive`.
+    pub fn case_sensitive(code: &str) -> bool {
+        Self::has_directive(code, |directive| matches!(directive, Directive::CaseSensitive))
+    }
+
+    /// How a label or `.ascii` name is written down in the tables and messages: as written
+    /// under `.casesensitive`, upper-cased otherwise.
+    fn label_name(name: &str, case_sensitive: bool) -> String {
+        if case_sensitive { name.to_string() } else { name.to_uppercase() }
+    }
+
+    /// Under `.autonop`, the addresses (as written, before any NOOPs go in) of the instructions
+    /// that get a NOOP in front of them.
+    fn nop_slots(code: &str) -> Vec<i32> {
+        if !Self::auto_nop(code) || Self::forwarding(code) {
+            return Vec::new();
+        }
+        let labels = Self::scan_written_labels(code);
+        let case_sensitive = Self::case_sensitive(code);
+        let mut slots = Vec::new();
+        let mut prev: Option<Instruction> = None;
+        let mut address = 0;
+        for line in code.lines() {
+            if let Some(values) = Self::word_values(line) {
+                address += values.len() as i32;
+                prev = None;
+                continue;
+            }
+            // Lines with errors take no address, as in `parse`.
+            if let Ok(Some(instr)) = Self::parse_line(line, address, 0, &labels, case_sensitive) {
+                if prev.as_ref().and_then(|prev| Self::raw_hazard(prev, &instr)).is_some() {
+                    slots.push(address);
+                }
+                prev = Some(instr);
+                address += 1;
+            }
+        }
+        slots
+    }
+
+    /// Where an instruction written at `address` ends up once the NOOPs in `slots` are in. A
+    /// label keeps its NOOP (`before`), so a jump there is still safe from the hazard.
+    fn shifted(address: i32, slots: &[i32], before: bool) -> i32 {
+        address + slots.iter().filter(|slot| if before { **slot < address } else { **slot <= address }).count() as i32
+    }
+
+    /// Maps every label (upper-cased unless `.casesensitive`) to the address of the instruction it marks.
+    pub fn scan_labels(code: &str) -> HashMap<String, i32> {
+        let slots = Self::nop_slots(code);
+        let mut labels = Self::scan_written_labels(code);
+        for address in labels.values_mut() {
+            *address = Self::shifted(*address, &slots, true);
+        }
+        labels
+    }
+
+    /// `scan_labels` without `.autonop`'s NOOPs.
+    fn scan_written_labels(code: &str) -> HashMap<String, i32> {
+        let case_sensitive = Self::case_sensitive(code);
+        let mut labels = HashMap::new();
+        let mut addr_counter = 0;
+        for line in code.lines() {
+            if Self::is_directive(line) {
+                addr_counter += Self::word_values(line).map_or(0, |values| values.len() as i32);
+                continue;
+            }
+            let clean = line.split(';').next().unwrap_or("").trim();
+            if let Some(idx) = clean.find(':') {
+                if let Some(label) = clean.get(0..idx) {
+                    if !label.contains(' ') {
+                        labels.insert(Self::label_name(label, case_sensitive), addr_counter);
+                    }
+                }
+                let after = clean.get(idx+1..).unwrap_or("").trim();
+                if !after.is_empty() {
+                    addr_counter += 1;
+                }
+            } else if !clean.is_empty() {
+                addr_counter += 1;
+            }
+        }
+        labels
+    }
+
+    /// Lines starting with `.` are directives for the toolchain; they take no address, apart
+    /// from `.word`.
+    fn is_directive(line: &str) -> bool {
+        line.trim_start().starts_with('.')
+    }
+
+    /// The values of a well-formed `.word` line.
+    fn word_values(line: &str) -> Option<Vec<String>> {
+        match Self::parse_directive(line) {
+            Ok(Directive::Word(values)) if Self::is_directive(line) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// The `.port` labels in `code`. Malformed ones are reported by `parse`.
+    pub fn scan_port_labels(code: &str) -> Vec<PortLabel> {
+        code.lines()
+            .rev()
+            .filter(|line| Self::is_directive(line))
+            .filter_map(|line| match Self::parse_directive(line) {
+                Ok(Directive::Port(label)) => Some(label),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The `.ascii` lines in `code` with their line numbers. Malformed ones are reported by
+    /// `parse`.
+    fn ascii_blocks(code: &str) -> Vec<(usize, String, String)> {
+        let case_sensitive = Self::case_sensitive(code);
+        code.lines()
+            .enumerate()
+            .filter(|(_, line)| Self::is_directive(line))
+            .filter_map(|(i, line)| match Self::parse_directive(line) {
+                Ok(Directive::Ascii { name, text }) => Some((i + 1, Self::label_name(&name, case_sensitive), text)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The bytes of every `.ascii` line, one after the other from RAM address 0, for the
+    /// emulator to put in RAM before each run. Data that doesn't fit, and names taken by a label
+    /// or an earlier `.ascii`, are errors.
+    pub fn scan_data(code: &str) -> (Vec<u8>, Vec<String>) {
+        let labels = Self::scan_labels(code);
+        let mut data = Vec::new();
+        let mut errors = Vec::new();
+        let mut names = HashSet::new();
+        for (line, name, text) in Self::ascii_blocks(code) {
+            if labels.contains_key(&name) || !names.insert(name.clone()) {
+                errors.push(format!("Line {}: {}", line, Code::DuplicateName.tag(format!("{} is already defined", name))));
+            } else if data.len() + text.len() > RAM_SIZE {
+                errors.push(format!(
+                    "Line {}: {}",
+                    line,
+                    Code::DataOverflow.tag(format!("`.ascii` data doesn't fit in RAM ({} bytes, {} already used)", RAM_SIZE, data.len()))
+                ));
+            } else {
+                data.extend(text.bytes());
+            }
+        }
+        (data, errors)
+    }
+
+    /// `NAME` (the RAM address) and `NAME_LEN` (the byte count) for each `.ascii` line.
+    fn data_names(code: &str) -> HashMap<String, i32> {
+        let mut names = HashMap::new();
+        let mut address = 0;
+        for (_, name, text) in Self::ascii_blocks(code) {
+            names.entry(format!("{name}_LEN")).or_insert(text.len() as i32);
+            names.entry(name).or_insert(address);
+            address += text.len() as i32;
+        }
+        names
+    }
+
+    /// Parses `.autonop`, `.casesensitive`, `.forwarding`, `.strict`, `.port <n> "text"`,
+    /// `.port <first>-<last> "text"`, `.ascii NAME "text"`, `.word A, B, ...`,
+    /// `.stack #base [up|down]` or `.watchdog <clocks> [clear]`. The text is quoted so it can hold
+    /// `;` and `:`.
+    fn parse_directive(line: &str) -> Result<Directive, String> {
+        let line = line.trim();
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let flag = match name.to_ascii_lowercase().as_str() {
+            ".autonop" => Some(Directive::AutoNop),
+            ".casesensitive" => Some(Directive::CaseSensitive),
+            ".forwarding" => Some(Directive::Forwarding),
+            ".strict" => Some(Directive::Strict),
+            _ => None,
+        };
+        if let Some(flag) = flag {
+            return match rest.split(';').next().unwrap_or("").trim() {
+                "" => Ok(flag),
+                _ => Err(format!("`{}` takes no arguments", name.to_ascii_lowercase())),
+            };
+        }
+        if name.eq_ignore_ascii_case(".ascii") {
+            return Self::parse_ascii(rest);
+        }
+        if name.eq_ignore_ascii_case(".word") {
+            let values: Vec<String> = rest.split(';').next().unwrap_or("").split([',', ' ', '\t']).filter(|value| !value.is_empty()).map(str::to_string).collect();
+            if values.is_empty() {
+                return Err("`.word` needs one or more numbers or labels, e.g. `.word IDLE, RUN`".to_string());
+            }
+            return Ok(Directive::Word(values));
+        }
+        if name.eq_ignore_ascii_case(".stack") {
+            let layout = rest.split(';').next().unwrap_or("").trim();
+            if layout.is_empty() {
+                return Err("`.stack` needs a RAM address to start at, e.g. `.stack #12` or `.stack #0 up`".to_string());
+            }
+            return StackLayout::parse(layout).map(Directive::Stack);
+        }
+        if name.eq_ignore_ascii_case(".watchdog") {
+            let timeout = rest.split(';').next().unwrap_or("").trim();
+            if timeout.is_empty() {
+                return Err("`.watchdog` needs a number of clocks, e.g. `.watchdog 500` or `.watchdog 500 clear`".to_string());
+            }
+            return Watchdog::parse(timeout).map(Directive::Watchdog);
+        }
+        if !name.eq_ignore_ascii_case(".port") {
+            let names = DIRECTIVES.iter().map(|(directive, _)| directive.to_string());
+            return Err(format!("Unknown directive \"{}\"{}", name, did_you_mean(&name.to_ascii_lowercase(), names)));
+        }
+        let usage = "`.port` needs a display port or range and a quoted label, e.g. `.port 3 \"Hundreds digit\"`";
+        let (ports, text) = rest.trim().split_once('"').ok_or(usage)?;
+        let (text, after) = text.split_once('"').ok_or(usage)?;
+        let after = after.trim();
+        if !(after.is_empty() || after.starts_with(';')) {
+            return Err(usage.to_string());
+        }
+        let port = |s: &str| s.trim().trim_start_matches('%').parse::<u8>().ok().filter(|p| *p < 8);
+        let (first, last) = match ports.trim().split_once('-') {
+            Some((first, last)) => (port(first), port(last)),
+            None => (port(ports), port(ports)),
+        };
+        match (first, last) {
+            (Some(first), Some(last)) if first <= last => Ok(Directive::Port(PortLabel { first, last, text: text.to_string() })),
+            _ => Err(format!("\"{}\" is not a display port (%0-%7) or a range of them like 0-2", ports.trim())),
+        }
+    }
+
+    fn parse_ascii(rest: &str) -> Result<Directive, String> {
+        let usage = "`.ascii` needs a name and quoted text, e.g. `.ascii MSG \"HELLO\"`";
+        let (name, text) = rest.trim().split_once('"').ok_or(usage)?;
+        let (text, after) = text.split_once('"').ok_or(usage)?;
+        let after = after.trim();
+        if !(after.is_empty() || after.starts_with(';')) {
+            return Err(usage.to_string());
+        }
+        let name = name.trim();
+        let is_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_name {
+            return Err(usage.to_string());
+        }
+        if !text.is_ascii() {
+            return Err(format!("`.ascii` text \"{}\" is not all ASCII", text));
+        }
+        Ok(Directive::Ascii { name: name.to_string(), text: text.to_string() })
+    }
+
+    /// Collects `; ASSERT <field> <op> <value>` comments. Each one is attached to the last
+    /// instruction at or above it (on the same line counts). Malformed ones are errors.
+    pub fn scan_assertions(code: &str) -> (Vec<Assertion>, Vec<String>) {
+        let slots = Self::nop_slots(code);
+        let mut assertions = Vec::new();
+        let mut errors = Vec::new();
+        let mut addr_counter = 0;
+        for (i, line) in code.lines().enumerate() {
+            if Self::is_directive(line) {
+                addr_counter += Self::word_values(line).map_or(0, |values| values.len() as i32);
+                continue;
+            }
+            let source_line = (i + 1) as i32;
+            let (code_part, comment) = line.split_once(';').unwrap_or((line, ""));
+            let clean = code_part.trim();
+            let after_label = match clean.find(':') {
+                Some(idx) if !clean[..idx].contains(' ') => clean[idx + 1..].trim(),
+                _ => clean,
+            };
+            if !after_label.is_empty() {
+                addr_counter += 1;
+            }
+
+            // The same comment can allow warnings: `; ASSERT R1 == 5 @allow(raw_hazard)`.
+            let comment = match comment.split_once("@allow(") {
+                Some((before, after)) => format!("{before} {}", after.split_once(')').map_or("", |(_, rest)| rest)),
+                None => comment.to_string(),
+            };
+            let comment = comment.trim();
+            let Some(keyword) = comment.get(..6).filter(|k| k.eq_ignore_ascii_case("ASSERT")) else { continue };
+            let condition = &comment[keyword.len()..];
+            if !condition.starts_with(char::is_whitespace) {
+                continue;
+            }
+            match Self::parse_assertion(condition.trim()) {
+                Ok((field, comparison, value)) => assertions.push(Assertion {
+                    after: Self::shifted(addr_counter - 1, &slots, false),
+                    source_line,
+                    text: condition.split_whitespace().collect::<Vec<_>>().join(" "),
+                    field,
+                    comparison,
+                    value,
+                }),
+                Err(e) => errors.push(format!("Line {}: {}", source_line, Code::BadAssertion.tag(e))),
+            }
+        }
+        (assertions, errors)
+    }
+
+    fn parse_assertion(condition: &str) -> Result<(StateField, Comparison, AssertValue), String> {
+        let usage = "ASSERT needs the form `ASSERT <field> <op> <value>`, e.g. `ASSERT R1 == 42`".to_string();
+        let op_start = condition.find(['=', '!', '<', '>']).ok_or(usage.clone())?;
+        let op_len = if condition[op_start + 1..].starts_with('=') { 2 } else { 1 };
+        let comparison = Comparison::parse(&condition[op_start..op_start + op_len])
+            .ok_or("Unknown comparison in ASSERT (use ==, !=, <, <=, > or >=)".to_string())?;
+        let left = condition[..op_start].trim();
+        let right = condition[op_start + op_len..].trim();
+        if left.is_empty() || right.is_empty() {
+            return Err(usage);
+        }
+        let field = StateField::parse(left).ok_or(format!("ASSERT can't check \"{}\"", left))?;
+        let value = match right.to_uppercase().as_str() {
+            "TRUE" => AssertValue::Number(1),
+            "FALSE" => AssertValue::Number(0),
+            _ => match Self::parse_literal(right) {
+                Ok(n) => AssertValue::Number(n),
+                Err(_) => AssertValue::Field(
+                    StateField::parse(right).ok_or(format!("ASSERT value \"{}\" is not a number or a state field", right))?,
+                ),
+            },
+        };
+        Ok((field, comparison, value))
+    }
+
+    pub fn check_warnings(instr: &Instruction, line: i32) -> Vec<String> {
         let mut warnings = Vec::new();
         let op = instr.operation;
         let a = &instr.a;
         let b = &instr.b;
+        let at = |operand: usize| location(line, Some(instr.columns[operand]).filter(|c| *c > 0));
 
         // 1. Check writing to R0
-        let writes_to_a = matches!(op, 
-            Operation::IMM | Operation::MOV | Operation::ADD | Operation::ADDC | 
-            Operation::SUB | Operation::AND | Operation::OR | Operation::XOR | 
-            Operation::SHR | Operation::NOT | Operation::LOAD | Operation::POP | 
-            Operation::INP
-        );
+        let writes_to_a = op.info().writes_register;
 
         if writes_to_a && a.type_ == OperandType::Register && a.data == 0 {
              let safe = match op {
@@ -94,7 +937,7 @@ impl Parser {
              };
 
              if !safe {
-                 warnings.push(format!("Line {}: Writing to Register 0 (Zero Register) effectively does nothing.", line));
+                 warnings.push(format!("{}: {}", at(0), Code::WriteToZero.tag("Writing to Register 0 (Zero Register) effectively does nothing.")));
              }
         }
         
@@ -102,32 +945,42 @@ impl Parser {
         if a.type_ == OperandType::Immediate
             && (a.data < 0 || a.data > 255)
                  && !matches!(op, Operation::JMP | Operation::CALL | Operation::BIE | Operation::BIG | Operation::BIL | Operation::BIO) {
-                     warnings.push(format!("Line {}: Immediate value {} is out of 8-bit range (0-255). It will be wrapped.", line, a.data));
+                     warnings.push(format!("{}: {}", at(0), Code::ImmediateRange.tag(format!("Immediate value {} is out of 8-bit range (0-255). It will be wrapped.", a.data))));
                  }
         if b.type_ == OperandType::Immediate
             && (b.data < 0 || b.data > 255) {
-                 warnings.push(format!("Line {}: Immediate value {} is out of 8-bit range (0-255). It will be wrapped.", line, b.data));
+                 warnings.push(format!("{}: {}", at(1), Code::ImmediateRange.tag(format!("Immediate value {} is out of 8-bit range (0-255). It will be wrapped.", b.data))));
             }
 
-        // 3. Port out of bounds
+        // 3. Port out of bounds (0-7 is the display, 8-255 are device ports)
         if op == Operation::OUT
              && a.type_ == OperandType::Port
-                 && (a.data < 0 || a.data > 7) {
-                     warnings.push(format!("Line {}: Port %{} is out of range (0-7).", line, a.data));
+                 && (a.data < 0 || a.data > 255) {
+                     warnings.push(format!("{}: {}", at(0), Code::PortRange.tag(format!("Port %{} is out of range (0-255).", a.data))));
+                 }
+        if op == Operation::INP
+             && b.type_ == OperandType::Port
+                 && (b.data < 0 || b.data > 255) {
+                     warnings.push(format!("{}: {}", at(1), Code::PortRange.tag(format!("Port %{} is out of range (0-255).", b.data))));
                  }
 
         // 4. RAM out of bounds
         if op == Operation::STORE
              && a.type_ == OperandType::MemoryAddress
                  && (a.data < 0 || a.data > 15) {
-                     warnings.push(format!("Line {}: Memory address #{} is out of RAM range (0-15).", line, a.data));
+                     warnings.push(format!("{}: {}", at(0), Code::RamRange.tag(format!("Memory address #{} is out of RAM range (0-15).", a.data))));
                  }
         if op == Operation::LOAD
              && b.type_ == OperandType::MemoryAddress
                  && (b.data < 0 || b.data > 15) {
-                     warnings.push(format!("Line {}: Memory address #{} is out of RAM range (0-15).", line, b.data));
+                     warnings.push(format!("{}: {}", at(1), Code::RamRange.tag(format!("Memory address #{} is out of RAM range (0-15).", b.data))));
                  }
 
+        // 5. Bank out of range
+        if op == Operation::BANK && !(0..BANKS as i32).contains(&a.data) {
+            warnings.push(format!("{}: {}", at(0), Code::RamRange.tag(format!("Bank {} is out of range (0-{}). It will be wrapped.", a.data, BANKS - 1))));
+        }
+
         warnings
     }
 
@@ -135,40 +988,38 @@ impl Parser {
 
     /// Returns the register index if the instruction writes to a register.
     fn get_write_register(instr: &Instruction) -> Option<i32> {
+        // POPA writes its last register, the lowest, in the clock the next instruction executes.
+        if instr.operation == Operation::POPA {
+            return Some(register_mask(instr.a.data).trailing_zeros() as i32);
+        }
         // Must target a register
         if instr.a.type_ != OperandType::Register {
             return None;
         }
 
-        match instr.operation {
-            Operation::IMM | Operation::MOV | Operation::LOAD | Operation::POP | Operation::INP => Some(instr.a.data),
-            Operation::ADD | Operation::ADDC | Operation::SUB | Operation::AND | Operation::OR | Operation::XOR => {
-                // 'X' prefix writes to ACC only, not the Register
-                if instr.args == OperationArgs::X {
-                    None
-                } else {
-                    Some(instr.a.data)
-                }
-            },
-            Operation::SHR | Operation::NOT => Some(instr.a.data),
-            _ => None
-        }
+        let info = instr.operation.info();
+        // 'X' prefix writes to ACC only, not the Register
+        let x_prefixed = info.prefixes && instr.args == OperationArgs::X;
+        (info.writes_register && !x_prefixed).then_some(instr.a.data)
     }
 
     /// Returns a list of registers that are read by the instruction.
     fn get_read_registers(instr: &Instruction) -> Vec<i32> {
         let mut reads = Vec::new();
+        // SYS hands R1 and R2 to the host service.
+        if instr.operation == Operation::SYS {
+            reads.extend([1, 2]);
+        }
 
         // Check Operand A (Source)
         if instr.a.type_ == OperandType::Register {
             match instr.operation {
                 // Math ops read A unless using U/X (which use ACC as source A)
-                Operation::ADD | Operation::ADDC | Operation::SUB | Operation::AND | Operation::OR | Operation::XOR => {
-                    if instr.args != OperationArgs::U && instr.args != OperationArgs::X {
+                Operation::ADD | Operation::ADDC | Operation::SUB | Operation::AND | Operation::OR | Operation::XOR
+                    if instr.args != OperationArgs::U && instr.args != OperationArgs::X => {
                         reads.push(instr.a.data);
-                    }
                 },
-                Operation::PUSH | Operation::ROUT => {
+                Operation::PUSH | Operation::ROUT | Operation::STORES => {
                     reads.push(instr.a.data);
                 },
                 _ => {}
@@ -191,35 +1042,50 @@ impl Parser {
         reads
     }
 
-    fn parse_line(line: &str, address: i32, source_line: i32, labels: &HashMap<String, i32>) -> Result<Option<Instruction>, String> {
-        let mut clean = line.split(';').next().unwrap_or("").trim().to_uppercase();
-        
-        if let Some(idx) = clean.find(':') {
-            clean = clean.get(idx+1..).unwrap_or("").trim().to_string();
+    /// Parses one source line into an instruction; `Ok(None)` for blank, comment or label-only lines.
+    pub fn parse_line(line: &str, address: i32, source_line: i32, labels: &HashMap<String, i32>, case_sensitive: bool) -> Result<Option<Instruction>, SyntaxError> {
+        if Self::is_directive(line) {
+            return Self::parse_directive(line).map(|_| None).map_err(|message| SyntaxError { column: None, code: Code::BadDirective, message });
         }
+        let code = line.split(';').next().unwrap_or("");
+        let body_start = code.find(':').map_or(0, |idx| idx + 1);
 
-        if clean.is_empty() { return Ok(None); }
-
-        let tokens: Vec<&str> = clean.split_whitespace().collect();
+        // Each token with the column it starts at, for messages that point at it.
+        let body = &code[body_start..];
+        let tokens: Vec<(usize, &str)> = body
+            .split_whitespace()
+            .map(|token| {
+                let offset = body_start + (token.as_ptr() as usize - body.as_ptr() as usize);
+                (line[..offset].chars().count() + 1, token)
+            })
+            .collect();
         if tokens.is_empty() { return Ok(None); }
 
-        let (op, args) = Self::parse_operation(tokens[0])?;
-        let needed = Self::get_needed_operands(op, args);
+        let (op, args) = Self::parse_operation(&tokens[0].1.to_uppercase()).map_err(|e| SyntaxError::at(tokens[0].0, Code::UnknownOperation, e))?;
+        let needed = op.operands(args);
 
         let mut token_idx = 1;
         let mut val_a = Operand::new(OperandType::Immediate, 0);
         let mut val_b = Operand::new(OperandType::Immediate, 0);
+        let mut columns = [0; 2];
 
         if needed.0
             && token_idx < tokens.len() {
-                val_a = Self::parse_operand(tokens[token_idx], labels)?;
+                let (column, token) = &tokens[token_idx];
+                val_a = Self::parse_operand(token, labels, case_sensitive).map_err(|e| SyntaxError::at(*column, Code::BadOperand, e))?;
+                columns[0] = *column;
                 token_idx += 1;
             }
         if needed.1
             && token_idx < tokens.len() {
-                val_b = Self::parse_operand(tokens[token_idx], labels)?;
-                token_idx += 1;
+                let (column, token) = &tokens[token_idx];
+                val_b = Self::parse_operand(token, labels, case_sensitive).map_err(|e| SyntaxError::at(*column, Code::BadOperand, e))?;
+                columns[1] = *column;
             }
+        // The PC only reaches 0-254; further out, the jump would overflow it.
+        if Self::is_branch(op) && !(0..255).contains(&val_a.data) {
+            return Err(SyntaxError::at(columns[0], Code::BadOperand, format!("Branch target {} is outside the program (addresses 0-254)", val_a.data)));
+        }
 
         Ok(Some(Instruction {
             operation: op,
@@ -228,116 +1094,110 @@ impl Parser {
             b: val_b,
             address,
             source_line,
+            columns,
         }))
     }
 
-    fn parse_operation(s: &str) -> Result<(Operation, OperationArgs), String> {
+    /// An operation as written, upper-cased, with its prefix if it has one: `XSUB` is SUB under X.
+    pub fn parse_operation(s: &str) -> Result<(Operation, OperationArgs), String> {
         if let Some(op) = Self::match_op(s) {
             return Ok((op, OperationArgs::None));
         }
         
         // Check prefixes
-        let prefix = s.chars().next().unwrap();
-        let suffix = &s[1..];
+        let prefix = s.chars().next().ok_or("Empty operation")?;
+        let suffix = &s[prefix.len_utf8()..];
         if let Some(op) = Self::match_op(suffix) {
             let args = match prefix {
                 'S' => OperationArgs::S,
                 'U' => OperationArgs::U,
                 'X' => OperationArgs::X,
-                _ => return Err(format!("Invalid operation: {}", s)),
+                _ => return Err(format!("Invalid operation: {}{}", s, Self::suggest_op(s))),
             };
             return Ok((op, args));
         }
 
-        Err(format!("Invalid operation: {}", s))
+        Err(format!("Invalid operation: {}{}", s, Self::suggest_op(s)))
     }
 
     fn match_op(s: &str) -> Option<Operation> {
-        match s {
-            "NOOP" | "NOP" => Some(Operation::NOOP),
-            "IMM" => Some(Operation::IMM),
-            "MOV" => Some(Operation::MOV),
-            "ADD" => Some(Operation::ADD),
-            "ADDC" => Some(Operation::ADDC),
-            "SUB" => Some(Operation::SUB),
-            "OR" => Some(Operation::OR),
-            "XOR" => Some(Operation::XOR),
-            "AND" => Some(Operation::AND),
-            "SHR" => Some(Operation::SHR),
-            "NOT" => Some(Operation::NOT),
-            "OUT" => Some(Operation::OUT),
-            "ROUT" => Some(Operation::ROUT),
-            "INP" => Some(Operation::INP),
-            "JMP" => Some(Operation::JMP),
-            "BIE" => Some(Operation::BIE),
-            "BIG" => Some(Operation::BIG),
-            "BIL" => Some(Operation::BIL),
-            "BIO" => Some(Operation::BIO),
-            "STORE" => Some(Operation::STORE),
-            "LOAD" => Some(Operation::LOAD),
-            "PUSH" => Some(Operation::PUSH),
-            "POP" => Some(Operation::POP),
-            "CALL" => Some(Operation::CALL),
-            "RET" => Some(Operation::RET),
-            _ => None
-        }
-    }
-
-    fn get_needed_operands(op: Operation, args: OperationArgs) -> (bool, bool) {
-        match op {
-            Operation::NOOP | Operation::RET => (false, false),
-            Operation::IMM | Operation::MOV | Operation::SHR | Operation::NOT | 
-            Operation::OUT | Operation::STORE | Operation::LOAD | Operation::ROUT => (true, true),
-            
-            Operation::ADD | Operation::ADDC | Operation::SUB | 
-            Operation::OR | Operation::XOR | Operation::AND => {
-                if args == OperationArgs::X { (false, true) } else { (true, true) }
-            },
+        OPERATIONS.iter().find(|info| info.mnemonic == s || info.aliases.contains(&s)).map(|info| info.operation)
+    }
 
-            Operation::JMP | Operation::BIE | Operation::BIG | 
-            Operation::BIL | Operation::BIO | Operation::INP | 
-            Operation::PUSH | Operation::POP | Operation::CALL => (true, false),
-        }
+    /// Every spelling the parser accepts, aliases included.
+    fn mnemonics() -> impl Iterator<Item = (&'static str, Operation)> {
+        OPERATIONS.iter().flat_map(|info| std::iter::once(info.mnemonic).chain(info.aliases.iter().copied()).map(|name| (name, info.operation)))
     }
 
-    fn parse_operand(s: &str, labels: &HashMap<String, i32>) -> Result<Operand, String> {
-        let first = s.chars().next().ok_or("Empty operand")?;
-        let rest = &s[1..];
+    /// " (did you mean JMP?)" for a near miss like `JPM`, or "". An `S`, `U` or `X` prefix is
+    /// kept when the rest is the near miss.
+    fn suggest_op(s: &str) -> String {
+        let names = Self::mnemonics().map(|(name, _)| name.to_string());
+        let prefixed = match s.chars().next() {
+            Some(prefix @ ('S' | 'U' | 'X')) => {
+                Self::mnemonics().filter(|(_, op)| op.info().prefixes).map(|(name, _)| format!("{prefix}{name}")).collect()
+            }
+            _ => Vec::new(),
+        };
+        did_you_mean(s, names.chain(prefixed))
+    }
 
-        if first == 'R' || first == '$' {
-            if let Ok(val) = Self::parse_binary(rest) {
-                return Ok(Operand::new(OperandType::Register, val));
+    /// `raw` is the token as written, so a character literal keeps its case.
+    fn parse_operand(raw: &str, labels: &HashMap<String, i32>, case_sensitive: bool) -> Result<Operand, String> {
+        let first = raw.chars().next().ok_or("Empty operand")?;
+        let rest = &raw[first.len_utf8()..];
+
+        let prefix = OPERAND_PREFIXES.iter().find(|(c, _)| *c == first.to_ascii_uppercase()).map(|(_, type_)| *type_);
+        match prefix {
+            // `R` only makes a register when a number follows; `RESET` is a label.
+            Some(OperandType::Register) => {
+                if let Ok(val) = Self::parse_literal(rest) {
+                    return Ok(Operand::new(OperandType::Register, val));
+                }
             }
-        } 
-        
-        if first == '#' || first == '@' {
-            let val = Self::parse_binary(rest)?;
-            return Ok(Operand::new(OperandType::MemoryAddress, val));
-        }
-        
-        if first == '%' {
-            let val = Self::parse_binary(rest)?;
-            return Ok(Operand::new(OperandType::Port, val));
+            Some(type_) => return Ok(Operand::new(type_, Self::parse_literal(rest)?)),
+            None if first == '\'' => return Ok(Operand::new(OperandType::Immediate, Self::parse_literal(raw)?)),
+            None => {}
         }
 
         // Immediate or Label
-        if let Ok(val) = Self::parse_binary(s) {
+        let name = Self::label_name(raw, case_sensitive);
+        let literal = Self::parse_literal(raw);
+        if let Ok(val) = literal {
              Ok(Operand::new(OperandType::Immediate, val))
+        } else if raw.starts_with(|c: char| c.is_ascii_digit()) && !labels.contains_key(&name) {
+            // Labels don't start with a digit, so this was meant as a number.
+            literal.map(|val| Operand::new(OperandType::Immediate, val))
         } else {
                 // Label lookup
-                if let Some(&addr) = labels.get(s) {
+                if let Some(&addr) = labels.get(&name) {
                     Ok(Operand::new(OperandType::Immediate, addr))
                 } 
                 else {
-                      Err(format!("Invalid value or unknown label: {}", s))
+                      Err(format!("Invalid value or unknown label: {}{}", name, did_you_mean(&name, labels.keys().cloned())))
             }
         }
     }
 
-    fn parse_binary(s: &str) -> Result<i32, String> {
-        let clean = s.replace('_', "");
-        if clean.starts_with('B') {
-            i32::from_str_radix(&clean[1..], 2).map_err(|_| format!("Invalid binary: {}", s))
+    /// A number as written in an operand: decimal, `0x2F` hex, `0b1010` or `B1010` binary, or
+    /// a character in quotes (`'A'` is 65). `_` can separate digits.
+    pub(crate) fn parse_literal(s: &str) -> Result<i32, String> {
+        if let Some(quoted) = s.strip_prefix('\'') {
+            let mut chars = quoted.chars();
+            return match (chars.next(), chars.next(), chars.next()) {
+                (Some(c), Some('\''), None) if c.is_ascii() && c != '\'' => Ok(c as i32),
+                (Some(c), Some('\''), None) if !c.is_ascii() => Err(format!("Character {} is not ASCII", s)),
+                _ => Err(format!(
+                    "Invalid character: {} (quote one character; spaces, `;` and `:` need their codes, e.g. 32)",
+                    s
+                )),
+            };
+        }
+        let clean = s.replace('_', "").to_uppercase();
+        if let Some(hex) = clean.strip_prefix("0X") {
+            i32::from_str_radix(hex, 16).map_err(|_| format!("Invalid hex: {}", s))
+        } else if let Some(binary) = clean.strip_prefix("0B").or_else(|| clean.strip_prefix('B')) {
+            i32::from_str_radix(binary, 2).map_err(|_| format!("Invalid binary: {}", s))
         } else {
             clean.parse::<i32>().map_err(|_| format!("Invalid number: {}", s))
         }
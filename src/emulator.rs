@@ -1,20 +1,23 @@
 use crate::parser::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Alu {
     pub accumalator: u8,
     pub flags: AluFlags,
 }
 
-#[derive(Debug)]
+/// Set by `ADD`, `ADDC`, `SHR` and `NOT` from their two inputs, as on V2, and kept through the
+/// other instructions.
+#[derive(Debug, Clone)]
 pub struct AluFlags {
     pub equals: bool,
-    pub greater_than: bool,
-    pub less_than: bool,
-    pub over_flow: bool,
+    pub greater: bool,
+    pub less: bool,
+    /// The last result didn't fit in a byte; `ADDC` adds it as the carry.
+    pub overflow: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Registers {
     regs: [u8; 8],
 }
@@ -47,29 +50,28 @@ impl Alu {
             } as u16,
             registers.read(instruction.b.data()) as u16,
         );
-        let mut result = match instruction.operation {
+        let result = match instruction.operation {
             Operation::ADD => a_data + b_data,
-            Operation::ADDC => a_data + b_data + 1,
+            Operation::ADDC => a_data + b_data + u16::from(self.flags.overflow),
             Operation::SHR => b_data >> 1,
-            Operation::NOT => !b_data,
-            _ => 0,
+            Operation::NOT => !b_data & 0xFF,
+            // Everything else leaves the accumulator and the flags alone.
+            _ => return,
         };
         self.flags = AluFlags {
             equals: a_data == b_data,
-            greater_than: a_data > b_data,
-            less_than: a_data < b_data,
-            over_flow: result > 255,
+            greater: a_data > b_data,
+            less: a_data < b_data,
+            overflow: result > 255,
         };
-        if result > 255 {
-            result -= 256;
-        }
-        self.accumalator = result as u8;
+        self.accumalator = (result & 0xFF) as u8;
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Ports {
     pub out: [u8; 8],
+    #[allow(dead_code)]
     pub input: [u8; 8],
 }
 
@@ -86,7 +88,7 @@ impl Ports {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Emulator {
     program: Program,
     pub program_counter: u8,
@@ -113,9 +115,9 @@ impl Emulator {
                 accumalator: 0,
                 flags: AluFlags {
                     equals: false,
-                    greater_than: false,
-                    less_than: false,
-                    over_flow: false,
+                    greater: false,
+                    less: false,
+                    overflow: false,
                 },
             },
             registers: Registers { regs: [0; 8] },
@@ -126,6 +128,22 @@ impl Emulator {
         }
     }
 
+    /// Starts the program over.
+    pub fn reset(&mut self) {
+        let program = std::mem::replace(&mut self.program, Program { instructions: vec![] });
+        *self = Self::new(program);
+    }
+
+    /// Swaps in another program and starts it.
+    pub fn load(&mut self, program: Program) {
+        *self = Self::new(program);
+    }
+
+    /// One instruction moves into EXECUTE every clock; V1 has no bubbles to pass over.
+    pub fn step(&mut self) {
+        self.clock();
+    }
+
     fn increment_program_counter(&mut self) {
         self.program_counter += 1;
         if self.program_counter >= 2u8.pow(Self::ROM_ADDRESS_BITS as u32) {
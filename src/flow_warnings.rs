@@ -0,0 +1,179 @@
+//! Checks the V2 assembler's warnings that follow the program's control flow: reading a register
+//! no path has written (W009), across branches, loops and subroutine calls, and writing one
+//! again before anything reads it (W010), within a basic block.
+
+use crate::electron_2::codes::Code;
+use crate::electron_2::parser::split_location;
+use crate::electron_2::Emulator as EmulatorV2;
+
+/// The line and message of each warning with `code` that `source` assembles with.
+fn warnings(source: &str, code: Code) -> Vec<(usize, String)> {
+    let v2 = EmulatorV2::new(source.to_string());
+    assert!(v2.errors.is_empty(), "V2 rejected the program: {}", v2.errors[0]);
+    let tag = format!("[{}] ", code.id());
+    v2.warnings
+        .iter()
+        .filter_map(|warning| match split_location(warning) {
+            (Some(line), _, message) => message.strip_prefix(&tag).map(|message| (line, message.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The registers named in `code` warnings, as (line, "Rn").
+fn registers_warned(source: &str, code: Code) -> Vec<(usize, String)> {
+    warnings(source, code).into_iter().map(|(line, message)| (line, message[..2].to_string())).collect()
+}
+
+#[test]
+fn read_before_any_write_warns() {
+    let source = "\
+IMM R1 1
+NOOP
+ADD R1 R2
+HLT";
+    assert_eq!(registers_warned(source, Code::UninitializedRead), [(3, "R2".to_string())]);
+}
+
+#[test]
+fn write_on_one_branch_arm_warns() {
+    let source = "\
+IMM R1 1
+SUB R1 R0
+BIE skip
+NOOP
+IMM R2 5
+skip:
+OUT %0 R2
+HLT";
+    assert_eq!(registers_warned(source, Code::UninitializedRead), [(7, "R2".to_string())]);
+}
+
+#[test]
+fn writes_on_both_branch_arms_are_quiet() {
+    let source = "\
+IMM R1 1
+SUB R1 R0
+BIE other
+NOOP
+IMM R2 5
+JMP join
+NOOP
+other:
+IMM R2 6
+join:
+OUT %0 R2
+HLT";
+    assert_eq!(registers_warned(source, Code::UninitializedRead), []);
+}
+
+#[test]
+fn loop_back_edge_doesnt_count_as_written_on_entry() {
+    // R1 is only written inside the loop, so the first time round it's still unwritten; R2 is
+    // written before the loop and stays written along the back edge.
+    let source = "\
+IMM R2 0
+loop:
+ADD R2 R1
+IMM R1 1
+JMP loop";
+    assert_eq!(registers_warned(source, Code::UninitializedRead), [(3, "R1".to_string())]);
+}
+
+#[test]
+fn call_returns_with_registers_written() {
+    // The caller's read after the CALL is quiet, but the subroutine's own read of R3 isn't.
+    let source = "\
+CALL setup
+NOOP
+OUT %0 R1
+HLT
+setup:
+IMM R1 5
+OUT %1 R3
+RET";
+    assert_eq!(registers_warned(source, Code::UninitializedRead), [(7, "R3".to_string())]);
+}
+
+#[test]
+fn allow_comment_drops_the_warning() {
+    let source = "\
+IMM R1 1
+ADD R1 R2 ; @allow(W009)
+OUT %0 R3 ; @allow(uninitialized_read)
+HLT";
+    assert_eq!(registers_warned(source, Code::UninitializedRead), []);
+}
+
+#[test]
+fn registers_past_r7_are_skipped() {
+    // They read 0 and drop writes, as R0 does. Negative ones used to overflow the register bits.
+    let source = "\
+IMM R1 1
+IMM R9 3
+ADD R1 R9
+MOV R2 R-1
+OUT %0 R2
+HLT";
+    assert_eq!(registers_warned(source, Code::UninitializedRead), []);
+}
+
+#[test]
+fn overwrite_before_read_warns() {
+    let source = "\
+IMM R1 1
+IMM R1 2
+OUT %0 R1
+HLT";
+    assert_eq!(registers_warned(source, Code::DeadStore), [(1, "R1".to_string())]);
+}
+
+#[test]
+fn read_in_between_is_quiet() {
+    let source = "\
+IMM R1 1
+OUT %0 R1
+IMM R1 2
+OUT %1 R1
+HLT";
+    assert_eq!(registers_warned(source, Code::DeadStore), []);
+}
+
+#[test]
+fn writes_in_different_blocks_are_quiet() {
+    // The branch ends one block, and the label starts another: what happens to the first write
+    // depends on where control goes.
+    let source = "\
+IMM R1 1
+SUB R1 R0
+BIE done
+IMM R1 2
+again:
+IMM R1 3
+OUT %0 R1
+JMP again
+done:
+HLT";
+    assert_eq!(registers_warned(source, Code::DeadStore), []);
+}
+
+#[test]
+fn implicit_reads_keep_the_write() {
+    // OUT, STORE and PUSH read operand B or A, and PUSHA every register in its mask.
+    let source = "\
+IMM R1 1
+OUT %0 R1
+IMM R1 2
+STORE #0 R1
+IMM R1 3
+PUSH R1
+IMM R1 4
+IMM R2 5
+PUSHA 0b00000110
+IMM R1 6
+IMM R2 7
+OUT %1 R1
+OUT %2 R2
+HLT";
+    assert_eq!(registers_warned(source, Code::DeadStore), []);
+}
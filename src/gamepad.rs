@@ -0,0 +1,193 @@
+//! `--gamepad`: the first connected gamepad's D-pad and buttons as the bits of an input port, so
+//! games can be played with a controller. `gamepad` lines in `~/.electronrc` change the port and
+//! which button sets which bit:
+//!
+//! ```text
+//! gamepad port 50       # read the buttons on %50 instead of %41
+//! gamepad x 4           # X sets bit 4 too, like A
+//! gamepad select none   # Select sets nothing
+//! ```
+
+use crate::line_editor::home_file;
+
+pub const DEFAULT_PORT: u8 = 41;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    X,
+    Y,
+    L1,
+    R1,
+    L2,
+    R2,
+    Select,
+    Start,
+}
+
+impl Button {
+    pub const ALL: [Button; 14] = [
+        Button::Up,
+        Button::Down,
+        Button::Left,
+        Button::Right,
+        Button::A,
+        Button::B,
+        Button::X,
+        Button::Y,
+        Button::L1,
+        Button::R1,
+        Button::L2,
+        Button::R2,
+        Button::Select,
+        Button::Start,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Button::Up => "up",
+            Button::Down => "down",
+            Button::Left => "left",
+            Button::Right => "right",
+            Button::A => "a",
+            Button::B => "b",
+            Button::X => "x",
+            Button::Y => "y",
+            Button::L1 => "l1",
+            Button::R1 => "r1",
+            Button::L2 => "l2",
+            Button::R2 => "r2",
+            Button::Select => "select",
+            Button::Start => "start",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Button> {
+        Self::ALL.into_iter().find(|button| button.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Which port the buttons are read on, and the bit each one sets while it's held.
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    pub port: u8,
+    /// By `Button::ALL` order; None for a button that sets nothing.
+    pub bits: [Option<u8>; 14],
+}
+
+impl Default for Mapping {
+    /// The D-pad on bits 0-3 (up, down, left, right), then A, B, Select and Start.
+    fn default() -> Self {
+        let mut bits = [None; 14];
+        for (bit, button) in [Button::Up, Button::Down, Button::Left, Button::Right, Button::A, Button::B, Button::Select, Button::Start].into_iter().enumerate() {
+            bits[button as usize] = Some(bit as u8);
+        }
+        Self { port: DEFAULT_PORT, bits }
+    }
+}
+
+/// Whether an `~/.electronrc` line sets up the gamepad, rather than being a debugger command.
+pub fn is_config(line: &str) -> bool {
+    line.split_whitespace().next().is_some_and(|word| word.eq_ignore_ascii_case("gamepad"))
+}
+
+impl Mapping {
+    /// The default mapping with the `gamepad` lines of `~/.electronrc` applied.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = home_file(".electronrc") else { return Ok(Self::default()) };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).map_err(|error| format!("{}: {error}", path.display())),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Applies the `gamepad` lines of `text` to the default mapping; other lines are left for
+    /// the debugger.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut mapping = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split(['#', ';']).next().unwrap_or("").trim();
+            if !is_config(line) {
+                continue;
+            }
+            let error = |message: String| format!("Line {}: {message}", number + 1);
+            let words: Vec<&str> = line.split_whitespace().skip(1).collect();
+            let [key, value] = words[..] else {
+                return Err(error(format!("expected `gamepad <button> <bit>` or `gamepad port <port>`, got \"{line}\"")));
+            };
+            if key.eq_ignore_ascii_case("port") {
+                mapping.port = match value.trim_start_matches('%').parse::<u8>() {
+                    Ok(port) if port >= 8 => port,
+                    _ => return Err(error(format!("the gamepad port is a number from 8 to 255, outside the display ports (got \"{value}\")"))),
+                };
+                continue;
+            }
+            let Some(button) = Button::from_name(key) else {
+                let names: Vec<&str> = Button::ALL.iter().map(|button| button.name()).collect();
+                return Err(error(format!("unknown gamepad button \"{key}\" (use {})", names.join(", "))));
+            };
+            mapping.bits[button as usize] = match value.parse::<u8>() {
+                Ok(bit) if bit < 8 => Some(bit),
+                _ if value.eq_ignore_ascii_case("none") => None,
+                _ => return Err(error(format!("a gamepad button sets a bit from 0 to 7, or none (got \"{value}\")"))),
+            };
+        }
+        Ok(mapping)
+    }
+
+    /// The port value while the buttons `held` picks are down.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub fn value(&self, held: impl Fn(Button) -> bool) -> u8 {
+        Button::ALL
+            .into_iter()
+            .filter(|&button| held(button))
+            .filter_map(|button| self.bits[button as usize])
+            .fold(0, |value, bit| value | 1 << bit)
+    }
+}
+
+#[cfg(feature = "gui")]
+pub use input::held;
+
+/// Reading the buttons from raylib, which needs the window.
+#[cfg(feature = "gui")]
+mod input {
+    use super::Button;
+    use raylib::consts::{GamepadAxis, GamepadButton};
+    use raylib::RaylibHandle;
+
+    /// The gamepad raylib numbers 0: the first one connected.
+    const GAMEPAD: i32 = 0;
+    /// How far the left stick has to lean to count as the D-pad.
+    const STICK_THRESHOLD: f32 = 0.5;
+
+    /// Whether the button is down on the first gamepad. The left stick works as the D-pad.
+    pub fn held(rl: &RaylibHandle, button: Button) -> bool {
+        if !rl.is_gamepad_available(GAMEPAD) {
+            return false;
+        }
+        let stick = |axis, sign: f32| rl.get_gamepad_axis_movement(GAMEPAD, axis) * sign > STICK_THRESHOLD;
+        let (button, leaning) = match button {
+            Button::Up => (GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP, stick(GamepadAxis::GAMEPAD_AXIS_LEFT_Y, -1.0)),
+            Button::Down => (GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN, stick(GamepadAxis::GAMEPAD_AXIS_LEFT_Y, 1.0)),
+            Button::Left => (GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT, stick(GamepadAxis::GAMEPAD_AXIS_LEFT_X, -1.0)),
+            Button::Right => (GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT, stick(GamepadAxis::GAMEPAD_AXIS_LEFT_X, 1.0)),
+            Button::A => (GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN, false),
+            Button::B => (GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT, false),
+            Button::X => (GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT, false),
+            Button::Y => (GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP, false),
+            Button::L1 => (GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1, false),
+            Button::R1 => (GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1, false),
+            Button::L2 => (GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_2, false),
+            Button::R2 => (GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_2, false),
+            Button::Select => (GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT, false),
+            Button::Start => (GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT, false),
+        };
+        leaning || rl.is_gamepad_button_down(GAMEPAD, button)
+    }
+}
@@ -0,0 +1,285 @@
+//! A small readline replacement for the debugger console: cursor movement, word/line kills and
+//! persistent history. Falls back to plain line reads when stdin is not a terminal.
+
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+
+const HISTORY_LIMIT: usize = 1000;
+
+pub struct LineEditor {
+    history: Vec<String>,
+    history_path: Option<PathBuf>,
+}
+
+impl LineEditor {
+    /// Loads the history file if it exists; new lines are appended to it as they are entered.
+    pub fn new(history_path: Option<PathBuf>) -> Self {
+        let mut history: Vec<String> = history_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(|l| l.to_string()).collect())
+            .unwrap_or_default();
+        if history.len() > HISTORY_LIMIT {
+            history.drain(..history.len() - HISTORY_LIMIT);
+        }
+        Self { history, history_path }
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().map(|l| l.as_str()) == Some(line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if let Some(path) = &self.history_path {
+            if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+    }
+
+    /// Reads one line. Returns None at end of input (Ctrl+D on an empty line).
+    pub fn read_line(&mut self, prompt: &str) -> Option<String> {
+        print!("{prompt}");
+        let _ = std::io::stdout().flush();
+        match raw::RawMode::enable() {
+            Some(_raw) => self.edit(prompt),
+            None => {
+                let mut line = String::new();
+                match std::io::stdin().lock().read_line(&mut line) {
+                    Ok(0) | Err(_) => None,
+                    Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+                }
+            }
+        }
+    }
+
+    fn edit(&mut self, prompt: &str) -> Option<String> {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // Index into history while browsing with the arrow keys; `history.len()` is the new line.
+        let mut browsing = self.history.len();
+        let mut draft: Vec<char> = Vec::new();
+        let mut stdin = std::io::stdin().lock();
+
+        loop {
+            let key = read_key(&mut stdin)?;
+            match key {
+                Key::Enter => {
+                    println!();
+                    return Some(line.into_iter().collect());
+                }
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left if cursor > 0 => cursor -= 1,
+                Key::Right if cursor < line.len() => cursor += 1,
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::KillToStart => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::KillToEnd => line.truncate(cursor),
+                Key::KillWord => {
+                    let mut start = cursor;
+                    while start > 0 && line[start - 1] == ' ' {
+                        start -= 1;
+                    }
+                    while start > 0 && line[start - 1] != ' ' {
+                        start -= 1;
+                    }
+                    line.drain(start..cursor);
+                    cursor = start;
+                }
+                Key::Up if browsing > 0 => {
+                    if browsing == self.history.len() {
+                        draft = line.clone();
+                    }
+                    browsing -= 1;
+                    line = self.history[browsing].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down if browsing < self.history.len() => {
+                    browsing += 1;
+                    line = if browsing == self.history.len() {
+                        draft.clone()
+                    } else {
+                        self.history[browsing].chars().collect()
+                    };
+                    cursor = line.len();
+                }
+                Key::Cancel => {
+                    println!("^C");
+                    return Some(String::new());
+                }
+                Key::Eof if line.is_empty() => {
+                    println!();
+                    return None;
+                }
+                _ => {}
+            }
+            let text: String = line.iter().collect();
+            print!("\r\x1B[K{prompt}{text}");
+            if cursor < line.len() {
+                print!("\x1B[{}D", line.len() - cursor);
+            }
+            let _ = std::io::stdout().flush();
+        }
+    }
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    KillToStart,
+    KillToEnd,
+    KillWord,
+    Cancel,
+    Eof,
+    Unknown,
+}
+
+fn read_byte(stdin: &mut impl Read) -> Option<u8> {
+    let mut byte = [0];
+    match stdin.read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+fn read_key(stdin: &mut impl Read) -> Option<Key> {
+    let byte = read_byte(stdin)?;
+    Some(match byte {
+        b'\r' | b'\n' => Key::Enter,
+        0x7f | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x05 => Key::End,
+        0x02 => Key::Left,
+        0x06 => Key::Right,
+        0x10 => Key::Up,
+        0x0e => Key::Down,
+        0x15 => Key::KillToStart,
+        0x0b => Key::KillToEnd,
+        0x17 => Key::KillWord,
+        0x03 => Key::Cancel,
+        0x04 => Key::Eof,
+        0x1b => {
+            if read_byte(stdin)? != b'[' {
+                return Some(Key::Unknown);
+            }
+            match read_byte(stdin)? {
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                b'C' => Key::Right,
+                b'D' => Key::Left,
+                b'H' => Key::Home,
+                b'F' => Key::End,
+                digit @ b'0'..=b'9' => {
+                    // ESC [ n ~ sequences: 1/7 home, 4/8 end, 3 delete.
+                    let mut last = digit;
+                    let mut number = digit;
+                    while last != b'~' {
+                        last = read_byte(stdin)?;
+                        if last.is_ascii_digit() {
+                            number = last;
+                        }
+                    }
+                    match number {
+                        b'1' | b'7' => Key::Home,
+                        b'4' | b'8' => Key::End,
+                        b'3' => Key::Delete,
+                        _ => Key::Unknown,
+                    }
+                }
+                _ => Key::Unknown,
+            }
+        }
+        0x00..=0x1f => Key::Unknown,
+        0x80.. => {
+            // Multi-byte UTF-8: the lead byte says how many continuation bytes follow.
+            let extra = if byte >= 0xf0 { 3 } else if byte >= 0xe0 { 2 } else { 1 };
+            let mut bytes = vec![byte];
+            for _ in 0..extra {
+                bytes.push(read_byte(stdin)?);
+            }
+            match std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()) {
+                Some(c) => Key::Char(c),
+                None => Key::Unknown,
+            }
+        }
+        _ => Key::Char(byte as char),
+    })
+}
+
+#[cfg(unix)]
+mod raw {
+    /// Puts the terminal in non-canonical, no-echo mode for one line; restored on drop.
+    pub struct RawMode {
+        original: libc::termios,
+    }
+
+    impl RawMode {
+        pub fn enable() -> Option<Self> {
+            unsafe {
+                if libc::isatty(libc::STDIN_FILENO) == 0 {
+                    return None;
+                }
+                let mut termios: libc::termios = std::mem::zeroed();
+                if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                    return None;
+                }
+                let original = termios;
+                // ISIG off too, so Ctrl+C cancels the line instead of quitting.
+                termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+                termios.c_cc[libc::VMIN] = 1;
+                termios.c_cc[libc::VTIME] = 0;
+                if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) != 0 {
+                    return None;
+                }
+                Some(Self { original })
+            }
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod raw {
+    pub struct RawMode;
+
+    impl RawMode {
+        pub fn enable() -> Option<Self> {
+            None
+        }
+    }
+}
+
+/// `~/<name>`, if a home directory is known.
+pub fn home_file(name: &str) -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(name))
+}
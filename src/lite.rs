@@ -0,0 +1,124 @@
+//! `--display lite`: the LED grid alone, drawn as filled squares into a pixel buffer that minifb
+//! puts on screen. It needs neither raylib's C toolchain nor OpenGL, so builds with only the
+//! `lite` feature still get a window. There are no panels, themes, sound or status in the
+//! title; the transport keys work as in the raylib window. Builds without the feature have no
+//! `LiteWindow` values, and `open` always fails.
+
+use crate::transport::Transport;
+
+/// The window's starting size; it can be resized, and the grid is laid out again to fit.
+#[cfg_attr(not(feature = "lite"), allow(dead_code))]
+const SIZE: (usize, usize) = (480, 480);
+
+#[cfg(feature = "lite")]
+pub use imp::LiteWindow;
+
+#[cfg(feature = "lite")]
+mod imp {
+    use super::{Transport, SIZE};
+    use minifb::{Key, KeyRepeat, Window, WindowOptions};
+
+    /// The classic theme's colours, as 0RGB.
+    const BACKGROUND: u32 = 0x000000;
+    const ON: u32 = 0xFF2828;
+    const OFF: u32 = 0x3C0A0A;
+    /// The line between the halves under `--compare`.
+    const DIVIDER: u32 = 0xF0F0F0;
+
+    pub struct LiteWindow {
+        window: Window,
+        buffer: Vec<u32>,
+    }
+
+    impl LiteWindow {
+        pub fn open(title: &str) -> Result<Self, String> {
+            let options = WindowOptions { resize: true, ..WindowOptions::default() };
+            let mut window = Window::new(title, SIZE.0, SIZE.1, options).map_err(|e| format!("Could not open the lite window: {e}"))?;
+            // The clock runs on its own thread, so the window only needs to keep up with the eye.
+            window.set_target_fps(60);
+            Ok(Self { window, buffer: Vec::new() })
+        }
+
+        pub fn should_close(&self) -> bool {
+            !self.window.is_open() || self.window.is_key_down(Key::Escape)
+        }
+
+        /// The same keys as the raylib window's transport buttons.
+        pub fn transport(&self) -> Option<Transport> {
+            [
+                (Key::Space, Transport::Pause),
+                (Key::N, Transport::Step),
+                (Key::Backspace, Transport::Reset),
+                (Key::F5, Transport::Reload),
+            ]
+            .into_iter()
+            .find(|(key, _)| self.window.is_key_pressed(*key, KeyRepeat::No))
+            .map(|(_, transport)| transport)
+        }
+
+        /// Draws the ports, and `compare`'s beside them under `--compare`. Waits out the rest of
+        /// the frame, so it paces the loop as the raylib window does.
+        pub fn draw(&mut self, ports: &[u8; 8], compare: Option<&[u8; 8]>) {
+            let (width, height) = self.window.get_size();
+            let (width, height) = (width.max(1), height.max(1));
+            self.buffer.clear();
+            self.buffer.resize(width * height, BACKGROUND);
+            match compare {
+                Some(compare) => {
+                    let half = width / 2;
+                    self.draw_grid(ports, (0, 0, half, height), width);
+                    self.draw_grid(compare, (half, 0, width - half, height), width);
+                    fill(&mut self.buffer, width, (half.saturating_sub(1), 0, 2, height), DIVIDER);
+                }
+                None => self.draw_grid(ports, (0, 0, width, height), width),
+            }
+            // A failed update leaves the old frame up; `should_close` notices a window that's gone.
+            let _ = self.window.update_with_buffer(&self.buffer, width, height);
+        }
+
+        /// One port per row, bit 7 on the left, as large as fits in the area and centred there.
+        fn draw_grid(&mut self, ports: &[u8; 8], (x, y, width, height): (usize, usize, usize, usize), stride: usize) {
+            let cell = (width.min(height) / 9).max(1);
+            let gap = cell / 8;
+            let left = x + (width - cell * 8) / 2;
+            let top = y + (height - cell * 8) / 2;
+            for (port, value) in ports.iter().enumerate() {
+                for bit in 0..8 {
+                    let color = if value & (0x80 >> bit) != 0 { ON } else { OFF };
+                    let square = (left + bit * cell + gap, top + port * cell + gap, cell - gap * 2, cell - gap * 2);
+                    fill(&mut self.buffer, stride, square, color);
+                }
+            }
+        }
+    }
+
+    /// Fills the rectangle `(x, y, width, height)` of a buffer `stride` pixels wide.
+    fn fill(buffer: &mut [u32], stride: usize, (x, y, width, height): (usize, usize, usize, usize), color: u32) {
+        for row in buffer.chunks_mut(stride).skip(y).take(height) {
+            let end = (x + width).min(row.len());
+            row[x.min(end)..end].fill(color);
+        }
+    }
+}
+
+#[cfg(not(feature = "lite"))]
+pub enum LiteWindow {}
+
+#[cfg(not(feature = "lite"))]
+impl LiteWindow {
+    pub fn open(_title: &str) -> Result<Self, String> {
+        Err("This build has no lite window (it was built without the lite feature). Use --display window or terminal.".to_string())
+    }
+
+    pub fn should_close(&self) -> bool {
+        match *self {}
+    }
+
+    pub fn transport(&self) -> Option<Transport> {
+        match *self {}
+    }
+
+    pub fn draw(&mut self, _ports: &[u8; 8], _compare: Option<&[u8; 8]>) {
+        match *self {}
+    }
+}
@@ -0,0 +1,499 @@
+//! `electron lsp`: a language server for editors, over stdin and stdout. It publishes the
+//! assembler's errors and warnings as the file is edited, jumps from a label to where it's
+//! defined, shows the instruction reference when hovering over an operation, and completes
+//! operations, labels and directives.
+//!
+//! Documents are synced whole on every change; programs are a few hundred lines at most.
+//! Positions count characters, which is what the protocol's UTF-16 units come to for the ASCII
+//! programs are written in.
+
+use crate::arch::{Arch, Diagnostic};
+use crate::electron_2::isa::{OperationInfo, OPERATIONS};
+use crate::electron_2::parser::{Parser, DIRECTIVES};
+use crate::electron_2::OperationArgs;
+use crate::server::json_string;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// The largest message taken, well over any program.
+const MAX_MESSAGE: usize = 1 << 24;
+
+// LSP's DiagnosticSeverity and CompletionItemKind values.
+const ERROR: u8 = 1;
+const WARNING: u8 = 2;
+const KEYWORD: u8 = 14;
+const CONSTANT: u8 = 21;
+
+/// Serves one editor until it sends `exit`. Returns the exit code the protocol asks for: 0 if
+/// `shutdown` came first, 1 otherwise.
+pub fn run(arch: Arch) -> i32 {
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::stdout().lock();
+    let mut server = Lsp { arch, documents: HashMap::new(), shut_down: false };
+    while let Some(body) = read_message(&mut input) {
+        let message = match Json::parse(&body) {
+            Ok(message) => message,
+            Err(error) => {
+                tracing::warn!("lsp: unreadable message: {error}");
+                continue;
+            }
+        };
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+        tracing::debug!("lsp: {method}");
+        if method == "exit" {
+            return if server.shut_down { 0 } else { 1 };
+        }
+        let replies = match message.get("id") {
+            Some(id) => {
+                let reply = match server.request(method, params) {
+                    Some(result) => format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{result}}}", id.to_json()),
+                    None => format!(
+                        "{{\"jsonrpc\":\"2.0\",\"id\":{},\"error\":{{\"code\":-32601,\"message\":{}}}}}",
+                        id.to_json(),
+                        json_string(&format!("Unknown method {method}"))
+                    ),
+                };
+                vec![reply]
+            }
+            None => server.notification(method, params),
+        };
+        for reply in replies {
+            if write!(output, "Content-Length: {}\r\n\r\n{reply}", reply.len()).and_then(|()| output.flush()).is_err() {
+                return 1;
+            }
+        }
+    }
+    1
+}
+
+struct Lsp {
+    arch: Arch,
+    /// The text of every open document, by URI.
+    documents: HashMap<String, String>,
+    shut_down: bool,
+}
+
+impl Lsp {
+    /// The result of a request as JSON, or None if the method isn't one the server has.
+    fn request(&mut self, method: &str, params: &Json) -> Option<String> {
+        let position = || {
+            let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+            let position = params.get("position")?;
+            let line = position.get("line")?.as_number()? as usize;
+            let character = position.get("character")?.as_number()? as usize;
+            Some((uri, self.documents.get(uri)?.as_str(), line, character))
+        };
+        let result = match method {
+            "initialize" => format!(
+                "{{\"capabilities\":{{\"textDocumentSync\":1,\"definitionProvider\":true,\"hoverProvider\":true,\
+                 \"completionProvider\":{{\"triggerCharacters\":[\".\"]}}}},\"serverInfo\":{{\"name\":\"electron\",\"version\":{}}}}}",
+                json_string(env!("CARGO_PKG_VERSION"))
+            ),
+            "shutdown" => {
+                self.shut_down = true;
+                "null".to_string()
+            }
+            "textDocument/definition" => position()
+                .and_then(|(uri, text, line, character)| {
+                    let (word, _) = word_at(text, line, character)?;
+                    let (line, start, end) = definition(text, &word)?;
+                    Some(format!("{{\"uri\":{},\"range\":{}}}", json_string(uri), range(line, start, end)))
+                })
+                .unwrap_or_else(|| "null".to_string()),
+            "textDocument/hover" => position()
+                .and_then(|(_, text, line, character)| {
+                    let (word, _) = word_at(text, line, character)?;
+                    let markdown = hover(text, &word, self.arch)?;
+                    Some(format!("{{\"contents\":{{\"kind\":\"markdown\",\"value\":{}}}}}", json_string(&markdown)))
+                })
+                .unwrap_or_else(|| "null".to_string()),
+            "textDocument/completion" => match position() {
+                Some((_, text, line, character)) => completions(text, line, character),
+                None => "[]".to_string(),
+            },
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// Handles a notification, and returns the notifications to send back.
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<String> {
+        let Some(uri) = params.get("textDocument").and_then(|document| document.get("uri")).and_then(Json::as_str) else {
+            return Vec::new();
+        };
+        let uri = uri.to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").and_then(|document| document.get("text")).and_then(Json::as_str);
+                self.documents.insert(uri.clone(), text.unwrap_or("").to_string());
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change is the whole text.
+                let changes = params.get("contentChanges").and_then(Json::as_array).unwrap_or(&[]);
+                if let Some(text) = changes.last().and_then(|change| change.get("text")).and_then(Json::as_str) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            _ => return Vec::new(),
+        }
+        vec![self.publish(&uri)]
+    }
+
+    /// The `publishDiagnostics` notification for a document, empty once it's closed.
+    fn publish(&self, uri: &str) -> String {
+        let mut diagnostics = Vec::new();
+        if let Some(text) = self.documents.get(uri) {
+            let assembled = self.arch.assemble(text);
+            for (severity, messages) in [(ERROR, &assembled.errors), (WARNING, &assembled.warnings)] {
+                diagnostics.extend(messages.iter().map(|diagnostic| diagnostic_json(text, diagnostic, severity)));
+            }
+        }
+        format!(
+            "{{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{{\"uri\":{},\"diagnostics\":[{}]}}}}",
+            json_string(uri),
+            diagnostics.join(",")
+        )
+    }
+}
+
+fn diagnostic_json(text: &str, diagnostic: &Diagnostic, severity: u8) -> String {
+    let line = diagnostic.line.map_or(0, |line| line.saturating_sub(1));
+    let source_line = text.lines().nth(line).unwrap_or("");
+    // The token at the column, as `check` underlines it, or the whole line.
+    let (start, end) = match diagnostic.column {
+        Some(column) => {
+            let start = column - 1;
+            let token = source_line.chars().skip(start).take_while(|c| !c.is_whitespace() && *c != ';').count().max(1);
+            (start, start + token)
+        }
+        None => (0, source_line.chars().count()),
+    };
+    let code = diagnostic.code.map_or(String::new(), |code| format!(",\"code\":{}", json_string(code.id())));
+    format!(
+        "{{\"range\":{},\"severity\":{severity},\"source\":\"electron\"{code},\"message\":{}}}",
+        range(line, start, end),
+        json_string(&diagnostic.message)
+    )
+}
+
+fn range(line: usize, start: usize, end: usize) -> String {
+    format!("{{\"start\":{{\"line\":{line},\"character\":{start}}},\"end\":{{\"line\":{line},\"character\":{end}}}}}")
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The word under the cursor and the character it starts at, outside comments.
+fn word_at(text: &str, line: usize, character: usize) -> Option<(String, usize)> {
+    let chars: Vec<char> = text.lines().nth(line)?.chars().collect();
+    if chars.iter().take(character).any(|c| *c == ';') {
+        return None;
+    }
+    let start = (0..character.min(chars.len())).rev().take_while(|i| is_word(chars[*i])).last().unwrap_or(character);
+    let end = (character..chars.len()).take_while(|i| is_word(chars[*i])).last().map_or(character, |i| i + 1);
+    (start < end).then(|| (chars[start..end].iter().collect(), start))
+}
+
+/// The line and characters of the label or `.ascii` name `word` where it's defined.
+fn definition(text: &str, word: &str) -> Option<(usize, usize, usize)> {
+    let case_sensitive = Parser::case_sensitive(text);
+    let same = |name: &str| if case_sensitive { name == word } else { name.eq_ignore_ascii_case(word) };
+    for (number, line) in text.lines().enumerate() {
+        let code = line.split(';').next().unwrap_or("");
+        let trimmed = code.trim_start();
+        let indent = code.chars().count() - trimmed.chars().count();
+        if let Some(rest) = trimmed.strip_prefix(".ascii") {
+            let name = rest.split_whitespace().next().unwrap_or("");
+            let start = indent + ".ascii".len() + rest.find(name).unwrap_or(0);
+            let len_name = format!("{name}_LEN");
+            if !name.is_empty() && (same(name) || same(&len_name)) {
+                return Some((number, start, start + name.chars().count()));
+            }
+        } else if let Some((label, _)) = trimmed.split_once(':') {
+            if !label.contains(char::is_whitespace) && same(label) {
+                return Some((number, indent, indent + label.chars().count()));
+            }
+        }
+    }
+    None
+}
+
+/// Markdown for an operation (from the instruction table) or a label (its address).
+fn hover(text: &str, word: &str, arch: Arch) -> Option<String> {
+    if let Ok((operation, args)) = Parser::parse_operation(&word.to_uppercase()) {
+        let info = operation.info();
+        let (a, b) = operation.operands(args);
+        let operands: Vec<&str> = [(a, info.operands[0]), (b, info.operands[1])]
+            .into_iter()
+            .filter_map(|(needed, operand)| operand.filter(|_| needed))
+            .collect();
+        let mut markdown = format!("```\n{} {}\n```\n\n{}", word.to_uppercase(), operands.join(" "), describe(info, args));
+        if arch == Arch::Electron1 && !info.v1 {
+            markdown.push_str("\n\nNot on Electron V1.");
+        }
+        return Some(markdown);
+    }
+    let case_sensitive = Parser::case_sensitive(text);
+    let name = if case_sensitive { word.to_string() } else { word.to_uppercase() };
+    let address = Parser::scan_labels(text).get(&name).copied()?;
+    Some(format!("Label `{name}`, instruction address {address}"))
+}
+
+fn describe(info: &OperationInfo, args: OperationArgs) -> String {
+    let mut text = info.description.to_string();
+    match args {
+        OperationArgs::U => text.push_str(" Under `U`, the accumulator takes the place of Rx."),
+        OperationArgs::X => text.push_str(" Under `X`, only the accumulator and flags are updated."),
+        _ => {}
+    }
+    if info.prefixes {
+        text.push_str("\n\nTakes the `S`, `U` and `X` prefixes.");
+    }
+    if info.sets_flags {
+        text.push_str(" Sets the accumulator and the EQUALS, GREATER, LESS and OVERFLOW flags.");
+    }
+    text.push_str(&format!("\n\nExample: `{}`", info.example));
+    text
+}
+
+/// Directives at the start of a line after `.`, operations where an operation goes, and labels
+/// and `.ascii` names as operands.
+fn completions(text: &str, line: usize, character: usize) -> String {
+    let before: String = text.lines().nth(line).unwrap_or("").chars().take(character).collect();
+    let before = before.split(';').next().unwrap_or("");
+    let item = |label: &str, kind: u8, detail: &str, documentation: &str| {
+        format!(
+            "{{\"label\":{},\"kind\":{kind},\"detail\":{},\"documentation\":{}}}",
+            json_string(label),
+            json_string(detail),
+            json_string(documentation)
+        )
+    };
+    if before.trim_start().starts_with('.') {
+        let items: Vec<String> = DIRECTIVES.iter().map(|(name, help)| item(name, KEYWORD, "directive", help)).collect();
+        return format!("[{}]", items.join(","));
+    }
+    let body = before.split_once(':').map_or(before, |(_, body)| body);
+    // Still on the first word: the operation.
+    let items: Vec<String> = if body.split_whitespace().count() + usize::from(body.ends_with(char::is_whitespace)) <= 1 {
+        OPERATIONS
+            .iter()
+            .map(|info| {
+                let operands: Vec<&str> = info.operands.iter().flatten().copied().collect();
+                item(info.mnemonic, KEYWORD, &operands.join(" "), info.description)
+            })
+            .collect()
+    } else {
+        let mut labels: Vec<(String, i32)> = Parser::scan_labels(text).into_iter().collect();
+        labels.sort();
+        let mut items: Vec<String> =
+            labels.iter().map(|(name, address)| item(name, CONSTANT, &format!("label, address {address}"), "")).collect();
+        for line in text.lines() {
+            if let Some(name) = line.trim_start().strip_prefix(".ascii").and_then(|rest| rest.split_whitespace().next()) {
+                items.push(item(name, CONSTANT, "RAM address of the .ascii text", ""));
+                items.push(item(&format!("{name}_LEN"), CONSTANT, "length of the .ascii text", ""));
+            }
+        }
+        items
+    };
+    format!("[{}]", items.join(","))
+}
+
+/// The body of the next message, or None once the editor has gone.
+fn read_message(input: &mut impl BufRead) -> Option<String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.filter(|length| *length <= MAX_MESSAGE)?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).ok()?;
+    String::from_utf8(body).ok()
+}
+
+/// Just enough JSON for the messages an editor sends.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let value = Self::value(&mut chars)?;
+        skip_space(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected '{c}' after the value")),
+        }
+    }
+
+    fn value(chars: &mut Chars) -> Result<Json, String> {
+        skip_space(chars);
+        match chars.peek().copied() {
+            Some('{') => {
+                chars.next();
+                let mut fields = Vec::new();
+                skip_space(chars);
+                if chars.next_if_eq(&'}').is_some() {
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    skip_space(chars);
+                    let Json::String(key) = Self::value(chars)? else { return Err("object keys must be strings".to_string()) };
+                    skip_space(chars);
+                    chars.next_if_eq(&':').ok_or("expected ':'")?;
+                    fields.push((key, Self::value(chars)?));
+                    skip_space(chars);
+                    match chars.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Json::Object(fields)),
+                        _ => return Err("expected ',' or '}'".to_string()),
+                    }
+                }
+            }
+            Some('[') => {
+                chars.next();
+                let mut items = Vec::new();
+                skip_space(chars);
+                if chars.next_if_eq(&']').is_some() {
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(Self::value(chars)?);
+                    skip_space(chars);
+                    match chars.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Json::Array(items)),
+                        _ => return Err("expected ',' or ']'".to_string()),
+                    }
+                }
+            }
+            Some('"') => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next().ok_or("unterminated string")? {
+                        '"' => return Ok(Json::String(text)),
+                        '\\' => match chars.next().ok_or("unterminated string")? {
+                            'n' => text.push('\n'),
+                            't' => text.push('\t'),
+                            'r' => text.push('\r'),
+                            'b' => text.push('\u{8}'),
+                            'f' => text.push('\u{c}'),
+                            'u' => {
+                                let mut unit = hex4(chars)?;
+                                // A surrogate pair is two escapes.
+                                if (0xD800..0xDC00).contains(&unit) && chars.next_if_eq(&'\\').is_some() && chars.next_if_eq(&'u').is_some() {
+                                    let low = hex4(chars)?;
+                                    unit = 0x10000 + ((unit - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                                }
+                                text.push(char::from_u32(unit).unwrap_or('\u{FFFD}'));
+                            }
+                            c => text.push(c),
+                        },
+                        c => text.push(c),
+                    }
+                }
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                    number.push(c);
+                }
+                number.parse().map(Json::Number).map_err(|_| format!("bad number {number}"))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(char::is_ascii_alphabetic) {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => Err(format!("unexpected {word}")),
+                }
+            }
+            Some(c) => Err(format!("unexpected '{c}'")),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Written back out, for echoing request ids.
+    fn to_json(&self) -> String {
+        match self {
+            Json::Null => "null".to_string(),
+            Json::Bool(value) => value.to_string(),
+            Json::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => format!("{}", *number as i64),
+            Json::Number(number) => number.to_string(),
+            Json::String(text) => json_string(text),
+            Json::Array(items) => format!("[{}]", items.iter().map(Json::to_json).collect::<Vec<_>>().join(",")),
+            Json::Object(fields) => {
+                let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{}:{}", json_string(key), value.to_json())).collect();
+                format!("{{{}}}", fields.join(","))
+            }
+        }
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_space(chars: &mut Chars) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn hex4(chars: &mut Chars) -> Result<u32, String> {
+    let digits: String = (0..4).filter_map(|_| chars.next()).collect();
+    u32::from_str_radix(&digits, 16).map_err(|_| format!("bad escape \\u{digits}"))
+}
@@ -1,367 +1,1116 @@
+use arch::Arch;
+use cli::{Command, Options};
+use compare::Comparison;
+use dashboard::Layout;
 use emulator::*;
+use lite::LiteWindow;
 use parser::*;
-use raylib::prelude::*;
+use multicore::LinkedCore;
+use profiler::Profiler;
+use runner::{MachineV1, MachineV2, Runner};
+use server::Server;
+use state_dump::StateDump;
+use stdin_lines::StdinLines;
+use timeline::PortHistory;
+use timestep::Timestep;
+use transport::Transport;
+use std::io::IsTerminal;
+use tracing_subscriber::EnvFilter;
+use tui::{Tui, TuiEvent};
+use window::{Display, Edit, Key, Window, WindowOptions};
 
+mod arch;
+mod batch;
+mod bench;
+#[cfg(feature = "gui")]
+mod buzzer;
+mod cli;
+mod compare;
+mod dashboard;
+mod debugger;
+mod devices;
+#[cfg(test)]
+mod differential;
+#[cfg(feature = "gui")]
+mod editor;
 mod emulator;
+#[cfg(test)]
+mod flow_warnings;
+mod gamepad;
+mod line_editor;
+mod lite;
+mod lsp;
+mod multicore;
+#[cfg(feature = "gui")]
+mod panels;
 mod parser;
+mod picker;
+#[cfg(feature = "gui")]
+mod problems;
+mod profiler;
+#[cfg(feature = "gui")]
+mod quick_open;
+mod recent;
+#[cfg(test)]
+mod regressions;
+mod repl;
+mod replay;
+mod runner;
+mod server;
+mod signals;
+#[cfg(feature = "gui")]
+mod sliders;
+mod state_dump;
+mod stdin_lines;
+mod syntax;
+mod syscalls;
+#[cfg(feature = "gui")]
+mod theme;
+mod timeline;
+mod timestep;
+mod tools;
+mod transport;
+mod tui;
+mod window;
 
+// The V2 core is also built standalone for the web emulator, so parts of its API go unused here.
 #[path = "electron-2/lib.rs"]
+#[allow(dead_code)]
 mod electron_2;
+use electron_2::parser::split_location;
 use electron_2::Emulator as EmulatorV2;
+use electron_2::Rom;
 
+#[cfg(feature = "gui")]
 const WINDOW_SIZE: (i32, i32) = (720, 720);
+/// How often the dashboard is polled and drawn when there is no window to pace it.
+const FRAME: std::time::Duration = std::time::Duration::from_millis(16);
 
-fn format_data(mut data: String, len: usize) -> String {
-    for _ in 0..len - data.len() {
-        data.push(' ')
-    }
-    data
+// --- V2 Helpers ---
+
+fn format_stats_v2(emulator: &EmulatorV2) -> String {
+    let stats = &emulator.stats;
+    let stalls = if emulator.interlock || stats.stalls > 0 { format!("  Stalls: {}", stats.stalls) } else { String::new() };
+    let dma = if stats.dma > 0 { format!("  DMA: {}", stats.dma) } else { String::new() };
+    format!(
+        "Cycles: {}  Retired: {}  Branches: {}  Flushed: {}{stalls}{dma}  IPC: {:.2}",
+        stats.cycles, stats.retired, stats.branches, stats.flushed, stats.ipc()
+    )
 }
 
-// --- V1 Helpers ---
-
-fn print_port(emulator: &Emulator, port: u8) {
-    let mut port_data = format!("{:b}", emulator.ports.out[port as usize]);
-    for _ in 0..8 - port_data.len() {
-        port_data.insert(0, '0');
-    }
-    print!(
-        "     Port {}: ({})  ",
-        port,
-        format_data(emulator.ports.out[port as usize].to_string(), 3),
-    );
-    for char in port_data.chars() {
-        if char == '0' {
-            print!("░░")
-        } else {
-            print!("▓▓");
+/// The terminal dashboard, unless -nt turned it off or stdout isn't a terminal.
+fn start_tui(terminal_output: bool) -> Option<Tui> {
+    if !terminal_output {
+        return None;
+    }
+    match Tui::start() {
+        Ok(tui) => Some(tui),
+        Err(error) => {
+            println!("Warning: No terminal dashboard: {error}");
+            None
         }
     }
-    println!();
 }
 
-fn draw_terminal_screen(emulator: &Emulator) {
-    print!("▓▓▓▒▒▒░░░       Pipelines         ░░░▒▒▒▓▓▓    ");
-    println!("▓▓▓▒▒▒░░░          Ports        ░░░▒▒▒▓▓▓");
-    println!("___________________________________________");
-    print!("| FETCH   | DECODE  | EXECUTE | WRITEBACK |");
-    print_port(emulator, 0);
-    print!(
-        "{}{}{}{}  |",
-        &emulator.fetch_register.operation.get_name(),
-        emulator.decode_register.operation.get_name(),
-        emulator.execute_register.operation.get_name(),
-        emulator.write_back_register.operation.get_name()
-    );
-    print_port(emulator, 1);
-    print!("▓▓▓▒▒▒░░░           ALU          ░░░▒▒▒▓▓▓ ");
-    print_port(emulator, 2);
-    print!("___________________________________________");
-    print_port(emulator, 3);
-    print!("| Accumalator |           Flags           |");
-    print_port(emulator, 4);
-    print!(
-        "|      {}    ",
-        format_data(emulator.alu.accumalator.to_string(), 3)
-    );
-    print!(
-        "| Equals: {}             |",
-        format_data(emulator.alu.flags.equals.to_string(), 5)
-    );
-    print_port(emulator, 5);
-    print!(
-        "|             | Greater: {}            |",
-        format_data(emulator.alu.flags.greater_than.to_string(), 5)
-    );
-    print_port(emulator, 6);
-    print!(
-        "|             | Less: {}               |",
-        format_data(emulator.alu.flags.less_than.to_string(), 5)
-    );
-    print_port(emulator, 7);
-    println!(
-        "|             | Overflow: {}           |",
-        format_data(emulator.alu.flags.less_than.to_string(), 5) // Note: V1 reused less_than for overflow print?? Fixed in V2
-    );
-    println!();
-    println!("__________________________________________");
-    println!();
-    println!("▓▓▓▒▒▒░░░         Memory         ░░░▒▒▒▓▓▓");
-    println!("__________________________________________");
-    println!("| Registers |");
-    for i in 0..8 { 
-        println!(
-            "|   {}: {}  |",
-            i,
-            format_data(emulator.registers.read(i).to_string(), 3)
-        );
+/// Shows a message on the dashboard while it's up, since printing would scroll it away.
+fn notify(tui: &mut Option<Tui>, message: String) {
+    match tui {
+        Some(tui) => tui.notify(message),
+        None => println!("{message}"),
     }
 }
 
-fn draw_ports(emulator: &Emulator, d: &mut RaylibDrawHandle, on_texture: &Texture2D, off_texture: &Texture2D) {
-    for (port, _) in emulator.ports.out.iter().enumerate() {
-        let mut port_data = format!("{:b}", emulator.ports.out[port]);
-        for _ in 0..8 - port_data.len() {
-            port_data.insert(0, '0');
-        }
-        for (i, char) in port_data.char_indices() {
-            if char == '1' {
-                d.draw_texture_pro(
-                    on_texture,
-                    Rectangle::new(0.0, 0.0, on_texture.width as f32, on_texture.height as f32),
-                    Rectangle::new(
-                    (i as i32 * WINDOW_SIZE.0 / 8) as f32,
-                    (port as i32 * WINDOW_SIZE.1 / 8) as f32,
-                    (WINDOW_SIZE.0 / 8) as f32,
-                    (WINDOW_SIZE.1 / 8) as f32,
-                    ),
-                    Vector2::zero(),
-                    0.0,
-                    Color::WHITE,
-                )
-            } else {
-                d.draw_texture_pro(
-                    off_texture,
-                    Rectangle::new(0.0, 0.0, off_texture.width as f32, off_texture.height as f32),
-                    Rectangle::new(
-                    (i as i32 * WINDOW_SIZE.0 / 8) as f32,
-                    (port as i32 * WINDOW_SIZE.1 / 8) as f32,
-                    (WINDOW_SIZE.0 / 8) as f32,
-                    (WINDOW_SIZE.1 / 8) as f32,
-                    ),
-                    Vector2::zero(),
-                    0.0,
-                    Color::WHITE,
-                )
-            }
-        }
+/// `I` in the window or on the dashboard.
+pub fn toggle_interlock(emulator: &mut EmulatorV2) -> String {
+    emulator.interlock = !emulator.interlock;
+    if emulator.interlock {
+        "Interlock on: RAW hazards stall the pipeline.".to_string()
+    } else {
+        "Interlock off: RAW hazards read the old value.".to_string()
     }
 }
 
-// --- V2 Helpers ---
+fn attach_devices(emulator: &mut EmulatorV2, uart: bool, disk_path: Option<&str>, gpio_dir: Option<&str>, seed: Option<u64>) -> Result<(), String> {
+    emulator.devices.push(Box::new(devices::Rng::new(seed)));
+    emulator.devices.push(Box::new(devices::Timer::new()));
+    emulator.devices.push(Box::new(devices::HostClock));
+    emulator.syscalls = Some(Box::new(syscalls::HostServices::new()));
+    if uart {
+        emulator.devices.push(Box::new(devices::Uart::new()));
+    }
+    if let Some(path) = disk_path {
+        emulator.devices.push(Box::new(devices::Disk::open(path)?));
+    }
+    if let Some(dir) = gpio_dir {
+        emulator.devices.push(Box::new(devices::Gpio::open(dir)?));
+    }
+    Ok(())
+}
 
-fn print_port_v2(emulator: &EmulatorV2, port: u8) {
-    let mut port_data = format!("{:b}", emulator.ports_out[port as usize]);
-    for _ in 0..8 - port_data.len() {
-        port_data.insert(0, '0');
-    }
-    print!(
-        "     Port {}: ({})  ",
-        port,
-        format_data(emulator.ports_out[port as usize].to_string(), 3),
-    );
-    for char in port_data.chars() {
-        if char == '0' {
-            print!("░░")
-        } else {
-            print!("▓▓");
+/// Loads the `--link` program as a second core wired to `emulator`, with the same built-in
+/// devices. Its random number port gets the next seed so the cores don't draw the same numbers.
+fn connect_linked_core(emulator: &mut EmulatorV2, link_file: Option<&str>, deterministic: bool, seed: Option<u64>) -> Result<Option<LinkedCore>, String> {
+    let Some(path) = link_file else { return Ok(None) };
+    let code = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut core = LinkedCore::connect(emulator, code)?;
+    core.emulator.deterministic = deterministic;
+    attach_devices(&mut core.emulator, false, None, None, seed.map(|seed| seed.wrapping_add(1)))?;
+    Ok(Some(core))
+}
+
+/// Writes the `--record` file. Runs that end in a fault are saved too, since those are the
+/// ones worth replaying; stderr keeps the message out of the UART's stdout.
+fn save_recording(emulator: &EmulatorV2, record_file: Option<&str>) {
+    if let Some(path) = record_file {
+        if let Err(error) = replay::save(emulator, path) {
+            eprintln!("Error: {error}");
         }
     }
-    println!();
 }
 
-fn draw_terminal_screen_v2(emulator: &EmulatorV2) {
-    print!("▓▓▓▒▒▒░░░     Electron 2 Pipeline     ░░░▒▒▒▓▓▓    ");
-    println!("▓▓▓▒▒▒░░░          Ports        ░░░▒▒▒▓▓▓");
-    println!("___________________________________________");
-    print!("| FETCH   | DECODE  | EXECUTE | WRITEBACK |");
-    print_port_v2(emulator, 0);
-    
-    // Formatting pipeline op names
-    let f_name = format_data(emulator.fetch_reg.operation.get_name(), 10);
-    let d_name = format_data(emulator.decode_reg.operation.get_name(), 10);
-    let e_name = format_data(emulator.execute_reg.operation.get_name(), 10);
-    let w_name = format_data(emulator.writeback_reg.operation.get_name(), 10);
-
-    print!(
-        "|{}|{}|{}|{}|",
-        f_name.get(0..9).unwrap_or(&f_name),
-        d_name.get(0..9).unwrap_or(&d_name),
-        e_name.get(0..9).unwrap_or(&e_name),
-        w_name.get(0..11).unwrap_or(&w_name)
-    );
-    
-    print_port_v2(emulator, 1);
-    print!("▓▓▓▒▒▒░░░           ALU          ░░░▒▒▒▓▓▓ ");
-    print_port_v2(emulator, 2);
-    print!("___________________________________________");
-    print_port_v2(emulator, 3);
-    print!("| Accumulator |           Flags           |");
-    print_port_v2(emulator, 4);
-    print!(
-        "|      {}    ",
-        format_data(emulator.alu.accumulator.to_string(), 3)
-    );
-    print!(
-        "| Equals: {}             |",
-        format_data(emulator.alu.flags.equals.to_string(), 5)
-    );
-    print_port_v2(emulator, 5);
-    print!(
-        "|             | Greater: {}            |",
-        format_data(emulator.alu.flags.greater.to_string(), 5)
-    );
-    print_port_v2(emulator, 6);
-    print!(
-        "|             | Less: {}               |",
-        format_data(emulator.alu.flags.less.to_string(), 5)
-    );
-    print_port_v2(emulator, 7);
-    println!(
-        "|             | Overflow: {}           |",
-        format_data(emulator.alu.flags.overflow.to_string(), 5)
-    );
-    println!();
-    println!("__________________________________________");
-    println!();
-    println!("▓▓▓▒▒▒░░░         Memory         ░░░▒▒▒▓▓▓");
-    println!("__________________________________________");
-    println!("| Registers |      RAM      |     Stack    |");
-    for i in 0..8 {
-        // Show Registers 0-7, RAM 0-7 and 8-15, Stack Pointer
-        let reg_val = format_data(emulator.registers.read(i as i32).to_string(), 3);
-        let ram_val_1 = format_data(emulator.ram[i].to_string(), 3);
-        let ram_val_2 = format_data(emulator.ram[i+8].to_string(), 3);
-        
-        let stack_marker = if emulator.sp == i as i32 { "< SP" } else if emulator.sp == (i+8) as i32 { "< SP" } else { "    " };
-        
-        println!(
-            "| R{}: {}  | #{:02}: {} #{:02}: {} | {}",
-            i, reg_val, i, ram_val_1, i+8, ram_val_2, stack_marker
-        );
+/// Opens the `--dump-state` and `--log-ports` files.
+fn create_dumps(dump_state: Option<&str>, log_ports: Option<&str>) -> Result<Vec<StateDump>, String> {
+    let mut dumps = Vec::new();
+    if let Some(path) = dump_state {
+        dumps.push(StateDump::create(path)?);
+    }
+    if let Some(path) = log_ports {
+        dumps.push(StateDump::create_port_log(path)?);
     }
+    Ok(dumps)
 }
 
-fn draw_ports_v2(emulator: &EmulatorV2, d: &mut RaylibDrawHandle, on_texture: &Texture2D, off_texture: &Texture2D) {
-    for (port, _) in emulator.ports_out.iter().enumerate() {
-        let mut port_data = format!("{:b}", emulator.ports_out[port]);
-        for _ in 0..8 - port_data.len() {
-            port_data.insert(0, '0');
-        }
-        for (i, char) in port_data.char_indices() {
-            if char == '1' {
-                d.draw_texture_pro(
-                    on_texture,
-                    Rectangle::new(0.0, 0.0, on_texture.width as f32, on_texture.height as f32),
-                    Rectangle::new(
-                    (i as i32 * WINDOW_SIZE.0 / 8) as f32,
-                    (port as i32 * WINDOW_SIZE.1 / 8) as f32,
-                    (WINDOW_SIZE.0 / 8) as f32,
-                    (WINDOW_SIZE.1 / 8) as f32,
-                    ),
-                    Vector2::zero(),
-                    0.0,
-                    Color::WHITE,
-                )
-            } else {
-                d.draw_texture_pro(
-                    off_texture,
-                    Rectangle::new(0.0, 0.0, off_texture.width as f32, off_texture.height as f32),
-                    Rectangle::new(
-                    (i as i32 * WINDOW_SIZE.0 / 8) as f32,
-                    (port as i32 * WINDOW_SIZE.1 / 8) as f32,
-                    (WINDOW_SIZE.0 / 8) as f32,
-                    (WINDOW_SIZE.1 / 8) as f32,
-                    ),
-                    Vector2::zero(),
-                    0.0,
-                    Color::WHITE,
-                )
+/// Completes the `--dump-state` and `--log-ports` files; like `save_recording`, errors go to stderr.
+fn finish_dumps(dumps: Vec<StateDump>) {
+    for error in dumps.into_iter().filter_map(|dump| dump.finish().err()) {
+        eprintln!("Error: {error}");
+    }
+}
+
+/// Assembles a V2 program to run it, or prints its errors and exits: a program with errors is
+/// only partly assembled, and running it anyway does confusing things.
+fn load_v2(file_name: &str, code: &str, rom: Option<Rom>, strict: bool) -> EmulatorV2 {
+    let (emulator, errors) = try_load_v2(file_name, code, rom, strict);
+    if !errors.is_empty() {
+        println!("Failed to assemble {file_name}. Fix the errors above and run it again.");
+        std::process::exit(1);
+    }
+    emulator
+}
+
+/// Assembles a V2 program, printing its errors. With any, they're returned and the emulator is
+/// left with nothing loaded rather than part of the program, for the window to list them until
+/// F5 loads the file again.
+fn try_load_v2(file_name: &str, code: &str, rom: Option<Rom>, strict: bool) -> (EmulatorV2, Vec<String>) {
+    let mut emulator = EmulatorV2::new(String::new());
+    emulator.source_file = file_name.to_string();
+    emulator.rom = rom;
+    emulator.strict = strict;
+    let Err(errors) = emulator.load_program(code.to_string()) else { return (emulator, Vec::new()) };
+    for error in &errors {
+        println!("Error: {error}");
+        if let (Some(line), Some(column), _) = split_location(error) {
+            if let Some(excerpt) = tools::underline(code, line, column) {
+                println!("{excerpt}");
             }
         }
     }
+    let _ = emulator.load_program(String::new());
+    (emulator, errors)
 }
 
-fn clear_terminal_screen() {
-    print!("\x1B[2J\x1B[1;1H");
+/// The end-of-run reports asked for with --stats, --profile, --timing and --deterministic. Under --uart
+/// stdout carries the program's output, so they go to stderr.
+fn print_run_summary_v2(emulator: &EmulatorV2, code: &str, profiler: Option<&Profiler>, show_stats: bool, uart: bool) {
+    let mut summary = String::new();
+    if show_stats {
+        summary.push_str(&format_stats_v2(emulator));
+        summary.push('\n');
+    }
+    if let Some(profiler) = profiler {
+        summary.push_str(&profiler.report(emulator, code));
+    }
+    if !emulator.assertions.is_empty() && emulator.fault.is_none() {
+        summary.push_str(&format!("{} assertion checks passed\n", emulator.stats.assertions));
+    }
+    if emulator.deterministic {
+        summary.push_str(&format!("Final state after {} clocks: hash {:016x}\n", emulator.stats.cycles, emulator.state_hash()));
+    }
+    if uart {
+        eprint!("{summary}");
+    } else {
+        print!("{summary}");
+    }
+}
+
+/// The title over one half of the `--compare` split: the file name and the architecture.
+fn caption(path: &str, arch: Arch) -> String {
+    let name = std::path::Path::new(path).file_name().map_or(path.into(), |name| name.to_string_lossy());
+    format!("{name} ({})", arch.name())
+}
+
+/// The window's title: the file, the clock and what the program is doing, ahead of the
+/// emulator's name so that several windows can be told apart in the task bar.
+fn window_title(path: &str, arch: Arch, timestep: &Timestep, turbo: bool, state: &str) -> String {
+    let name = std::path::Path::new(path).file_name().map_or(path.into(), |name| name.to_string_lossy());
+    let clock = if turbo { "turbo".to_string() } else { timestep.speed() };
+    format!("{name} - {clock} - {state} - {}", arch.info().title)
+}
+
+/// Sends `tracing` events to stderr, filtered by `--log-level` or else `RUST_LOG`. Without either
+/// nothing is logged.
+fn init_logging(log_level: Option<&str>) -> Result<(), String> {
+    let filter = match log_level {
+        Some(filter) => EnvFilter::try_new(filter).map_err(|e| format!("--log-level: \"{filter}\": {e}"))?,
+        None => match EnvFilter::try_from_default_env() {
+            Ok(filter) => filter,
+            Err(_) => return Ok(()),
+        },
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .init();
+    Ok(())
 }
 
 fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
-    let mut file_name = String::new();
-    let mut terminal_output = true;
-    let mut clock_speed = 1.0;
-    let mut show_fps = false;
-    let mut use_v2 = false;
+    signals::install();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let options = match cli::parse(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            println!("Error: {error}");
+            println!("Run `electron --help` for usage.");
+            std::process::exit(2);
+        }
+    };
+    if let Err(error) = init_logging(options.log_level.as_deref()) {
+        println!("Error: {error}");
+        std::process::exit(2);
+    }
+    if options.help {
+        print!("{}", cli::usage(options.command));
+        return;
+    }
 
-    for (i, str) in args.iter().enumerate() {
-        if str == "-f" {
-            file_name = args.get(i + 1).unwrap_or(&String::new()).clone()
+    let code = match options.command {
+        Command::Run => {
+            run(options);
+            0
         }
-        if str == "-c" {
-            clock_speed = args.get(i + 1).unwrap().parse::<f32>().unwrap();
+        Command::Check => tools::check(&options.file_name, options.arch, options.strict),
+        Command::Assemble => tools::assemble(&options.file_name, options.arch, options.strict, options.output.as_deref()),
+        Command::Disasm => tools::disasm(&options.file_name, options.output.as_deref()),
+        Command::Test => tools::test(&options.test_dir, options.test_cycles, options.seed),
+        Command::Isa => tools::isa(options.output.as_deref()),
+        Command::Explain => tools::explain(&options.explain_code),
+        Command::Lsp => lsp::run(options.arch),
+        Command::GenSyntax => tools::gen_syntax(options.syntax_format, options.output.as_deref()),
+        Command::Repl => {
+            let mut emulator = EmulatorV2::new(String::new());
+            match attach_devices(&mut emulator, false, options.disk_path.as_deref(), options.gpio_dir.as_deref(), options.seed) {
+                Ok(()) => {
+                    repl::Repl::new(emulator).run();
+                    0
+                }
+                Err(error) => {
+                    println!("Error: {error}");
+                    1
+                }
+            }
         }
-        if str == "-nt" {
-            terminal_output = false;
+    };
+    std::process::exit(code);
+}
+
+fn run(options: Options) {
+    let Options {
+        mut file_name,
+        mut terminal_output,
+        display,
+        clock_speed,
+        show_fps,
+        turbo,
+        arch,
+        buzzer_port,
+        speaker,
+        deterministic,
+        von_neumann,
+        interlock,
+        strict,
+        separate_stack,
+        stack,
+        watchdog,
+        rom_file,
+        profile,
+        timing,
+        show_stats,
+        uart,
+        debug,
+        run_until_halt,
+        exit_port,
+        max_cycles,
+        bench,
+        disk_path,
+        gpio_dir,
+        seed,
+        link_file,
+        compare_file,
+        compare_arch,
+        serve,
+        theme,
+        scale,
+        fullscreen,
+        matrix,
+        strobe_port,
+        adc_channels,
+        gamepad,
+        mouse,
+        record_file,
+        replay_file,
+        dump_state,
+        log_ports,
+        dashboards,
+        layout_name,
+        ..
+    } = options;
+
+    // Started without a program, as by double-clicking the binary: pick one in a window.
+    if file_name.is_empty() {
+        let windowed = display.unwrap_or_else(Display::default_for_build) == Display::Window;
+        if !windowed || bench.is_some() || run_until_halt || debug {
+            println!("Error: No file name given. Use -f <filename>");
+            return;
         }
-        if str == "-fps" {
-            show_fps = true;
+        match picker::pick(arch.info().title) {
+            Ok(Some(picked)) => file_name = picked,
+            Ok(None) => return,
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
         }
-        if str == "-v2" {
-            use_v2 = true;
+    }
+    if let Err(error) = std::fs::metadata(&file_name) {
+        println!("Error: {file_name}: {error}");
+        return;
+    }
+    if bench.is_none() && !run_until_halt {
+        recent::remember(&file_name);
+    }
+    tracing::info!(file = %file_name, arch = arch.name(), "starting the emulator");
+    let rom = match rom_file {
+        Some(file) => match std::fs::read_to_string(&file) {
+            Ok(source) => Some(Rom { file, source }),
+            Err(error) => {
+                println!("Error: {file}: {error}");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let mut layouts: Vec<Layout> = Vec::new();
+    for path in &dashboards {
+        match Layout::load(path) {
+            Ok(loaded) => layouts.extend(loaded),
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
         }
     }
 
-    if file_name.is_empty() {
-        println!("Error: No file name given. Use -f <filename>");
+    // None is the built-in dashboard; L cycles through the scripted layouts and back.
+    let mut selected_layout = match &layout_name {
+        Some(name) => match layouts.iter().position(|layout| &layout.name == name) {
+            Some(index) => Some(index),
+            None => {
+                println!("Error: No layout named \"{name}\". Load layout scripts with --dashboard <file>.");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    if compare_file.is_some() && (bench.is_some() || run_until_halt || debug) {
+        println!("Error: --compare shows two programs side by side, so it can't be combined with --bench, --run-until-halt or --debug.");
+        return;
+    }
+    if serve.is_some() && (bench.is_some() || run_until_halt || debug) {
+        println!("Error: --serve controls a running emulator, so it can't be combined with --bench, --run-until-halt or --debug.");
         return;
     }
 
-    let (mut rl, thread) = raylib::init()
-        .width(WINDOW_SIZE.0)
-        .title(if use_v2 { "Electron 2 Emulator" } else { "Electron Emulator" })
-        .height(WINDOW_SIZE.1)
-        .build();
+    if let Some(clocks) = bench {
+        if run_until_halt || debug {
+            println!("Error: --bench runs on its own, so it can't be combined with --run-until-halt or --debug.");
+            return;
+        }
+        let code = std::fs::read_to_string(&file_name).expect("Failed to read file");
+        let result = if arch == Arch::Electron2 {
+            let mut emulator = load_v2(&file_name, &code, rom.clone(), strict);
+            emulator.stack_override = stack;
+            emulator.watchdog_override = watchdog;
+            emulator.set_von_neumann(von_neumann);
+            emulator.interlock = interlock;
+            emulator.separate_stack = separate_stack;
+            bench::bench_v2(&mut emulator, clocks)
+        } else {
+            match ProgramLoader::reload_program(&file_name, strict) {
+                Ok(program) => bench::bench_v1(&mut Emulator::new(program), clocks),
+                // Already worded as "Error on line N: ...".
+                Err(error) => {
+                    println!("{error}");
+                    std::process::exit(1);
+                }
+            }
+        };
+        match result {
+            Ok(result) => print!("{}", result.report()),
+            Err(error) => {
+                println!("Error: {error}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    let mut last_clock = std::time::Instant::now();
-    let tick_speed = (1.0/clock_speed * 1000.0) as u128;
-    
-    let on_texture = rl.load_texture_from_image(&thread, &Image::load_image_from_mem(".png", &include_bytes!("on.png").to_vec(), include_bytes!("on.png").len() as i32).unwrap()).unwrap();
-    let off_texture = rl.load_texture_from_image(&thread, &Image::load_image_from_mem(".png", &include_bytes!("off.png").to_vec(), include_bytes!("off.png").len() as i32).unwrap()).unwrap();
+    if run_until_halt {
+        if arch != Arch::Electron2 {
+            println!("Error: --run-until-halt needs the electron2 architecture (-v2), which has the HLT instruction.");
+            return;
+        }
+        if debug {
+            println!("Error: --run-until-halt and --debug can't be combined.");
+            return;
+        }
+        let code = std::fs::read_to_string(&file_name).expect("Failed to read file");
+        let mut emulator = load_v2(&file_name, &code, rom.clone(), strict);
+        emulator.deterministic = deterministic;
+        emulator.stack_override = stack;
+        emulator.watchdog_override = watchdog;
+        emulator.set_von_neumann(von_neumann);
+        emulator.interlock = interlock;
+        emulator.separate_stack = separate_stack;
+        if let Err(error) = attach_devices(&mut emulator, uart, disk_path.as_deref(), gpio_dir.as_deref(), seed)
+            .and_then(|()| replay::prepare(&mut emulator, replay_file.as_deref(), record_file.is_some()))
+        {
+            println!("Error: {error}");
+            return;
+        }
+        let mut linked = match connect_linked_core(&mut emulator, link_file.as_deref(), deterministic, seed) {
+            Ok(linked) => linked,
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
+        };
+        let mut dumps = match create_dumps(dump_state.as_deref(), log_ports.as_deref()) {
+            Ok(dumps) => dumps,
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
+        };
+        let mut profiler = (profile || timing).then(|| Profiler::new(profile, timing));
+        let result = batch::run_until_halt(&mut emulator, max_cycles, uart, profiler.as_mut(), linked.as_mut(), &mut dumps);
+        print_run_summary_v2(&emulator, &code, profiler.as_ref(), show_stats, uart);
+        save_recording(&emulator, record_file.as_deref());
+        finish_dumps(dumps);
+        let code = match result {
+            Ok(()) => match emulator.exit_code {
+                Some(code) => code as i32,
+                None => exit_port.map(|port| emulator.read_port(port as i32) as i32).unwrap_or(0),
+            },
+            Err(error) => {
+                eprintln!("Error: {error}");
+                error.exit_code()
+            }
+        };
+        std::process::exit(code);
+    }
 
-    if use_v2 {
-        // --- V2 Execution ---
-        println!("Starting Electron 2 Emulator...");
+    if debug {
+        if arch != Arch::Electron2 {
+            println!("Error: The debugger only supports the electron2 architecture (-v2).");
+            return;
+        }
+        if uart {
+            println!("Error: The debugger console needs stdin, so it can't be combined with --uart.");
+            return;
+        }
+        if link_file.is_some() {
+            println!("Error: The debugger steps one core, so it can't be combined with --link.");
+            return;
+        }
+        if dump_state.is_some() || log_ports.is_some() {
+            println!("Error: --dump-state and --log-ports record a run, so they can't be combined with --debug.");
+            return;
+        }
         let code = std::fs::read_to_string(&file_name).expect("Failed to read file");
-        let mut emulator = EmulatorV2::new(code);
-
-        while !rl.window_should_close() {
-            if (std::time::Instant::now() - last_clock).as_millis() > tick_speed {
-                emulator.clock();
-                last_clock = std::time::Instant::now();
-                clear_terminal_screen();
-                if terminal_output {
-                    draw_terminal_screen_v2(&emulator);
+        let mut emulator = load_v2(&file_name, &code, rom.clone(), strict);
+        emulator.deterministic = deterministic;
+        emulator.stack_override = stack;
+        emulator.watchdog_override = watchdog;
+        emulator.set_von_neumann(von_neumann);
+        emulator.interlock = interlock;
+        emulator.separate_stack = separate_stack;
+        if let Err(error) = attach_devices(&mut emulator, false, disk_path.as_deref(), gpio_dir.as_deref(), seed)
+            .and_then(|()| replay::prepare(&mut emulator, replay_file.as_deref(), record_file.is_some()))
+        {
+            println!("Error: {error}");
+            return;
+        }
+        let mut debugger = debugger::Debugger::new(emulator, code);
+        debugger.run();
+        save_recording(&debugger.into_emulator(), record_file.as_deref());
+        return;
+    }
+
+    // The UART owns stdio, so keep the dashboard and raylib's log chatter off stdout.
+    if uart {
+        terminal_output = false;
+    }
+    let display = display.unwrap_or_else(Display::default_for_build);
+    let compare_arch = compare_arch.unwrap_or(arch);
+    let compare = match compare_file.as_deref().map(|path| Comparison::load(path, compare_arch, von_neumann, interlock, separate_stack, stack, watchdog)).transpose() {
+        Ok(compare) => compare,
+        Err(error) => {
+            println!("Error: {error}");
+            return;
+        }
+    };
+    // Each half of the split is captioned with its file and architecture, which may be all that differs.
+    let captions = compare_file.as_deref().map(|path| [caption(&file_name, arch), caption(path, compare_arch)]);
+    // The --adc sliders in the window set what the device reads.
+    let (adc, adc_values) = adc_channels.filter(|_| arch == Arch::Electron2).map(devices::Adc::new).unzip();
+    // And it reads the gamepad into the port the --gamepad device serves.
+    let (pad, pad_buttons) = match gamepad && arch == Arch::Electron2 {
+        true => match gamepad::Mapping::load() {
+            Ok(mapping) => {
+                let (device, buttons) = devices::Gamepad::new(mapping.port);
+                (Some(device), Some((mapping, buttons)))
+            }
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
+        },
+        false => (None, None),
+    };
+    if buzzer_port.is_some() && speaker.is_some() {
+        println!("Error: --buzzer and --speaker both play through the window's sound; use one.");
+        return;
+    }
+    let (speaker_device, speaker_samples) = speaker.filter(|_| arch == Arch::Electron2).map(|(port, bit)| devices::Speaker::new(port, bit)).unzip();
+    let (pointer, pointer_state) = match mouse && arch == Arch::Electron2 {
+        true => {
+            let (device, state) = devices::Mouse::new();
+            (Some(device), Some(state))
+        }
+        false => (None, None),
+    };
+    let mut window = match display {
+        Display::Window => match Window::open(WindowOptions {
+            title: arch.info().title,
+            theme: theme.as_deref(),
+            scale,
+            matrix: matrix || strobe_port.is_some(),
+            buzzer_port,
+            speaker: speaker_samples,
+            show_fps,
+            fullscreen,
+            compare: captions.clone(),
+            quiet: uart,
+            adc: adc_values,
+            gamepad: pad_buttons,
+            mouse: pointer_state,
+        }) {
+            Ok(window) => Some(window),
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
+        },
+        Display::Lite | Display::Terminal | Display::Headless => {
+            if display == Display::Terminal && !terminal_output {
+                println!("Error: --display terminal draws the LEDs on the terminal dashboard, which -nt and --uart turn off.");
+                return;
+            }
+            if theme.is_some() || scale.is_some() || fullscreen || matrix || buzzer_port.is_some() || speaker.is_some() || show_fps {
+                println!("Warning: --theme, --scale, --fullscreen, --matrix, --buzzer, --speaker and -fps only apply to the window (--display window).");
+            }
+            if adc.is_some() {
+                println!("Warning: --adc's sliders are in the window (--display window), so its channels stay at 0.");
+            }
+            if pad.is_some() || pointer.is_some() {
+                println!("Warning: --gamepad and --mouse are read by the window (--display window), so their ports stay at 0.");
+            }
+            None
+        }
+    };
+    let mut lite = match display {
+        Display::Lite => match LiteWindow::open(arch.info().title) {
+            Ok(lite) => Some(lite),
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
+        },
+        _ => None,
+    };
+    let timestep = Timestep::new(clock_speed as f64);
+
+    if arch == Arch::Electron2 {
+        // --- V2 Execution ---
+        let mut code = std::fs::read_to_string(&file_name).expect("Failed to read file");
+        // The window lists a program's errors and waits for F5, rather than exiting.
+        let (mut emulator, startup_errors) = match window {
+            Some(_) => try_load_v2(&file_name, &code, rom.clone(), strict),
+            None => (load_v2(&file_name, &code, rom.clone(), strict), Vec::new()),
+        };
+        if !startup_errors.is_empty() {
+            println!("Failed to assemble {file_name}. Fix the errors above and press F5 in the window to load it again.");
+        }
+        emulator.deterministic = deterministic;
+        emulator.stack_override = stack;
+        emulator.watchdog_override = watchdog;
+        emulator.set_von_neumann(von_neumann);
+        emulator.interlock = interlock;
+        emulator.separate_stack = separate_stack;
+        if let Err(error) = attach_devices(&mut emulator, uart, disk_path.as_deref(), gpio_dir.as_deref(), seed)
+            .and_then(|()| replay::prepare(&mut emulator, replay_file.as_deref(), record_file.is_some()))
+        {
+            println!("Error: {error}");
+            return;
+        }
+        let linked = match connect_linked_core(&mut emulator, link_file.as_deref(), deterministic, seed) {
+            Ok(linked) => linked,
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
+        };
+        if let Some(adc) = adc {
+            emulator.devices.push(Box::new(adc));
+        }
+        if let Some(pad) = pad {
+            emulator.devices.push(Box::new(pad));
+        }
+        if let Some(pointer) = pointer {
+            emulator.devices.push(Box::new(pointer));
+        }
+        if let Some(speaker) = speaker_device {
+            emulator.devices.push(Box::new(speaker));
+        }
+        // Under --strobe the LEDs show the last latched frame rather than the live ports.
+        let frame = strobe_port.map(|port| {
+            let (latch, frame) = devices::FrameLatch::new(port);
+            emulator.devices.push(Box::new(latch));
+            frame
+        });
+        // Tab switches the LEDs and dashboard between the cores under --link.
+        let mut show_linked = false;
+        if let Some(window) = &mut window {
+            window.set_source(&code);
+            window.set_problems(&startup_errors, &emulator.warnings);
+        }
+        // Nothing runs until the program assembles.
+        let mut unassembled = !startup_errors.is_empty();
+        let mut stdin_lines: Option<StdinLines> = None;
+        let mut prompted = false;
+        let profiler = (profile || timing).then(|| Profiler::new(profile, timing));
+        let dumps = match create_dumps(dump_state.as_deref(), log_ports.as_deref()) {
+            Ok(dumps) => dumps,
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
+        };
+        // What the window and dashboard draw, copied out of the running machine every frame.
+        let mut shown = EmulatorV2::new(String::new());
+        let mut shown_linked = EmulatorV2::new(String::new());
+        let mut history = PortHistory::default();
+        let mut paused = unassembled;
+        let server = match serve.as_deref().map(Server::start).transpose() {
+            Ok(server) => server,
+            Err(error) => {
+                println!("Error: {error}");
+                return;
+            }
+        };
+        let mut tui = start_tui(terminal_output);
+        if tui.is_none() && window.is_none() && lite.is_none() && server.is_none() {
+            println!("Error: Nothing would show the run: there's no window, no terminal dashboard and no --serve. Add --run-until-halt to run it headless.");
+            std::process::exit(1);
+        }
+        // The dashboard owns the terminal, so what SYS prints goes in its messages.
+        let mut sys_output = None;
+        if tui.is_some() {
+            emulator.events.enable(tui::EVENT_CAPACITY);
+            let (services, output) = syscalls::HostServices::captured();
+            emulator.syscalls = Some(Box::new(services));
+            sys_output = Some(output);
+        }
+        if let Some(screen) = &mut tui {
+            screen.captions = captions;
+        }
+        if let Some(server) = &server {
+            let message = format!("Serving the API on http://{}/.", server.address);
+            if uart { eprintln!("{message}") } else { notify(&mut tui, message) }
+        }
+        let subscribers = Vec::new();
+        let runner = Runner::spawn(MachineV2 { emulator, linked, compare, profiler, dumps, history: PortHistory::default(), subscribers, timestep, turbo, paused });
+
+        while !window.as_ref().is_some_and(Window::should_close) && !lite.as_ref().is_some_and(LiteWindow::should_close) && !signals::shutdown_requested() {
+            let compare_ports;
+            // Everything that touches the machine happens here, and the drawing after the lock is let go.
+            {
+                let mut machine = runner.lock();
+                let machine = &mut *machine;
+                let mut control = None;
+                // Reload, a recent program picked from the menu, or a file dropped on the window,
+                // which is then the one Reload reads.
+                let mut load = None;
+                let mut edit = None;
+                if let Some(screen) = &mut tui {
+                    match screen.poll(machine.emulator.waiting_for_input) {
+                        Some(TuiEvent::Quit) => break,
+                        Some(TuiEvent::Input(value)) => machine.emulator.resolve_input(value as i32),
+                        Some(TuiEvent::Control(pressed)) => control = Some(pressed),
+                        Some(TuiEvent::Faster) => notify(&mut tui, machine.timestep.change_speed(true)),
+                        Some(TuiEvent::Slower) => notify(&mut tui, machine.timestep.change_speed(false)),
+                        Some(TuiEvent::Interlock) => notify(&mut tui, toggle_interlock(&mut machine.emulator)),
+                        Some(TuiEvent::Open(path)) => load = Some(path),
+                        None => {}
+                    }
+                }
+                if machine.emulator.waiting_for_input && uart {
+                    machine.emulator.fault = Some("INP waits for a typed value, but stdin is attached to the UART (--uart).".to_string());
+                    break;
+                } else if machine.emulator.waiting_for_input && tui.is_none() {
+                    // Parked on INP: the dashboard takes the value, or else a line of stdin, without
+                    // blocking the window or Ctrl+C.
+                    let lines = stdin_lines.get_or_insert_with(StdinLines::spawn);
+                    if !prompted {
+                        println!("INP: enter a value (0-255) and press Enter:");
+                        prompted = true;
+                    }
+                    if let Some(line) = lines.try_next() {
+                        match line.trim().parse::<u8>() {
+                            Ok(value) => {
+                                machine.emulator.resolve_input(value as i32);
+                                prompted = false;
+                            },
+                            Err(_) => println!("Error: \"{}\" is not a value between 0 and 255.", line.trim()),
+                        }
+                    }
+                }
+                for line in sys_output.as_deref().map(syscalls::take_lines).unwrap_or_default() {
+                    notify(&mut tui, line);
+                }
+                if machine.emulator.fault.is_some() {
+                    break;
+                }
+                if let Some(window) = &mut window {
+                    let state = if machine.emulator.halted {
+                        "halted"
+                    } else if machine.emulator.waiting_for_input {
+                        "waiting for INP"
+                    } else if machine.paused {
+                        "paused"
+                    } else {
+                        "running"
+                    };
+                    window.set_title(&window_title(&file_name, arch, &machine.timestep, machine.turbo, state));
+                    if window.pressed(Key::NextCore) && machine.linked.is_some() {
+                        show_linked = !show_linked;
+                    }
+                    if window.pressed(Key::Interlock) {
+                        notify(&mut tui, toggle_interlock(&mut machine.emulator));
+                    }
+                    if window.pressed(Key::Pwm) {
+                        notify(&mut tui, window.toggle_pwm());
+                    }
+                    if window.pressed(Key::Fullscreen) {
+                        notify(&mut tui, window.toggle_fullscreen());
+                    }
+                    edit = window.edit();
+                    window.handle_panels(&mut machine.emulator);
+                }
+                while let Some(request) = server.as_ref().and_then(Server::next_request) {
+                    if let Some(new_code) = server::handle(machine, request) {
+                        if let Some(window) = &mut window {
+                            window.set_source(&new_code);
+                            window.set_problems(&[], &machine.emulator.warnings);
+                        }
+                        code = new_code;
+                        prompted = false;
+                        notify(&mut tui, "Loaded the program sent to /load.".to_string());
+                    }
+                }
+                load = load.or_else(|| window.as_mut().and_then(Window::opened_file));
+                match control.or_else(|| window.as_ref().and_then(Window::transport)).or_else(|| lite.as_ref().and_then(LiteWindow::transport)) {
+                    Some(Transport::Pause) => machine.paused = !machine.paused,
+                    Some(Transport::Step) if !machine.emulator.waiting_for_input => {
+                        machine.step();
+                        if machine.emulator.fault.is_some() {
+                            break;
+                        }
+                    }
+                    Some(Transport::Reset) => {
+                        machine.reset();
+                        prompted = false;
+                    }
+                    Some(Transport::Reload) => load = Some(file_name.clone()),
+                    _ => {}
+                }
+                // The program to load in its place, the file it's from, and what to say once it runs.
+                let next = match (load, edit) {
+                    (Some(path), _) => match std::fs::read_to_string(&path) {
+                        Ok(new_code) => {
+                            let done = format!("{} {path}.", if path == file_name { "Reloaded" } else { "Loaded" });
+                            Some((path, new_code, done))
+                        }
+                        Err(error) => {
+                            notify(&mut tui, format!("Error: {path}: {error}"));
+                            None
+                        }
+                    },
+                    (None, Some(Edit::Apply(new_code))) => Some((file_name.clone(), new_code, "Applied the edited program.".to_string())),
+                    (None, Some(Edit::Save(new_code))) => match std::fs::write(&file_name, format!("{new_code}\n")) {
+                        Ok(()) => Some((file_name.clone(), new_code, format!("Saved {file_name} and applied it."))),
+                        Err(error) => {
+                            notify(&mut tui, format!("Error: {file_name}: {error}"));
+                            None
+                        }
+                    },
+                    (None, None) => None,
+                };
+                if let Some((path, new_code, done)) = next {
+                    let old_file = std::mem::replace(&mut machine.emulator.source_file, path.clone());
+                    match machine.load(new_code.clone()) {
+                        Ok(()) => {
+                            if let Some(window) = &mut window {
+                                window.set_source(&new_code);
+                                window.set_problems(&[], &machine.emulator.warnings);
+                            }
+                            if unassembled {
+                                machine.paused = false;
+                                unassembled = false;
+                            }
+                            code = new_code;
+                            prompted = false;
+                            notify(&mut tui, done);
+                            recent::remember(&path);
+                            file_name = path;
+                        }
+                        Err(errors) => {
+                            machine.emulator.source_file = old_file;
+                            if let Some(window) = &mut window {
+                                window.set_problems(&errors, &[]);
+                            }
+                            for error in errors {
+                                notify(&mut tui, format!("Error: {error}"));
+                            }
+                            notify(&mut tui, "Kept the program that was running.".to_string());
+                        }
+                    }
+                }
+                // The flashes follow the main core, so they're left out while the linked one is shown.
+                let stack_in_ram = !machine.emulator.separate_stack;
+                let events = machine.emulator.events.drain();
+                match &mut tui {
+                    Some(screen) if !show_linked => screen.observe(events, stack_in_ram),
+                    _ => drop(events),
+                }
+                runner::mirror(&mut shown, &machine.emulator);
+                if let Some(core) = machine.linked.as_ref().filter(|_| show_linked) {
+                    runner::mirror(&mut shown_linked, &core.emulator);
                 }
+                history.clone_from(&machine.history);
+                compare_ports = machine.compare.as_ref().map(Comparison::ports);
+                paused = machine.paused;
             }
-            let mut d = rl.begin_drawing(&thread);
-            d.clear_background(Color::BLACK);
-            draw_ports_v2(&emulator, &mut d, &on_texture, &off_texture);
-            if show_fps {
-                d.draw_text(&d.get_fps().to_string(), 0, 0, 25, Color::WHITE);
+            let core = if show_linked { &shown_linked } else { &shown };
+            if window.as_ref().is_some_and(|window| window.pressed(Key::NextLayout)) && !layouts.is_empty() {
+                selected_layout = match selected_layout {
+                    None => Some(0),
+                    Some(i) if i + 1 < layouts.len() => Some(i + 1),
+                    Some(_) => None,
+                };
             }
+            let strobed = match &frame {
+                Some(frame) if !show_linked => Some(*frame.lock().unwrap()),
+                _ => None,
+            };
+            let ports = strobed.unwrap_or(core.ports_out);
+            if let Some(screen) = &mut tui {
+                screen.paused = paused;
+                screen.leds = (display == Display::Terminal).then_some(ports);
+                screen.compare_leds = compare_ports;
+                screen.draw_v2(core, selected_layout.map(|i| &layouts[i]), &history);
+            }
+            match (&mut window, &mut lite) {
+                (Some(window), _) => window.draw_v2(core, &shown, &history, strobed.as_ref(), compare_ports.as_ref(), paused),
+                // The window paces the loop when there is one.
+                (None, Some(lite)) => lite.draw(&ports, compare_ports.as_ref()),
+                (None, None) => std::thread::sleep(FRAME),
+            }
+        }
+        // Close the window, which saves its layout, and hand the terminal back before printing
+        // anything.
+        if let Some(window) = window {
+            window.close();
         }
+        drop(tui);
+        let MachineV2 { emulator, profiler, dumps, .. } = runner.finish();
 
+        if let Some(fault) = &emulator.fault {
+            // Under --uart stdout belongs to the program.
+            if uart {
+                eprintln!("Error: {fault}");
+            } else {
+                println!("Error: {fault}");
+            }
+        }
+        print_run_summary_v2(&emulator, &code, profiler.as_ref(), show_stats, uart);
+        save_recording(&emulator, record_file.as_deref());
+        finish_dumps(dumps);
+        if emulator.fault.is_some() {
+            std::process::exit(1);
+        }
     } else {
         // --- V1 Execution (Legacy) ---
-        let program = ProgramLoader::load_program(&file_name);
-        let mut emulator = Emulator::new(program);
-
-        while !rl.window_should_close() {
-            if (std::time::Instant::now() - last_clock).as_millis() > tick_speed {
-                emulator.clock();
-                last_clock = std::time::Instant::now();
-                clear_terminal_screen();
-                if terminal_output {
-                    draw_terminal_screen(&emulator);
+        if deterministic {
+            println!("Warning: --deterministic only applies to the V2 emulator (-v2).");
+        }
+        if von_neumann || interlock || separate_stack || stack.is_some() || watchdog.is_some() || rom.is_some() {
+            println!("Warning: --von-neumann, --interlock, --separate-stack, --stack, --watchdog and --rom only apply to the V2 emulator (-v2).");
+        }
+        if profile || timing || show_stats {
+            println!("Warning: --profile, --timing and --stats only apply to the V2 emulator (-v2).");
+        }
+        if uart || disk_path.is_some() || gpio_dir.is_some() || seed.is_some() || link_file.is_some() {
+            println!("Warning: Peripherals (--uart, --disk, --gpio-dir, --seed, --link) only apply to the V2 emulator (-v2).");
+        }
+        if record_file.is_some() || replay_file.is_some() {
+            println!("Warning: --record and --replay only apply to the V2 emulator (-v2).");
+        }
+        if dump_state.is_some() || log_ports.is_some() {
+            println!("Warning: --dump-state and --log-ports only apply to the V2 emulator (-v2).");
+        }
+        if serve.is_some() {
+            println!("Warning: --serve only applies to the V2 emulator (-v2).");
+        }
+        if adc_channels.is_some() || gamepad || mouse || speaker.is_some() {
+            println!("Warning: --adc, --gamepad, --mouse and --speaker only apply to the V2 emulator (-v2).");
+        }
+        if strobe_port.is_some() {
+            println!("Warning: --strobe only applies to the V2 emulator (-v2); V1 can only write the display ports.");
+        }
+        let program = ProgramLoader::load_program(&file_name, strict);
+        let emulator = Emulator::new(program);
+        let mut shown = emulator.clone();
+        let mut problem = None;
+        let mut paused = false;
+        let mut tui = start_tui(terminal_output);
+        if tui.is_none() && window.is_none() && lite.is_none() {
+            println!("Error: Nothing would show the run: there's no window and no terminal dashboard.");
+            std::process::exit(1);
+        }
+        if let Some(screen) = &mut tui {
+            screen.captions = captions;
+        }
+        let runner = Runner::spawn(MachineV1 { emulator, compare, timestep, turbo, paused });
+
+        while !window.as_ref().is_some_and(Window::should_close) && !lite.as_ref().is_some_and(LiteWindow::should_close) && !signals::shutdown_requested() {
+            let compare_ports;
+            {
+                let mut machine = runner.lock();
+                let mut control = None;
+                let mut load = None;
+                match tui.as_mut().and_then(|screen| screen.poll(false)) {
+                    Some(TuiEvent::Quit) => break,
+                    Some(TuiEvent::Control(pressed)) => control = Some(pressed),
+                    Some(TuiEvent::Faster) => notify(&mut tui, machine.timestep.change_speed(true)),
+                    Some(TuiEvent::Slower) => notify(&mut tui, machine.timestep.change_speed(false)),
+                    Some(TuiEvent::Open(path)) => load = Some(path),
+                    Some(TuiEvent::Input(_) | TuiEvent::Interlock) | None => {}
                 }
+                load = load.or_else(|| window.as_mut().and_then(Window::opened_file));
+                match control.or_else(|| window.as_ref().and_then(Window::transport)).or_else(|| lite.as_ref().and_then(LiteWindow::transport)) {
+                    Some(Transport::Pause) => machine.paused = !machine.paused,
+                    Some(Transport::Step) => machine.step(),
+                    Some(Transport::Reset) => machine.reset(),
+                    Some(Transport::Reload) => load = Some(file_name.clone()),
+                    None => {}
+                }
+                if let Some(path) = load {
+                    match ProgramLoader::reload_program(&path, strict) {
+                        Ok(program) => {
+                            machine.emulator.load(program);
+                            if let Some(compare) = &mut machine.compare {
+                                compare.reset();
+                            }
+                            if let Some(window) = &mut window {
+                                window.set_problems(&[], &[]);
+                            }
+                            if path != file_name {
+                                notify(&mut tui, format!("Loaded {path}."));
+                                recent::remember(&path);
+                                file_name = path;
+                            }
+                        }
+                        Err(error) => {
+                            if let Some(window) = &mut window {
+                                window.set_problems(std::slice::from_ref(&error), &[]);
+                            }
+                            notify(&mut tui, format!("{error} Kept the program that was running."));
+                        }
+                    }
+                }
+                problem = machine.problem();
+                if problem.is_some() {
+                    break;
+                }
+                if let Some(window) = &mut window {
+                    let state = if machine.paused { "paused" } else { "running" };
+                    window.set_title(&window_title(&file_name, arch, &machine.timestep, machine.turbo, state));
+                }
+                shown.clone_from(&machine.emulator);
+                compare_ports = machine.compare.as_ref().map(Comparison::ports);
+                paused = machine.paused;
+            }
+            if let Some(screen) = &mut tui {
+                screen.paused = paused;
+                screen.leds = (display == Display::Terminal).then_some(shown.ports.out);
+                screen.compare_leds = compare_ports;
+                screen.draw_v1(&shown);
             }
-            let mut d = rl.begin_drawing(&thread);
-            d.clear_background(Color::BLACK);
-            draw_ports(&emulator, &mut d, &on_texture, &off_texture);
-            if show_fps {
-                d.draw_text(&d.get_fps().to_string(), 0, 0, 25, Color::WHITE);
+            if let Some(window) = window.as_mut().filter(|window| window.pressed(Key::Pwm)) {
+                notify(&mut tui, window.toggle_pwm());
             }
+            if let Some(window) = window.as_mut().filter(|window| window.pressed(Key::Fullscreen)) {
+                notify(&mut tui, window.toggle_fullscreen());
+            }
+            match (&mut window, &mut lite) {
+                (Some(window), _) => window.draw_v1(&shown.ports.out, compare_ports.as_ref(), paused),
+                (None, Some(lite)) => lite.draw(&shown.ports.out, compare_ports.as_ref()),
+                (None, None) => std::thread::sleep(FRAME),
+            }
+        }
+        if let Some(window) = window {
+            window.close();
+        }
+        drop(tui);
+        runner.finish();
+        if let Some(problem) = problem {
+            println!("Error: {problem}");
+            std::process::exit(1);
         }
     }
 }
@@ -0,0 +1,41 @@
+//! `--link`: a second V2 core run in lock-step with the main one. The two are wired together
+//! through the link ports (%20-%23, see `devices::link`), so programs can talk to each other.
+
+use crate::devices::link::{Link, LinkBus};
+use crate::electron_2::Emulator as EmulatorV2;
+
+pub struct LinkedCore {
+    pub emulator: EmulatorV2,
+    bus: LinkBus,
+}
+
+impl LinkedCore {
+    /// Loads `code` as core B and attaches the other end of the link to core A.
+    pub fn connect(core_a: &mut EmulatorV2, code: String) -> Result<LinkedCore, String> {
+        let mut emulator = EmulatorV2::new(code);
+        if let Some(error) = emulator.errors.first() {
+            return Err(format!("Core B: {error}"));
+        }
+        let (end_a, end_b, bus) = Link::pair();
+        core_a.devices.push(Box::new(end_a));
+        emulator.devices.push(Box::new(end_b));
+        Ok(LinkedCore { emulator, bus })
+    }
+
+    /// Clocks core B once core A has clocked, then latches what both wrote to the link.
+    pub fn clock(&mut self) {
+        self.emulator.clock();
+        self.bus.commit();
+    }
+
+    /// Why core B can't keep up with core A, if it can't.
+    pub fn problem(&self) -> Option<String> {
+        if let Some(fault) = &self.emulator.fault {
+            return Some(format!("Core B: {fault}"));
+        }
+        if self.emulator.waiting_for_input {
+            return Some("Core B: INP waits for a typed value, but only core A can ask for one. Read a link port instead.".to_string());
+        }
+        None
+    }
+}
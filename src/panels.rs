@@ -0,0 +1,435 @@
+//! Panels drawn over the V2 LEDs in the raylib window, each toggled with a key, or beside them
+//! in the split layout. They show what the terminal dashboard shows, without its flicker and
+//! without needing a terminal at all.
+
+use crate::electron_2::disassembler::Disassembler;
+use crate::electron_2::hazards::Hazard;
+use crate::electron_2::Emulator as EmulatorV2;
+use crate::line_editor::home_file;
+use crate::timeline::PortHistory;
+use crate::transport;
+use crate::WINDOW_SIZE;
+use raylib::prelude::*;
+use std::time::{Duration, Instant};
+
+const FONT_SIZE: i32 = 20;
+const LINE_HEIGHT: i32 = 24;
+const PADDING: i32 = 12;
+const BACKGROUND: Color = Color::new(10, 10, 20, 220);
+const TITLE: Color = Color::new(140, 170, 255, 255);
+const TEXT: Color = Color::new(230, 230, 230, 255);
+const HIGHLIGHT: Color = Color::new(255, 200, 60, 255);
+const CHANGED: Color = Color::new(90, 230, 120, 255);
+const BREAKPOINT: Color = Color::new(230, 50, 50, 255);
+const BREAKPOINT_HINT: Color = Color::new(230, 50, 50, 90);
+const CURRENT_LINE: Color = Color::new(90, 70, 20, 255);
+const STAGE_BOX: Color = Color::new(40, 40, 70, 230);
+const FLUSH: Color = Color::new(200, 30, 30, 230);
+const HAZARD: Color = Color::new(255, 140, 40, 255);
+const HAZARD_LINE: Color = Color::new(110, 50, 10, 255);
+
+const STAGE_GAP: i32 = 8;
+const SOURCE_WIDTH: i32 = 390;
+const STAGE_NAMES: [&str; 4] = ["FETCH", "DECODE", "EXECUTE", "WRITEBACK"];
+/// How long instructions take to slide into their next stage after a clock.
+const SLIDE: Duration = Duration::from_millis(150);
+/// How long the bubble left by a flush stays red.
+const FLUSH_FLASH: Duration = Duration::from_millis(400);
+
+const GUTTER_WIDTH: i32 = 18;
+/// Widest source line shown, in characters; longer lines are cut.
+const SOURCE_COLUMNS: usize = 30;
+
+/// Timeline rows: the pixels per clock, the height of each bit's row, and the room for `%7.7`.
+const WAVE_STEP: i32 = 4;
+const WAVE_ROW: i32 = 10;
+const WAVE_LABEL_WIDTH: i32 = 40;
+const WAVE_FONT_SIZE: i32 = 10;
+const WAVE: Color = Color::new(90, 230, 120, 255);
+
+const REGISTER_NAMES: [&str; 11] = ["R0", "R1", "R2", "R3", "R4", "R5", "R6", "R7", "ACC", "PC", "SP"];
+
+#[derive(Default)]
+pub struct Panels {
+    /// M: the 16 RAM bytes.
+    pub ram: bool,
+    /// R: the registers, accumulator, PC and SP.
+    pub registers: bool,
+    /// P: the four pipeline stages.
+    pub pipeline: bool,
+    /// S: the program source, following the instruction in EXECUTE.
+    pub source: bool,
+    /// T: the port waveforms, above the pipeline panel.
+    pub timeline: bool,
+    lines: Vec<String>,
+    /// The clock the register values below were read after, and the values one clock earlier,
+    /// for highlighting what the last clock changed.
+    cycle: u64,
+    values: [i32; 11],
+    before: [i32; 11],
+    clocked_at: Option<Instant>,
+    flushed: u64,
+    flushed_at: Option<Instant>,
+}
+
+impl Panels {
+    /// Panels for a core running `code`, all hidden.
+    pub fn new(code: &str) -> Self {
+        Self { lines: code.lines().map(String::from).collect(), ..Default::default() }
+    }
+
+    /// Shows `code` in the source view, after the program was reloaded.
+    pub fn set_source(&mut self, code: &str) {
+        self.lines = code.lines().map(String::from).collect();
+    }
+
+    /// Each panel's toggle with the name the layout file uses for it.
+    fn toggles(&mut self) -> [(&'static str, &mut bool); 5] {
+        [
+            ("ram", &mut self.ram),
+            ("registers", &mut self.registers),
+            ("pipeline", &mut self.pipeline),
+            ("source", &mut self.source),
+            ("timeline", &mut self.timeline),
+        ]
+    }
+
+    /// Toggles the panels whose keys were pressed this frame. Clicking a line in the source view
+    /// toggles a breakpoint on its instruction in `main`, the core running that source. `mouse`
+    /// is the pointer relative to where the panels are drawn.
+    pub fn handle_input(&mut self, rl: &RaylibHandle, mouse: Vector2, main: &mut EmulatorV2) {
+        if rl.is_key_pressed(KeyboardKey::KEY_M) {
+            self.ram = !self.ram;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_R) {
+            self.registers = !self.registers;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_P) {
+            self.pipeline = !self.pipeline;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_S) {
+            self.source = !self.source;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_T) {
+            self.timeline = !self.timeline;
+        }
+        if self.source && rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            let line = self.gutter_line_at(mouse, main);
+            if let Some(address) = line.and_then(|line| line_address(main, line)) {
+                if !main.breakpoints.remove(&address) {
+                    main.breakpoints.insert(address);
+                }
+            }
+        }
+    }
+
+    /// The source line whose gutter or line number is under `mouse`, counted from 1.
+    fn gutter_line_at(&self, mouse: Vector2, emulator: &EmulatorV2) -> Option<i32> {
+        let (top, rows) = self.source_area();
+        let row = (mouse.y as i32 - top - PADDING - LINE_HEIGHT).div_euclid(LINE_HEIGHT);
+        let in_gutter = (mouse.x as i32) < PADDING + GUTTER_WIDTH + measure_text("000", FONT_SIZE);
+        (in_gutter && (0..rows).contains(&row)).then(|| (self.first_source_line(emulator, rows) + row as usize + 1) as i32)
+    }
+
+    /// `emulator` is the core on show. The source view always shows `main`, whose program it has,
+    /// and the timeline `main`'s ports.
+    pub fn draw(&mut self, d: &mut impl RaylibDraw, mouse: Vector2, emulator: &EmulatorV2, main: &EmulatorV2, history: &PortHistory) {
+        if emulator.stats.cycles != self.cycle {
+            self.cycle = emulator.stats.cycles;
+            self.before = self.values;
+            self.values = register_values(emulator);
+            self.clocked_at = Some(Instant::now());
+            if emulator.stats.flushed != self.flushed {
+                self.flushed_at = Some(Instant::now());
+            }
+            self.flushed = emulator.stats.flushed;
+        }
+        if self.ram {
+            draw_ram(d, emulator);
+        }
+        if self.registers {
+            self.draw_registers(d);
+        }
+        // The hazards with an instruction in the pipeline. They're in `main`'s program, so the
+        // pipeline panel only shows them while it shows `main`.
+        let hazards = main.hazards();
+        let stages = [&main.fetch_reg, &main.decode_reg, &main.execute_reg, &main.writeback_reg];
+        let active: Vec<&Hazard> = hazards.iter().filter(|hazard| stages.iter().any(|instr| hazard.involves(instr.address))).collect();
+        if self.pipeline {
+            self.draw_pipeline(d, emulator, if std::ptr::eq(emulator, main) { &active } else { &[] });
+        }
+        if self.source {
+            self.draw_source(d, mouse, main, &hazards, &active);
+        }
+        if self.timeline {
+            self.draw_timeline(d, history);
+        }
+    }
+
+    /// The source view sits on the left, between the register panel and the pipeline panel when
+    /// those are open. Returns its top edge and how many lines fit.
+    fn source_area(&self) -> (i32, i32) {
+        let top = if self.registers { frame_height(REGISTER_NAMES.len() as i32) } else { 0 };
+        let bottom = if self.pipeline { pipeline_top() } else { WINDOW_SIZE.1 - transport::BAR_HEIGHT };
+        (top, ((bottom - top - PADDING * 2) / LINE_HEIGHT - 1).max(1))
+    }
+
+    /// Index of the first line shown, keeping the line in EXECUTE near the middle.
+    fn first_source_line(&self, emulator: &EmulatorV2, rows: i32) -> usize {
+        let current = (emulator.execute_reg.source_line - 1).max(0) as usize;
+        let last_start = self.lines.len().saturating_sub(rows as usize);
+        current.saturating_sub(rows as usize / 2).min(last_start)
+    }
+
+    /// The line in EXECUTE is highlighted. Breakpoints are red dots in the gutter, and hovering
+    /// over the gutter of a line with an instruction shows where a click would put one. Lines
+    /// with a RAW hazard or in a branch's shadow are tagged, and while one of the two
+    /// instructions is in the pipeline both lines light up and the title says why.
+    fn draw_source(&self, d: &mut impl RaylibDraw, mouse: Vector2, emulator: &EmulatorV2, hazards: &[Hazard], active: &[&Hazard]) {
+        let (top, rows) = self.source_area();
+        let line_of = |address: i32| emulator.instructions.get(address as usize).filter(|_| !emulator.in_rom(address)).map(|instr| instr.source_line);
+        let title = match active.first() {
+            Some(hazard) => match (hazard.register, line_of(hazard.address), line_of(hazard.cause)) {
+                (Some(register), Some(line), Some(cause)) => format!("RAW: line {line} reads R{register} early from {cause}"),
+                (Some(register), Some(line), None) => format!("RAW: line {line} reads R{register} early"),
+                (None, Some(line), _) => format!("Shadow: line {line} never runs"),
+                _ => "Source (click a line number)".to_string(),
+            },
+            None => "Source (click a line number)".to_string(),
+        };
+        let (x, mut y) = draw_frame(d, 0, top, SOURCE_WIDTH, rows, &title);
+        let first = self.first_source_line(emulator, rows);
+        let hovered = self.gutter_line_at(mouse, emulator);
+        for (i, text) in self.lines.iter().enumerate().skip(first).take(rows as usize) {
+            let line = i as i32 + 1;
+            let executing = emulator.execute_reg.address >= 0
+                && !emulator.in_rom(emulator.execute_reg.address)
+                && emulator.execute_reg.source_line == line;
+            let address = line_address(emulator, line);
+            let dot = (x + GUTTER_WIDTH / 2 - PADDING / 2, y + FONT_SIZE / 2);
+            match address {
+                Some(address) if emulator.breakpoints.contains(&address) => d.draw_circle(dot.0, dot.1, 6.0, BREAKPOINT),
+                Some(_) if hovered == Some(line) => d.draw_circle(dot.0, dot.1, 6.0, BREAKPOINT_HINT),
+                _ => {}
+            }
+            let conflicting = active.iter().any(|hazard| [hazard.address, hazard.cause].into_iter().any(|address| line_of(address) == Some(line)));
+            if conflicting || executing {
+                let background = if conflicting { HAZARD_LINE } else { CURRENT_LINE };
+                d.draw_rectangle(x + GUTTER_WIDTH - 4, y - 2, SOURCE_WIDTH - GUTTER_WIDTH - PADDING, LINE_HEIGHT, background);
+            }
+            let tag = hazards.iter().find(|hazard| line_of(hazard.address) == Some(line)).map(|hazard| match hazard.register {
+                Some(register) => format!("RAW R{register}"),
+                None => "shadow".to_string(),
+            });
+            let right = SOURCE_WIDTH - PADDING;
+            let room = match &tag {
+                Some(tag) => {
+                    let tag_x = right - PADDING - measure_text(tag, FONT_SIZE);
+                    d.draw_text(tag, tag_x, y, FONT_SIZE, HAZARD);
+                    tag_x - PADDING
+                }
+                None => right,
+            };
+            let mut text = format!("{line:>3} {}", text.chars().take(SOURCE_COLUMNS).collect::<String>());
+            while measure_text(&text, FONT_SIZE) > room - x - GUTTER_WIDTH && !text.is_empty() {
+                text.pop();
+            }
+            let color = if executing { HIGHLIGHT } else { TEXT };
+            d.draw_text(&text, x + GUTTER_WIDTH, y, FONT_SIZE, color);
+            y += LINE_HEIGHT;
+        }
+    }
+
+    /// The bits of the ports that weren't 0 throughout, most significant first, across the
+    /// window and oldest on the left. Drawn above the pipeline panel when that is open.
+    fn draw_timeline(&self, d: &mut impl RaylibDraw, history: &PortHistory) {
+        let samples = ((WINDOW_SIZE.0 - PADDING * 2 - WAVE_LABEL_WIDTH) / WAVE_STEP) as usize;
+        let ports = history.active_ports(samples);
+        let bottom = if self.pipeline { pipeline_top() - STAGE_GAP } else { WINDOW_SIZE.1 - transport::BAR_HEIGHT };
+        let rows = (ports.len() as i32 * 8).max(1);
+        let top = (bottom - PADDING * 2 - LINE_HEIGHT - rows * WAVE_ROW).max(0);
+        d.draw_rectangle(0, top, WINDOW_SIZE.0, bottom - top, BACKGROUND);
+        let shown = samples.min(history.len());
+        d.draw_text(&format!("Timeline (last {shown} clocks)"), PADDING, top + PADDING, FONT_SIZE, TITLE);
+        let mut y = top + PADDING + LINE_HEIGHT;
+        if ports.is_empty() {
+            d.draw_text("Every port stayed 0.", PADDING, y, WAVE_FONT_SIZE, TEXT);
+            return;
+        }
+        for port in ports {
+            for bit in (0..8).rev() {
+                if y + WAVE_ROW > bottom {
+                    return;
+                }
+                d.draw_text(&format!("%{port}.{bit}"), PADDING, y, WAVE_FONT_SIZE, TEXT);
+                let (high, low) = (y + 1, y + WAVE_ROW - 2);
+                let mut x = PADDING + WAVE_LABEL_WIDTH;
+                let mut last = None;
+                for sample in history.recent(samples) {
+                    let level = if sample[port] & (1 << bit) != 0 { high } else { low };
+                    if last.is_some_and(|last| last != level) {
+                        d.draw_line(x, high, x, low, WAVE);
+                    }
+                    d.draw_line(x, level, x + WAVE_STEP, level, WAVE);
+                    last = Some(level);
+                    x += WAVE_STEP;
+                }
+                y += WAVE_ROW;
+            }
+        }
+    }
+
+    /// One box per stage along the bottom of the window, above the transport bar. After a clock
+    /// the instructions slide in from the stage they came from, and a taken branch turns the
+    /// bubble it left in DECODE red. The instructions in `active` hazards are outlined.
+    fn draw_pipeline(&self, d: &mut impl RaylibDraw, emulator: &EmulatorV2, active: &[&Hazard]) {
+        let gap = STAGE_GAP;
+        let width = (WINDOW_SIZE.0 - gap * 5) / 4;
+        let height = frame_height(1);
+        let y = pipeline_top();
+        let progress = self.clocked_at.map_or(1.0, |at| (at.elapsed().as_secs_f32() / SLIDE.as_secs_f32()).min(1.0));
+        let slide = ((1.0 - progress) * (width + gap) as f32) as i32;
+        let flushing = self.flushed_at.is_some_and(|at| at.elapsed() < FLUSH_FLASH);
+        let stages = [&emulator.fetch_reg, &emulator.decode_reg, &emulator.execute_reg, &emulator.writeback_reg];
+
+        for (i, instr) in stages.iter().enumerate() {
+            let x = gap + i as i32 * (width + gap);
+            let flushed = flushing && i == 1 && instr.address < 0;
+            d.draw_rectangle(x, y, width, height, if flushed { FLUSH } else { STAGE_BOX });
+            if active.iter().any(|hazard| hazard.involves(instr.address)) {
+                d.draw_rectangle_lines_ex(Rectangle::new(x as f32, y as f32, width as f32, height as f32), 2, HAZARD);
+            }
+            d.draw_text(STAGE_NAMES[i], x + PADDING, y + PADDING, FONT_SIZE, TITLE);
+            let text = if flushed {
+                "flushed".to_string()
+            } else if instr.address < 0 {
+                "-".to_string()
+            } else {
+                format!("{}: {}", instr.address, Disassembler::instruction(instr))
+            };
+            // Keep sliding text inside the window; FETCH's comes in from the left edge.
+            let text_x = (x + PADDING - slide).max(PADDING);
+            d.draw_text(&text, text_x, y + PADDING + LINE_HEIGHT, FONT_SIZE, TEXT);
+        }
+    }
+
+    /// Values the last clock changed are drawn in green. Under --turbo that is the last frame's
+    /// worth of clocks.
+    fn draw_registers(&self, d: &mut impl RaylibDraw) {
+        let (x, mut y) = draw_frame(d, 0, 0, 230, REGISTER_NAMES.len() as i32, "Registers");
+        for (i, name) in REGISTER_NAMES.iter().enumerate() {
+            let value = self.values[i];
+            let color = if value != self.before[i] { CHANGED } else { TEXT };
+            d.draw_text(&format!("{name:<4} {value:3}  0x{value:02X}"), x, y, FONT_SIZE, color);
+            y += LINE_HEIGHT;
+        }
+    }
+}
+
+/// Which panels are open and where they go, kept in `~/.electron_layout` between runs:
+///
+/// ```text
+/// split on
+/// panels registers source
+/// position 120 80
+/// ```
+#[derive(Default)]
+pub struct Layout {
+    /// D: the panels in their own half of a window twice as wide, instead of over the LEDs.
+    pub split: bool,
+    open: Vec<String>,
+    /// Where the window was on the screen.
+    pub position: Option<(i32, i32)>,
+}
+
+impl Layout {
+    const FILE: &'static str = ".electron_layout";
+
+    /// The saved layout, or the default one (nothing open, no split) if there is none. The file
+    /// is only ever written by `save`, so lines it doesn't understand are skipped.
+    pub fn load() -> Self {
+        let text = home_file(Self::FILE).and_then(|path| std::fs::read_to_string(path).ok()).unwrap_or_default();
+        let mut layout = Self::default();
+        for line in text.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["split", value] => layout.split = *value == "on",
+                ["panels", names @ ..] => layout.open = names.iter().map(|name| name.to_string()).collect(),
+                ["position", x, y] => layout.position = x.parse().ok().zip(y.parse().ok()),
+                _ => {}
+            }
+        }
+        layout
+    }
+
+    /// Saving is best effort: a layout that can't be written only means the next run starts
+    /// with the default one.
+    pub fn save(&self) {
+        let Some(path) = home_file(Self::FILE) else { return };
+        let mut text = format!("split {}\npanels {}\n", if self.split { "on" } else { "off" }, self.open.join(" "));
+        if let Some((x, y)) = self.position {
+            text.push_str(&format!("position {x} {y}\n"));
+        }
+        let _ = std::fs::write(path, text);
+    }
+
+    /// Opens the panels that were open when the layout was saved.
+    pub fn restore(&self, panels: &mut Panels) {
+        for (name, open) in panels.toggles() {
+            *open = self.open.iter().any(|saved| saved == name);
+        }
+    }
+
+    /// Notes which of `panels` are open, to be saved.
+    pub fn remember(&mut self, panels: &mut Panels) {
+        self.open = panels.toggles().into_iter().filter(|(_, open)| **open).map(|(name, _)| name.to_string()).collect();
+    }
+}
+
+/// The address of the instruction on source line `line`, if it has one. The panel shows the
+/// program, so the ROM's instructions don't count.
+fn line_address(emulator: &EmulatorV2, line: i32) -> Option<i32> {
+    emulator.instructions.iter().find(|instr| instr.source_line == line && !emulator.in_rom(instr.address)).map(|instr| instr.address)
+}
+
+fn register_values(emulator: &EmulatorV2) -> [i32; 11] {
+    let mut values = [0; 11];
+    for (i, value) in values.iter_mut().take(8).enumerate() {
+        *value = emulator.registers.read(i as i32) as i32;
+    }
+    values[8] = emulator.alu.accumulator as i32;
+    values[9] = emulator.pc;
+    values[10] = emulator.sp;
+    values
+}
+
+fn frame_height(lines: i32) -> i32 {
+    PADDING * 2 + LINE_HEIGHT * (lines + 1)
+}
+
+fn pipeline_top() -> i32 {
+    WINDOW_SIZE.1 - transport::BAR_HEIGHT - frame_height(1) - STAGE_GAP
+}
+
+/// A dark box with a title, returning where the first line of content goes.
+fn draw_frame(d: &mut impl RaylibDraw, x: i32, y: i32, width: i32, lines: i32, title: &str) -> (i32, i32) {
+    d.draw_rectangle(x, y, width, frame_height(lines), BACKGROUND);
+    d.draw_text(title, x + PADDING, y + PADDING, FONT_SIZE, TITLE);
+    (x + PADDING, y + PADDING + LINE_HEIGHT)
+}
+
+/// Every RAM byte in hex, decimal and binary, with the stack pointer marked. The stack grows
+/// down from #15 (unless `.stack` says otherwise) and SP points at the next free byte, so the
+/// bytes above it are the live stack.
+/// Under `--separate-stack` the stack isn't in RAM, so nothing is marked.
+fn draw_ram(d: &mut impl RaylibDraw, emulator: &EmulatorV2) {
+    let width = 330;
+    let (x, mut y) = draw_frame(d, WINDOW_SIZE.0 - width, 0, width, 16, "RAM   hex  dec  binary");
+    for (address, value) in emulator.ram.iter().enumerate() {
+        let at_sp = !emulator.separate_stack && emulator.sp == address as i32;
+        let marker = if at_sp { "< SP" } else { "" };
+        let line = format!("#{address:02}   {value:02X}  {value:3}  {value:08b} {marker}");
+        d.draw_text(&line, x, y, FONT_SIZE, if at_sp { HIGHLIGHT } else { TEXT });
+        y += LINE_HEIGHT;
+    }
+}
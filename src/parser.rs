@@ -1,12 +1,14 @@
 pub struct ProgramLoader;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Program {
     pub instructions: Vec<Instruction>,
 }
 
 impl ProgramLoader {
-    pub fn load_program(file_name: &str) -> Program {
+    /// Reads and compiles `file_name`, printing its messages, or exits on errors. Under `strict`
+    /// the warnings are errors too.
+    pub fn load_program(file_name: &str, strict: bool) -> Program {
         if file_name.is_empty() {
             println!("Error: No file name given. This argument is required: \"-f your_file_name\".");
             std::process::exit(1);
@@ -20,20 +22,10 @@ impl ProgramLoader {
         };
         let mut buffer = String::new();
         std::io::Read::read_to_string(&mut file, &mut buffer).unwrap();
-        let mut errors: Vec<CompileMessage> = vec![];
-        let mut warnings: Vec<CompileMessage> = vec![];
-        let mut instructions = vec![];
-        for (line_num, line) in buffer.lines().enumerate() {
-            if let Ok(instruction) = parse_line(line) {
-                instructions.push(instruction)
-            } else if let Err(error) = parse_line(line) {
-                errors.push(CompileMessage {
-                    line: line_num,
-                    message: error,
-                })
-            }
+        let (mut instructions, mut errors, mut warnings) = Self::compile(&buffer);
+        if strict {
+            errors.append(&mut warnings);
         }
-        add_warnings(&instructions, &mut warnings);
         for warning in warnings {
             println!("Warning on line {}: {}.", warning.line, warning.message);
         }
@@ -60,6 +52,41 @@ impl ProgramLoader {
             std::process::exit(1)
         }
     }
+
+    /// Reads and compiles `file_name` again for a running emulator: quiet, and returning the
+    /// first error instead of exiting. Under `strict` that may be a warning.
+    pub fn reload_program(file_name: &str, strict: bool) -> Result<Program, String> {
+        let source = std::fs::read_to_string(file_name).map_err(|e| format!("{file_name}: {e}"))?;
+        let (mut instructions, mut errors, warnings) = Self::compile(&source);
+        if strict {
+            errors.extend(warnings);
+        }
+        if let Some(error) = errors.first() {
+            return Err(format!("Error on line {}: {}.", error.line, error.message));
+        }
+        for _ in 0..32 - instructions.len().min(32) {
+            instructions.push(Instruction::none())
+        }
+        Ok(Program { instructions })
+    }
+
+    /// Parses `source` without printing anything. Message lines count from 0.
+    pub fn compile(source: &str) -> (Vec<Instruction>, Vec<CompileMessage>, Vec<CompileMessage>) {
+        let mut errors: Vec<CompileMessage> = vec![];
+        let mut warnings: Vec<CompileMessage> = vec![];
+        let mut instructions = vec![];
+        for (line_num, line) in source.lines().enumerate() {
+            match parse_line(line) {
+                Ok(instruction) => instructions.push(instruction),
+                Err(error) => errors.push(CompileMessage {
+                    line: line_num,
+                    message: error,
+                }),
+            }
+        }
+        add_warnings(&instructions, &mut warnings);
+        (instructions, errors, warnings)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -101,9 +128,9 @@ impl Operation {
     }
 }
 
-struct CompileMessage {
-    line: usize,
-    message: String,
+pub struct CompileMessage {
+    pub line: usize,
+    pub message: String,
 }
 
 impl Operation {
@@ -187,16 +214,18 @@ impl Oprand {
     }
 }
 
-fn operation_from_str(line: &Vec<String>) -> Result<(Operation, OperationArgs), String> {
+fn operation_from_str(line: &[String]) -> Result<(Operation, OperationArgs), String> {
     if line.is_empty() {
         return Ok((Operation::NOOP, OperationArgs::None));
     }
     let string = line.first().unwrap().clone();
     if let Ok(operation) = match_operation_name(&string) {
         Ok((operation, OperationArgs::None))
-    } else if let Ok(operation) = match_operation_name(string.get(1..).unwrap()) {
+    } else if let Ok(operation) = match_operation_name(string.get(1..).unwrap_or("")) {
         if operation.is_alu_operation() {
-            Ok((operation, operation_args_from_str(string).unwrap()))
+            let args = operation_args_from_str(string.clone())
+                .map_err(|_| format!("\"{string}\" has an unknown ALU prefix (use S, U or X)"))?;
+            Ok((operation, args))
         } else {
             Err(format!("\"{:?}\" does not take ALU arguments", operation))
         }
@@ -206,7 +235,7 @@ fn operation_from_str(line: &Vec<String>) -> Result<(Operation, OperationArgs),
 }
 
 fn operation_args_from_str(string: String) -> Result<OperationArgs, ()> {
-    match string.get(0..1).unwrap() {
+    match string.get(0..1).unwrap_or("") {
         // first character of word
         "U" => Ok(OperationArgs::U),
         "S" => Ok(OperationArgs::S),
@@ -233,35 +262,29 @@ fn match_operation_name(str: &str) -> Result<Operation, ()> {
 
 fn parse_oprand_binary(str: &str) -> Result<u8, String> {
     let string = str.to_string().replace('_', "");
-    if string.get(0..1).unwrap() == "B" {
-        let binary = string.get(1..).unwrap();
-        let mut total = 0;
-        for (place, char) in binary.chars().rev().enumerate() {
-            total += char.to_string().parse::<u8>().unwrap() * 2u8.pow(place as u32);
-        }
-        Ok(total)
+    if let Some(binary) = string.strip_prefix('B') {
+        u8::from_str_radix(binary, 2).map_err(|_| format!("\"{str}\" is not a valid binary number"))
     } else {
-        Ok(string.parse().unwrap())
+        string.parse().map_err(|_| format!("\"{str}\" is not a valid number"))
     }
 }
 
 fn parse_oprand(oprand: &str) -> Result<Oprand, String> {
-    let prefix = oprand.get(0..1);
+    let prefix = oprand.get(0..1).unwrap_or("");
+    let rest = oprand.get(1..).unwrap_or("");
     if let Ok(a) = oprand.parse() {
         Ok(Oprand::Immediate(a))
-    } else if prefix.unwrap() == "R" {
-        Ok(Oprand::Register(parse_oprand_binary(oprand.get(1..).unwrap()).unwrap()))
-    } else if prefix.unwrap() == "#" {
-        Ok(Oprand::MemoryAddress(
-            parse_oprand_binary(oprand.get(1..).unwrap()).unwrap(),
-        ))
-    } else if prefix.unwrap() == "%" {
-        Ok(Oprand::Port(parse_oprand_binary(oprand.get(1..).unwrap()).unwrap()))
+    } else if prefix == "R" {
+        Ok(Oprand::Register(parse_oprand_binary(rest)?))
+    } else if prefix == "#" {
+        Ok(Oprand::MemoryAddress(parse_oprand_binary(rest)?))
+    } else if prefix == "%" {
+        Ok(Oprand::Port(parse_oprand_binary(rest)?))
     } else if let Ok(a) = parse_oprand_binary(oprand) {
         Ok(Oprand::Immediate(a))
     }
     else {
-        return Err(format!("\"{oprand}\" is not a valid oprand"));
+        Err(format!("\"{oprand}\" is not a valid oprand"))
     }
 }
 
@@ -277,13 +300,13 @@ fn parse_line(line: &str) -> Result<Instruction, String> {
         (
             if operation.needed_oprands(&operation_args).0.is_some() {
                 words.remove(0);
-                parse_oprand(words.first().unwrap())?
+                parse_oprand(words.first().ok_or(format!("{:?} is missing oprand A", operation))?)?
             } else {
                 Oprand::Immediate(0)
             },
             if operation.needed_oprands(&operation_args).1.is_some() {
                 words.remove(0);
-                parse_oprand(words.first().unwrap())?
+                parse_oprand(words.first().ok_or(format!("{:?} is missing oprand B", operation))?)?
             } else {
                 Oprand::Immediate(0)
             },
@@ -297,7 +320,7 @@ fn parse_line(line: &str) -> Result<Instruction, String> {
     })
 }
 
-fn add_warnings(instructions: &Vec<Instruction>, warnings: &mut Vec<CompileMessage>) {
+fn add_warnings(instructions: &[Instruction], warnings: &mut Vec<CompileMessage>) {
     for (line_num, instruction) in instructions.iter().enumerate() {
         let needed_oprands = instruction
             .operation
@@ -0,0 +1,137 @@
+//! The window `electron run` opens when it's given no program, as when the binary is started by
+//! double-clicking it: the programs run lately and those in the working directory, to pick one
+//! from, or drop one on.
+
+#[cfg(feature = "gui")]
+pub use gui::pick;
+
+/// What a program file is called: `.elt`, or `.asm` as older examples are.
+const EXTENSIONS: [&str; 2] = ["elt", "asm"];
+
+/// The programs in `dir`, by name.
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+fn programs(dir: &std::path::Path) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|error| format!("{}: {error}", dir.display()))?;
+    let mut files: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known))))
+        .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .collect();
+    files.sort_by_key(|name| name.to_lowercase());
+    Ok(files)
+}
+
+/// The recent programs, as full paths, then those in `dir` that aren't among them.
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+fn listing(dir: &std::path::Path) -> Result<Vec<String>, String> {
+    let mut files = crate::recent::load();
+    let recent = files.len();
+    for name in programs(dir)? {
+        let path = std::fs::canonicalize(dir.join(&name)).map(|path| path.to_string_lossy().into_owned());
+        if !files[..recent].iter().any(|known| path.as_ref().is_ok_and(|path| path == known)) {
+            files.push(name);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(feature = "gui")]
+mod gui {
+    use super::listing;
+    use crate::recent;
+    use crate::window::monitor_scale;
+    use crate::WINDOW_SIZE;
+    use raylib::prelude::*;
+
+    const TITLE_SIZE: i32 = 30;
+    const FONT_SIZE: i32 = 20;
+    const ROW_HEIGHT: i32 = 30;
+    const PADDING: i32 = 24;
+    const LIST_TOP: i32 = PADDING * 2 + TITLE_SIZE + FONT_SIZE;
+    const BACKGROUND: Color = Color::new(10, 10, 20, 255);
+    const TITLE: Color = Color::new(140, 170, 255, 255);
+    const TEXT: Color = Color::new(230, 230, 230, 255);
+    const HINT: Color = Color::new(140, 140, 150, 255);
+    const SELECTED: Color = Color::new(90, 70, 20, 255);
+
+    /// Lists the programs in the working directory until one is clicked, chosen with the arrow
+    /// keys and Enter, or dropped on the window. None when the window is closed (or Escape
+    /// pressed) without one.
+    pub fn pick(title: &str) -> Result<Option<String>, String> {
+        let dir = std::env::current_dir().map_err(|error| format!("The working directory: {error}"))?;
+        let files = listing(&dir)?;
+        let (mut rl, thread) = raylib::init().width(WINDOW_SIZE.0).height(WINDOW_SIZE.1).title(title).build();
+        rl.set_target_fps(60);
+        let scale = monitor_scale(&rl);
+        rl.set_window_size((WINDOW_SIZE.0 as f32 * scale) as i32, (WINDOW_SIZE.1 as f32 * scale) as i32);
+        rl.set_mouse_scale(1.0 / scale, 1.0 / scale);
+        let view = Camera2D { offset: Vector2::zero(), target: Vector2::zero(), rotation: 0.0, zoom: scale };
+        let rows = ((WINDOW_SIZE.1 - LIST_TOP - PADDING) / ROW_HEIGHT) as usize;
+        let (mut selected, mut first) = (0, 0);
+        // The pointer only moves the selection when it moves, so it doesn't undo the arrow keys.
+        let mut last_mouse = Vector2::zero();
+        let folder = dir.display().to_string();
+
+        while !rl.window_should_close() {
+            if rl.is_file_dropped() {
+                let dropped = rl.get_dropped_files().into_iter().next();
+                rl.clear_dropped_files();
+                if dropped.is_some() {
+                    return Ok(dropped);
+                }
+            }
+            if !files.is_empty() {
+                let mouse = rl.get_mouse_position();
+                let hovered = (mouse.y as i32 - LIST_TOP).div_euclid(ROW_HEIGHT);
+                let hovered = (0..rows as i32).contains(&hovered).then(|| first + hovered as usize).filter(|row| *row < files.len());
+                if mouse != last_mouse {
+                    selected = hovered.unwrap_or(selected);
+                    last_mouse = mouse;
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+                    selected = (selected + 1).min(files.len() - 1);
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+                    selected = selected.saturating_sub(1);
+                }
+                let clicked = hovered.filter(|_| rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON));
+                if let Some(row) = clicked.or(rl.is_key_pressed(KeyboardKey::KEY_ENTER).then_some(selected)) {
+                    return Ok(Some(files[row].clone()));
+                }
+                let wheel = rl.get_mouse_wheel_move();
+                if wheel != 0.0 {
+                    let last_first = files.len().saturating_sub(rows);
+                    first = if wheel > 0.0 { first.saturating_sub(1) } else { (first + 1).min(last_first) };
+                    selected = selected.clamp(first, first + rows - 1);
+                }
+                // Keep the selection in view as the arrow keys move it.
+                first = first.min(selected).max((selected + 1).saturating_sub(rows));
+            }
+
+            let mut d = rl.begin_drawing(&thread);
+            d.clear_background(BACKGROUND);
+            let mut d = d.begin_mode2D(view);
+            d.draw_text("Open a program", PADDING, PADDING, TITLE_SIZE, TITLE);
+            d.draw_text(&folder, PADDING, PADDING + TITLE_SIZE + 8, FONT_SIZE, HINT);
+            if files.is_empty() {
+                d.draw_text("There are no .elt or .asm programs here.", PADDING, LIST_TOP, FONT_SIZE, TEXT);
+                d.draw_text("Drop one on this window, or run electron -f <file>.", PADDING, LIST_TOP + ROW_HEIGHT, FONT_SIZE, HINT);
+                continue;
+            }
+            for (row, name) in files.iter().enumerate().skip(first).take(rows) {
+                let y = LIST_TOP + (row - first) as i32 * ROW_HEIGHT;
+                if row == selected {
+                    d.draw_rectangle(PADDING / 2, y - 4, WINDOW_SIZE.0 - PADDING, ROW_HEIGHT, SELECTED);
+                }
+                d.draw_text(&recent::shorten(name, 56), PADDING, y, FONT_SIZE, TEXT);
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(not(feature = "gui"))]
+pub fn pick(_title: &str) -> Result<Option<String>, String> {
+    Err("No file name given. Use -f <filename> (this build has no window to pick one in).".to_string())
+}
@@ -0,0 +1,104 @@
+//! The assembler's errors and warnings, listed over the bottom of the LEDs. They'd otherwise
+//! only reach stdout, which a window started with `-nt` or by double-clicking may not have.
+//! The list opens when a program loaded with warnings or failed to, and W shows or hides it.
+
+use crate::transport;
+use crate::WINDOW_SIZE;
+use raylib::prelude::*;
+
+const FONT_SIZE: i32 = 18;
+const LINE_HEIGHT: i32 = 22;
+const PADDING: i32 = 12;
+/// Rows of messages shown at once; the mouse wheel scrolls through the rest.
+const ROWS: usize = 8;
+const BACKGROUND: Color = Color::new(10, 10, 20, 235);
+const TITLE: Color = Color::new(140, 170, 255, 255);
+const ERROR: Color = Color::new(240, 80, 80, 255);
+const WARNING: Color = Color::new(240, 190, 60, 255);
+const HINT: Color = Color::new(140, 140, 150, 255);
+
+#[derive(Default)]
+pub struct Problems {
+    errors: usize,
+    warnings: usize,
+    /// The messages cut into rows that fit, each with whether it's part of an error.
+    rows: Vec<(String, bool)>,
+    first: usize,
+    pub open: bool,
+}
+
+impl Problems {
+    /// Lists the messages from the last load, opening the list when there are any and closing
+    /// it when there are none.
+    pub fn set(&mut self, errors: &[String], warnings: &[String]) {
+        self.errors = errors.len();
+        self.warnings = warnings.len();
+        self.rows.clear();
+        for (messages, error) in [(errors, true), (warnings, false)] {
+            for message in messages {
+                let label = if error { "Error" } else { "Warning" };
+                self.rows.extend(wrap(&format!("{label}: {message}"), WINDOW_SIZE.0 - PADDING * 2).into_iter().map(|row| (row, error)));
+            }
+        }
+        self.first = 0;
+        self.open = !self.rows.is_empty();
+    }
+
+    /// W shows or hides the list, and the mouse wheel over it scrolls. `mouse` is the pointer
+    /// on the canvas.
+    pub fn handle_input(&mut self, rl: &RaylibHandle, mouse: Vector2) {
+        if rl.is_key_pressed(KeyboardKey::KEY_W) {
+            self.open = !self.open && !self.rows.is_empty();
+        }
+        let wheel = rl.get_mouse_wheel_move();
+        if self.open && wheel != 0.0 && mouse.y as i32 >= self.top() {
+            let last_first = self.rows.len().saturating_sub(ROWS);
+            self.first = if wheel > 0.0 { self.first.saturating_sub(1) } else { (self.first + 1).min(last_first) };
+        }
+    }
+
+    fn top(&self) -> i32 {
+        let shown = self.rows.len().min(ROWS) as i32;
+        WINDOW_SIZE.1 - transport::BAR_HEIGHT - PADDING * 2 - LINE_HEIGHT * (shown + 1)
+    }
+
+    /// A box along the bottom of the canvas, above the transport bar: the counts, then the
+    /// errors in red and the warnings in yellow.
+    pub fn draw(&self, d: &mut impl RaylibDraw) {
+        if !self.open {
+            return;
+        }
+        let top = self.top();
+        d.draw_rectangle(0, top, WINDOW_SIZE.0, WINDOW_SIZE.1 - transport::BAR_HEIGHT - top, BACKGROUND);
+        let title = format!("Errors: {}  Warnings: {}", self.errors, self.warnings);
+        d.draw_text(&title, PADDING, top + PADDING, FONT_SIZE, TITLE);
+        let hint = match (self.errors > 0, self.rows.len() > ROWS) {
+            (true, true) => "F5 reloads once fixed. Scroll for more, W hides",
+            (true, false) => "F5 reloads once fixed. W hides",
+            (false, true) => "Scroll for more, W hides",
+            (false, false) => "W hides",
+        };
+        d.draw_text(hint, WINDOW_SIZE.0 - PADDING - measure_text(hint, FONT_SIZE), top + PADDING, FONT_SIZE, HINT);
+        for (i, (row, error)) in self.rows.iter().skip(self.first).take(ROWS).enumerate() {
+            let y = top + PADDING + LINE_HEIGHT * (i as i32 + 1);
+            d.draw_text(row, PADDING, y, FONT_SIZE, if *error { ERROR } else { WARNING });
+        }
+    }
+}
+
+/// `text` broken between words into rows no wider than `width` pixels, the rows after the
+/// first indented.
+fn wrap(text: &str, width: i32) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    for word in text.split_whitespace() {
+        let joined = if row.is_empty() { word.to_string() } else { format!("{row} {word}") };
+        if measure_text(&joined, FONT_SIZE) > width && !row.trim().is_empty() {
+            rows.push(std::mem::replace(&mut row, format!("    {word}")));
+        } else {
+            row = joined;
+        }
+    }
+    rows.push(row);
+    rows
+}
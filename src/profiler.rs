@@ -0,0 +1,180 @@
+//! `--profile`: counts how often each instruction address reaches the execute stage and how long
+//! the program sat waiting on `INP`, then prints the hottest instructions first.
+//!
+//! `--timing`: sorts every clock by what the execute stage did with it (real work, a `NOOP`, the
+//! bubble a taken branch leaves, an interlock stall, or nothing while the pipeline fills or after
+//! the program ran off its end), in total and per label.
+
+use crate::electron_2::{Emulator as EmulatorV2, Operation};
+use std::collections::HashMap;
+
+/// What the execute stage spent a clock on, for `--timing`.
+#[derive(Clone, Copy)]
+enum Slot {
+    Work,
+    Noop,
+    Flush,
+    Stall,
+    Idle,
+}
+
+const SLOTS: usize = 5;
+const SLOT_NAMES: [&str; SLOTS] = ["executing", "NOOPs", "flush bubbles", "stalls", "idle"];
+
+#[derive(Default)]
+pub struct Profiler {
+    executed: HashMap<i32, u64>,
+    clocks: u64,
+    stalled: u64,
+    /// Print the hot spots (`--profile`) and the timing breakdown (`--timing`).
+    pub hot_spots: bool,
+    pub timing: bool,
+    /// Clocks by slot, for the instruction they belong to (-1 for none).
+    slots: HashMap<i32, [u64; SLOTS]>,
+    /// The counters as of the last clock, to see what this one did.
+    branches: u64,
+    stalls: u64,
+    dma: u64,
+    /// The clock on which the bubble from the last taken branch reaches execute.
+    flush_due: Option<u64>,
+}
+
+impl Profiler {
+    pub fn new(hot_spots: bool, timing: bool) -> Self {
+        Self { hot_spots, timing, ..Self::default() }
+    }
+
+    /// Call after every clock; counts the instruction that just went through execute.
+    pub fn record(&mut self, emulator: &EmulatorV2) {
+        self.clocks += 1;
+        let stats = &emulator.stats;
+        // While the DMA engine has the bus, the instruction in execute is only waiting there.
+        let stolen = stats.dma > self.dma;
+        let address = emulator.execute_reg.address;
+        if address >= 0 && !stolen {
+            *self.executed.entry(address).or_insert(0) += 1;
+        }
+
+        // A branch empties fetch before decode copies it, so the bubble executes next clock.
+        let stalled = stats.stalls > self.stalls;
+        if stats.branches > self.branches {
+            self.flush_due = Some(self.clocks + 1);
+        }
+        (self.branches, self.stalls, self.dma) = (stats.branches, stats.stalls, stats.dma);
+        let slot = if stolen {
+            Slot::Stall
+        } else if address >= 0 && emulator.execute_reg.operation == Operation::NOOP {
+            Slot::Noop
+        } else if address >= 0 {
+            Slot::Work
+        } else if stalled {
+            Slot::Stall
+        } else if self.flush_due == Some(self.clocks) {
+            Slot::Flush
+        } else {
+            Slot::Idle
+        };
+        // A bubble is charged to the instruction waiting behind it.
+        let owner = if address >= 0 { address } else { emulator.decode_reg.address };
+        self.slots.entry(owner).or_insert([0; SLOTS])[slot as usize] += 1;
+    }
+
+    /// Call for every clock period spent parked on `INP`.
+    pub fn record_stall(&mut self) {
+        self.stalled += 1;
+    }
+
+    pub fn report(&self, emulator: &EmulatorV2, code: &str) -> String {
+        let mut out = String::new();
+        if self.hot_spots {
+            out.push_str(&self.hot_spot_report(emulator, code));
+        }
+        if self.timing {
+            out.push_str(&self.timing_report(emulator));
+        }
+        out
+    }
+
+    fn hot_spot_report(&self, emulator: &EmulatorV2, code: &str) -> String {
+        let total: u64 = self.executed.values().sum();
+        let mut rows: Vec<(&i32, &u64)> = self.executed.iter().collect();
+        rows.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        let lines: Vec<&str> = code.lines().collect();
+        let mut out = format!(
+            "Profile: {} clocks, {} instructions executed, {} clocks stalled waiting for INP\n",
+            self.clocks, total, self.stalled
+        );
+        out.push_str("   count      %  addr  line  source\n");
+        for (address, count) in rows {
+            let line = emulator
+                .instructions
+                .get(*address as usize)
+                .map(|instr| instr.source_line)
+                .unwrap_or(0);
+            // The ROM's lines aren't in `code`; the source map has them.
+            let source = match emulator.source_at(*address) {
+                Some(source) if emulator.in_rom(*address) => source.text.as_str(),
+                _ => lines.get((line as usize).wrapping_sub(1)).map(|l| l.trim()).unwrap_or(""),
+            };
+            out.push_str(&format!(
+                "{:>8} {:>5.1}%  {:>4}  {:>4}  {}\n",
+                count,
+                *count as f64 * 100.0 / total as f64,
+                address,
+                line,
+                source
+            ));
+        }
+        out
+    }
+
+    fn timing_report(&self, emulator: &EmulatorV2) -> String {
+        let mut totals = [0; SLOTS];
+        for counts in self.slots.values() {
+            for (total, count) in totals.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+        let retired = emulator.stats.retired;
+        let cpi = if retired == 0 { 0.0 } else { self.clocks as f64 / retired as f64 };
+        let mut out = format!("Timing: {} clocks, {} instructions retired, CPI {:.2}\n", self.clocks, retired, cpi);
+        for (name, count) in SLOT_NAMES.iter().zip(totals) {
+            let percent = if self.clocks == 0 { 0.0 } else { count as f64 * 100.0 / self.clocks as f64 };
+            out.push_str(&format!("  {:<14} {:>8} {:>5.1}%\n", name, count, percent));
+        }
+
+        // Each label's region runs from it to the next label.
+        let mut labels: Vec<(i32, &str)> = emulator.labels.iter().map(|(name, address)| (*address, name.as_str())).collect();
+        labels.sort();
+        if labels.is_empty() {
+            return out;
+        }
+        let mut regions: Vec<(&str, [u64; SLOTS])> = Vec::new();
+        let mut addresses: Vec<&i32> = self.slots.keys().collect();
+        addresses.sort();
+        for address in addresses {
+            let name = match labels.iter().rev().find(|(start, _)| start <= address) {
+                _ if *address < 0 => "(nothing)",
+                Some((_, name)) => name,
+                None => "(start)",
+            };
+            if regions.last().is_none_or(|(last, _)| *last != name) {
+                regions.push((name, [0; SLOTS]));
+            }
+            let region = &mut regions.last_mut().unwrap().1;
+            for (total, count) in region.iter_mut().zip(self.slots[address]) {
+                *total += count;
+            }
+        }
+        out.push_str("  region            clocks     work    NOOPs  flushes   stalls\n");
+        for (name, counts) in regions {
+            let clocks: u64 = counts.iter().sum();
+            out.push_str(&format!(
+                "  {:<14} {:>9} {:>8} {:>8} {:>8} {:>8}\n",
+                name, clocks, counts[Slot::Work as usize], counts[Slot::Noop as usize], counts[Slot::Flush as usize], counts[Slot::Stall as usize]
+            ));
+        }
+        out
+    }
+}
@@ -0,0 +1,78 @@
+//! O: the quick-open menu over the window, listing the recent programs by number. A number key or
+//! a click loads one in place of the running program, and O again closes the menu.
+
+use crate::recent;
+use crate::WINDOW_SIZE;
+use raylib::prelude::*;
+
+const FONT_SIZE: i32 = 20;
+const ROW_HEIGHT: i32 = 30;
+const PADDING: i32 = 16;
+const WIDTH: i32 = 640;
+/// Characters of a path that fit in a row beside its number.
+const PATH_COLUMNS: usize = 52;
+const BACKGROUND: Color = Color::new(10, 10, 20, 240);
+const TITLE: Color = Color::new(140, 170, 255, 255);
+const TEXT: Color = Color::new(230, 230, 230, 255);
+const HOVER: Color = Color::new(90, 70, 20, 255);
+const NUMBER_KEYS: [KeyboardKey; recent::MAX_RECENT] = [
+    KeyboardKey::KEY_ONE,
+    KeyboardKey::KEY_TWO,
+    KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR,
+    KeyboardKey::KEY_FIVE,
+    KeyboardKey::KEY_SIX,
+    KeyboardKey::KEY_SEVEN,
+    KeyboardKey::KEY_EIGHT,
+    KeyboardKey::KEY_NINE,
+];
+
+pub struct QuickOpen {
+    files: Vec<String>,
+}
+
+impl QuickOpen {
+    /// The menu as the recent list is now.
+    pub fn new() -> Self {
+        Self { files: recent::load() }
+    }
+
+    /// The program picked this frame, by its number or a click on it.
+    pub fn picked(&self, rl: &RaylibHandle) -> Option<String> {
+        let clicked = rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON).then(|| rl.get_mouse_position());
+        self.files.iter().enumerate().find_map(|(i, file)| {
+            let hit = clicked.is_some_and(|mouse| self.row(i).check_collision_point_rec(mouse));
+            (hit || rl.is_key_pressed(NUMBER_KEYS[i])).then(|| file.clone())
+        })
+    }
+
+    fn top(&self) -> i32 {
+        let height = ROW_HEIGHT * (self.files.len().max(1) as i32 + 1) + PADDING * 2;
+        (WINDOW_SIZE.1 - height) / 2
+    }
+
+    fn row(&self, index: usize) -> Rectangle {
+        let y = self.top() + PADDING + ROW_HEIGHT * (index as i32 + 1);
+        Rectangle::new(((WINDOW_SIZE.0 - WIDTH) / 2) as f32, (y - 4) as f32, WIDTH as f32, ROW_HEIGHT as f32)
+    }
+
+    /// A box in the middle of the window, the row under the pointer highlighted. `mouse` is the
+    /// pointer on the canvas.
+    pub fn draw(&self, d: &mut impl RaylibDraw, mouse: Vector2) {
+        let (left, top) = ((WINDOW_SIZE.0 - WIDTH) / 2, self.top());
+        let height = ROW_HEIGHT * (self.files.len().max(1) as i32 + 1) + PADDING * 2;
+        d.draw_rectangle(left, top, WIDTH, height, BACKGROUND);
+        d.draw_text("Recent programs (O closes)", left + PADDING, top + PADDING, FONT_SIZE, TITLE);
+        if self.files.is_empty() {
+            d.draw_text("None yet: the programs you run are listed here.", left + PADDING, top + PADDING + ROW_HEIGHT, FONT_SIZE, TEXT);
+        }
+        for (i, file) in self.files.iter().enumerate() {
+            let row = self.row(i);
+            if row.check_collision_point_rec(mouse) {
+                d.draw_rectangle_rec(row, HOVER);
+            }
+            let text = format!("{}  {}", i + 1, recent::shorten(file, PATH_COLUMNS));
+            d.draw_text(&text, left + PADDING, row.y as i32 + 4, FONT_SIZE, TEXT);
+        }
+    }
+}
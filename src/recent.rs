@@ -0,0 +1,37 @@
+//! The programs run lately, newest first, kept in `~/.electron_recent`: O in the window and on
+//! the dashboard lists them to load one, and the program picker puts them at the top.
+
+use crate::line_editor::home_file;
+use std::path::Path;
+
+/// As many as the number keys reach.
+pub const MAX_RECENT: usize = 9;
+const FILE: &str = ".electron_recent";
+
+/// The recent programs as absolute paths, leaving out any since moved or deleted.
+pub fn load() -> Vec<String> {
+    let text = home_file(FILE).and_then(|path| std::fs::read_to_string(path).ok()).unwrap_or_default();
+    text.lines().filter(|line| Path::new(line).is_file()).take(MAX_RECENT).map(String::from).collect()
+}
+
+/// Puts `file` at the top of the list. Saving is best effort: a list that can't be written only
+/// means it's shorter next run.
+pub fn remember(file: &str) {
+    let (Ok(path), Some(saved)) = (std::fs::canonicalize(file), home_file(FILE)) else { return };
+    let path = path.to_string_lossy().into_owned();
+    let mut files = load();
+    files.retain(|known| *known != path);
+    files.insert(0, path);
+    files.truncate(MAX_RECENT);
+    let _ = std::fs::write(saved, files.join("\n") + "\n");
+}
+
+/// `path` cut to its last `columns` characters, so the file name stays in view.
+pub fn shorten(path: &str, columns: usize) -> String {
+    let length = path.chars().count();
+    if length <= columns {
+        return path.to_string();
+    }
+    let tail: String = path.chars().skip(length + 3 - columns).collect();
+    format!("...{tail}")
+}
@@ -0,0 +1,35 @@
+//! Programs that once panicked the V2 assembler or emulator. Each is loaded and clocked as the
+//! `run` fuzz target does (see `fuzz/`), with the debug build's overflow checks on.
+
+use crate::electron_2::Emulator as EmulatorV2;
+
+/// Loads `source` and clocks it for as long as the fuzz target does, answering every `INP`.
+fn run(source: &str) -> EmulatorV2 {
+    let mut v2 = EmulatorV2::new(source.to_string());
+    for i in 0..256 {
+        if v2.waiting_for_input {
+            v2.resolve_input(i);
+        }
+        v2.clock();
+    }
+    v2
+}
+
+#[test]
+fn branch_past_the_pc_range_is_an_error() {
+    for source in ["JMP 2147483647", "CALL 255", "BIE -1"] {
+        let v2 = run(source);
+        assert!(v2.errors.iter().any(|e| e.contains("[E002] Branch target")), "{source}: {:?}", v2.errors);
+    }
+    assert!(run("JMP 254").errors.is_empty());
+}
+
+#[test]
+fn extreme_stack_offsets_wrap() {
+    // 2147483647 is 15 more than a multiple of 16, and -2147483648 a multiple of it.
+    let wrapped = run("IMM R1 7\nPUSH R1\nIMM R2 9\nPUSH R2\nNOOP\nNOOP\nLOADS R3 2147483647\nSTORES R1 -2147483648\nNOOP\nHLT");
+    let direct = run("IMM R1 7\nPUSH R1\nIMM R2 9\nPUSH R2\nNOOP\nNOOP\nLOADS R3 15\nSTORES R1 0\nNOOP\nHLT");
+    assert!(wrapped.fault.is_none() && wrapped.halted, "{:?}", wrapped.fault);
+    assert_eq!(wrapped.registers.read(3), direct.registers.read(3));
+    assert_eq!(wrapped.ram, direct.ram);
+}
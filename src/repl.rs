@@ -0,0 +1,149 @@
+//! `electron repl`: type V2 instructions and watch them execute one at a time against a live
+//! machine. Each instruction runs through the whole pipeline on its own before the next prompt, so
+//! there are no hazards to think about, and the state it changed is printed afterwards.
+
+use crate::debugger::{print_ports, print_ram, print_registers};
+use crate::electron_2::parser::{split_location, Parser};
+use crate::electron_2::{Emulator as EmulatorV2, Instruction, Snapshot};
+use crate::line_editor::{home_file, LineEditor};
+use std::collections::HashMap;
+
+const HELP: &str = "\
+Type an instruction (e.g. `IMM R1 5`, `ADD R1 R2`, `OUT %0 R1`) to execute it.
+Commands:
+  regs | ram | ports   show part of the machine state
+  reset                clear registers, RAM, ports and flags
+  help                 this text
+  quit                 leave the REPL
+Branches, CALL and RET only move the PC, since there is no program to jump around in.";
+
+pub struct Repl {
+    emulator: EmulatorV2,
+    editor: LineEditor,
+}
+
+impl Repl {
+    pub fn new(emulator: EmulatorV2) -> Self {
+        Self { emulator, editor: LineEditor::new(home_file(".electron_repl_history")) }
+    }
+
+    pub fn run(&mut self) {
+        println!("Electron 2 REPL. Type `help` for commands.");
+        while let Some(line) = self.editor.read_line("electron> ") {
+            self.editor.add_history(&line);
+            match line.trim().to_lowercase().as_str() {
+                "" => {}
+                "help" | "?" => println!("{HELP}"),
+                "quit" | "exit" | "q" => break,
+                "regs" => print_registers(&self.emulator),
+                "ram" => print_ram(&self.emulator),
+                "ports" => print_ports(&self.emulator),
+                "reset" => {
+                    let _ = self.emulator.load_program(String::new());
+                    println!("Machine reset.");
+                }
+                _ => match Parser::parse_line(&line, 0, 1, &HashMap::new(), false) {
+                    Ok(Some(instr)) => {
+                        for warning in Parser::check_warnings(&instr, 1) {
+                            println!("Warning: {}", split_location(&warning).2);
+                        }
+                        let before = self.emulator.snapshot();
+                        if self.execute(instr) {
+                            print_changes(&before, &self.emulator.snapshot());
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => println!("Error: {e}"),
+                },
+            }
+        }
+    }
+
+    /// Runs one instruction from fetch to writeback at the current PC, then moves the PC to the
+    /// next instruction or the branch target. Returns false if it was abandoned at an `INP` prompt.
+    fn execute(&mut self, instr: Instruction) -> bool {
+        let pc = self.emulator.pc;
+        let branches = self.emulator.stats.branches;
+        let mut program = vec![Instruction::none(); pc as usize];
+        program.push(Instruction { address: pc, ..instr });
+        self.emulator.instructions = program;
+        self.emulator.fetch_reg = Instruction::none();
+        self.emulator.decode_reg = Instruction::none();
+
+        // Fetch, decode, execute, writeback.
+        let mut stages = 0;
+        while stages < 4 {
+            if self.emulator.waiting_for_input {
+                let Some(value) = self.editor.read_line("INP value (0-255)> ") else { return false };
+                match value.trim().parse::<u8>() {
+                    Ok(value) => self.emulator.resolve_input(value as i32),
+                    Err(_) => println!("Error: \"{}\" is not a value between 0 and 255.", value.trim()),
+                }
+                continue;
+            }
+            self.emulator.clock();
+            stages += 1;
+            if self.emulator.halted {
+                // Nothing to stop here; just say so and keep the machine usable.
+                println!("HLT: a program would stop here.");
+                self.emulator.halted = false;
+                break;
+            }
+        }
+        self.emulator.instructions.clear();
+        self.emulator.pc = if self.emulator.stats.branches > branches {
+            // Branch targets (and RET's return address) travel in operand A.
+            self.emulator.writeback_reg.a.data
+        } else if pc + 1 >= 255 {
+            0
+        } else {
+            pc + 1
+        };
+        true
+    }
+}
+
+/// Prints every piece of architectural state that differs, e.g. `R1: 0 -> 5`.
+fn print_changes(before: &Snapshot, after: &Snapshot) {
+    let mut changes = Vec::new();
+    for i in 1..8 {
+        if before.registers[i] != after.registers[i] {
+            changes.push(format!("R{i}: {} -> {}", before.registers[i], after.registers[i]));
+        }
+    }
+    if before.accumulator != after.accumulator {
+        changes.push(format!("ACC: {} -> {}", before.accumulator, after.accumulator));
+    }
+    let flags = [
+        ("Equals", before.flags.equals, after.flags.equals),
+        ("Greater", before.flags.greater, after.flags.greater),
+        ("Less", before.flags.less, after.flags.less),
+        ("Overflow", before.flags.overflow, after.flags.overflow),
+    ];
+    for (name, old, new) in flags {
+        if old != new {
+            changes.push(format!("{name}: {old} -> {new}"));
+        }
+    }
+    for i in 0..16 {
+        if before.ram[i] != after.ram[i] {
+            changes.push(format!("#{i}: {} -> {}", before.ram[i], after.ram[i]));
+        }
+    }
+    for i in 0..8 {
+        if before.ports_out[i] != after.ports_out[i] {
+            changes.push(format!("%{i}: {} -> {}", before.ports_out[i], after.ports_out[i]));
+        }
+    }
+    if before.sp != after.sp {
+        changes.push(format!("SP: {} -> {}", before.sp, after.sp));
+    }
+    if before.pc != after.pc {
+        changes.push(format!("PC: {} -> {}", before.pc, after.pc));
+    }
+    if changes.is_empty() {
+        println!("(no change)");
+    } else {
+        println!("{}", changes.join(", "));
+    }
+}
@@ -0,0 +1,38 @@
+//! `--record` and `--replay`: the files that carry a V2 run's outside inputs. Each line is one
+//! `InputEvent` (`inp <cycle> <value>` or `port <cycle> <port> <value>`); `;` starts a comment.
+
+use crate::electron_2::{Emulator as EmulatorV2, InputEvent};
+
+/// Sets `emulator` up to replay `replay_file` and/or record into a new log.
+pub fn prepare(emulator: &mut EmulatorV2, replay_file: Option<&str>, record: bool) -> Result<(), String> {
+    if let Some(path) = replay_file {
+        emulator.replay = Some(load(path)?);
+        emulator.replay_pos = 0;
+    }
+    if record {
+        emulator.recording = Some(Vec::new());
+    }
+    Ok(())
+}
+
+pub fn load(path: &str) -> Result<Vec<InputEvent>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut events = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or("").trim();
+        if !line.is_empty() {
+            events.push(InputEvent::parse(line).map_err(|e| format!("{path}: Line {}: {e}", number + 1))?);
+        }
+    }
+    Ok(events)
+}
+
+/// Writes what `emulator` recorded, if it was recording.
+pub fn save(emulator: &EmulatorV2, path: &str) -> Result<(), String> {
+    let Some(events) = &emulator.recording else { return Ok(()) };
+    let mut text = format!("; Inputs of an Electron 2 run, {} events. Replay with --replay.\n", events.len());
+    for event in events {
+        text.push_str(&format!("{event}\n"));
+    }
+    std::fs::write(path, text).map_err(|e| format!("{path}: {e}"))
+}
@@ -0,0 +1,314 @@
+//! Runs the emulator on its own thread while the main thread draws the window and dashboard. The
+//! machine sits behind a mutex: the emulation thread holds it for a few milliseconds of clocks at
+//! a time, and the main thread holds it just long enough each frame to apply input and copy out
+//! what it draws, so a slow terminal never holds up the clock and a fast clock never holds up
+//! the frame rate.
+
+use crate::compare::Comparison;
+use crate::electron_2::Emulator as EmulatorV2;
+use crate::emulator::Emulator;
+use crate::multicore::LinkedCore;
+use crate::profiler::Profiler;
+use crate::server;
+use crate::state_dump::StateDump;
+use crate::timeline::PortHistory;
+use crate::timestep::Timestep;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// The longest the emulation thread clocks before letting go of the machine. This is what
+/// `--turbo` runs for, back to back, and what caps `-c`.
+const SLICE: Duration = Duration::from_millis(4);
+/// The pause between slices, in which the main thread can take the machine.
+const REST: Duration = Duration::from_millis(1);
+
+pub trait Machine: Send + 'static {
+    /// Runs the clocks owed since the last call, for at most `budget`.
+    fn run_for(&mut self, budget: Duration);
+}
+
+pub struct Runner<M: Machine> {
+    shared: Arc<Mutex<M>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<M: Machine> Runner<M> {
+    pub fn spawn(machine: M) -> Self {
+        let shared = Arc::new(Mutex::new(machine));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = std::thread::Builder::new()
+            .name("emulator".to_string())
+            .spawn({
+                let (shared, stop) = (shared.clone(), stop.clone());
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        shared.lock().unwrap().run_for(SLICE);
+                        std::thread::sleep(REST);
+                    }
+                }
+            })
+            .expect("Failed to start the emulator thread");
+        Self { shared, stop, thread: Some(thread) }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, M> {
+        self.shared.lock().unwrap()
+    }
+
+    /// Stops the emulation thread, waits for it to let go, and hands the machine back.
+    pub fn finish(mut self) -> M {
+        self.stop_thread();
+        let shared = self.shared.clone();
+        drop(self);
+        match Arc::try_unwrap(shared) {
+            Ok(machine) => machine.into_inner().unwrap(),
+            Err(_) => unreachable!("the emulator thread has exited"),
+        }
+    }
+
+    fn stop_thread(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<M: Machine> Drop for Runner<M> {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+/// The V2 core and everything that is fed after each of its clocks.
+pub struct MachineV2 {
+    pub emulator: EmulatorV2,
+    pub linked: Option<LinkedCore>,
+    pub compare: Option<Comparison>,
+    pub profiler: Option<Profiler>,
+    pub dumps: Vec<StateDump>,
+    /// For the timeline view.
+    pub history: PortHistory,
+    /// The `--serve` WebSocket clients, each sent the state after every clock.
+    pub subscribers: Vec<SyncSender<String>>,
+    pub timestep: Timestep,
+    pub turbo: bool,
+    /// Set by Pause, Step or a breakpoint.
+    pub paused: bool,
+}
+
+impl MachineV2 {
+    /// One clock of both cores, recorded. Stops on a breakpoint.
+    fn clock(&mut self) {
+        self.emulator.clock();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(&self.emulator);
+        }
+        for dump in &mut self.dumps {
+            dump.record(&self.emulator);
+        }
+        self.history.record(&self.emulator);
+        server::publish(&mut self.subscribers, &self.emulator);
+        if let Some(core) = &mut self.linked {
+            core.clock();
+            if let Some(problem) = core.problem() {
+                self.emulator.fault = Some(problem);
+            }
+        }
+        if let Some(compare) = &mut self.compare {
+            compare.clock();
+            if let Some(problem) = compare.problem() {
+                self.emulator.fault = Some(problem);
+            }
+        }
+        if self.emulator.at_breakpoint() {
+            self.paused = true;
+        }
+    }
+
+    /// The Step button: clocks until the next instruction executes.
+    pub fn step(&mut self) {
+        self.paused = true;
+        let clocks = self.emulator.step_with(|core| {
+            if let Some(profiler) = &mut self.profiler {
+                profiler.record(core);
+            }
+            for dump in &mut self.dumps {
+                dump.record(core);
+            }
+            self.history.record(core);
+            server::publish(&mut self.subscribers, core);
+        });
+        if let Some(core) = &mut self.linked {
+            for _ in 0..clocks {
+                core.clock();
+            }
+            if let Some(problem) = core.problem() {
+                self.emulator.fault = Some(problem);
+            }
+        }
+        if let Some(compare) = &mut self.compare {
+            for _ in 0..clocks {
+                compare.clock();
+            }
+            if let Some(problem) = compare.problem() {
+                self.emulator.fault = Some(problem);
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.emulator.reset();
+        self.history.clear();
+        if let Some(core) = &mut self.linked {
+            core.emulator.reset();
+        }
+        if let Some(compare) = &mut self.compare {
+            compare.reset();
+        }
+    }
+}
+
+impl MachineV2 {
+    /// Swaps in `code` and starts it over, or keeps the running program if `code` has errors.
+    pub fn load(&mut self, code: String) -> Result<(), Vec<String>> {
+        // Parsed on the side first, so a broken edit doesn't stop the running program.
+        let mut trial = EmulatorV2::new(String::new());
+        trial.rom.clone_from(&self.emulator.rom);
+        trial.load_program(code.clone())?;
+        let _ = self.emulator.load_program(code);
+        self.history.clear();
+        if let Some(core) = &mut self.linked {
+            core.emulator.reset();
+        }
+        if let Some(compare) = &mut self.compare {
+            compare.reset();
+        }
+        Ok(())
+    }
+}
+
+impl Machine for MachineV2 {
+    fn run_for(&mut self, budget: Duration) {
+        if self.emulator.fault.is_some() || self.emulator.halted {
+            return;
+        }
+        if self.emulator.waiting_for_input {
+            // The main thread answers it; until then each clock period counts as a stall.
+            let idle = self.timestep.due();
+            if let Some(profiler) = &mut self.profiler {
+                for _ in 0..idle {
+                    profiler.record_stall();
+                }
+            }
+            self.timestep.ran(idle);
+            return;
+        }
+        if self.paused {
+            self.timestep.idle();
+            return;
+        }
+        let due = if self.turbo { u64::MAX } else { self.timestep.due() };
+        let start = Instant::now();
+        let mut ran = 0;
+        while ran < due {
+            self.clock();
+            ran += 1;
+            if self.paused || self.emulator.waiting_for_input || self.emulator.fault.is_some() || start.elapsed() >= budget {
+                break;
+            }
+        }
+        self.timestep.ran(ran);
+    }
+}
+
+pub struct MachineV1 {
+    pub emulator: Emulator,
+    pub compare: Option<Comparison>,
+    pub timestep: Timestep,
+    pub turbo: bool,
+    pub paused: bool,
+}
+
+impl MachineV1 {
+    /// One clock of both programs. V1 itself can't fault, so only a V2 `--compare` program can
+    /// stop the run.
+    fn clock(&mut self) {
+        self.emulator.clock();
+        if let Some(compare) = &mut self.compare {
+            compare.clock();
+        }
+    }
+
+    /// The Step button: one instruction, which on V1 is one clock.
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.emulator.step();
+        if let Some(compare) = &mut self.compare {
+            compare.clock();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.emulator.reset();
+        if let Some(compare) = &mut self.compare {
+            compare.reset();
+        }
+    }
+
+    /// Why the run has to stop, if it does.
+    pub fn problem(&self) -> Option<String> {
+        self.compare.as_ref().and_then(Comparison::problem)
+    }
+}
+
+impl Machine for MachineV1 {
+    fn run_for(&mut self, budget: Duration) {
+        if self.problem().is_some() {
+            return;
+        }
+        if self.paused {
+            self.timestep.idle();
+            return;
+        }
+        let due = if self.turbo { u64::MAX } else { self.timestep.due() };
+        let start = Instant::now();
+        let mut ran = 0;
+        while ran < due {
+            self.clock();
+            ran += 1;
+            if start.elapsed() >= budget || self.problem().is_some() {
+                break;
+            }
+        }
+        self.timestep.ran(ran);
+    }
+}
+
+/// Copies what the window and dashboard draw from `from` into `into`, reusing its buffers, so
+/// they can be drawn without holding the machine.
+pub fn mirror(into: &mut EmulatorV2, from: &EmulatorV2) {
+    into.restore(&from.checkpoint());
+    into.instructions.clone_from(&from.instructions);
+    into.labels.clone_from(&from.labels);
+    into.breakpoints.clone_from(&from.breakpoints);
+    into.port_labels.clone_from(&from.port_labels);
+    into.source_map.clone_from(&from.source_map);
+    into.rom.clone_from(&from.rom);
+    into.rom_end = from.rom_end;
+    into.errors.clone_from(&from.errors);
+    into.warnings.clone_from(&from.warnings);
+    into.forwarding = from.forwarding;
+    into.interlock = from.interlock;
+    into.von_neumann = from.von_neumann;
+    into.separate_stack = from.separate_stack;
+    into.program_stack = from.program_stack;
+    into.stack_override = from.stack_override;
+    into.program_watchdog = from.program_watchdog;
+    into.watchdog_override = from.watchdog_override;
+    into.deterministic = from.deterministic;
+}
@@ -0,0 +1,433 @@
+//! `--serve <address>`: a small HTTP API on the running V2 emulator, for web dashboards and
+//! scripts. Every response is JSON, and any origin may call it.
+//!
+//! ```text
+//! GET  /state      the machine after the last clock, as a --dump-state line
+//! GET  /registers  pc, sp, registers, acc and flags
+//! GET  /ram        RAM, #0-#15
+//! GET  /ports      the output ports
+//! POST /step       one instruction (pauses the clock), then the state
+//! POST /reset      starts the program over, then the state
+//! POST /pause      stops the clock
+//! POST /run        starts it again
+//! POST /input      answers a waiting INP with the value in the body
+//! POST /load       loads the source in the body, or answers 400 with its errors
+//! GET  /stream     a WebSocket sent the state after every clock
+//! ```
+//!
+//! Connections are served on their own threads, which hand each request to the main loop; it
+//! applies them while it holds the machine, along with the window's and dashboard's input.
+
+use crate::electron_2::Emulator as EmulatorV2;
+use crate::runner::MachineV2;
+use crate::state_dump::state_json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How many clocks a `/stream` client can fall behind before it misses some.
+const BACKLOG: usize = 4096;
+/// The largest `/load` body taken, well over any program that fits in the ROM.
+const MAX_BODY: usize = 1 << 20;
+/// How often the listener checks whether the server is shutting down.
+const POLL: Duration = Duration::from_millis(20);
+/// How long a client has to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Server {
+    pub address: SocketAddr,
+    requests: Receiver<Request>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+pub struct Request {
+    command: Command,
+    reply: Sender<Response>,
+}
+
+enum Command {
+    State,
+    Registers,
+    Ram,
+    Ports,
+    Step,
+    Reset,
+    Pause,
+    Run,
+    Input(String),
+    Load(String),
+    Stream(SyncSender<String>),
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self { status, body: format!("{{\"error\":{}}}", json_string(message)) }
+    }
+}
+
+impl Server {
+    pub fn start(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("--serve {address}: {e}"))?;
+        let address = listener.local_addr().map_err(|e| format!("--serve {address}: {e}"))?;
+        // Non-blocking, so the listener can notice the stop flag between connections.
+        listener.set_nonblocking(true).map_err(|e| format!("--serve {address}: {e}"))?;
+        let (sender, requests) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = std::thread::Builder::new()
+            .name("server".to_string())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        match listener.accept() {
+                            Ok((stream, _)) => {
+                                let sender = sender.clone();
+                                let _ = std::thread::Builder::new().name("connection".to_string()).spawn(move || serve(stream, sender));
+                            }
+                            Err(_) => std::thread::sleep(POLL),
+                        }
+                    }
+                }
+            })
+            .map_err(|e| format!("--serve {address}: {e}"))?;
+        tracing::info!(%address, "serving the API");
+        Ok(Self { address, requests, stop, thread: Some(thread) })
+    }
+
+    /// The next request waiting for the machine, if any.
+    pub fn next_request(&self) -> Option<Request> {
+        self.requests.try_recv().ok()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Applies `request` to the machine and answers it. Returns the code of a program it loaded,
+/// which replaces the file's as a reload would.
+pub fn handle(machine: &mut MachineV2, request: Request) -> Option<String> {
+    let mut loaded = None;
+    let emulator = &machine.emulator;
+    let response = match request.command {
+        Command::State => Response::ok(state_json(emulator)),
+        Command::Registers => Response::ok(registers_json(emulator)),
+        Command::Ram => Response::ok(format!("{{\"ram\":{}}}", json_list(&emulator.ram))),
+        Command::Ports => Response::ok(format!("{{\"ports\":{}}}", json_list(&emulator.ports_out))),
+        Command::Step if emulator.waiting_for_input => Response::error(409, "INP is waiting for a value. POST it to /input."),
+        Command::Step => {
+            machine.step();
+            Response::ok(state_json(&machine.emulator))
+        }
+        Command::Reset => {
+            machine.reset();
+            Response::ok(state_json(&machine.emulator))
+        }
+        Command::Pause => {
+            machine.paused = true;
+            Response::ok("{\"paused\":true}".to_string())
+        }
+        Command::Run => {
+            machine.paused = false;
+            Response::ok("{\"paused\":false}".to_string())
+        }
+        Command::Input(_) if !emulator.waiting_for_input => Response::error(409, "No INP is waiting for a value."),
+        Command::Input(body) => match body.trim().parse::<u8>() {
+            Ok(value) => {
+                machine.emulator.resolve_input(value as i32);
+                Response::ok(state_json(&machine.emulator))
+            }
+            Err(_) => Response::error(400, &format!("\"{}\" is not a value between 0 and 255.", body.trim())),
+        },
+        Command::Load(code) => match machine.load(code.clone()) {
+            Ok(()) => {
+                loaded = Some(code);
+                Response::ok(state_json(&machine.emulator))
+            }
+            Err(errors) => {
+                let errors: Vec<String> = errors.iter().map(|error| json_string(error)).collect();
+                Response { status: 400, body: format!("{{\"errors\":[{}]}}", errors.join(",")) }
+            }
+        },
+        Command::Stream(subscriber) => {
+            machine.subscribers.push(subscriber);
+            Response::ok(String::new())
+        }
+    };
+    let _ = request.reply.send(response);
+    loaded
+}
+
+/// Sends the state after a clock to every `/stream` client. One that has fallen `BACKLOG`
+/// clocks behind misses this one; one that has gone is dropped.
+pub fn publish(subscribers: &mut Vec<SyncSender<String>>, emulator: &EmulatorV2) {
+    if subscribers.is_empty() {
+        return;
+    }
+    let state = state_json(emulator);
+    subscribers.retain(|subscriber| !matches!(subscriber.try_send(state.clone()), Err(TrySendError::Disconnected(_))));
+}
+
+/// Reads one request from `stream`, has the main loop answer it, and writes the answer back.
+fn serve(stream: TcpStream, requests: Sender<Request>) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let Ok(reader) = stream.try_clone() else { return };
+    let mut reader = BufReader::new(reader);
+    let Some(Head { method, path, headers }) = read_head(&mut reader) else { return };
+    let header = |name: &str| headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str());
+    let mut stream = stream;
+
+    if method == "OPTIONS" {
+        let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST\r\nAccess-Control-Allow-Headers: *\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        return;
+    }
+    if path == "/stream" {
+        match header("Sec-WebSocket-Key") {
+            Some(key) if method == "GET" => stream_states(stream, key, &requests),
+            _ => write_response(&mut stream, &Response::error(400, "/stream is a WebSocket.")),
+        }
+        return;
+    }
+    let body = match header("Content-Length").map(str::parse::<usize>) {
+        None => String::new(),
+        Some(Ok(length)) if length <= MAX_BODY => {
+            let mut body = vec![0; length];
+            if reader.read_exact(&mut body).is_err() {
+                return;
+            }
+            String::from_utf8_lossy(&body).into_owned()
+        }
+        Some(_) => {
+            write_response(&mut stream, &Response::error(413, "The body is too large."));
+            return;
+        }
+    };
+    let command = match (method.as_str(), path.as_str()) {
+        ("GET", "/state") => Command::State,
+        ("GET", "/registers") => Command::Registers,
+        ("GET", "/ram") => Command::Ram,
+        ("GET", "/ports") => Command::Ports,
+        ("POST", "/step") => Command::Step,
+        ("POST", "/reset") => Command::Reset,
+        ("POST", "/pause") => Command::Pause,
+        ("POST", "/run") => Command::Run,
+        ("POST", "/input") => Command::Input(body),
+        ("POST", "/load") => Command::Load(body),
+        (_, "/state" | "/registers" | "/ram" | "/ports" | "/step" | "/reset" | "/pause" | "/run" | "/input" | "/load") => {
+            write_response(&mut stream, &Response::error(405, &format!("{method} isn't allowed on {path}.")));
+            return;
+        }
+        _ => {
+            write_response(&mut stream, &Response::error(404, &format!("No endpoint {path}.")));
+            return;
+        }
+    };
+    let response = ask(&requests, command).unwrap_or_else(|| Response::error(503, "The emulator has stopped."));
+    write_response(&mut stream, &response);
+}
+
+/// Hands `command` to the main loop and waits for its answer.
+fn ask(requests: &Sender<Request>, command: Command) -> Option<Response> {
+    let (reply, answer) = mpsc::channel();
+    requests.send(Request { command, reply }).ok()?;
+    answer.recv().ok()
+}
+
+/// The request line and headers.
+struct Head {
+    method: String,
+    /// Without the query string.
+    path: String,
+    headers: Vec<(String, String)>,
+}
+
+fn read_head(reader: &mut impl BufRead) -> Option<Head> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.split('?').next()?.to_string();
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Some(Head { method, path, headers });
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        _ => "Service Unavailable",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).and_then(|()| stream.write_all(response.body.as_bytes()));
+}
+
+/// `/stream`: accepts the WebSocket, then sends a text message per clock until the client goes
+/// or the emulator stops. What the client sends is never read.
+fn stream_states(mut stream: TcpStream, key: &str, requests: &Sender<Request>) {
+    let accept = base64(&sha1(format!("{key}258EAFA5-E914-47DA-95CA-C5AB0DC85B11").as_bytes()));
+    let handshake = format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n");
+    if stream.write_all(handshake.as_bytes()).is_err() {
+        return;
+    }
+    let (subscriber, states) = mpsc::sync_channel(BACKLOG);
+    if ask(requests, Command::Stream(subscriber)).is_none() {
+        return;
+    }
+    for state in states {
+        if write_frame(&mut stream, 0x1, state.as_bytes()).is_err() {
+            return;
+        }
+    }
+    // The machine is gone: a close frame with status 1001, going away.
+    let _ = write_frame(&mut stream, 0x8, &1001u16.to_be_bytes());
+}
+
+/// One unmasked, unfragmented WebSocket frame, as a server sends them.
+fn write_frame(stream: &mut TcpStream, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        length @ 0..=125 => frame.push(length as u8),
+        length @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+fn registers_json(emulator: &EmulatorV2) -> String {
+    let flags = &emulator.alu.flags;
+    let registers: Vec<u8> = (0..8).map(|i| emulator.registers.read(i)).collect();
+    format!(
+        "{{\"pc\":{},\"sp\":{},\"registers\":{},\"acc\":{},\"flags\":{{\"equals\":{},\"greater\":{},\"less\":{},\"overflow\":{}}}}}",
+        emulator.pc,
+        emulator.sp,
+        json_list(&registers),
+        emulator.alu.accumulator,
+        flags.equals,
+        flags.greater,
+        flags.less,
+        flags.overflow,
+    )
+}
+
+fn json_list(values: &[u8]) -> String {
+    let values: Vec<String> = values.iter().map(u8::to_string).collect();
+    format!("[{}]", values.join(","))
+}
+
+pub fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// SHA-1, which the WebSocket handshake needs and nothing else does.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, temp);
+        }
+        for (total, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *total = total.wrapping_add(value);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, word) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,88 @@
+//! `--adc`: a slider for each analog channel, in a row above the transport buttons. Drag one
+//! with the mouse, or pick a channel with Up and Down and turn it with Left and Right.
+
+use crate::devices::adc::FIRST_PORT;
+use crate::transport;
+use crate::WINDOW_SIZE;
+use raylib::prelude::*;
+use std::sync::{Arc, Mutex};
+
+const HEIGHT: i32 = 28;
+const FONT_SIZE: i32 = 18;
+/// How far Left and Right turn the selected channel each frame they're held.
+const KEY_STEP: u8 = 2;
+const TRACK: Color = Color::new(40, 40, 70, 230);
+const FILL: Color = Color::new(60, 140, 200, 230);
+const SELECTED: Color = Color::new(150, 110, 20, 255);
+const LABEL: Color = Color::new(230, 230, 230, 255);
+
+pub struct Sliders {
+    /// What the `Adc` device reads, one value per channel.
+    values: Arc<Mutex<Vec<u8>>>,
+    /// The channel the arrow keys turn.
+    selected: usize,
+    /// The slider being dragged, until the mouse button is let go.
+    dragging: Option<usize>,
+}
+
+impl Sliders {
+    pub fn new(values: Arc<Mutex<Vec<u8>>>) -> Self {
+        Self { values, selected: 0, dragging: None }
+    }
+
+    fn rect(&self, channel: usize, channels: usize) -> Rectangle {
+        let width = WINDOW_SIZE.0 / channels as i32;
+        Rectangle::new(
+            (channel as i32 * width) as f32,
+            (WINDOW_SIZE.1 - transport::BAR_HEIGHT - HEIGHT - 2) as f32,
+            (width - 2) as f32,
+            HEIGHT as f32,
+        )
+    }
+
+    pub fn handle_input(&mut self, rl: &RaylibHandle) {
+        let mut values = self.values.lock().unwrap();
+        let channels = values.len();
+        if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.selected = (self.selected + 1) % channels;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.selected = (self.selected + channels - 1) % channels;
+        }
+        let value = &mut values[self.selected];
+        if rl.is_key_down(KeyboardKey::KEY_RIGHT) {
+            *value = value.saturating_add(KEY_STEP);
+        }
+        if rl.is_key_down(KeyboardKey::KEY_LEFT) {
+            *value = value.saturating_sub(KEY_STEP);
+        }
+
+        let mouse = rl.get_mouse_position();
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            self.dragging = (0..channels).find(|&channel| self.rect(channel, channels).check_collision_point_rec(mouse));
+        } else if !rl.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON) {
+            self.dragging = None;
+        }
+        if let Some(channel) = self.dragging {
+            let rect = self.rect(channel, channels);
+            values[channel] = ((mouse.x - rect.x) / rect.width * 255.0).round().clamp(0.0, 255.0) as u8;
+            self.selected = channel;
+        }
+    }
+
+    /// Each slider filled to its value, labeled with its port and value.
+    pub fn draw(&self, d: &mut impl RaylibDraw) {
+        let values = self.values.lock().unwrap();
+        for (channel, value) in values.iter().enumerate() {
+            let rect = self.rect(channel, values.len());
+            d.draw_rectangle_rec(rect, TRACK);
+            d.draw_rectangle_rec(Rectangle::new(rect.x, rect.y, rect.width * f32::from(*value) / 255.0, rect.height), FILL);
+            if channel == self.selected {
+                d.draw_rectangle_lines_ex(rect, 2, SELECTED);
+            }
+            let label = format!("ADC %{}: {value}", FIRST_PORT as usize + channel);
+            let x = rect.x as i32 + (rect.width as i32 - measure_text(&label, FONT_SIZE)) / 2;
+            d.draw_text(&label, x, rect.y as i32 + (HEIGHT - FONT_SIZE) / 2, FONT_SIZE, LABEL);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver};
+
+/// Reads stdin line by line on a background thread, so waiting for `INP` never blocks the
+/// render loop (and closing the window or pressing Ctrl+C still works while a program waits).
+pub struct StdinLines {
+    rx: Receiver<String>,
+}
+
+impl StdinLines {
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { rx }
+    }
+
+    pub fn try_next(&self) -> Option<String> {
+        self.rx.try_recv().ok()
+    }
+}
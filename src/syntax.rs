@@ -0,0 +1,159 @@
+//! `electron gen-syntax`: syntax highlighting for editors, generated from the tables the
+//! assembler reads (the operations in `isa::OPERATIONS`, `parser::DIRECTIVES` and
+//! `parser::OPERAND_PREFIXES`), so a new operation or directive is highlighted as soon as it
+//! assembles.
+
+use crate::electron_2::isa::{Group, OperationInfo, OPERATIONS};
+use crate::electron_2::parser::{DIRECTIVES, OPERAND_PREFIXES};
+use crate::electron_2::OperandType;
+use crate::server::json_string;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// A `.tmLanguage.json` grammar, for VS Code, Sublime Text and others.
+    TextMate,
+    /// A tree-sitter `grammar.js`.
+    TreeSitter,
+    /// The `queries/highlights.scm` that goes with the tree-sitter grammar.
+    TreeSitterHighlights,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 3] = ["textmate", "tree-sitter", "tree-sitter-highlights"];
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name.to_lowercase().as_str() {
+            "textmate" => Some(Format::TextMate),
+            "tree-sitter" => Some(Format::TreeSitter),
+            "tree-sitter-highlights" => Some(Format::TreeSitterHighlights),
+            _ => None,
+        }
+    }
+}
+
+pub fn generate(format: Format) -> String {
+    match format {
+        Format::TextMate => textmate(),
+        Format::TreeSitter => tree_sitter(),
+        Format::TreeSitterHighlights => HIGHLIGHTS.to_string(),
+    }
+}
+
+/// The operations as regex alternatives: the branches, the ones that take a prefix, and the
+/// rest.
+fn mnemonics() -> [String; 3] {
+    [
+        spellings(|info| info.group == Group::FlowControl),
+        spellings(|info| info.prefixes),
+        spellings(|info| info.group != Group::FlowControl && !info.prefixes),
+    ]
+}
+
+/// Every spelling of the operations `include` picks, aliases too, longest first so `ADDC`
+/// isn't read as `ADD`.
+fn spellings(include: impl Fn(&OperationInfo) -> bool) -> String {
+    let mut names: Vec<&str> = OPERATIONS
+        .iter()
+        .filter(|info| include(info))
+        .flat_map(|info| std::iter::once(info.mnemonic).chain(info.aliases.iter().copied()))
+        .collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    names.join("|")
+}
+
+fn directives() -> String {
+    DIRECTIVES.iter().map(|(name, _)| name.trim_start_matches('.')).collect::<Vec<_>>().join("|")
+}
+
+/// `[R$]`: the characters that start an operand of this type, as a regex class.
+fn prefix_class(type_: OperandType) -> String {
+    let chars: String = OPERAND_PREFIXES.iter().filter(|(_, t)| *t == type_).map(|(c, _)| *c).collect();
+    format!("[{}]", chars.replace('$', "\\$"))
+}
+
+fn textmate() -> String {
+    let [branches, prefixed, others] = mnemonics();
+    let patterns = [
+        ("comment.line.semicolon.electron", ";.*$".to_string()),
+        ("string.quoted.double.electron", "\"[^\"]*\"".to_string()),
+        ("keyword.other.directive.electron", format!("(?i)^\\s*\\.(?:{})\\b", directives())),
+        ("entity.name.label.electron", "^\\s*[A-Za-z_][A-Za-z0-9_]*(?=:)".to_string()),
+        ("keyword.control.electron", format!("(?i)\\b(?:{branches})\\b")),
+        ("keyword.operator.electron", format!("(?i)\\b[SUX]?(?:{prefixed})\\b")),
+        ("keyword.other.electron", format!("(?i)\\b(?:{others})\\b")),
+        ("variable.other.register.electron", format!("(?i)(?<![A-Za-z0-9_]){}[0-9]+\\b", prefix_class(OperandType::Register))),
+        ("variable.other.address.electron", format!("{}[0-9][0-9A-Za-z_]*", prefix_class(OperandType::MemoryAddress))),
+        ("variable.other.port.electron", format!("{}[0-9][0-9A-Za-z_]*", prefix_class(OperandType::Port))),
+        ("constant.character.electron", "'[^']'".to_string()),
+        ("constant.numeric.electron", "(?i)\\b(?:0x[0-9a-f_]+|0?b[01_]+|[0-9][0-9_]*)\\b".to_string()),
+    ];
+    let patterns: Vec<String> = patterns
+        .iter()
+        .map(|(name, pattern)| format!("    {{ \"name\": {}, \"match\": {} }}", json_string(name), json_string(pattern)))
+        .collect();
+    format!(
+        "{{\n  \"$schema\": \"https://raw.githubusercontent.com/martinring/tmlanguage/master/tmlanguage.json\",\n  \
+         \"name\": \"Electron\",\n  \"scopeName\": \"source.electron\",\n  \"fileTypes\": [\"elt\", \"asm\"],\n  \
+         \"comment\": \"Generated by electron gen-syntax from the assembler's tables.\",\n  \"patterns\": [\n{}\n  ]\n}}\n",
+        patterns.join(",\n")
+    )
+}
+
+fn tree_sitter() -> String {
+    let [branches, prefixed, others] = mnemonics();
+    format!(
+        r#"// Generated by `electron gen-syntax --format tree-sitter` from the assembler's tables.
+// Highlight it with the queries from `electron gen-syntax --format tree-sitter-highlights`.
+
+module.exports = grammar({{
+  name: 'electron',
+
+  extras: $ => [/[ \t\r]/, $.comment],
+
+  rules: {{
+    source_file: $ => repeat(choice($.label, $.directive, $.instruction, '\n')),
+
+    label: $ => seq(field('name', $.identifier), ':'),
+
+    instruction: $ => prec.right(seq(field('operation', $.operation), repeat(field('operand', $._operand)))),
+
+    directive: $ => prec.right(seq(field('name', $.directive_name), repeat(choice($.string, $._operand, ',')))),
+
+    operation: $ => token(prec(1, /(?:{branches})|[SUX]?(?:{prefixed})|(?:{others})/i)),
+
+    directive_name: $ => token(prec(1, /\.(?:{directives})/i)),
+
+    _operand: $ => choice($.register, $.address, $.port, $.number, $.character, $.identifier),
+
+    register: $ => token(prec(2, /{register}[0-9]+/i)),
+    address: $ => /{address}[0-9][0-9A-Za-z_]*/,
+    port: $ => /{port}[0-9][0-9A-Za-z_]*/,
+    number: $ => token(prec(1, /-?(?:0[xX][0-9a-fA-F_]+|0?[bB][01_]+|[0-9][0-9_]*)/)),
+    character: $ => /'[^']'/,
+    string: $ => /"[^"\n]*"/,
+    identifier: $ => /[A-Za-z_][A-Za-z0-9_]*/,
+    comment: $ => token(seq(';', /.*/)),
+  }},
+}});
+"#,
+        directives = directives(),
+        register = prefix_class(OperandType::Register),
+        address = prefix_class(OperandType::MemoryAddress),
+        port = prefix_class(OperandType::Port),
+    )
+}
+
+const HIGHLIGHTS: &str = "; Generated by `electron gen-syntax --format tree-sitter-highlights`.
+
+(comment) @comment
+(label name: (identifier) @label)
+(operation) @keyword
+(directive_name) @keyword.directive
+(register) @variable.builtin
+(address) @variable
+(port) @variable
+(number) @number
+(character) @character
+(string) @string
+(identifier) @constant
+";
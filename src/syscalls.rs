@@ -0,0 +1,91 @@
+//! The host services behind V2's `SYS n`: printing, reading a number, sleeping and exiting, so
+//! programs get useful I/O without a peripheral for each. The numbers are listed in the README;
+//! arguments are in R1 and R2 and a result comes back in R1.
+
+use crate::electron_2::{SysResult, Syscalls};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const EXIT: u8 = 0;
+pub const PRINT_NUMBER: u8 = 1;
+pub const PRINT_CHAR: u8 = 2;
+pub const READ_NUMBER: u8 = 3;
+pub const SLEEP: u8 = 4;
+pub const PRINT_TEXT: u8 = 5;
+
+/// `SYS 4` sleeps R1 times this.
+const SLEEP_UNIT: Duration = Duration::from_millis(10);
+
+pub struct HostServices {
+    /// Where the printing services write: None for stdout, or a buffer a frontend that owns the
+    /// terminal (the dashboard) empties and shows itself.
+    captured: Option<Arc<Mutex<String>>>,
+}
+
+impl Default for HostServices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HostServices {
+    pub fn new() -> Self {
+        Self { captured: None }
+    }
+
+    /// Services whose text collects in the returned buffer instead of going to stdout.
+    pub fn captured() -> (Self, Arc<Mutex<String>>) {
+        let buffer = Arc::new(Mutex::new(String::new()));
+        (Self { captured: Some(buffer.clone()) }, buffer)
+    }
+
+    fn print(&mut self, text: &str) {
+        match &self.captured {
+            Some(buffer) => buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push_str(text),
+            None => {
+                let mut stdout = std::io::stdout().lock();
+                let _ = stdout.write_all(text.as_bytes());
+                let _ = stdout.flush();
+            }
+        }
+    }
+}
+
+impl Syscalls for HostServices {
+    fn call(&mut self, number: u8, registers: [u8; 8], ram: &[u8; 16]) -> SysResult {
+        let [_, r1, r2, ..] = registers;
+        match number {
+            EXIT => SysResult::Exit(r1),
+            PRINT_NUMBER => {
+                self.print(&format!("{r1}\n"));
+                SysResult::Done
+            }
+            PRINT_CHAR => {
+                self.print(&char::from(r1).to_string());
+                SysResult::Done
+            }
+            READ_NUMBER => SysResult::Input,
+            SLEEP => {
+                std::thread::sleep(SLEEP_UNIT * u32::from(r1));
+                SysResult::Done
+            }
+            PRINT_TEXT => {
+                let start = usize::from(r1).min(ram.len());
+                let end = (start + usize::from(r2)).min(ram.len());
+                self.print(&String::from_utf8_lossy(&ram[start..end]));
+                SysResult::Done
+            }
+            _ => SysResult::Fault(format!("there's no host service {number} (0-{PRINT_TEXT} are)")),
+        }
+    }
+}
+
+/// Takes the complete lines out of a `captured` buffer, leaving any unfinished one.
+pub fn take_lines(buffer: &Mutex<String>) -> Vec<String> {
+    let mut text = buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(end) = text.rfind('\n') else { return Vec::new() };
+    let lines = text[..end].lines().map(str::to_string).collect();
+    text.replace_range(..=end, "");
+    lines
+}
@@ -0,0 +1,338 @@
+//! `--theme`: how the output port LEDs look. A theme is one of the built-in ones or a file of
+//! `key = value` lines (`;` starts a comment):
+//!
+//! ```text
+//! style = textures        ; textures, squares or nixie
+//! on = my-on.png          ; images for `textures`, relative to the theme file
+//! off = my-off.png
+//! background = #000000
+//! on-color = #ff9020      ; colors for `squares` and `nixie`
+//! off-color = #301808
+//! ```
+
+use crate::electron_2::PortLabel;
+use crate::transport;
+use crate::WINDOW_SIZE;
+use raylib::prelude::*;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Style {
+    /// An image per LED state.
+    Textures,
+    /// Flat colored squares.
+    Squares,
+    /// Glowing 1s and 0s in tubes.
+    Nixie,
+}
+
+pub struct Theme {
+    pub style: Style,
+    /// Image files for `Style::Textures`; None means the built-in LED pictures.
+    pub on_image: Option<String>,
+    pub off_image: Option<String>,
+    pub background: Color,
+    pub on_color: Color,
+    pub off_color: Color,
+}
+
+pub const BUILT_IN: &[&str] = &["classic", "flat", "nixie"];
+
+const MATRIX_MARGIN: i32 = 24;
+const LABEL_FONT_SIZE: i32 = 20;
+const LABEL_COLOR: Color = Color::new(240, 240, 240, 255);
+const LABEL_BACKGROUND: Color = Color::new(0, 0, 0, 180);
+/// How far each frame moves an LED's brightness toward that frame's duty cycle, so it settles
+/// over the last few frames and a PWM period that beats against the frame rate doesn't shimmer.
+const PWM_SMOOTHING: f32 = 0.3;
+
+impl Theme {
+    /// The pictures of real LEDs the emulator has always used.
+    pub fn classic() -> Self {
+        Self {
+            style: Style::Textures,
+            on_image: None,
+            off_image: None,
+            background: Color::BLACK,
+            on_color: Color::new(255, 40, 40, 255),
+            off_color: Color::new(60, 10, 10, 255),
+        }
+    }
+
+    /// Loads a built-in theme by name, or else a theme file.
+    pub fn load(spec: &str) -> Result<Self, String> {
+        match spec {
+            "classic" => Ok(Self::classic()),
+            "flat" => Ok(Self {
+                style: Style::Squares,
+                background: Color::new(20, 20, 24, 255),
+                on_color: Color::new(80, 220, 120, 255),
+                off_color: Color::new(40, 48, 44, 255),
+                ..Self::classic()
+            }),
+            "nixie" => Ok(Self {
+                style: Style::Nixie,
+                background: Color::new(12, 8, 6, 255),
+                on_color: Color::new(255, 140, 40, 255),
+                off_color: Color::new(70, 40, 24, 255),
+                ..Self::classic()
+            }),
+            path => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("{path}: {e} (built-in themes: {})", BUILT_IN.join(", ")))?;
+                Self::parse(&text, Path::new(path).parent().unwrap_or(Path::new(""))).map_err(|e| format!("{path}: {e}"))
+            }
+        }
+    }
+
+    /// Reads a theme file. Image paths are taken relative to `dir`.
+    fn parse(text: &str, dir: &Path) -> Result<Self, String> {
+        let mut theme = Self::classic();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("Line {}: {message}", number + 1);
+            let (key, value) = line.split_once('=').ok_or_else(|| error(format!("expected `key = value`, got \"{line}\"")))?;
+            let (key, value) = (key.trim(), value.trim());
+            let image = || Some(dir.join(value).to_string_lossy().into_owned());
+            match key {
+                "style" => {
+                    theme.style = match value {
+                        "textures" => Style::Textures,
+                        "squares" => Style::Squares,
+                        "nixie" => Style::Nixie,
+                        _ => return Err(error(format!("unknown style \"{value}\" (use textures, squares or nixie)"))),
+                    }
+                }
+                "on" => theme.on_image = image(),
+                "off" => theme.off_image = image(),
+                "background" => theme.background = parse_color(value).ok_or_else(|| error(bad_color(value)))?,
+                "on-color" => theme.on_color = parse_color(value).ok_or_else(|| error(bad_color(value)))?,
+                "off-color" => theme.off_color = parse_color(value).ok_or_else(|| error(bad_color(value)))?,
+                _ => return Err(error(format!("unknown key \"{key}\""))),
+            }
+        }
+        Ok(theme)
+    }
+}
+
+fn bad_color(value: &str) -> String {
+    format!("\"{value}\" is not a color (use #rrggbb or #rrggbbaa)")
+}
+
+/// `#rrggbb`, or `#rrggbbaa` with alpha.
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { byte(6)? } else { 255 };
+    Some(Color::new(byte(0)?, byte(2)?, byte(4)?, alpha))
+}
+
+/// A theme with its images loaded, ready to draw the LED grid.
+pub struct Leds {
+    theme: Theme,
+    textures: Option<(Texture2D, Texture2D)>,
+    /// `--matrix`: draw the ports as one square 8x8 matrix in a bezel instead of eight rows
+    /// that fill the window.
+    pub matrix: bool,
+    /// Light each LED by the share of recent clocks its bit was on, so software PWM looks
+    /// dimmed; off shows each bit as it is this frame. B toggles it.
+    pub pwm: bool,
+    /// The screen's size while the window fills it (F11): the grid is laid out to it instead
+    /// of the canvas, and there's no transport bar to leave room for.
+    pub fullscreen: Option<(i32, i32)>,
+    /// Each bit's brightness (0 to 1) for `pwm`, for the main grid and the `--compare` one.
+    levels: [[[f32; 8]; 8]; 2],
+}
+
+impl Leds {
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, theme: Theme) -> Result<Self, String> {
+        let textures = match theme.style {
+            Style::Textures => {
+                // The pictures are far larger than a cell, so they're mipmapped to stay smooth at
+                // any cell size and window scale.
+                let mut load = |path: &Option<String>, built_in: &[u8]| -> Result<Texture2D, String> {
+                    let mut texture = match path {
+                        Some(path) => rl.load_texture(thread, path).map_err(|e| format!("{path}: {e}"))?,
+                        None => {
+                            let image = Image::load_image_from_mem(".png", &built_in.to_vec(), built_in.len() as i32)?;
+                            rl.load_texture_from_image(thread, &image)?
+                        }
+                    };
+                    texture.gen_texture_mipmaps();
+                    texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_TRILINEAR);
+                    Ok(texture)
+                };
+                let on = load(&theme.on_image, include_bytes!("on.png"))?;
+                let off = load(&theme.off_image, include_bytes!("off.png"))?;
+                Some((on, off))
+            }
+            Style::Squares | Style::Nixie => None,
+        };
+        Ok(Self { theme, textures, matrix: false, pwm: true, fullscreen: None, levels: [[[0.0; 8]; 8]; 2] })
+    }
+
+    pub fn background(&self) -> Color {
+        self.theme.background
+    }
+
+    /// Top-left corner and cell size of the LED grid. Under `--matrix` the cells are square
+    /// and evenly pitched inside a frame, above the transport bar, so the rows read as one
+    /// picture.
+    fn grid(&self) -> (i32, i32, i32, i32) {
+        let (width, height, _) = self.area();
+        if !self.matrix {
+            return (0, 0, width / 8, height / 8);
+        }
+        self.square_grid(0, width)
+    }
+
+    /// The width and height the LEDs fill, and where the transport bar starts in it.
+    fn area(&self) -> (i32, i32, i32) {
+        match self.fullscreen {
+            Some((width, height)) => (width, height, height),
+            None => (WINDOW_SIZE.0, WINDOW_SIZE.1, WINDOW_SIZE.1 - transport::BAR_HEIGHT),
+        }
+    }
+
+    /// Where `point` is over the LED grid, from (0, 0) at the top left to (255, 255) at the
+    /// bottom right, clamped at the edges, so each LED is 32 steps wide and tall.
+    pub fn grid_position(&self, point: Vector2) -> (u8, u8) {
+        let (x, y, width, height) = self.grid();
+        let scale = |offset: f32, size: i32| (offset / (size * 8) as f32 * 256.0).clamp(0.0, 255.0) as u8;
+        (scale(point.x - x as f32, width), scale(point.y - y as f32, height))
+    }
+
+    /// A grid of square cells centered in the `width` columns from `left`, above the transport
+    /// bar.
+    fn square_grid(&self, left: i32, width: i32) -> (i32, i32, i32, i32) {
+        let (_, _, area) = self.area();
+        let size = (width.min(area) - MATRIX_MARGIN * 2) / 8;
+        (left + width / 2 - size * 4, area / 2 - size * 4, size, size)
+    }
+
+    /// Folds a frame's port values into grid `grid`'s brightness (0 the main one, 1 the
+    /// `--compare` one): one sample per clock since the last frame, or just the current values.
+    pub fn observe<'a>(&mut self, grid: usize, samples: impl IntoIterator<Item = &'a [u8; 8]>) {
+        let mut on = [[0u32; 8]; 8];
+        let mut count = 0;
+        for sample in samples {
+            count += 1;
+            for (on, value) in on.iter_mut().zip(sample) {
+                for (bit, on) in on.iter_mut().enumerate() {
+                    *on += u32::from(value & (0x80 >> bit) != 0);
+                }
+            }
+        }
+        if count == 0 {
+            return;
+        }
+        for (levels, on) in self.levels[grid].iter_mut().zip(on) {
+            for (level, on) in levels.iter_mut().zip(on) {
+                *level += (on as f32 / count as f32 - *level) * PWM_SMOOTHING;
+            }
+        }
+    }
+
+    /// Draws the eight output ports as rows of LEDs, port 0 at the top and the most significant
+    /// bit on the left.
+    pub fn draw(&self, d: &mut impl RaylibDraw, ports: &[u8; 8]) {
+        self.draw_grid(d, ports, 0, self.grid());
+    }
+
+    /// `--compare`: each program's LEDs in its own half of the window, under its file name.
+    pub fn draw_pair(&self, d: &mut impl RaylibDraw, ports: [&[u8; 8]; 2], captions: &[String; 2]) {
+        let (width, _, bar) = self.area();
+        let half = width / 2;
+        for (i, (ports, caption)) in ports.into_iter().zip(captions).enumerate() {
+            let left = i as i32 * half;
+            let grid = self.square_grid(left, half);
+            self.draw_grid(d, ports, i, grid);
+            let text_x = left + (half - measure_text(caption, LABEL_FONT_SIZE)) / 2;
+            d.draw_text(caption, text_x, grid.1 - LABEL_FONT_SIZE - MATRIX_MARGIN, LABEL_FONT_SIZE, LABEL_COLOR);
+        }
+        d.draw_rectangle(half - 1, 0, 2, bar, LABEL_COLOR);
+    }
+
+    fn draw_grid(&self, d: &mut impl RaylibDraw, ports: &[u8; 8], levels: usize, (x, y, width, height): (i32, i32, i32, i32)) {
+        if self.matrix {
+            let bezel = MATRIX_MARGIN / 2;
+            d.draw_rectangle(x - bezel, y - bezel, width * 8 + bezel * 2, height * 8 + bezel * 2, Color::new(35, 35, 40, 255));
+            d.draw_rectangle(x, y, width * 8, height * 8, self.theme.background);
+        }
+        for (port, value) in ports.iter().enumerate() {
+            for bit in 0..8 {
+                let level = match self.pwm {
+                    true => self.levels[levels][port][bit as usize],
+                    false => f32::from(u8::from(value & (0x80 >> bit) != 0)),
+                };
+                let cell = Rectangle::new((x + bit * width) as f32, (y + port as i32 * height) as f32, width as f32, height as f32);
+                self.draw_cell(d, cell, level);
+            }
+        }
+    }
+
+    /// Writes each `.port` label over the right end of its rows, with a bar down the side of
+    /// a group's rows.
+    pub fn draw_labels(&self, d: &mut impl RaylibDraw, labels: &[PortLabel]) {
+        let (x, y, width, height) = self.grid();
+        let right = x + width * 8;
+        for label in labels {
+            let top = y + label.first as i32 * height;
+            let rows = (label.last - label.first + 1) as i32;
+            let text_width = measure_text(&label.text, LABEL_FONT_SIZE);
+            let text_y = top + (rows * height - LABEL_FONT_SIZE) / 2;
+            if rows > 1 {
+                d.draw_rectangle(right - 6, top + 4, 4, rows * height - 8, LABEL_COLOR);
+            }
+            d.draw_rectangle(right - text_width - 20, text_y - 4, text_width + 10, LABEL_FONT_SIZE + 8, LABEL_BACKGROUND);
+            d.draw_text(&label.text, right - text_width - 15, text_y, LABEL_FONT_SIZE, LABEL_COLOR);
+        }
+    }
+
+    /// One LED at `level`, from 0 (off) to 1 (fully on); the themes blend between their two
+    /// looks for the levels between.
+    fn draw_cell(&self, d: &mut impl RaylibDraw, cell: Rectangle, level: f32) {
+        let color = blend(self.theme.off_color, self.theme.on_color, level);
+        match (&self.textures, self.theme.style) {
+            (Some((on, off)), _) => {
+                let source = Rectangle::new(0.0, 0.0, off.width as f32, off.height as f32);
+                if level < 1.0 {
+                    d.draw_texture_pro(off, source, cell, Vector2::zero(), 0.0, Color::WHITE);
+                }
+                if level > 0.0 {
+                    let source = Rectangle::new(0.0, 0.0, on.width as f32, on.height as f32);
+                    d.draw_texture_pro(on, source, cell, Vector2::zero(), 0.0, Color::WHITE.fade(level));
+                }
+            }
+            (None, Style::Nixie) => {
+                let tube = Rectangle::new(cell.x + 10.0, cell.y + 4.0, cell.width - 20.0, cell.height - 8.0);
+                if level > 0.0 {
+                    let glow = Color::new(color.r, color.g, color.b, (50.0 * level) as u8);
+                    d.draw_circle((cell.x + cell.width / 2.0) as i32, (cell.y + cell.height / 2.0) as i32, cell.width / 2.5, glow);
+                }
+                d.draw_rectangle_rounded_lines(tube, 0.6, 8, 2, Color::new(90, 90, 100, 255));
+                let digit = if level >= 0.5 { "1" } else { "0" };
+                let size = (cell.height * 0.6) as i32;
+                let x = (cell.x + cell.width / 2.0) as i32 - measure_text(digit, size) / 2;
+                d.draw_text(digit, x, (cell.y + cell.height / 2.0) as i32 - size / 2, size, color);
+            }
+            (None, _) => {
+                let inset = 4.0;
+                let square = Rectangle::new(cell.x + inset, cell.y + inset, cell.width - inset * 2.0, cell.height - inset * 2.0);
+                d.draw_rectangle_rec(square, color);
+            }
+        }
+    }
+}
+
+/// The color `amount` (0 to 1) of the way from `from` to `to`.
+fn blend(from: Color, to: Color, amount: f32) -> Color {
+    let mix = |a: u8, b: u8| (f32::from(a) + (f32::from(b) - f32::from(a)) * amount.clamp(0.0, 1.0)).round() as u8;
+    Color::new(mix(from.r, to.r), mix(from.g, to.g), mix(from.b, to.b), mix(from.a, to.a))
+}
@@ -0,0 +1,50 @@
+//! The logic-analyzer view: each output port bit drawn as a waveform over the last clocks, so a
+//! serial-style protocol on the ports can be read off instead of guessed from the blinking LEDs.
+//! T shows it in the window and on the dashboard (V2).
+
+use crate::electron_2::Emulator as EmulatorV2;
+use std::collections::VecDeque;
+
+/// How many clocks of history are kept. Each view shows as many of the latest as fit.
+pub const CAPACITY: usize = 256;
+
+/// The output ports after each of the last `CAPACITY` clocks, oldest first.
+#[derive(Clone, Default)]
+pub struct PortHistory {
+    samples: VecDeque<[u8; 8]>,
+}
+
+impl PortHistory {
+    /// Call after every clock.
+    pub fn record(&mut self, emulator: &EmulatorV2) {
+        if self.samples.len() == CAPACITY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(emulator.ports_out);
+    }
+
+    /// For a reset or reload, whose clocks start over.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// The latest `count` samples, oldest first.
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &[u8; 8]> {
+        self.samples.iter().skip(self.samples.len().saturating_sub(count))
+    }
+
+    /// The ports that were anything but 0 in the latest `count` samples. The rest would only
+    /// be flat lines.
+    pub fn active_ports(&self, count: usize) -> Vec<usize> {
+        (0..8).filter(|&port| self.recent(count).any(|sample| sample[port] != 0)).collect()
+    }
+
+    /// One bit over the latest `count` samples, `▔` while high and `▁` while low.
+    pub fn waveform(&self, port: usize, bit: u8, count: usize) -> String {
+        self.recent(count).map(|sample| if sample[port] & (1 << bit) != 0 { '▔' } else { '▁' }).collect()
+    }
+}
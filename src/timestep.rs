@@ -0,0 +1,62 @@
+//! Paces the emulated clock against the host clock, independently of the frame rate: each run of
+//! the emulation thread adds the time since the last one, and runs however many whole clocks that
+//! pays for. `-c 5000` runs 5000 clocks a second whatever the frame rate.
+
+use std::time::{Duration, Instant};
+
+/// The slowest and fastest speeds `+` and `-` reach.
+const MIN_HZ: f64 = 0.1;
+const MAX_HZ: f64 = 10_000_000.0;
+/// The most time the clock can owe. After a stall of the host (a suspended laptop, say) the
+/// emulator drops the rest rather than racing to catch up.
+const MAX_LAG: Duration = Duration::from_millis(250);
+
+pub struct Timestep {
+    hz: f64,
+    last: Instant,
+    /// Clocks owed, including the fraction of the next one.
+    owed: f64,
+}
+
+impl Timestep {
+    pub fn new(hz: f64) -> Self {
+        Self { hz, last: Instant::now(), owed: 0.0 }
+    }
+
+    /// Whole clocks owed since the last call, not counting any `ran` has paid for.
+    pub fn due(&mut self) -> u64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).min(MAX_LAG);
+        self.last = now;
+        self.owed = (self.owed + elapsed.as_secs_f64() * self.hz).min((MAX_LAG.as_secs_f64() * self.hz).max(1.0));
+        self.owed as u64
+    }
+
+    /// Pays for `clocks` of the ones `due` reported.
+    pub fn ran(&mut self, clocks: u64) {
+        self.owed = (self.owed - clocks as f64).max(0.0);
+    }
+
+    /// Forgets the time that passed while paused, so resuming doesn't run a burst of clocks.
+    pub fn idle(&mut self) {
+        self.last = Instant::now();
+        self.owed = 0.0;
+    }
+
+    /// The clock speed for showing, like `10 Hz` or `2.5 kHz`.
+    pub fn speed(&self) -> String {
+        let (value, unit) = match self.hz {
+            hz if hz >= 1_000_000.0 => (hz / 1_000_000.0, "MHz"),
+            hz if hz >= 1_000.0 => (hz / 1_000.0, "kHz"),
+            hz => (hz, "Hz"),
+        };
+        let value = format!("{value:.2}");
+        format!("{} {unit}", value.trim_end_matches('0').trim_end_matches('.'))
+    }
+
+    /// `+` and `-` on the dashboard: doubles or halves the clock.
+    pub fn change_speed(&mut self, faster: bool) -> String {
+        self.hz = if faster { self.hz * 2.0 } else { self.hz / 2.0 }.clamp(MIN_HZ, MAX_HZ);
+        format!("Clock: {:.2} Hz.", self.hz)
+    }
+}
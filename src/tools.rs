@@ -0,0 +1,320 @@
+//! Subcommands that work on program files without opening a window. Each returns the process
+//! exit code.
+
+use crate::arch::Arch;
+use crate::electron_2::codes::Code;
+use crate::electron_2::disassembler::Disassembler;
+use crate::electron_2::isa;
+use crate::electron_2::{Emulator as EmulatorV2, StateField};
+use crate::syntax::{self, Format};
+use std::path::{Path, PathBuf};
+
+/// `electron check`: assemble only and report every error and warning as
+/// `file:line[:column]: severity: message` (the format editors' problem matchers expect), with
+/// the token at fault underlined below when the column is known. Exits 1 if there were errors.
+pub fn check(file_name: &str, arch: Arch, strict: bool) -> i32 {
+    let source = match std::fs::read_to_string(file_name) {
+        Ok(source) => source,
+        Err(e) => {
+            println!("{file_name}: error: {e}");
+            return 1;
+        }
+    };
+
+    let mut assembled = arch.assemble(&source);
+    if strict {
+        assembled.promote_warnings();
+    }
+    for (kind, messages) in [("error", &assembled.errors), ("warning", &assembled.warnings)] {
+        for diagnostic in messages {
+            let message = &diagnostic.message;
+            // `warning[W003]`, as rustc writes its codes.
+            let severity = match diagnostic.code {
+                Some(code) => format!("{kind}[{}]", code.id()),
+                None => kind.to_string(),
+            };
+            match (diagnostic.line, diagnostic.column) {
+                (Some(line), Some(column)) => {
+                    println!("{file_name}:{line}:{column}: {severity}: {message}");
+                    if let Some(excerpt) = underline(&source, line, column) {
+                        println!("{excerpt}");
+                    }
+                }
+                (Some(line), None) => println!("{file_name}:{line}: {severity}: {message}"),
+                _ => println!("{file_name}: {severity}: {message}"),
+            }
+        }
+    }
+    let (errors, warnings) = (assembled.errors.len(), assembled.warnings.len());
+    if errors == 0 {
+        println!("{file_name}: ok, {} instructions, {warnings} warnings", assembled.listing.len());
+    } else {
+        println!("{file_name}: {errors} errors, {warnings} warnings");
+    }
+    if let Some(code) = assembled.errors.iter().chain(&assembled.warnings).find_map(|diagnostic| diagnostic.code) {
+        println!("For more about a message, run `electron explain` with its code, e.g. `electron explain {}`.", code.id());
+    }
+    i32::from(errors > 0)
+}
+
+/// The source line a message points at, with the token starting at `column` underlined:
+///
+/// ```text
+///    3 | ADD R1 FOO
+///      |        ^^^
+/// ```
+pub fn underline(source: &str, line: usize, column: usize) -> Option<String> {
+    let text = source.lines().nth(line.checked_sub(1)?)?;
+    let token = text.chars().skip(column - 1).take_while(|c| !c.is_whitespace() && *c != ';').count().max(1);
+    let gutter = " ".repeat(line.to_string().len());
+    Some(format!(" {line} | {text}\n {gutter} | {}{}", " ".repeat(column - 1), "^".repeat(token)))
+}
+
+/// `electron assemble`: the numbered instruction listing that goes into ROM, one canonical
+/// instruction per line. A RAW hazard or a branch's shadow is noted after both instructions it
+/// involves.
+pub fn assemble(file_name: &str, arch: Arch, strict: bool, output: Option<&str>) -> i32 {
+    let source = match std::fs::read_to_string(file_name) {
+        Ok(source) => source,
+        Err(e) => {
+            println!("Error: {file_name}: {e}");
+            return 1;
+        }
+    };
+    let mut assembled = arch.assemble(&source);
+    if strict {
+        assembled.promote_warnings();
+    }
+    if !assembled.errors.is_empty() {
+        for diagnostic in assembled.errors {
+            let message = diagnostic.message;
+            match (diagnostic.line, diagnostic.column) {
+                (Some(line), Some(column)) => println!("Error: Line {line}, column {column}: {message}"),
+                (Some(line), None) => println!("Error: Line {line}: {message}"),
+                _ => println!("Error: {message}"),
+            }
+        }
+        return 1;
+    }
+    let listing: Vec<String> = assembled
+        .listing
+        .iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let address = i as i32;
+            let notes: Vec<String> = assembled
+                .hazards
+                .iter()
+                .filter(|hazard| hazard.involves(address))
+                .map(|hazard| format!("[{}] {}", hazard.code.id(), if hazard.address == address { hazard.note() } else { hazard.cause_note() }))
+                .collect();
+            if notes.is_empty() {
+                format!("{i:>3}: {text}")
+            } else {
+                format!("{i:>3}: {text:<16} ; {}", notes.join("; "))
+            }
+        })
+        .collect();
+    write_output(output, &(listing.join("\n") + "\n"))
+}
+
+/// `electron disasm`: a V2 program printed back as canonical assembly that assembles to the same
+/// instructions.
+pub fn disasm(file_name: &str, output: Option<&str>) -> i32 {
+    let code = match std::fs::read_to_string(file_name) {
+        Ok(code) => code,
+        Err(e) => {
+            println!("Error: {file_name}: {e}");
+            return 1;
+        }
+    };
+    let emulator = EmulatorV2::new(code);
+    if !emulator.errors.is_empty() {
+        for error in &emulator.errors {
+            println!("Error: {error}");
+        }
+        return 1;
+    }
+    let mut listing = Disassembler::program(&emulator.instructions, &emulator.labels);
+    // The `.ascii` names became plain numbers; one block puts the same bytes in RAM.
+    if !emulator.ram_data.is_empty() {
+        listing = format!(".ascii DATA \"{}\"\n{listing}", String::from_utf8_lossy(&emulator.ram_data));
+    }
+    // Labels are only stored with lower case letters under `.casesensitive`.
+    if emulator.labels.keys().any(|name| name.chars().any(char::is_lowercase)) {
+        listing = format!(".casesensitive\n{listing}");
+    }
+    write_output(output, &listing)
+}
+
+/// `electron isa`: the instruction-set reference, from the same table the parser and ALU read.
+pub fn isa(output: Option<&str>) -> i32 {
+    write_output(output, &isa::reference())
+}
+
+/// `electron gen-syntax`: a highlighting grammar generated from the assembler's tables.
+pub fn gen_syntax(format: Format, output: Option<&str>) -> i32 {
+    write_output(output, &syntax::generate(format))
+}
+
+/// `electron explain <code>`: what an assembler error or warning means, why the machine does
+/// that, and a program that gets it next to the same program fixed. Without a code, lists them.
+pub fn explain(id: &str) -> i32 {
+    if id.is_empty() {
+        for code in Code::ALL {
+            println!("{}  {}", code.id(), code.explanation().summary);
+        }
+        return 0;
+    }
+    let Some(code) = Code::find(id) else {
+        println!("Error: No message has the code \"{id}\" (run `electron explain` for the list)");
+        return 1;
+    };
+    let explanation = code.explanation();
+    let indent = |text: &str| text.lines().map(|line| format!("    {line}\n")).collect::<String>();
+    match code.name() {
+        Some(name) => println!("{} ({name}): {}\n", code.id(), explanation.summary),
+        None => println!("{}: {}\n", code.id(), explanation.summary),
+    }
+    println!("{}\n", wrap(explanation.details, 80));
+    println!("For example:\n\n{}", indent(explanation.example));
+    print!("Fixed:\n\n{}", indent(explanation.fixed));
+    if let Some(name) = code.name() {
+        println!("\nTo keep it on purpose, end the line with `; @allow({name})`.");
+    }
+    0
+}
+
+/// `text` broken between words into lines of at most `width` characters.
+fn wrap(text: &str, width: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// `electron test`: runs every `.elt`/`.asm` program in `dir` for `cycles` clocks (or until it
+/// halts) and compares the final state with `<name>.expected` next to it. Programs without an
+/// expected file are skipped. Exits 1 if any program failed.
+///
+/// An expected file lists one `<field> = <value>` per line, with fields spelled as in
+/// `ASSERT` (`R1`, `#3`, `%0`, `ACC`, ...) and `;` comments.
+pub fn test(dir: &str, cycles: u64, seed: Option<u64>) -> i32 {
+    let mut programs: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("elt") | Some("asm")))
+            .collect(),
+        Err(e) => {
+            println!("Error: {dir}: {e}");
+            return 1;
+        }
+    };
+    programs.sort();
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for program in &programs {
+        let name = program.file_name().unwrap_or_default().to_string_lossy();
+        let expected_path = program.with_extension("expected");
+        if !expected_path.exists() {
+            println!("SKIP {name} (no {})", expected_path.file_name().unwrap_or_default().to_string_lossy());
+            skipped += 1;
+            continue;
+        }
+        match run_golden(program, &expected_path, cycles, seed.unwrap_or(0)) {
+            Ok(()) => {
+                println!("PASS {name}");
+                passed += 1;
+            }
+            Err(problems) => {
+                println!("FAIL {name}");
+                for problem in problems {
+                    println!("  {problem}");
+                }
+                failed += 1;
+            }
+        }
+    }
+    println!("{passed} passed, {failed} failed, {skipped} skipped");
+    if failed > 0 { 1 } else { 0 }
+}
+
+/// Runs one program and lists every way its final state differs from the expected file.
+fn run_golden(program: &Path, expected_path: &Path, cycles: u64, seed: u64) -> Result<(), Vec<String>> {
+    let code = std::fs::read_to_string(program).map_err(|e| vec![e.to_string()])?;
+    let expected_text = std::fs::read_to_string(expected_path).map_err(|e| vec![e.to_string()])?;
+    let expected = parse_expected(&expected_text).map_err(|e| vec![format!("{}: {e}", expected_path.display())])?;
+
+    let mut emulator = EmulatorV2::new(code);
+    if !emulator.errors.is_empty() {
+        return Err(emulator.errors.clone());
+    }
+    // Seeded and deterministic, so an expected file means the same thing on every machine.
+    emulator.deterministic = true;
+    crate::attach_devices(&mut emulator, false, None, None, Some(seed)).map_err(|e| vec![e])?;
+    // What `SYS` prints isn't part of the state compared, so it stays out of the report.
+    emulator.syscalls = Some(Box::new(crate::syscalls::HostServices::captured().0));
+    while emulator.stats.cycles < cycles && !emulator.halted {
+        if let Some(fault) = &emulator.fault {
+            return Err(vec![fault.clone()]);
+        }
+        if emulator.waiting_for_input {
+            return Err(vec![format!("Stopped waiting for INP after {} clocks", emulator.stats.cycles)]);
+        }
+        emulator.clock();
+    }
+    if let Some(fault) = &emulator.fault {
+        return Err(vec![fault.clone()]);
+    }
+
+    let state = emulator.snapshot();
+    let problems: Vec<String> = expected
+        .iter()
+        .filter(|(field, value)| state.get(*field) != *value)
+        .map(|(field, value)| format!("{} is {}, expected {value}", field.name(), state.get(*field)))
+        .collect();
+    if problems.is_empty() { Ok(()) } else { Err(problems) }
+}
+
+fn parse_expected(text: &str) -> Result<Vec<(StateField, i32)>, String> {
+    let mut expected = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (field, value) = line.split_once('=').ok_or(format!("line {}: expected `<field> = <value>`", i + 1))?;
+        let field = StateField::parse(field).ok_or(format!("line {}: unknown field \"{}\"", i + 1, field.trim()))?;
+        let value = value.trim_start_matches('=').trim();
+        let value = match value.to_uppercase().as_str() {
+            "TRUE" => 1,
+            "FALSE" => 0,
+            _ => value.parse::<i32>().map_err(|_| format!("line {}: \"{value}\" is not a number", i + 1))?,
+        };
+        expected.push((field, value));
+    }
+    Ok(expected)
+}
+
+fn write_output(output: Option<&str>, text: &str) -> i32 {
+    match output {
+        Some(path) => match std::fs::write(path, text) {
+            Ok(()) => 0,
+            Err(e) => {
+                println!("Error: {path}: {e}");
+                1
+            }
+        },
+        None => {
+            print!("{text}");
+            0
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! The Pause, Step, Reset and Reload buttons along the bottom of the window, shared by both
+//! emulators. Each button also has a key.
+
+#[cfg(feature = "gui")]
+pub use buttons::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Transport {
+    /// Stop the clock, or start it again.
+    Pause,
+    /// Run to the next instruction and pause there.
+    Step,
+    /// Start the loaded program over.
+    Reset,
+    /// Read the program file again and start it.
+    Reload,
+}
+
+/// The buttons themselves, which need the window.
+#[cfg(feature = "gui")]
+mod buttons {
+    use super::Transport;
+    use crate::WINDOW_SIZE;
+    use raylib::prelude::*;
+
+    pub const BAR_HEIGHT: i32 = 34;
+    const FONT_SIZE: i32 = 18;
+    const BUTTON: Color = Color::new(40, 40, 70, 230);
+    const BUTTON_ACTIVE: Color = Color::new(150, 110, 20, 230);
+    const LABEL: Color = Color::new(230, 230, 230, 255);
+
+    const BUTTONS: [(Transport, &str, KeyboardKey); 4] = [
+        (Transport::Pause, "Pause [Space]", KeyboardKey::KEY_SPACE),
+        (Transport::Step, "Step [N]", KeyboardKey::KEY_N),
+        (Transport::Reset, "Reset [Bksp]", KeyboardKey::KEY_BACKSPACE),
+        (Transport::Reload, "Reload [F5]", KeyboardKey::KEY_F5),
+    ];
+
+    fn button_rect(index: usize) -> Rectangle {
+        let width = WINDOW_SIZE.0 / BUTTONS.len() as i32;
+        Rectangle::new(
+            (index as i32 * width) as f32,
+            (WINDOW_SIZE.1 - BAR_HEIGHT) as f32,
+            (width - 2) as f32,
+            BAR_HEIGHT as f32,
+        )
+    }
+
+    /// The control asked for this frame, by key or by clicking its button. The buttons only
+    /// take clicks while they're `shown`, and the keys only count with `keys`.
+    pub fn input(rl: &RaylibHandle, shown: bool, keys: bool) -> Option<Transport> {
+        let clicked = (shown && rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON)).then(|| rl.get_mouse_position());
+        BUTTONS.iter().enumerate().find_map(|(i, (action, _, key))| {
+            let hit = clicked.is_some_and(|mouse| button_rect(i).check_collision_point_rec(mouse));
+            (hit || keys && rl.is_key_pressed(*key)).then_some(*action)
+        })
+    }
+
+    /// The Pause button lights up while the clock is paused.
+    pub fn draw(d: &mut impl RaylibDraw, paused: bool) {
+        for (i, (action, label, _)) in BUTTONS.iter().enumerate() {
+            let rect = button_rect(i);
+            let active = paused && *action == Transport::Pause;
+            d.draw_rectangle_rec(rect, if active { BUTTON_ACTIVE } else { BUTTON });
+            let label = if active { "Resume [Space]" } else { label };
+            let x = rect.x as i32 + (rect.width as i32 - measure_text(label, FONT_SIZE)) / 2;
+            d.draw_text(label, x, rect.y as i32 + (BAR_HEIGHT - FONT_SIZE) / 2, FONT_SIZE, LABEL);
+        }
+    }
+}
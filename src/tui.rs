@@ -0,0 +1,594 @@
+//! The terminal dashboard: a ratatui screen with panels for the pipeline, ALU, registers, RAM,
+//! ports and warnings, drawn next to the emulator window. The terminal is in raw mode while it's
+//! up, so keys typed there arrive as events: Tab moves between panels, the arrow keys scroll the
+//! focused one, digits answer a waiting `INP`, and the window's transport keys work here too,
+//! for when the window is out of reach (over SSH, say).
+
+use crate::dashboard::Layout;
+use crate::electron_2::{Emulator as EmulatorV2, Event as CoreEvent, PortLabel};
+use crate::emulator::Emulator;
+use crate::recent;
+use crate::timeline::PortHistory;
+use crate::transport::Transport;
+use ratatui::backend::{CrosstermBackend, TestBackend};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::{cursor, execute, terminal};
+use ratatui::layout::{self, Constraint, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use std::io::Stdout;
+use std::time::{Duration, Instant};
+
+/// Redraws are skipped if the last one was more recent than this, so a fast clock doesn't
+/// spend its time on the terminal.
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+const MAX_NOTICES: usize = 3;
+const SNAPSHOT_HEIGHT: u16 = 30;
+/// How long a port or RAM cell stays lit after a write.
+const FLASH: Duration = Duration::from_millis(300);
+/// The most core events kept between frames. At fast clocks the oldest are dropped, which only
+/// loses flashes that would have been over anyway.
+pub const EVENT_CAPACITY: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Panel {
+    Pipeline,
+    Alu,
+    Registers,
+    Ram,
+    Ports,
+    Warnings,
+}
+
+const PANELS: [Panel; 6] = [Panel::Pipeline, Panel::Alu, Panel::Registers, Panel::Ram, Panel::Ports, Panel::Warnings];
+
+pub enum TuiEvent {
+    /// Q, or Ctrl+C, which raw mode delivers as a key instead of a signal.
+    Quit,
+    /// A value typed for a waiting `INP`.
+    Input(u8),
+    /// The same keys as the window's buttons: Space, N, Backspace and F5.
+    Control(Transport),
+    /// `+` and `-`: double or halve the clock speed.
+    Faster,
+    Slower,
+    /// `i`: turn the V2 interlock on or off.
+    Interlock,
+    /// A program picked from the recent ones `o` lists, to load in place of the running one.
+    Open(String),
+}
+
+/// What the panels show, gathered from either emulator.
+struct View {
+    title: &'static str,
+    stages: [String; 4],
+    accumulator: u8,
+    flags: [(&'static str, bool); 4],
+    registers: Vec<(&'static str, i32)>,
+    /// RAM and the stack pointer, for V2.
+    ram: Option<([u8; 16], i32)>,
+    ports: [u8; 8],
+    port_labels: Vec<PortLabel>,
+    /// Errors and warnings, for V2.
+    warnings: Option<Vec<String>>,
+    status: Vec<String>,
+    waiting_for_input: bool,
+}
+
+/// What to draw in green: the registers the last change touched, and the ports and RAM cells
+/// written in the last `FLASH`, and whether a flush has just emptied fetch (drawn red).
+#[derive(Default)]
+struct Highlights {
+    registers: Vec<bool>,
+    ports: [bool; 8],
+    ram: [bool; 16],
+    flush: bool,
+}
+
+impl View {
+    fn panels(&self) -> Vec<Panel> {
+        PANELS
+            .into_iter()
+            .filter(|panel| match panel {
+                Panel::Ram => self.ram.is_some(),
+                Panel::Warnings => self.warnings.is_some(),
+                _ => true,
+            })
+            .collect()
+    }
+}
+
+pub struct Tui {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    focus: Panel,
+    scroll: [u16; PANELS.len()],
+    /// Digits typed so far for a waiting `INP`.
+    input: String,
+    /// The latest messages for the status line.
+    notices: Vec<String>,
+    last_draw: Option<Instant>,
+    /// The panels the last view had, for Tab to cycle through.
+    panels: Vec<Panel>,
+    /// Register values as last drawn, and which of them the last change touched (drawn in green,
+    /// as in the window).
+    registers: Vec<i32>,
+    changed: Vec<bool>,
+    /// When each port and RAM cell was last written, and the last flush, from the core's events
+    /// (V2).
+    port_writes: [Option<Instant>; 8],
+    ram_writes: [Option<Instant>; 16],
+    last_flush: Option<Instant>,
+    /// T: the timeline in place of the panels (V2).
+    timeline: bool,
+    /// O: the recent programs, listed on the status line for a digit to pick one.
+    recent: Option<Vec<String>>,
+    /// Set by the frontend so the status line can say the clock is stopped.
+    pub paused: bool,
+    /// Set by the frontend under `--display terminal`: the ports to light as LEDs above the
+    /// panels, in place of the window.
+    pub leds: Option<[u8; 8]>,
+    /// The `--compare` program's ports, drawn beside those.
+    pub compare_leds: Option<[u8; 8]>,
+    /// Under `--compare`, the titles of the two grids, main program first.
+    pub captions: Option<[String; 2]>,
+}
+
+impl Tui {
+    /// Switches the terminal to raw mode and the alternate screen. Fails when stdout isn't a
+    /// terminal.
+    pub fn start() -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = std::io::stdout();
+        if let Err(error) = execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(error);
+        }
+        Ok(Self {
+            terminal: Terminal::new(CrosstermBackend::new(stdout))?,
+            focus: Panel::Pipeline,
+            scroll: [0; PANELS.len()],
+            input: String::new(),
+            notices: Vec::new(),
+            last_draw: None,
+            panels: PANELS.to_vec(),
+            registers: Vec::new(),
+            changed: Vec::new(),
+            port_writes: [None; 8],
+            ram_writes: [None; 16],
+            last_flush: None,
+            timeline: false,
+            recent: None,
+            paused: false,
+            leds: None,
+            compare_leds: None,
+            captions: None,
+        })
+    }
+
+    /// A message for the status line, in place of printing over the screen. The last few stay.
+    pub fn notify(&mut self, message: String) {
+        if self.notices.len() == MAX_NOTICES {
+            self.notices.remove(0);
+        }
+        self.notices.push(message);
+        self.last_draw = None;
+    }
+
+    /// Takes the events the core queued since the last frame, for the flashes. Pushes only
+    /// flash RAM when `stack_in_ram`, as it is unless `--separate-stack`.
+    pub fn observe(&mut self, events: impl Iterator<Item = CoreEvent>, stack_in_ram: bool) {
+        let now = Instant::now();
+        for event in events {
+            match event {
+                CoreEvent::PortWrite { port, .. } if port < 8 => self.port_writes[port as usize] = Some(now),
+                CoreEvent::MemoryWrite { address, .. } if (0..16).contains(&address) => self.ram_writes[address as usize] = Some(now),
+                CoreEvent::StackPush { address, .. } if stack_in_ram && (0..16).contains(&address) => {
+                    self.ram_writes[address as usize] = Some(now)
+                }
+                CoreEvent::Flush { .. } => self.last_flush = Some(now),
+                _ => {}
+            }
+        }
+    }
+
+    /// Handles the keys typed since the last call. `waiting` says whether an `INP` is waiting
+    /// for digits.
+    pub fn poll(&mut self, waiting: bool) -> Option<TuiEvent> {
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            let Ok(Event::Key(key)) = event::read() else {
+                // Resizes and the like: just draw again.
+                self.last_draw = None;
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.last_draw = None;
+            let scroll = &mut self.scroll[PANELS.iter().position(|p| *p == self.focus).unwrap_or(0)];
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Some(TuiEvent::Quit),
+                KeyCode::Char('q') => return Some(TuiEvent::Quit),
+                KeyCode::Char(' ') => return Some(TuiEvent::Control(Transport::Pause)),
+                KeyCode::Char('n') => return Some(TuiEvent::Control(Transport::Step)),
+                KeyCode::Backspace if !waiting => return Some(TuiEvent::Control(Transport::Reset)),
+                KeyCode::F(5) => return Some(TuiEvent::Control(Transport::Reload)),
+                KeyCode::Char('+' | '=') => return Some(TuiEvent::Faster),
+                KeyCode::Char('-') => return Some(TuiEvent::Slower),
+                KeyCode::Char('i') => return Some(TuiEvent::Interlock),
+                KeyCode::Char('t') => self.timeline = !self.timeline,
+                KeyCode::Char('o') => self.recent = if self.recent.is_some() { None } else { Some(recent::load()) },
+                KeyCode::Char(digit @ '1'..='9') if !waiting && self.recent.is_some() => {
+                    let picked = self.recent.as_ref().and_then(|files| files.get(digit as usize - '1' as usize)).cloned();
+                    if let Some(file) = picked {
+                        self.recent = None;
+                        return Some(TuiEvent::Open(file));
+                    }
+                }
+                KeyCode::Tab => self.focus = self.next_panel(1),
+                KeyCode::BackTab => self.focus = self.next_panel(self.panels.len() - 1),
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => *scroll = scroll.saturating_add(1),
+                KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+                KeyCode::PageDown => *scroll = scroll.saturating_add(10),
+                KeyCode::Home => *scroll = 0,
+                KeyCode::Char(digit @ '0'..='9') if waiting && self.input.len() < 3 => self.input.push(digit),
+                KeyCode::Backspace if waiting => {
+                    self.input.pop();
+                }
+                KeyCode::Enter if waiting => match std::mem::take(&mut self.input).parse::<u8>() {
+                    Ok(value) => return Some(TuiEvent::Input(value)),
+                    Err(_) => self.notify("INP takes a value between 0 and 255.".to_string()),
+                },
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn next_panel(&self, step: usize) -> Panel {
+        let at = self.panels.iter().position(|p| *p == self.focus).unwrap_or(0);
+        self.panels[(at + step) % self.panels.len()]
+    }
+
+    /// Draws the V2 dashboard, or the timeline of `history` or the scripted layout when one of
+    /// those is selected.
+    pub fn draw_v2(&mut self, emulator: &EmulatorV2, layout: Option<&Layout>, history: &PortHistory) {
+        if !self.due() {
+            return;
+        }
+        let width = self.terminal.size().map_or(80, |size| size.width) as usize;
+        let body = if self.timeline {
+            Some(timeline_text(history, width.saturating_sub(TIMELINE_LABEL_WIDTH + 2)))
+        } else {
+            layout.map(|layout| (layout.name.clone(), layout.render(&emulator.snapshot())))
+        };
+        self.draw(&view_v2(emulator), body);
+    }
+
+    pub fn draw_v1(&mut self, emulator: &Emulator) {
+        if !self.due() {
+            return;
+        }
+        self.draw(&view_v1(emulator), None);
+    }
+
+    fn due(&self) -> bool {
+        self.last_draw.is_none_or(|at| at.elapsed() >= FRAME_INTERVAL)
+    }
+
+    fn draw(&mut self, view: &View, layout: Option<(String, String)>) {
+        self.panels = view.panels();
+        // The highlight stays on until the next change, so it still shows at a slow clock.
+        let values: Vec<i32> = view.registers.iter().map(|(_, value)| *value).collect();
+        if values != self.registers {
+            self.changed = values.iter().enumerate().map(|(i, value)| self.registers.get(i) != Some(value)).collect();
+            self.registers = values;
+        }
+        if !self.panels.contains(&self.focus) {
+            self.focus = self.panels[0];
+        }
+        let mut status = view.status.clone();
+        if view.waiting_for_input {
+            status.push(format!("INP: type a value (0-255) and press Enter: {}_", self.input));
+        }
+        status.extend(self.notices.iter().cloned());
+        match &self.recent {
+            Some(files) if files.is_empty() => status.push("Recent programs: none yet (o closes).".to_string()),
+            Some(files) => {
+                let names: Vec<String> = files.iter().enumerate().map(|(i, file)| format!("{}: {}", i + 1, recent::shorten(file, 40))).collect();
+                status.push(format!("Recent programs (press the number, o closes)  {}", names.join("  ")));
+            }
+            None => {}
+        }
+        if self.paused {
+            status.push("Paused.".to_string());
+        }
+        status.push("Tab: next panel  Up/Down: scroll  Space: pause  N: step  Bksp: reset  F5: reload  O: recent  +/-: speed  T: timeline  Q: quit".to_string());
+
+        let lit = |at: &Option<Instant>| at.is_some_and(|at| at.elapsed() < FLASH);
+        let highlights = Highlights {
+            registers: self.changed.clone(),
+            ports: self.port_writes.each_ref().map(lit),
+            ram: self.ram_writes.each_ref().map(lit),
+            flush: lit(&self.last_flush),
+        };
+        let (focus, scroll, leds) = (self.focus, self.scroll, self.leds);
+        let compare = self.compare_leds.zip(self.captions.as_ref());
+        let _ = self.terminal.draw(|frame| {
+            let [body, footer] =
+                layout::Layout::vertical([Constraint::Min(0), Constraint::Length(status.len() as u16 + 2)]).areas(frame.area());
+            let body = match leds {
+                Some(ports) => {
+                    let [grid, rest] = layout::Layout::vertical([Constraint::Length(LED_GRID_HEIGHT), Constraint::Min(0)]).areas(body);
+                    match compare {
+                        Some((compare, [main_caption, compare_caption])) => {
+                            let [left, right] = layout::Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(grid);
+                            frame.render_widget(led_grid(&ports, &view.port_labels, main_caption), left);
+                            frame.render_widget(led_grid(&compare, &[], compare_caption), right);
+                        }
+                        None => frame.render_widget(led_grid(&ports, &view.port_labels, "LEDs"), grid),
+                    }
+                    rest
+                }
+                None => body,
+            };
+            match &layout {
+                Some((name, text)) => {
+                    frame.render_widget(Paragraph::new(text.as_str()).block(Block::bordered().title(format!(" {name} "))), body)
+                }
+                None => draw_panels(frame, body, view, focus, &scroll, &highlights),
+            }
+            let lines: Vec<Line> = status
+                .iter()
+                .map(|line| if line.starts_with("Error") { Line::from(line.as_str()).red() } else { Line::from(line.as_str()) })
+                .collect();
+            frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(format!(" {} ", view.title))), footer);
+        });
+        self.last_draw = Some(Instant::now());
+    }
+}
+
+/// Room for a timeline row's `%7.7 ` label.
+const TIMELINE_LABEL_WIDTH: usize = 5;
+
+/// The latest clocks that fit in `width` columns, one row per bit of each port that wasn't 0
+/// throughout: a text logic analyzer.
+fn timeline_text(history: &PortHistory, width: usize) -> (String, String) {
+    let count = width.min(history.len());
+    let ports = history.active_ports(count);
+    let mut text = String::new();
+    if ports.is_empty() {
+        text.push_str("Every port stayed 0.");
+    }
+    for port in ports {
+        for bit in (0..8).rev() {
+            text.push_str(&format!("%{port}.{bit} {}\n", history.waveform(port, bit, count)));
+        }
+    }
+    (format!("Timeline (last {count} clocks)"), text)
+}
+
+/// Each LED is a block this many cells wide and tall, with a gap on the right.
+const LED_WIDTH: usize = 4;
+const LED_HEIGHT: usize = 2;
+const LED_GRID_HEIGHT: u16 = 8 * LED_HEIGHT as u16 + 2;
+const LED_ON: Color = Color::Rgb(255, 40, 40);
+const LED_OFF: Color = Color::Rgb(60, 10, 10);
+
+/// The window's LED grid drawn in filled cells, for `--display terminal`: port 0 at the top and
+/// the most significant bit on the left, with each `.port` label after its first row.
+fn led_grid(ports: &[u8; 8], labels: &[PortLabel], title: &str) -> Paragraph<'static> {
+    let mut lines = Vec::new();
+    for (port, value) in ports.iter().enumerate() {
+        for row in 0..LED_HEIGHT {
+            let mut spans: Vec<Span> = (0..8)
+                .map(|bit| {
+                    let color = if value & (0x80 >> bit) != 0 { LED_ON } else { LED_OFF };
+                    Span::styled(format!("{} ", "█".repeat(LED_WIDTH - 1)), Style::new().fg(color))
+                })
+                .collect();
+            if row == 0 {
+                if let Some(label) = labels.iter().find(|label| label.first == port as u8) {
+                    spans.push(Span::from(format!(" {}", label.text)));
+                }
+            }
+            lines.push(Line::from(spans));
+        }
+    }
+    Paragraph::new(lines).block(Block::bordered().title(format!(" {title} ")))
+}
+
+/// The V2 panels as plain text, for the debugger's `show` where the screen can't be taken over.
+pub fn snapshot_v2(emulator: &EmulatorV2) -> String {
+    let width = terminal::size().map_or(100, |(width, _)| width.min(120));
+    let Ok(mut terminal) = Terminal::new(TestBackend::new(width, SNAPSHOT_HEIGHT)) else {
+        return String::new();
+    };
+    let view = view_v2(emulator);
+    let _ = terminal.draw(|frame| draw_panels(frame, frame.area(), &view, Panel::Pipeline, &[0; PANELS.len()], &Highlights::default()));
+    let buffer = terminal.backend().buffer();
+    let area = buffer.area;
+    (0..area.height)
+        .map(|y| (0..area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>().trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn view_v2(emulator: &EmulatorV2) -> View {
+        let mut status = vec![crate::format_stats_v2(emulator)];
+        if emulator.deterministic {
+            status.push(format!("State hash: {:016x}", emulator.state_hash()));
+        }
+        let stage = |instr: &crate::electron_2::Instruction| {
+            if instr.address < 0 { "-".to_string() } else { crate::electron_2::disassembler::Disassembler::instruction(instr) }
+        };
+        let flags = &emulator.alu.flags;
+        let mut registers: Vec<(&str, i32)> =
+            REGISTER_NAMES.iter().enumerate().map(|(i, name)| (*name, emulator.registers.read(i as i32) as i32)).collect();
+        registers.push(("PC", emulator.pc));
+        registers.push(("SP", emulator.sp));
+        View {
+            title: "Electron 2",
+            stages: [
+                stage(&emulator.fetch_reg),
+                stage(&emulator.decode_reg),
+                stage(&emulator.execute_reg),
+                stage(&emulator.writeback_reg),
+            ],
+            accumulator: emulator.alu.accumulator,
+            flags: [("Equals", flags.equals), ("Greater", flags.greater), ("Less", flags.less), ("Overflow", flags.overflow)],
+            registers,
+            // Under --separate-stack SP points into the stack, not RAM.
+            ram: Some((emulator.ram, if emulator.separate_stack { -1 } else { emulator.sp })),
+            ports: emulator.ports_out,
+            port_labels: emulator.port_labels.clone(),
+            warnings: Some(emulator.errors.iter().map(|e| format!("Error: {e}")).chain(emulator.warnings.iter().map(|w| format!("Warning: {w}"))).collect()),
+            status,
+            waiting_for_input: emulator.waiting_for_input,
+        }
+}
+
+fn view_v1(emulator: &Emulator) -> View {
+        let flags = &emulator.alu.flags;
+        View {
+            title: "Electron",
+            stages: [
+                emulator.fetch_register.operation.get_name(),
+                emulator.decode_register.operation.get_name(),
+                emulator.execute_register.operation.get_name(),
+                emulator.write_back_register.operation.get_name(),
+            ],
+            accumulator: emulator.alu.accumalator,
+            flags: [
+                ("Equals", flags.equals),
+                ("Greater", flags.greater),
+                ("Less", flags.less),
+                ("Overflow", flags.overflow),
+            ],
+            registers: REGISTER_NAMES.iter().enumerate().map(|(i, name)| (*name, emulator.registers.read(i as u8) as i32)).collect(),
+            ram: None,
+            ports: emulator.ports.out,
+            port_labels: Vec::new(),
+            warnings: None,
+            status: vec![format!("PC: {}", emulator.program_counter)],
+            waiting_for_input: false,
+        }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = execute!(self.terminal.backend_mut(), terminal::LeaveAlternateScreen, cursor::Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+const REGISTER_NAMES: [&str; 8] = ["R0", "R1", "R2", "R3", "R4", "R5", "R6", "R7"];
+
+/// Each stage keeps its color from box to box, so an instruction can be followed down the
+/// pipeline.
+const STAGE_COLORS: [Color; 4] = [Color::Cyan, Color::Blue, Color::Magenta, Color::Green];
+
+/// Pipeline across the top, then ALU and registers, RAM and the ports side by side, and the
+/// warnings below. Narrow terminals stack the middle panels instead.
+fn draw_panels(frame: &mut Frame, area: Rect, view: &View, focus: Panel, scroll: &[u16; PANELS.len()], highlights: &Highlights) {
+    let panel = |kind: Panel, title: &str, lines: Vec<Line<'static>>| {
+        let mut block = Block::bordered().title(format!(" {title} "));
+        if kind == focus {
+            block = block.border_style(Style::new().add_modifier(Modifier::BOLD)).title_style(Style::new().add_modifier(Modifier::REVERSED));
+        }
+        let offset = scroll[PANELS.iter().position(|p| *p == kind).unwrap_or(0)];
+        Paragraph::new(lines).block(block).scroll((offset, 0)).wrap(Wrap { trim: false })
+    };
+
+    let [pipeline, middle, warnings] = layout::Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(if area.width >= 80 { 20 } else { 40 }),
+        Constraint::Min(0),
+    ])
+    .areas(area);
+
+    let stages = ["FETCH", "DECODE", "EXECUTE", "WRITEBACK"];
+    let cells = layout::Layout::horizontal([Constraint::Ratio(1, 4); 4]).split(pipeline);
+    for (i, cell) in cells.iter().enumerate() {
+        let color = STAGE_COLORS[i];
+        let color = if i == 0 && highlights.flush { Color::Red } else { color };
+        let mut block = Block::bordered().title(format!(" {} ", stages[i])).border_style(Style::new().fg(color));
+        if focus == Panel::Pipeline {
+            block = block.border_style(Style::new().fg(color).add_modifier(Modifier::BOLD));
+        }
+        // An empty stage is a bubble.
+        let text = if view.stages[i] == "-" { Span::from("-").dark_gray() } else { Span::from(view.stages[i].clone()).fg(color) };
+        frame.render_widget(Paragraph::new(Line::from(text)).block(block), *cell);
+    }
+
+    let columns = if area.width >= 80 {
+        layout::Layout::horizontal([Constraint::Length(22), Constraint::Length(32), Constraint::Min(30)]).split(middle)
+    } else {
+        layout::Layout::vertical([Constraint::Length(20), Constraint::Length(20), Constraint::Length(10)]).split(middle)
+    };
+    let left = layout::Layout::vertical([Constraint::Length(7), Constraint::Min(0)]).split(columns[0]);
+
+    let mut alu = vec![Line::from(format!("ACC: {}", view.accumulator))];
+    alu.extend(view.flags.iter().map(|(name, set)| {
+        let value = if *set { Span::from("true").green() } else { Span::from("false").red() };
+        Line::from(vec![Span::from(format!("{name}: ")), value])
+    }));
+    frame.render_widget(panel(Panel::Alu, "ALU", alu), left[0]);
+
+    let registers = view
+        .registers
+        .iter()
+        .enumerate()
+        .map(|(i, (name, value))| {
+            let line = Line::from(format!("{name:<3} {value:>3}  0x{value:02X}"));
+            if highlights.registers.get(i) == Some(&true) { line.green().bold() } else { line }
+        })
+        .collect();
+    frame.render_widget(panel(Panel::Registers, "Registers", registers), left[1]);
+
+    if let Some((ram, sp)) = view.ram {
+        let ram = ram
+            .iter()
+            .enumerate()
+            .map(|(address, value)| {
+                let marker = if sp == address as i32 { " < SP" } else { "" };
+                let line = Line::from(format!("#{address:02}  {value:>3}  {value:08b}{marker}"));
+                if highlights.ram[address] { line.green().bold() } else { line }
+            })
+            .collect();
+        frame.render_widget(panel(Panel::Ram, "RAM", ram), columns[1]);
+    }
+
+    let ports = view
+        .ports
+        .iter()
+        .enumerate()
+        .map(|(port, value)| {
+            let leds: String = (0..8).map(|bit| if value & (0x80 >> bit) != 0 { "▓▓" } else { "░░" }).collect();
+            let label = view.port_labels.iter().find(|l| (l.first..=l.last).contains(&(port as u8)));
+            let label = match label {
+                Some(label) if label.first == port as u8 => format!(" {}", label.text),
+                Some(_) => " │".to_string(),
+                None => String::new(),
+            };
+            let line = Line::from(format!("%{port} {value:>3} {leds}{label}"));
+            if highlights.ports[port] { line.green().bold() } else { line }
+        })
+        .collect();
+    let ports_area = if view.ram.is_some() { columns[2] } else { columns[1] };
+    frame.render_widget(panel(Panel::Ports, "Ports", ports), ports_area);
+
+    if let Some(messages) = &view.warnings {
+        let lines = if messages.is_empty() {
+            vec![Line::from("No errors or warnings.")]
+        } else {
+            messages
+                .iter()
+                .map(|m| if m.starts_with("Error") { Line::from(m.clone()).red() } else { Line::from(m.clone()).yellow() })
+                .collect()
+        };
+        frame.render_widget(panel(Panel::Warnings, "Warnings", lines), warnings);
+    }
+}
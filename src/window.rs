@@ -0,0 +1,566 @@
+//! The emulator window: the LED grid, the V2 panels, the transport buttons and the buzzer, drawn
+//! with raylib. Builds without the `gui` feature have no raylib and so no window; `Window` is then
+//! a type with no values, `open` always fails, and the LEDs are drawn in the lite window
+//! (`--display lite`) or the terminal dashboard (`--display terminal`) instead.
+
+use crate::electron_2::Emulator as EmulatorV2;
+use crate::gamepad::Mapping;
+use crate::timeline::PortHistory;
+use crate::transport::Transport;
+use std::sync::{Arc, Mutex};
+
+/// Everything about the window that comes from the command line.
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub struct WindowOptions<'a> {
+    pub title: &'a str,
+    pub theme: Option<&'a str>,
+    /// `--scale`: how many screen pixels each of the window's draws as, or None for the
+    /// monitor's DPI scale.
+    pub scale: Option<f32>,
+    pub matrix: bool,
+    pub buzzer_port: Option<usize>,
+    /// `--speaker`: the port bit's samples, clock by clock, to play in place of the buzzer.
+    pub speaker: Option<Arc<Mutex<Vec<bool>>>>,
+    pub show_fps: bool,
+    /// `--fullscreen`: start with the LEDs filling the screen, as F11 does.
+    pub fullscreen: bool,
+    /// `--compare`: the captions over the two halves of the window, main program first.
+    pub compare: Option<[String; 2]>,
+    /// Keep raylib's log lines off stdout, which carries the UART.
+    pub quiet: bool,
+    /// `--adc`: the channel values the window's sliders set.
+    pub adc: Option<Arc<Mutex<Vec<u8>>>>,
+    /// `--gamepad`: how to turn the buttons into the port value the window keeps up to date.
+    pub gamepad: Option<(Mapping, Arc<Mutex<u8>>)>,
+    /// `--mouse`: the X, Y and buttons values the window keeps up to date.
+    pub mouse: Option<Arc<Mutex<[u8; 3]>>>,
+}
+
+/// `--display`: what draws the LEDs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Display {
+    /// The raylib window, which needs the `gui` feature.
+    Window,
+    /// The LEDs alone in a minifb window, which needs the `lite` feature.
+    Lite,
+    /// Filled cells at the top of the terminal dashboard, and no window.
+    Terminal,
+    /// Nothing: the dashboard alone, or with `-nt` no output at all, for `--serve`.
+    Headless,
+}
+
+impl Display {
+    pub const NAMES: &'static [&'static str] = &["window", "lite", "terminal", "none"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "window" => Some(Display::Window),
+            "lite" => Some(Display::Lite),
+            "terminal" => Some(Display::Terminal),
+            "none" => Some(Display::Headless),
+            _ => None,
+        }
+    }
+
+    /// The window where there is one, else the lite window, else the terminal.
+    pub fn default_for_build() -> Self {
+        if cfg!(feature = "gui") {
+            Display::Window
+        } else if cfg!(feature = "lite") {
+            Display::Lite
+        } else {
+            Display::Terminal
+        }
+    }
+}
+
+/// The window's own keys, apart from the transport buttons and panel toggles.
+#[derive(Clone, Copy)]
+pub enum Key {
+    /// Tab: show the other core under `--link`.
+    NextCore,
+    /// I: turn the V2 interlock on or off.
+    Interlock,
+    /// L: the next dashboard layout.
+    NextLayout,
+    /// B: LED brightness from each bit's duty cycle, or each bit as it is.
+    Pwm,
+    /// F11: the LEDs alone, filling the screen, or back to the window.
+    Fullscreen,
+}
+
+/// What the source editor asks for: the edited program loaded in place of the running one,
+/// and with `Save`, written to the program file first.
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub enum Edit {
+    Apply(String),
+    Save(String),
+}
+
+#[cfg(feature = "gui")]
+pub use gui::{monitor_scale, Window};
+
+#[cfg(feature = "gui")]
+mod gui {
+    use super::{Edit, EmulatorV2, Key, Mapping, PortHistory, Transport, WindowOptions};
+    use crate::buzzer::{Buzzer, Sound};
+    use crate::editor::{self, Editor};
+    use crate::gamepad;
+    use crate::panels::{Layout, Panels};
+    use crate::problems::Problems;
+    use crate::quick_open::QuickOpen;
+    use crate::sliders::Sliders;
+    use crate::theme::{Leds, Theme};
+    use crate::transport;
+    use crate::WINDOW_SIZE;
+    use raylib::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    pub struct Window {
+        rl: RaylibHandle,
+        thread: RaylibThread,
+        leds: Leds,
+        buzzer: Option<Buzzer>,
+        sliders: Option<Sliders>,
+        gamepad: Option<(Mapping, Arc<Mutex<u8>>)>,
+        mouse: Option<Arc<Mutex<[u8; 3]>>>,
+        /// The V2 panels, once a program's source is set.
+        panels: Option<Panels>,
+        /// The panels that were open last run and whether they go beside the LEDs, saved again
+        /// when the window closes.
+        layout: Layout,
+        show_fps: bool,
+        /// Under `--compare`, the window is split between the programs.
+        captions: Option<[String; 2]>,
+        /// Everything is laid out on the 720x720 canvas and drawn this many times larger.
+        scale: f32,
+        /// F11: fullscreen with only the LEDs, laid out to the screen, for showing on a
+        /// projector. The panels, sliders, transport bar and frame rate are hidden.
+        presenting: bool,
+        /// O: the recent programs, while the menu is open.
+        quick_open: Option<QuickOpen>,
+        /// E: the source editor, while it's open. It takes the keyboard, so the window's own
+        /// keys are off until Esc closes it.
+        editor: Option<Editor>,
+        /// The program `set_source` was last given, for the editor to start from.
+        source: String,
+        /// W: the last load's errors and warnings.
+        problems: Problems,
+        /// What the title bar says now, so it's only set again when that changes.
+        title: String,
+        /// `main`'s clock count at the last frame, to know how many clocks of history are new.
+        last_cycles: u64,
+    }
+
+    /// The monitor's DPI scale, though never so large that the window no longer fits on it, nor
+    /// below 1.
+    pub fn monitor_scale(rl: &RaylibHandle) -> f32 {
+        let fits = get_monitor_height(get_current_monitor()) as f32 * 0.9 / WINDOW_SIZE.1 as f32;
+        rl.get_window_scale_dpi().x.min(fits).max(1.0)
+    }
+
+    impl Window {
+        pub fn open(options: WindowOptions) -> Result<Self, String> {
+            // Before raylib starts, which logs as it does.
+            if options.quiet {
+                set_trace_log(TraceLogLevel::LOG_WARNING);
+            }
+            let theme = options.theme.map_or(Ok(Theme::classic()), Theme::load)?;
+            let (mut rl, thread) = raylib::init().width(WINDOW_SIZE.0).height(WINDOW_SIZE.1).title(options.title).build();
+            // The clock runs on its own thread, so the window only needs to keep up with the eye.
+            rl.set_target_fps(60);
+            let scale = options.scale.unwrap_or_else(|| monitor_scale(&rl));
+            // The clicks and drags then land on the canvas however large it's drawn.
+            rl.set_mouse_scale(1.0 / scale, 1.0 / scale);
+            let layout = Layout::load();
+            if let Some((x, y)) = layout.position {
+                rl.set_window_position(x, y);
+            }
+            let mut leds = Leds::new(&mut rl, &thread, theme)?;
+            leds.matrix = options.matrix;
+            let sound = options.buzzer_port.map(Sound::Tone).or(options.speaker.map(Sound::Bits));
+            let buzzer = sound.map(|sound| Buzzer::new(&thread, sound));
+            let sliders = options.adc.map(Sliders::new);
+            let mut window = Self { rl, thread, leds, buzzer, sliders, gamepad: options.gamepad, mouse: options.mouse, panels: None, layout, show_fps: options.show_fps, captions: options.compare, scale, presenting: false, quick_open: None, editor: None, source: String::new(), problems: Problems::default(), title: options.title.to_string(), last_cycles: 0 };
+            window.fit_layout();
+            if options.fullscreen {
+                window.toggle_fullscreen();
+            }
+            Ok(window)
+        }
+
+        pub fn should_close(&self) -> bool {
+            self.rl.window_should_close()
+        }
+
+        pub fn pressed(&self, key: Key) -> bool {
+            self.editor.is_none() && self.rl.is_key_pressed(match key {
+                Key::NextCore => KeyboardKey::KEY_TAB,
+                Key::Interlock => KeyboardKey::KEY_I,
+                Key::NextLayout => KeyboardKey::KEY_L,
+                Key::Pwm => KeyboardKey::KEY_B,
+                Key::Fullscreen => KeyboardKey::KEY_F11,
+            })
+        }
+
+        /// Closes the window, saving the layout for the next run: the open panels if there were
+        /// any, and where the window is.
+        pub fn close(mut self) {
+            if let Some(panels) = &mut self.panels {
+                self.layout.remember(panels);
+            }
+            if !self.presenting {
+                let position = self.rl.get_window_position();
+                self.layout.position = Some((position.x as i32, position.y as i32));
+            }
+            self.layout.save();
+        }
+
+        pub fn set_title(&mut self, title: &str) {
+            if title != self.title {
+                self.rl.set_window_title(&self.thread, title);
+                self.title = title.to_string();
+            }
+        }
+
+        /// Switches the LEDs between duty-cycle brightness and the instantaneous view.
+        pub fn toggle_pwm(&mut self) -> String {
+            self.leds.pwm = !self.leds.pwm;
+            if self.leds.pwm {
+                "LEDs: brightness follows each bit's duty cycle.".to_string()
+            } else {
+                "LEDs: each bit as it is this frame.".to_string()
+            }
+        }
+
+        /// Fills the screen with the LEDs alone, or goes back to the window as it was. The
+        /// monitor switches to its own resolution, so the grid is drawn at the screen's pixels
+        /// rather than scaled up from the canvas.
+        pub fn toggle_fullscreen(&mut self) -> String {
+            self.presenting = !self.presenting;
+            if self.presenting {
+                let monitor = get_current_monitor();
+                self.rl.set_window_size(get_monitor_width(monitor), get_monitor_height(monitor));
+                self.rl.toggle_fullscreen();
+                self.rl.set_mouse_scale(1.0, 1.0);
+                "Fullscreen: only the LEDs. F11 goes back to the window.".to_string()
+            } else {
+                self.rl.toggle_fullscreen();
+                self.rl.set_mouse_scale(1.0 / self.scale, 1.0 / self.scale);
+                self.fit_layout();
+                "Fullscreen off.".to_string()
+            }
+        }
+
+        /// The transport control asked for this frame, by key or button.
+        pub fn transport(&self) -> Option<Transport> {
+            transport::input(&self.rl, !self.presenting, self.editor.is_none())
+        }
+
+        /// A program to load in place of the running one: picked from the O menu this frame, or
+        /// dropped on the window since the last call (the first, when several were dropped
+        /// together).
+        pub fn opened_file(&mut self) -> Option<String> {
+            if self.rl.is_key_pressed(KeyboardKey::KEY_O) && !self.presenting && self.editor.is_none() {
+                self.quick_open = match self.quick_open {
+                    Some(_) => None,
+                    None => Some(QuickOpen::new()),
+                };
+            }
+            if let Some(file) = self.quick_open.as_ref().and_then(|menu| menu.picked(&self.rl)) {
+                self.quick_open = None;
+                return Some(file);
+            }
+            if !self.rl.is_file_dropped() {
+                return None;
+            }
+            let file = self.rl.get_dropped_files().into_iter().next();
+            self.rl.clear_dropped_files();
+            file
+        }
+
+        /// Shows `code` in the source panel, and in the editor unless it has changes of its own.
+        /// The first time, the panels open as they were saved.
+        pub fn set_source(&mut self, code: &str) {
+            self.source = code.to_string();
+            if let Some(editor) = self.editor.as_mut().filter(|editor| !editor.changed) {
+                editor.set_text(code);
+            }
+            match &mut self.panels {
+                Some(panels) => panels.set_source(code),
+                None => {
+                    let mut panels = Panels::new(code);
+                    self.layout.restore(&mut panels);
+                    self.panels = Some(panels);
+                    self.fit_layout();
+                }
+            }
+        }
+
+        /// Lists the errors and warnings from loading the program, or from failing to, over the
+        /// LEDs. An empty list closes it.
+        pub fn set_problems(&mut self, errors: &[String], warnings: &[String]) {
+            self.problems.set(errors, warnings);
+        }
+
+        /// E opens the source editor and Esc closes it; in between, it takes the keys. Returns
+        /// the edited program when it's to be applied.
+        pub fn edit(&mut self) -> Option<Edit> {
+            if self.panels.is_none() || self.presenting {
+                return None;
+            }
+            let mouse = self.panel_mouse();
+            match &mut self.editor {
+                None if self.rl.is_key_pressed(KeyboardKey::KEY_E) && self.quick_open.is_none() => {
+                    editor::discard_typed();
+                    self.editor = Some(Editor::new(&self.source));
+                    // Esc closes the editor rather than the window.
+                    self.rl.set_exit_key(None);
+                    None
+                }
+                None => None,
+                Some(_) if self.rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) => {
+                    self.editor = None;
+                    self.rl.set_exit_key(Some(KeyboardKey::KEY_ESCAPE));
+                    None
+                }
+                Some(editor) => editor.handle_input(&self.rl, mouse),
+            }
+        }
+
+        /// Panel toggles, D for the split layout, and breakpoints clicked in the source panel,
+        /// which go to `main`.
+        pub fn handle_panels(&mut self, main: &mut EmulatorV2) {
+            if self.panels.is_none() || self.presenting || self.editor.is_some() {
+                return;
+            }
+            if self.rl.is_key_pressed(KeyboardKey::KEY_D) {
+                self.layout.split = !self.layout.split;
+                self.fit_layout();
+            }
+            let mouse = self.panel_mouse();
+            if let Some(panels) = &mut self.panels {
+                panels.handle_input(&self.rl, mouse, main);
+            }
+        }
+
+        /// The panels only go beside the LEDs once there are panels to show.
+        fn split(&self) -> bool {
+            self.layout.split && self.panels.is_some()
+        }
+
+        /// Sizes the window for the scale, doubling its width for the split layout, the LEDs
+        /// keeping the left half.
+        fn fit_layout(&mut self) {
+            if self.presenting {
+                return;
+            }
+            let columns = if self.split() { 2 } else { 1 };
+            let (width, height) = (WINDOW_SIZE.0 * columns, WINDOW_SIZE.1);
+            self.rl.set_window_size((width as f32 * self.scale) as i32, (height as f32 * self.scale) as i32);
+        }
+
+        /// The pointer relative to the panels, which start at the right half when split.
+        fn panel_mouse(&self) -> Vector2 {
+            let mouse = self.rl.get_mouse_position();
+            if self.split() { Vector2::new(mouse.x - WINDOW_SIZE.0 as f32, mouse.y) } else { mouse }
+        }
+
+        /// Draws the canvas `left` pixels in, at the window's scale. Fullscreen draws at the
+        /// screen's own pixels.
+        fn view(&self, left: i32) -> Camera2D {
+            let zoom = if self.presenting { 1.0 } else { self.scale };
+            Camera2D { offset: Vector2::new(left as f32 * zoom, 0.0), target: Vector2::zero(), rotation: 0.0, zoom }
+        }
+
+        /// Draws `core` (the shown one of the linked cores), with its ports on the LEDs, or the
+        /// `--strobe` frame if there is one, and the `--compare` program's beside them. The
+        /// buzzer and timeline follow `main`.
+        pub fn draw_v2(&mut self, core: &EmulatorV2, main: &EmulatorV2, history: &PortHistory, strobed: Option<&[u8; 8]>, compare: Option<&[u8; 8]>, paused: bool) {
+            if let Some(buzzer) = &mut self.buzzer {
+                buzzer.update(&main.ports_out);
+            }
+            let ports = strobed.unwrap_or(&core.ports_out);
+            // A reset starts the count over, which leaves nothing new. `history` is `main`'s
+            // ports clock by clock, so the brightness only uses it when those are what's shown.
+            let clocks = main.stats.cycles.saturating_sub(self.last_cycles);
+            self.last_cycles = main.stats.cycles;
+            if strobed.is_none() && std::ptr::eq(core, main) && clocks > 0 {
+                self.leds.observe(0, history.recent(clocks.min(history.len() as u64) as usize));
+            } else {
+                self.leds.observe(0, [ports]);
+            }
+            if let Some(compare) = compare {
+                self.leds.observe(1, [compare]);
+            }
+            self.fit_leds();
+            if !self.presenting && self.editor.is_none() {
+                if let Some(sliders) = &mut self.sliders {
+                    sliders.handle_input(&self.rl);
+                }
+                self.problems.handle_input(&self.rl, self.rl.get_mouse_position());
+            }
+            if let Some((mapping, buttons)) = &self.gamepad {
+                *buttons.lock().unwrap() = mapping.value(|button| gamepad::held(&self.rl, button));
+            }
+            if let Some(mouse) = &self.mouse {
+                self.read_mouse(&mut mouse.lock().unwrap());
+            }
+            let (mouse, pointer) = (self.panel_mouse(), self.rl.get_mouse_position());
+            let (canvas, side) = (self.view(0), self.view(if self.split() { WINDOW_SIZE.0 } else { 0 }));
+            let mut d = self.rl.begin_drawing(&self.thread);
+            d.clear_background(self.leds.background());
+            {
+                let mut d = d.begin_mode2D(canvas);
+                match (compare, &self.captions) {
+                    (Some(compare), Some(captions)) => self.leds.draw_pair(&mut d, [ports, compare], captions),
+                    _ => {
+                        self.leds.draw(&mut d, ports);
+                        self.leds.draw_labels(&mut d, &core.port_labels);
+                    }
+                }
+            }
+            if self.presenting {
+                return;
+            }
+            match (&self.editor, &mut self.panels) {
+                (Some(editor), _) => editor.draw(&mut d.begin_mode2D(side)),
+                (None, Some(panels)) => panels.draw(&mut d.begin_mode2D(side), mouse, core, main, history),
+                (None, None) => {}
+            }
+            let mut d = d.begin_mode2D(canvas);
+            if let Some(sliders) = &self.sliders {
+                sliders.draw(&mut d);
+            }
+            if self.editor.is_none() {
+                self.problems.draw(&mut d);
+            }
+            transport::draw(&mut d, paused);
+            if let Some(menu) = &self.quick_open {
+                menu.draw(&mut d, pointer);
+            }
+            if self.show_fps {
+                d.draw_text(&d.get_fps().to_string(), 0, 0, 25, Color::WHITE);
+            }
+        }
+
+        /// Fullscreen, the LEDs fill the screen as it really is, whatever size the monitor
+        /// switched to.
+        fn fit_leds(&mut self) {
+            self.leds.fullscreen = self.presenting.then(|| (self.rl.get_screen_width(), self.rl.get_screen_height()));
+        }
+
+        /// The pointer and buttons while the window has focus. Away from it the buttons read as
+        /// let go and the pointer stays where it was.
+        fn read_mouse(&self, state: &mut [u8; 3]) {
+            if !self.rl.is_window_focused() {
+                state[2] = 0;
+                return;
+            }
+            let (x, y) = self.leds.grid_position(self.rl.get_mouse_position());
+            let buttons = [(MouseButton::MOUSE_LEFT_BUTTON, 1), (MouseButton::MOUSE_RIGHT_BUTTON, 2), (MouseButton::MOUSE_MIDDLE_BUTTON, 4)];
+            let held = buttons.into_iter().filter(|(button, _)| self.rl.is_mouse_button_down(*button)).fold(0, |held, (_, bit)| held | bit);
+            *state = [x, y, held];
+        }
+
+        pub fn draw_v1(&mut self, ports: &[u8; 8], compare: Option<&[u8; 8]>, paused: bool) {
+            if let Some(buzzer) = &mut self.buzzer {
+                buzzer.update(ports);
+            }
+            self.leds.observe(0, [ports]);
+            if let Some(compare) = compare {
+                self.leds.observe(1, [compare]);
+            }
+            self.fit_leds();
+            let canvas = self.view(0);
+            let mouse = self.rl.get_mouse_position();
+            if !self.presenting {
+                self.problems.handle_input(&self.rl, mouse);
+            }
+            let mut d = self.rl.begin_drawing(&self.thread);
+            d.clear_background(self.leds.background());
+            let mut d = d.begin_mode2D(canvas);
+            match (compare, &self.captions) {
+                (Some(compare), Some(captions)) => self.leds.draw_pair(&mut d, [ports, compare], captions),
+                _ => self.leds.draw(&mut d, ports),
+            }
+            if self.presenting {
+                return;
+            }
+            self.problems.draw(&mut d);
+            transport::draw(&mut d, paused);
+            if let Some(menu) = &self.quick_open {
+                menu.draw(&mut d, mouse);
+            }
+            if self.show_fps {
+                d.draw_text(&d.get_fps().to_string(), 0, 0, 25, Color::WHITE);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "gui"))]
+pub enum Window {}
+
+#[cfg(not(feature = "gui"))]
+impl Window {
+    pub fn open(_options: WindowOptions) -> Result<Self, String> {
+        Err("This build has no window (it was built without the gui feature). Use --display terminal.".to_string())
+    }
+
+    pub fn should_close(&self) -> bool {
+        match *self {}
+    }
+
+    pub fn pressed(&self, _key: Key) -> bool {
+        match *self {}
+    }
+
+    pub fn close(self) {
+        match self {}
+    }
+
+    pub fn set_title(&mut self, _title: &str) {
+        match *self {}
+    }
+
+    pub fn toggle_pwm(&mut self) -> String {
+        match *self {}
+    }
+
+    pub fn toggle_fullscreen(&mut self) -> String {
+        match *self {}
+    }
+
+    pub fn transport(&self) -> Option<Transport> {
+        match *self {}
+    }
+
+    pub fn opened_file(&mut self) -> Option<String> {
+        match *self {}
+    }
+
+    pub fn set_source(&mut self, _code: &str) {
+        match *self {}
+    }
+
+    pub fn set_problems(&mut self, _errors: &[String], _warnings: &[String]) {
+        match *self {}
+    }
+
+    pub fn edit(&mut self) -> Option<Edit> {
+        match *self {}
+    }
+
+    pub fn handle_panels(&mut self, _main: &mut EmulatorV2) {
+        match *self {}
+    }
+
+    pub fn draw_v2(&mut self, _core: &EmulatorV2, _main: &EmulatorV2, _history: &PortHistory, _strobed: Option<&[u8; 8]>, _compare: Option<&[u8; 8]>, _paused: bool) {
+        match *self {}
+    }
+
+    pub fn draw_v1(&mut self, _ports: &[u8; 8], _compare: Option<&[u8; 8]>, _paused: bool) {
+        match *self {}
+    }
+}